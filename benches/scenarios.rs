@@ -0,0 +1,67 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// run with `cargo bench --features bench`, a single scenario can be
+// selected by name, e.g. `cargo bench --features bench -- repetition_ip`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+fn bench_scenarios(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scenarios");
+
+    for scenario in anreg::scenarios() {
+        let process = scenario.process().unwrap();
+        let text = scenario.corpus.text();
+
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(scenario.name, |b| b.iter(|| scenario.run(&process, &text)));
+    }
+
+    group.finish();
+}
+
+fn bench_compile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile");
+
+    for scenario in anreg::scenarios() {
+        group.bench_function(scenario.name, |b| b.iter(|| scenario.process().unwrap()));
+    }
+
+    group.finish();
+}
+
+// a big pattern with many definitions that refer to each other, the
+// lexer, the macro expander and the parser handle lots of identifiers.
+fn macro_heavy_pattern(count: usize) -> String {
+    let mut source = String::from("define(d0, char_word)\n");
+    for i in 1..count {
+        source.push_str(&format!(
+            "define(d{}, (d{}, ['a'..'f', char_digit] || start || char_any))\n",
+            i,
+            i - 1
+        ));
+    }
+    for i in (0..count).step_by(count / 16) {
+        source.push_str(&format!("d{} ||\n", i));
+    }
+    source.push_str("'.'\n");
+    source
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    let source = macro_heavy_pattern(64);
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("macro_heavy", |b| {
+        b.iter(|| anreg::parse_from_str(&source).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_scenarios, bench_compile, bench_parse);
+criterion_main!(benches);
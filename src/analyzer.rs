@@ -0,0 +1,465 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::fmt::Display;
+
+use crate::{
+    ast::{
+        CharSet, CharSetElement, Expression, FunctionCall, FunctionCallArg, FunctionName, Literal,
+        NodeLocation, Program,
+    },
+    error::Error,
+    location::Location,
+    options::ParseOptions,
+    parser::parse_from_str_with_options,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct Warning {
+    pub message: String,
+    pub expression: String, // the text of the problematic expression
+    pub location: Location,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} Expression: {} at line {}, column {}",
+            self.message,
+            self.expression,
+            self.location.line + 1,
+            self.location.column + 1
+        )
+    }
+}
+
+// the locations of the expressions are `SourceMap::expressions`.
+pub fn analyze(program: &Program, locations: &[NodeLocation]) -> Vec<Warning> {
+    let mut warnings = vec![];
+    for (expression, node) in program.expressions.iter().zip(locations) {
+        find_catastrophic_repetitions(expression, node, &mut warnings);
+    }
+    warnings
+}
+
+pub fn analyze_from_str(s: &str) -> Result<Vec<Warning>, Error> {
+    let (program, source_map) = parse_from_str_with_options(s, &ParseOptions::default())?;
+    Ok(analyze(&program, &source_map.expressions))
+}
+
+// Find the nested unbounded repetitions which may cause catastrophic
+// (exponential) backtracking, e.g. `('a'+)+`, `(char_word*, '-'?)*`.
+//
+// the inner repetition is ambiguous when it is followed by an expression
+// that can match empty within the outer repetition, and the chars
+// it repeats overlap the chars that can start the outer repetition,
+// because the text can be split between the inner and the outer
+// repetition in many ways.
+//
+// the node is the location of the expression, the sub-expressions that
+// have no locations take the location of their parent.
+fn find_catastrophic_repetitions(
+    expression: &Expression,
+    node: &NodeLocation,
+    warnings: &mut Vec<Warning>,
+) {
+    let mut children = node.children.iter();
+    let mut visit = |e: &Expression, warnings: &mut Vec<Warning>| {
+        find_catastrophic_repetitions(e, children.next().unwrap_or(node), warnings)
+    };
+
+    match expression {
+        Expression::Literal(_) | Expression::Identifier(_) => {}
+        Expression::Group(expressions) => {
+            for e in expressions {
+                visit(e, warnings);
+            }
+        }
+        Expression::OrList(alternatives) => {
+            for e in alternatives {
+                visit(e, warnings);
+            }
+        }
+        Expression::FunctionCall(function_call) => {
+            if is_unbounded_repetition(function_call) {
+                let body = &function_call.expression;
+                if has_ambiguous_repetition(body, &get_first_chars(body)) {
+                    warnings.push(Warning {
+                        message:
+                            "Nested unbounded repetitions may cause catastrophic backtracking."
+                                .to_owned(),
+                        expression: expression.to_string(),
+                        location: node.location,
+                    });
+                }
+            }
+
+            visit(&function_call.expression, warnings);
+            for arg in &function_call.args {
+                if let FunctionCallArg::Expression(e) = arg {
+                    visit(e, warnings);
+                }
+            }
+        }
+    }
+}
+
+// whether the body of the outer repetition ends with an inner unbounded
+// repetition (i.e. the rest can match empty) which overlaps the first
+// chars of the body, the alternatives are checked one by one, e.g.
+// `('a'+ || 'b')+`.
+fn has_ambiguous_repetition(body: &Expression, body_first_chars: &FirstChars) -> bool {
+    let mut items = vec![];
+    flatten_sequence(body, &mut items);
+
+    items.iter().enumerate().any(|(idx, item)| {
+        let rest_nullable = items[(idx + 1)..].iter().all(|e| is_nullable(e));
+        rest_nullable
+            && match item {
+                Expression::OrList(alternatives) => alternatives
+                    .iter()
+                    .any(|e| has_ambiguous_repetition(e, body_first_chars)),
+                _ => get_unbounded_repetition_body(item)
+                    .is_some_and(|inner| get_first_chars(inner).overlaps(body_first_chars)),
+            }
+    })
+}
+
+fn is_unbounded_repetition(function_call: &FunctionCall) -> bool {
+    matches!(
+        function_call.name,
+        FunctionName::OneOrMore
+            | FunctionName::ZeroOrMore
+            | FunctionName::AtLeast
+            | FunctionName::OneOrMoreLazy
+            | FunctionName::ZeroOrMoreLazy
+            | FunctionName::AtLeastLazy
+    )
+}
+
+// returns the repeated expression if the expression is an unbounded
+// repetition, the capture functions are transparent.
+fn get_unbounded_repetition_body(expression: &Expression) -> Option<&Expression> {
+    match expression {
+        Expression::FunctionCall(function_call) => {
+            if is_unbounded_repetition(function_call) {
+                Some(&function_call.expression)
+            } else if matches!(
+                function_call.name,
+                FunctionName::Name | FunctionName::Capture
+            ) {
+                get_unbounded_repetition_body(&function_call.expression)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// flatten the nested groups into a sequence of expressions,
+// e.g. `('a', ('b', 'c'))` -> `'a', 'b', 'c'`.
+fn flatten_sequence<'a>(expression: &'a Expression, items: &mut Vec<&'a Expression>) {
+    match expression {
+        Expression::Group(expressions) => {
+            for e in expressions {
+                flatten_sequence(e, items);
+            }
+        }
+        _ => items.push(expression),
+    }
+}
+
+// whether the expression can match an empty string.
+pub fn is_nullable(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(literal) => match literal {
+            Literal::String(s) => s.is_empty(),
+            Literal::Status(_) => true,
+            _ => false,
+        },
+        Expression::Identifier(_) => {
+            // the back-reference may refer to an empty capture
+            true
+        }
+        Expression::Group(expressions) => expressions.iter().all(is_nullable),
+        Expression::OrList(alternatives) => alternatives.iter().any(is_nullable),
+        Expression::FunctionCall(function_call) => match function_call.name {
+            FunctionName::Optional
+            | FunctionName::OptionalLazy
+            | FunctionName::ZeroOrMore
+            | FunctionName::ZeroOrMoreLazy => true,
+            FunctionName::Repeat
+            | FunctionName::RepeatLazy
+            | FunctionName::RepeatRange
+            | FunctionName::RepeatRangeLazy
+            | FunctionName::AtLeast
+            | FunctionName::AtLeastLazy => {
+                matches!(function_call.args.first(), Some(FunctionCallArg::Number(0)))
+                    || is_nullable(&function_call.expression)
+            }
+            FunctionName::OneOrMore
+            | FunctionName::OneOrMoreLazy
+            | FunctionName::IsBefore
+            | FunctionName::IsAfter
+            | FunctionName::IsNotBefore
+            | FunctionName::IsNotAfter
+            | FunctionName::Name
+            | FunctionName::Capture
+            | FunctionName::Normalized
+            | FunctionName::IgnoreCase
+            | FunctionName::Tag => is_nullable(&function_call.expression),
+            // the arguments follow the expression, see `emit_function_line`
+            FunctionName::Line => {
+                is_nullable(&function_call.expression)
+                    && function_call.args.iter().all(|arg| match arg {
+                        FunctionCallArg::Expression(e) => is_nullable(e),
+                        _ => true,
+                    })
+            }
+        },
+    }
+}
+
+// A conservative set of chars, `any` means the set may contain any char.
+#[derive(Debug, PartialEq, Default)]
+pub struct FirstChars {
+    pub any: bool,
+    pub ranges: Vec<(char, char)>, // the included ranges
+}
+
+impl FirstChars {
+    fn add_char(&mut self, c: char) {
+        self.ranges.push((c, c));
+    }
+
+    fn add_range(&mut self, start: char, end_included: char) {
+        self.ranges.push((start, end_included));
+    }
+
+    fn merge(&mut self, other: FirstChars) {
+        self.any |= other.any;
+        self.ranges.extend(other.ranges);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.any && self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.any || self.ranges.iter().any(|(s, e)| *s <= c && c <= *e)
+    }
+
+    pub fn overlaps(&self, other: &FirstChars) -> bool {
+        if self.is_empty() || other.is_empty() {
+            return false;
+        }
+
+        if self.any || other.any {
+            return true;
+        }
+
+        self.ranges
+            .iter()
+            .any(|(s1, e1)| other.ranges.iter().any(|(s2, e2)| s1 <= e2 && s2 <= e1))
+    }
+}
+
+// the chars that can start a match of the expression.
+pub fn get_first_chars(expression: &Expression) -> FirstChars {
+    let mut first_chars = FirstChars::default();
+
+    match expression {
+        Expression::Literal(literal) => match literal {
+            Literal::Char(c) => first_chars.add_char(*c),
+            Literal::String(s) => {
+                if let Some(c) = s.chars().next() {
+                    first_chars.add_char(c);
+                }
+            }
+            Literal::CharSet(charset) => add_charset(&mut first_chars, charset),
+            Literal::PresetCharSet(name) => add_preset_charset(&mut first_chars, name),
+            Literal::Status(_) => {}
+            Literal::NumberRange(_) => first_chars.add_range('0', '9'),
+            Literal::Special(_) | Literal::Byte(_) | Literal::ByteRange(_) => {
+                first_chars.any = true;
+            }
+        },
+        Expression::Identifier(_) => {
+            first_chars.any = true;
+        }
+        Expression::Group(expressions) => {
+            for e in expressions {
+                first_chars.merge(get_first_chars(e));
+                if !is_nullable(e) {
+                    break;
+                }
+            }
+        }
+        Expression::OrList(alternatives) => {
+            for e in alternatives {
+                first_chars.merge(get_first_chars(e));
+            }
+        }
+        Expression::FunctionCall(function_call) if function_call.name == FunctionName::Line => {
+            // the arguments follow the expression
+            let expressions = std::iter::once(function_call.expression.as_ref()).chain(
+                function_call.args.iter().filter_map(|arg| match arg {
+                    FunctionCallArg::Expression(e) => Some(e.as_ref()),
+                    _ => None,
+                }),
+            );
+            for e in expressions {
+                first_chars.merge(get_first_chars(e));
+                if !is_nullable(e) {
+                    break;
+                }
+            }
+        }
+        Expression::FunctionCall(function_call) => {
+            // the look-around assertions do not consume chars,
+            // so only the subject expression is counted.
+            first_chars.merge(get_first_chars(&function_call.expression))
+        }
+    }
+
+    first_chars
+}
+
+fn add_charset(first_chars: &mut FirstChars, charset: &CharSet) {
+    if charset.negative {
+        first_chars.any = true;
+        return;
+    }
+
+    for element in &charset.elements {
+        match element {
+            CharSetElement::Char(c) => first_chars.add_char(*c),
+            CharSetElement::CharRange(range) => {
+                first_chars.add_range(range.start, range.end_included)
+            }
+            CharSetElement::PresetCharSet(name) => add_preset_charset(first_chars, name),
+            CharSetElement::Status(_) => {}
+        }
+    }
+}
+
+fn add_preset_charset(first_chars: &mut FirstChars, name: &str) {
+    match name {
+        "char_word" => {
+            first_chars.add_range('a', 'z');
+            first_chars.add_range('A', 'Z');
+            first_chars.add_range('0', '9');
+            first_chars.add_char('_');
+        }
+        "char_digit" => first_chars.add_range('0', '9'),
+        "char_space" | "char_space_ascii" => {
+            first_chars.add_char(' ');
+            first_chars.add_char('\t');
+            first_chars.add_char('\r');
+            first_chars.add_char('\n');
+            first_chars.add_char('\x0c');
+        }
+        _ => {
+            // the negative preset charsets
+            first_chars.any = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::location::Location;
+
+    use super::analyze_from_str;
+
+    fn get_warning_expressions(s: &str) -> Vec<String> {
+        analyze_from_str(s)
+            .unwrap()
+            .into_iter()
+            .map(|w| w.expression)
+            .collect()
+    }
+
+    #[test]
+    fn test_catastrophic_repetitions() {
+        assert_eq!(
+            get_warning_expressions(r#"('a'+)+"#),
+            vec!["one_or_more((one_or_more('a')))"]
+        );
+
+        assert_eq!(
+            get_warning_expressions(r#"'x', ('a'*)*"#),
+            vec!["zero_or_more((zero_or_more('a')))"]
+        );
+
+        // the rest of the outer repetition can match empty
+        assert_eq!(
+            get_warning_expressions(r#"(char_word+, '-'?)+"#),
+            vec!["one_or_more((one_or_more(char_word), optional('-')))"]
+        );
+
+        // the alternatives
+        assert_eq!(
+            get_warning_expressions(r#"('a'+ || 'b')+"#),
+            vec!["one_or_more((one_or_more('a') || 'b'))"]
+        );
+        assert_eq!(
+            get_warning_expressions(r#"(['a'..'f']+ || char_digit)+"#),
+            vec!["one_or_more((one_or_more(['a'..'f']) || char_digit))"]
+        );
+
+        // overlapping charsets
+        assert_eq!(
+            get_warning_expressions(r#"(['a'..'f'], char_word+)*"#),
+            vec!["zero_or_more((['a'..'f'], one_or_more(char_word)))"]
+        );
+
+        // the form feed is a white space
+        assert_eq!(
+            get_warning_expressions(r#"('\u{c}', char_space+)*"#).len(),
+            1
+        );
+
+        // nested deeply
+        assert_eq!(
+            get_warning_expressions(r#"'a', ('b', name(('c'+)*, inner)), end"#),
+            vec!["zero_or_more((one_or_more('c')))"]
+        );
+    }
+
+    #[test]
+    fn test_safe_repetitions() {
+        // separated by a required char
+        assert!(get_warning_expressions(r#"('a'+, 'b')+"#).is_empty());
+
+        // the inner chars do not overlap
+        assert!(get_warning_expressions(r#"('a', 'b'+)+"#).is_empty());
+        assert!(get_warning_expressions(r#"('a', 'b'+ || 'c')+"#).is_empty());
+
+        // bounded repetition
+        assert!(get_warning_expressions(r#"('a'+){3}"#).is_empty());
+        assert!(get_warning_expressions(r#"('a'{2})+"#).is_empty());
+
+        // not nested
+        assert!(get_warning_expressions(r#"'a'+, 'a'+"#).is_empty());
+    }
+
+    #[test]
+    fn test_warning_location() {
+        let warnings = analyze_from_str("'a',\n  ('b', ('c'+)*)").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].location, Location::new_range(0, 13, 1, 8, 7));
+        assert_eq!(
+            warnings[0].to_string(),
+            "Nested unbounded repetitions may cause catastrophic backtracking. \
+             Expression: zero_or_more((one_or_more('c'))) at line 2, column 9"
+        );
+    }
+}
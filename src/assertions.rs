@@ -0,0 +1,227 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the helpers for unit-testing the patterns, they are available with
+// the feature `testing`, e.g.
+//
+// ```rust
+// #[test]
+// fn test_date() {
+//     assert_matches("date_iso8601()", "on 2024-02-29.", Some(3..13));
+//     assert_captures(
+//         "date_iso8601()",
+//         "on 2024-02-29.",
+//         &[("year", "2024"), ("month", "02"), ("day", "29")],
+//     );
+// }
+// ```
+//
+// the failure messages show the text with the expected and the actual
+// ranges marked, e.g.
+//
+// ```text
+// The match does not equal the expected range.
+// expected:
+// | on 2024-02-29.
+// |    ^^^^^^^^^^ 3..13
+// actual:
+// | on 2024-02-29.
+// |    ^^^^^^^ 3..10
+// ```
+
+use std::ops::Range;
+
+use crate::{errorprinter::format_snippet, process::Process};
+
+// check the first match of the pattern in the text, `None` means
+// the pattern should not match.
+#[track_caller]
+pub fn assert_matches(pattern: &str, text: &str, expected: Option<Range<usize>>) {
+    if let Err(message) = check_matches(pattern, text, expected) {
+        panic!("{}", message);
+    }
+}
+
+// check the named capture groups of the first match of the pattern
+// in the text.
+#[track_caller]
+pub fn assert_captures(pattern: &str, text: &str, expected: &[(&str, &str)]) {
+    if let Err(message) = check_captures(pattern, text, expected) {
+        panic!("{}", message);
+    }
+}
+
+// the non-panicking version of `assert_matches`, returns the
+// failure message.
+pub fn check_matches(
+    pattern: &str,
+    text: &str,
+    expected: Option<Range<usize>>,
+) -> Result<(), String> {
+    let process = new_process(pattern)?;
+    let mut instance = process.new_instance(text);
+    let actual = instance.exec(0).map(|m| m.start..m.end);
+
+    if actual == expected {
+        return Ok(());
+    }
+
+    Err(format!(
+        "The match does not equal the expected range.\nexpected:\n{}\nactual:\n{}",
+        format_range(text, &expected),
+        format_range(text, &actual)
+    ))
+}
+
+// the non-panicking version of `assert_captures`, returns the
+// failure message.
+pub fn check_captures(pattern: &str, text: &str, expected: &[(&str, &str)]) -> Result<(), String> {
+    let process = new_process(pattern)?;
+    let mut instance = process.new_instance(text);
+
+    let Some(match_ranges) = instance.exec_with_captures(0) else {
+        return Err(format!(
+            "The pattern does not match the text.\n{}",
+            format_snippet(text, 0, 0, "no match")
+        ));
+    };
+
+    let mut messages = vec![];
+    for (name, expected_value) in expected {
+        let Some(index) = process.capture_group_index(name) else {
+            messages.push(format!("The capture group \"{}\" does not exist.", name));
+            continue;
+        };
+
+        let actual = match_ranges[index].as_ref().map(|m| m.start..m.end);
+        let actual_value = actual.as_ref().map(|range| {
+            text.chars()
+                .skip(range.start)
+                .take(range.len())
+                .collect::<String>()
+        });
+
+        if actual_value.as_deref() == Some(*expected_value) {
+            continue;
+        }
+
+        messages.push(format!(
+            "The capture group \"{}\" does not match.\nexpected: {:?}\nactual: {}\n{}",
+            name,
+            expected_value,
+            match &actual_value {
+                Some(value) => format!("{:?}", value),
+                None => "(not captured)".to_owned(),
+            },
+            format_range(text, &actual)
+        ));
+    }
+
+    if messages.is_empty() {
+        Ok(())
+    } else {
+        Err(messages.join("\n\n"))
+    }
+}
+
+fn new_process(pattern: &str) -> Result<Process, String> {
+    Process::new(pattern)
+        .map_err(|e| format!("Failed to compile the pattern.\n{}", e.with_source(pattern)))
+}
+
+fn format_range(text: &str, range: &Option<Range<usize>>) -> String {
+    match range {
+        Some(range) => format_snippet(
+            text,
+            range.start,
+            range.len(),
+            &format!("{}..{}", range.start, range.end),
+        ),
+        None => "(no match)".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_str_eq;
+
+    use super::{assert_captures, assert_matches, check_captures, check_matches};
+
+    #[test]
+    fn test_assert_matches() {
+        assert_matches(r#"'b'+"#, "abbc", Some(1..3));
+        assert_matches(r#"'x'"#, "abbc", None);
+
+        assert_str_eq!(
+            check_matches(r#"'b'"#, "abbc", Some(1..3)).unwrap_err(),
+            "The match does not equal the expected range.
+expected:
+| abbc
+|  ^^ 1..3
+actual:
+| abbc
+|  ^ 1..2"
+        );
+
+        assert_str_eq!(
+            check_matches(r#"'x'"#, "abbc", Some(0..1)).unwrap_err(),
+            "The match does not equal the expected range.
+expected:
+| abbc
+| ^ 0..1
+actual:
+(no match)"
+        );
+
+        // err: invalid pattern
+        assert!(check_matches(r#"'a"#, "abc", None)
+            .unwrap_err()
+            .starts_with("Failed to compile the pattern."));
+    }
+
+    #[test]
+    fn test_assert_captures() {
+        assert_captures(
+            r#"name(char_digit+, major), '.', name(char_digit+, minor)"#,
+            "v1.23",
+            &[("major", "1"), ("minor", "23")],
+        );
+
+        assert_str_eq!(
+            check_captures(
+                r#"name(char_digit+, major), '.', name(char_digit+, minor), name('-'?, pre)"#,
+                "v1.23",
+                &[
+                    ("major", "2"),
+                    ("minor", "23"),
+                    ("pre", "-"),
+                    ("patch", "0")
+                ],
+            )
+            .unwrap_err(),
+            r#"The capture group "major" does not match.
+expected: "2"
+actual: "1"
+| v1.23
+|  ^ 1..2
+
+The capture group "pre" does not match.
+expected: "-"
+actual: ""
+| v1.23
+|      ^____ 5..5
+
+The capture group "patch" does not exist."#
+        );
+
+        assert_str_eq!(
+            check_captures(r#"name('x', x)"#, "abc", &[("x", "x")]).unwrap_err(),
+            "The pattern does not match the text.
+| abc
+| ^____ no match"
+        );
+    }
+}
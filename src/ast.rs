@@ -6,9 +6,446 @@
 
 use std::fmt::Display;
 
+use crate::{
+    commentprinter::ProgramWithComments,
+    error::Error,
+    location::Location,
+    options::CompileOptions,
+    process::Process,
+    references::{check_back_references, find_back_references, BackReference},
+    simplifier::simplify_expressions,
+    token::format_identifier,
+};
+
 #[derive(Debug, PartialEq)]
 pub struct Program {
     pub expressions: Vec<Expression>,
+
+    // the test cases embedded in the pattern, e.g.
+    // `expect_match("user@example.com")`, see `run_self_tests`.
+    pub tests: Vec<SelfTest>,
+
+    // the options set by the directive `options(...)` at the top of
+    // the pattern, e.g. `options(ignore_case, multiline)`, they are
+    // applied in addition to the ones passed to the compiler.
+    pub options: PatternOptions,
+
+    // the version declared by the directive `syntax_version(...)` at
+    // the top of the pattern, the pattern is parsed with the latest
+    // syntax if it is not declared, see `SyntaxVersion`.
+    pub syntax_version: Option<SyntaxVersion>,
+}
+
+// the versions of the ANREG syntax, a pattern can declare the version
+// it is written in, e.g. `syntax_version(1)`, so that the later versions
+// can add keywords or change the meaning of the syntax without breaking
+// the existing patterns, i.e. the parser accepts only the syntax of the
+// declared version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SyntaxVersion {
+    V1,
+}
+
+impl SyntaxVersion {
+    pub const LATEST: SyntaxVersion = SyntaxVersion::V1;
+
+    pub fn from_number(number: u32) -> Option<SyntaxVersion> {
+        match number {
+            1 => Some(SyntaxVersion::V1),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        match self {
+            SyntaxVersion::V1 => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PatternOptions {
+    pub ignore_case: bool,
+    pub multiline: bool,
+    pub dot_all: bool,
+    pub crlf: bool,
+    pub unicode: bool,
+    pub grapheme: bool,
+}
+
+impl PatternOptions {
+    // set the option by its name, returns false if the name is unknown.
+    pub fn set(&mut self, name: &str) -> bool {
+        let flag = match name {
+            "ignore_case" => &mut self.ignore_case,
+            "multiline" => &mut self.multiline,
+            "dot_all" => &mut self.dot_all,
+            "crlf" => &mut self.crlf,
+            "unicode" => &mut self.unicode,
+            "grapheme" => &mut self.grapheme,
+            _ => return false,
+        };
+        *flag = true;
+        true
+    }
+
+    // the names of the options that are set.
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.ignore_case, "ignore_case"),
+            (self.multiline, "multiline"),
+            (self.dot_all, "dot_all"),
+            (self.crlf, "crlf"),
+            (self.unicode, "unicode"),
+            (self.grapheme, "grapheme"),
+        ]
+        .into_iter()
+        .filter_map(|(is_set, name)| is_set.then_some(name))
+        .collect()
+    }
+
+    fn union(self, other: PatternOptions) -> PatternOptions {
+        PatternOptions {
+            ignore_case: self.ignore_case || other.ignore_case,
+            multiline: self.multiline || other.multiline,
+            dot_all: self.dot_all || other.dot_all,
+            crlf: self.crlf || other.crlf,
+            unicode: self.unicode || other.unicode,
+            grapheme: self.grapheme || other.grapheme,
+        }
+    }
+
+    // the compile options with these options set additionally.
+    pub fn apply(&self, options: &CompileOptions) -> CompileOptions {
+        CompileOptions {
+            ignore_case: options.ignore_case || self.ignore_case,
+            multiline: options.multiline || self.multiline,
+            dot_all: options.dot_all || self.dot_all,
+            crlf: options.crlf || self.crlf,
+            unicode: options.unicode || self.unicode,
+            grapheme: options.grapheme || self.grapheme,
+            ..options.clone()
+        }
+    }
+}
+
+// a test case of the pattern, i.e. the directive `expect_match(text)`
+// or `expect_no_match(text)` at the top level of the pattern.
+#[derive(Debug, PartialEq)]
+pub struct SelfTest {
+    pub text: String,
+    pub should_match: bool,
+}
+
+impl Program {
+    // a program that matches the text literally, the metacharacters
+    // of ANREG in the text have no special meaning, so it is safe to
+    // build patterns from untrusted input.
+    pub fn literal(text: &str) -> Program {
+        Program {
+            expressions: vec![Expression::Literal(Literal::String(text.to_owned()))],
+            tests: vec![],
+            options: PatternOptions::default(),
+            syntax_version: None,
+        }
+    }
+
+    // combine programs into one that matches them in sequence.
+    //
+    // the options of the programs are merged, the syntax version is the
+    // later one, and the test cases are dropped since they do not apply
+    // to the combined program.
+    pub fn concat(a: Program, b: Program) -> Program {
+        let options = a.options.union(b.options);
+        let syntax_version = a.syntax_version.max(b.syntax_version);
+        let mut expressions = a.expressions;
+        expressions.extend(b.expressions);
+        Program {
+            expressions,
+            tests: vec![],
+            options,
+            syntax_version,
+        }
+    }
+
+    // combine programs into one that matches any of them, the
+    // programs are tried in order.
+    //
+    // the capture indices are assigned when the combined program
+    // is compiled, so the captures of each program are numbered
+    // in the order of the programs.
+    pub fn alternate(programs: Vec<Program>) -> Program {
+        let options = programs
+            .iter()
+            .fold(PatternOptions::default(), |options, program| {
+                options.union(program.options)
+            });
+        let syntax_version = programs.iter().filter_map(|p| p.syntax_version).max();
+
+        let mut alternatives: Vec<Expression> =
+            programs.into_iter().map(Program::into_expression).collect();
+        let expressions = if alternatives.len() > 1 {
+            vec![Expression::OrList(alternatives)]
+        } else {
+            alternatives.pop().into_iter().collect()
+        };
+
+        Program {
+            expressions,
+            tests: vec![],
+            options,
+            syntax_version,
+        }
+    }
+
+    // a program with only one expression is unwrapped, otherwise
+    // the expressions are combined into a group.
+    fn into_expression(mut self) -> Expression {
+        if self.expressions.len() == 1 {
+            self.expressions.pop().unwrap()
+        } else {
+            Expression::Group(self.expressions)
+        }
+    }
+
+    // wrap the program in a named capture group.
+    pub fn capture(program: Program, name: &str) -> Program {
+        let options = program.options;
+        let syntax_version = program.syntax_version;
+        let function_call = FunctionCall {
+            name: FunctionName::Name,
+            expression: Box::new(program.into_expression()),
+            args: vec![FunctionCallArg::Identifier(name.to_owned())],
+        };
+
+        Program {
+            expressions: vec![Expression::FunctionCall(Box::new(function_call))],
+            tests: vec![],
+            options,
+            syntax_version,
+        }
+    }
+
+    // wrap the program in a tag, the tag of the match tells which
+    // program produced it when the tagged programs are combined by
+    // `alternate`, e.g. the routes of a web server:
+    //
+    // ```
+    // let program = Program::alternate(vec![
+    //     Program::tag(Program::literal("/users"), "users"),
+    //     Program::tag(Program::literal("/posts"), "posts"),
+    // ]);
+    // ```
+    pub fn tag(program: Program, name: &str) -> Program {
+        let options = program.options;
+        let syntax_version = program.syntax_version;
+        let function_call = FunctionCall {
+            name: FunctionName::Tag,
+            expression: Box::new(program.into_expression()),
+            args: vec![FunctionCallArg::Expression(Box::new(Expression::Literal(
+                Literal::String(name.to_owned()),
+            )))],
+        };
+
+        Program {
+            expressions: vec![Expression::FunctionCall(Box::new(function_call))],
+            tests: vec![],
+            options,
+            syntax_version,
+        }
+    }
+
+    // add the prefix to the names of the capture groups, e.g. the
+    // program `name(char_digit+, year)` with the prefix "start_" becomes
+    // `name(char_digit+, start_year)`, so that a program can be embedded
+    // in a bigger one without clashing group names.
+    pub fn prefix_group_names(self, prefix: &str) -> Program {
+        self.rename_groups(|name| format!("{}{}", prefix, name))
+    }
+
+    // rename the capture groups with the function, the references
+    // to the groups (i.e. the identifiers in the expressions) are
+    // renamed consistently.
+    pub fn rename_groups(mut self, rename: impl Fn(&str) -> String) -> Program {
+        for expression in &mut self.expressions {
+            rename_groups(expression, &rename);
+        }
+        self
+    }
+
+    // simplify the expressions, e.g. flatten the nested groups, merge
+    // the adjacent chars and strings and canonicalize the repetitions,
+    // see the module `simplifier` for the details.
+    //
+    // the simplified program matches the same texts with the same
+    // captures, it is usually compiled into fewer states and prints
+    // cleaner, e.g. `('a', ('b', 'c'){1,1})?` is simplified to
+    // `optional("abc")`.
+    pub fn simplify(self) -> Program {
+        Program {
+            expressions: simplify_expressions(self.expressions),
+            ..self
+        }
+    }
+
+    // print the program along with the comments of the source map,
+    // which are collected by `parse_from_str_with_options` when
+    // `ParseOptions::keep_comments` is set, e.g.
+    //
+    // ```
+    // let (program, source_map) = parse_from_str_with_options(s, &options)?;
+    // let formatted = program.with_comments(&source_map).to_string();
+    // ```
+    pub fn with_comments<'a>(&'a self, source_map: &'a SourceMap) -> ProgramWithComments<'a> {
+        ProgramWithComments {
+            program: self,
+            source_map,
+        }
+    }
+
+    // the back-references of the program, i.e. the identifiers that
+    // refer to the capture groups, in the order of the function form of
+    // the pattern, see the module `references`.
+    pub fn back_references(&self) -> Vec<BackReference> {
+        find_back_references(self, None)
+            .into_iter()
+            .map(|(reference, _)| reference)
+            .collect()
+    }
+
+    // check that every back-reference refers to a capture group defined
+    // before it, e.g. it is useful for the programs that are built or
+    // combined by code, the error lists all unknown and forward
+    // references.
+    //
+    // the same check is made by the compiler, and the error reports the
+    // locations if the program is compiled from the source.
+    pub fn validate(&self) -> Result<(), Error> {
+        check_back_references(self, None)
+    }
+
+    // compile the program and run its test cases, returns the
+    // failed ones, e.g. the texts of `expect_match` that do not
+    // match the pattern.
+    //
+    // a test case passes if the pattern matches (or does not match)
+    // any part of the text, use `start` and `end` in the pattern
+    // to match the whole text.
+    pub fn run_self_tests(&self, options: &CompileOptions) -> Result<Vec<&SelfTest>, Error> {
+        let process = Process::from_program(self, options)?;
+
+        let failed_tests = self
+            .tests
+            .iter()
+            .filter(|test| {
+                let mut instance = process.new_instance(&test.text);
+                instance.exec(0).is_some() != test.should_match
+            })
+            .collect();
+
+        Ok(failed_tests)
+    }
+}
+
+fn rename_groups(expression: &mut Expression, rename: &dyn Fn(&str) -> String) {
+    match expression {
+        Expression::Identifier(name) => *name = rename(name),
+        Expression::Group(expressions) => {
+            for expression in expressions {
+                rename_groups(expression, rename);
+            }
+        }
+        Expression::OrList(alternatives) => {
+            for alternative in alternatives {
+                rename_groups(alternative, rename);
+            }
+        }
+        Expression::FunctionCall(function_call) => {
+            rename_groups(&mut function_call.expression, rename);
+
+            // the identifier is the name of the group, or the back-reference
+            // of a look-around, e.g. `'a'.is_after(x)`.
+            for arg in &mut function_call.args {
+                match arg {
+                    FunctionCallArg::Identifier(name) => *name = rename(name),
+                    FunctionCallArg::Expression(expression) => rename_groups(expression, rename),
+                    _ => {}
+                }
+            }
+        }
+        Expression::Literal(_) => {}
+    }
+}
+
+// the locations of a parsed program, see `parse_from_str_with_locations`.
+#[derive(Debug, PartialEq)]
+pub struct SourceMap {
+    // the locations of the expressions of the program, in the same order
+    pub expressions: Vec<NodeLocation>,
+    pub definitions: Vec<DefinitionLocation>,
+
+    // the comments that are not attached to any expression or
+    // definition, e.g. the comments of an empty program.
+    pub comments: Vec<SourceComment>,
+}
+
+// the location of an expression and its children.
+//
+// the children are in the order of the expression's sub-expressions:
+// - group: the elements.
+// - function call: the expression, then the arguments that are
+//   expressions, e.g. the `'b'` of `'a'.is_before('b')`.
+// - logic or: the alternatives.
+// - literal and identifier: none.
+//
+// the expressions that come from a `define` have the locations
+// inside the definition.
+#[derive(Debug, PartialEq)]
+pub struct NodeLocation {
+    pub location: Location,
+    pub children: Vec<NodeLocation>,
+
+    // the comments before the expression, and the ones that follow
+    // the expression on the same line, they are collected only if
+    // `ParseOptions::keep_comments` is set.
+    pub leading_comments: Vec<SourceComment>,
+    pub trailing_comments: Vec<SourceComment>,
+}
+
+impl NodeLocation {
+    pub fn new(location: Location, children: Vec<NodeLocation>) -> Self {
+        NodeLocation {
+            location,
+            children,
+            leading_comments: vec![],
+            trailing_comments: vec![],
+        }
+    }
+}
+
+// the location of a `define` statement and the identifiers that
+// refer to it.
+#[derive(Debug, PartialEq)]
+pub struct DefinitionLocation {
+    pub name: String,
+    pub name_location: Location,
+    pub location: Location, // the whole statement
+    pub references: Vec<Location>,
+
+    // the comments before the statement, i.e. the documentation
+    pub doc_comments: Vec<SourceComment>,
+
+    // the comments inside the statement and the ones that follow
+    // the statement on the same line
+    pub comments: Vec<SourceComment>,
+}
+
+// a comment in the source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceComment {
+    pub text: String, // the content without `//` or `/*` and `*/`
+    pub block: bool,  // whether it is a block comment `/*...*/`
+    pub location: Location,
 }
 
 #[derive(Debug, PartialEq)]
@@ -31,8 +468,12 @@ pub enum Expression {
     /**
      * Disjunction
      * https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Regular_expressions/Disjunction
+     *
+     * the alternatives are tried in order, e.g. `'a' || 'b' || 'c'` has
+     * three alternatives, and the list is flat, so the thousands of
+     * alternatives (e.g. in a generated pattern) do not nest deeply.
      */
-    Or(Box<Expression>, Box<Expression>),
+    OrList(Vec<Expression>),
 }
 
 #[derive(Debug, PartialEq)]
@@ -56,6 +497,26 @@ pub enum Literal {
     Status(String),
     CharSet(CharSet),
     PresetCharSet(String),
+
+    /**
+     * `char_any` matches any char except the new line char `'\n'`,
+     * `char_any_including_newline` matches any char, and `grapheme_any`
+     * matches an extended grapheme cluster except the new line.
+     */
+    Special(String),
+
+    /**
+     * `byte(0x..)` and `byte_range(0x..0x..)` match raw bytes,
+     * they only match in bytes mode.
+     */
+    Byte(u8),
+    ByteRange(ByteRange),
+
+    /**
+     * `number_range(0, 255)` matches the decimal numbers within
+     * the range, it is expanded to an alternation of digits by the compiler.
+     */
+    NumberRange(NumberRange),
 }
 
 #[derive(Debug, PartialEq)]
@@ -79,6 +540,18 @@ pub struct CharRange {
 }
 
 #[derive(Debug, PartialEq)]
+pub struct ByteRange {
+    pub start: u8,
+    pub end_included: u8,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NumberRange {
+    pub start: u32,
+    pub end_included: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FunctionName {
     // Greedy quantifier
     Optional,
@@ -105,6 +578,18 @@ pub enum FunctionName {
     // Capture
     Name,
     Capture,
+
+    // String matching by canonical equivalence
+    Normalized,
+
+    // String matching ignoring case, e.g. `ignore_case("select")`
+    IgnoreCase,
+
+    // the whole line, i.e. the expression between `line_start` and `line_end`
+    Line,
+
+    // the tag of the matched alternative, e.g. `tag("GET", "read")`
+    Tag,
 }
 
 impl Display for FunctionName {
@@ -128,20 +613,46 @@ impl Display for FunctionName {
             FunctionName::IsNotAfter => f.write_str("is_not_after"),
             FunctionName::Name => f.write_str("name"),
             FunctionName::Capture => f.write_str("capture"),
+            FunctionName::Normalized => f.write_str("normalized"),
+            FunctionName::IgnoreCase => f.write_str("ignore_case"),
+            FunctionName::Line => f.write_str("line"),
+            FunctionName::Tag => f.write_str("tag"),
+        }
+    }
+}
+
+impl CharRange {
+    // check that the start is not greater than the end, e.g. `'z'..'a'`
+    // is invalid, the parser checks the ranges in the source, and the
+    // compiler checks the ones of the programs that are constructed
+    // directly.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.start > self.end_included {
+            Err(Error::Message(format!(
+                "The start of char range {} is greater than the end.",
+                self
+            )))
+        } else {
+            Ok(())
         }
     }
 }
 
 impl Display for CharRange {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "'{}'..'{}'", self.start, self.end_included)
+        write!(
+            f,
+            "{}..{}",
+            quote_char(self.start),
+            quote_char(self.end_included)
+        )
     }
 }
 
 impl Display for CharSetElement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CharSetElement::Char(c) => write!(f, "'{}'", c),
+            CharSetElement::Char(c) => f.write_str(&quote_char(*c)),
             CharSetElement::CharRange(c) => write!(f, "{}", c),
             CharSetElement::PresetCharSet(p) => f.write_str(p),
             CharSetElement::Status(s) => f.write_str(s),
@@ -163,11 +674,21 @@ impl Display for CharSet {
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Literal::Char(c) => write!(f, "'{}'", c),
-            Literal::String(s) => write!(f, "\"{}\"", s),
+            Literal::Char(c) => f.write_str(&quote_char(*c)),
+            Literal::String(s) => f.write_str(&quote(s)),
             Literal::CharSet(c) => write!(f, "{}", c),
             Literal::PresetCharSet(p) => f.write_str(p),
+            Literal::Special(s) => f.write_str(s),
+            Literal::Byte(b) => write!(f, "byte(0x{:02x})", b),
+            Literal::ByteRange(ByteRange {
+                start,
+                end_included,
+            }) => write!(f, "byte_range(0x{:02x}..0x{:02x})", start, end_included),
             Literal::Status(s) => f.write_str(s),
+            Literal::NumberRange(NumberRange {
+                start,
+                end_included,
+            }) => write!(f, "number_range({}, {})", start, end_included),
         }
     }
 }
@@ -176,7 +697,7 @@ impl Display for FunctionCallArg {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             FunctionCallArg::Number(n) => write!(f, "{}", n),
-            FunctionCallArg::Identifier(i) => write!(f, "{}", i),
+            FunctionCallArg::Identifier(i) => f.write_str(&format_identifier(i)),
             FunctionCallArg::Expression(e) => write!(f, "{}", e),
         }
     }
@@ -197,13 +718,16 @@ impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expression::Literal(l) => write!(f, "{}", l),
-            Expression::Identifier(id) => f.write_str(id),
+            Expression::Identifier(id) => f.write_str(&format_identifier(id)),
             Expression::Group(g) => {
                 let s: Vec<String> = g.iter().map(|e| e.to_string()).collect();
                 write!(f, "({})", s.join(", "))
             }
             Expression::FunctionCall(fc) => write!(f, "{}", fc),
-            Expression::Or(left, right) => write!(f, "{} || {}", left, right),
+            Expression::OrList(alternatives) => {
+                let s: Vec<String> = alternatives.iter().map(|e| e.to_string()).collect();
+                f.write_str(&s.join(" || "))
+            }
         }
     }
 }
@@ -211,6 +735,15 @@ impl Display for Expression {
 impl Display for Program {
     // for debug
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(version) = self.syntax_version {
+            writeln!(f, "syntax_version({})", version.number())?;
+        }
+
+        let option_names = self.options.names();
+        if !option_names.is_empty() {
+            writeln!(f, "options({})", option_names.join(", "))?;
+        }
+
         let mut exp_strings: Vec<String> = vec![];
         for (idx, expression) in self.expressions.iter().enumerate() {
             match expression {
@@ -232,9 +765,61 @@ impl Display for Program {
 
         if !exp_strings.is_empty() {
             exp_strings.pop(); // remove the last ',' or '\n'
-            write!(f, "{}", exp_strings.join(""))
+        }
+
+        for test in &self.tests {
+            if !exp_strings.is_empty() {
+                exp_strings.push("\n".to_owned());
+            }
+            exp_strings.push(test.to_string());
+        }
+
+        write!(f, "{}", exp_strings.join(""))
+    }
+}
+
+// quote the text as an ANREG string literal, e.g. `a"b` is quoted
+// as `"a\"b"`, so that it can be spliced into a pattern source safely.
+impl Display for SelfTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = if self.should_match {
+            "expect_match"
         } else {
-            f.write_str("")
+            "expect_no_match"
+        };
+        write!(f, "{}({})", name, quote(&self.text))
+    }
+}
+
+pub fn quote(text: &str) -> String {
+    let mut s = String::from("\"");
+    for c in text.chars() {
+        push_escaped_char(&mut s, c, '"');
+    }
+    s.push('"');
+    s
+}
+
+// quote the char as an ANREG char literal, e.g. `'` is quoted as `'\''`.
+fn quote_char(c: char) -> String {
+    let mut s = String::from("'");
+    push_escaped_char(&mut s, c, '\'');
+    s.push('\'');
+    s
+}
+
+fn push_escaped_char(s: &mut String, c: char, quotation_mark: char) {
+    match c {
+        '\\' => s.push_str("\\\\"),
+        '\t' => s.push_str("\\t"),
+        '\r' => s.push_str("\\r"),
+        '\n' => s.push_str("\\n"),
+        '\0' => s.push_str("\\0"),
+        c if c == quotation_mark => {
+            s.push('\\');
+            s.push(c);
         }
+        c if c.is_control() => s.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        c => s.push(c),
     }
 }
@@ -0,0 +1,72 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use crate::process::{Instance, MatchRange};
+
+// the iterator of the first match of each text, see `Process::match_all`.
+//
+// one instance is reset to each text, so the buffers of the frames and
+// states are allocated once for all texts.
+pub struct MatchAll<'a, I> {
+    instance: Instance<'a>,
+    texts: I,
+}
+
+impl<'a, I> MatchAll<'a, I> {
+    pub(crate) fn new(instance: Instance<'a>, texts: I) -> Self {
+        MatchAll { instance, texts }
+    }
+}
+
+impl<'t, I> Iterator for MatchAll<'_, I>
+where
+    I: Iterator<Item = &'t str>,
+{
+    type Item = Option<MatchRange>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = self.texts.next()?;
+        self.instance.reset(text);
+        Some(self.instance.exec(0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.texts.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::process::{MatchRange, Process};
+
+    #[test]
+    fn test_match_all() {
+        let process = Process::new(r#"start, char_digit+, end"#).unwrap();
+        let column = "12\nabc\n\n345";
+        let results: Vec<Option<MatchRange>> = process.match_all(column.lines()).collect();
+        assert_eq!(
+            results,
+            vec![
+                Some(MatchRange::new(0, 2)),
+                None,
+                None,
+                Some(MatchRange::new(0, 3))
+            ]
+        );
+
+        let texts = ["1", "x", "2"];
+        assert_eq!(
+            process
+                .match_all(texts.iter().copied())
+                .filter(Option::is_some)
+                .count(),
+            2
+        );
+        assert_eq!(process.match_all(std::iter::empty()).count(), 0);
+    }
+}
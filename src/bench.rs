@@ -0,0 +1,169 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the standard benchmark scenarios over the bundled corpora, so that the
+// performance work on the compiler and the matcher can be measured
+// consistently, e.g.
+//
+// ```
+// for scenario in scenarios() {
+//     let process = scenario.process()?;
+//     let text = scenario.corpus.text();
+//     let count = scenario.run(&process, &text);
+// }
+// ```
+//
+// the benchmarks are run by `cargo bench --features bench`.
+
+use crate::{error::Error, options::CompileOptions, process::Process};
+
+// the minimum size (in bytes) of the text of a corpus, the bundled
+// text is repeated to reach it.
+pub const CORPUS_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corpus {
+    // the English prose, mostly ASCII
+    English,
+
+    // the lines of a server log, with timestamps, levels and IP addresses
+    Log,
+
+    // the texts in Chinese, Greek, Russian, Japanese, Korean and
+    // the Latin letters with diacritics
+    Unicode,
+}
+
+impl Corpus {
+    // the bundled text of the corpus.
+    pub fn source(&self) -> &'static str {
+        match self {
+            Corpus::English => include_str!("../benches/corpora/english.txt"),
+            Corpus::Log => include_str!("../benches/corpora/log.txt"),
+            Corpus::Unicode => include_str!("../benches/corpora/unicode.txt"),
+        }
+    }
+
+    // the bundled text repeated to `CORPUS_SIZE` bytes at least.
+    pub fn text(&self) -> String {
+        let source = self.source();
+        source.repeat(CORPUS_SIZE.div_ceil(source.len()))
+    }
+}
+
+pub struct Scenario {
+    pub name: &'static str,
+    pub pattern: &'static str,
+    pub options: CompileOptions,
+    pub corpus: Corpus,
+}
+
+impl Scenario {
+    fn new(name: &'static str, pattern: &'static str, corpus: Corpus) -> Self {
+        Scenario {
+            name,
+            pattern,
+            options: CompileOptions::default(),
+            corpus,
+        }
+    }
+
+    fn with_options(self, options: CompileOptions) -> Self {
+        Scenario { options, ..self }
+    }
+
+    pub fn process(&self) -> Result<Process, Error> {
+        Process::new_with_options(self.pattern, &self.options)
+    }
+
+    // find all matches in the text, returns the number of matches.
+    pub fn run(&self, process: &Process, text: &str) -> usize {
+        let mut instance = process.new_instance(text);
+        let mut count = 0;
+        while instance.exec_next().is_some() {
+            count += 1;
+        }
+        count
+    }
+}
+
+pub fn scenarios() -> Vec<Scenario> {
+    vec![
+        // literal scan
+        Scenario::new("literal", r#""lighthouse""#, Corpus::English),
+        Scenario::new("literal_rare", r#""estuary""#, Corpus::English),
+        Scenario::new("literal_ignore_case", r#""the""#, Corpus::English).with_options(
+            CompileOptions {
+                ignore_case: true,
+                ..CompileOptions::default()
+            },
+        ),
+        // alternation-heavy
+        Scenario::new(
+            "alternation_words",
+            r#""apples" || "pears" || "cheese" || "bread" || "honey" || "candles" || "jam" || "fish""#,
+            Corpus::English,
+        ),
+        Scenario::new(
+            "alternation_levels",
+            r#"("INFO" || "DEBUG" || "WARN" || "ERROR"), ' '+, '[', char_word+, ']'"#,
+            Corpus::Log,
+        ),
+        // repetition backtracking
+        Scenario::new(
+            "repetition_ip",
+            r#"(char_digit{1,3}, '.'){3}, char_digit{1,3}"#,
+            Corpus::Log,
+        ),
+        Scenario::new(
+            "repetition_line",
+            r#"start, char_any*, "ERROR", char_any*, "ms", end"#,
+            Corpus::Log,
+        )
+        .with_options(CompileOptions {
+            multiline: true,
+            ..CompileOptions::default()
+        }),
+        Scenario::new(
+            "repetition_words",
+            r#"(char_word+, ' ')+, char_word+, '.'"#,
+            Corpus::English,
+        ),
+        // Unicode-heavy
+        Scenario::new("unicode_words", r#"char_word+"#, Corpus::Unicode).with_options(
+            CompileOptions {
+                unicode: true,
+                ..CompileOptions::default()
+            },
+        ),
+        Scenario::new("unicode_literal", r#""灯塔""#, Corpus::Unicode),
+        Scenario::new("unicode_ignore_case", r#""χάρτη""#, Corpus::Unicode).with_options(
+            CompileOptions {
+                ignore_case: true,
+                ..CompileOptions::default()
+            },
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scenarios, CORPUS_SIZE};
+
+    #[test]
+    fn test_scenarios() {
+        for scenario in scenarios() {
+            let process = scenario.process().unwrap();
+            let text = scenario.corpus.text();
+            assert!(text.len() >= CORPUS_SIZE);
+            assert!(
+                scenario.run(&process, &text) > 0,
+                "no match in scenario \"{}\"",
+                scenario.name
+            );
+        }
+    }
+}
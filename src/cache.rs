@@ -0,0 +1,154 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the global cache of the compiled patterns, for the applications that
+// receive the same patterns repeatedly, e.g. the filters of requests, e.g.
+//
+// ```
+// let process = cached_compile(filter)?;
+// let mut instance = process.new_instance(text);
+// ```
+//
+// the patterns are compiled with the default options, and the cache keeps
+// the recently used ones, the least recently used pattern is dropped when
+// the cache is full. the cache is shared by all threads behind a lock,
+// and the lock is not held while compiling.
+//
+// it is enabled by the feature "cache".
+
+use std::sync::{Arc, Mutex};
+
+use crate::{error::Error, process::Process};
+
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+static CACHE: Mutex<PatternCache> = Mutex::new(PatternCache {
+    entries: vec![],
+    capacity: DEFAULT_CACHE_CAPACITY,
+});
+
+struct PatternCache {
+    // the patterns and their processes, the most recently used one is
+    // the last.
+    entries: Vec<(String, Arc<Process>)>,
+    capacity: usize,
+}
+
+impl PatternCache {
+    fn get(&mut self, pattern: &str) -> Option<Arc<Process>> {
+        let idx = self.entries.iter().position(|(p, _)| p == pattern)?;
+        let entry = self.entries.remove(idx);
+        let process = Arc::clone(&entry.1);
+        self.entries.push(entry);
+        Some(process)
+    }
+
+    fn insert(&mut self, pattern: &str, process: Arc<Process>) {
+        // the pattern may be compiled by another thread meanwhile
+        self.entries.retain(|(p, _)| p != pattern);
+        self.entries.push((pattern.to_owned(), process));
+        self.shrink();
+    }
+
+    fn shrink(&mut self) {
+        if self.entries.len() > self.capacity {
+            let excess = self.entries.len() - self.capacity;
+            self.entries.drain(..excess);
+        }
+    }
+}
+
+// a poisoned lock is recovered, since the cache is always consistent
+// between the operations.
+fn lock() -> std::sync::MutexGuard<'static, PatternCache> {
+    CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// returns the process of the pattern from the cache, or compiles the
+// pattern and adds it to the cache, the errors are not cached.
+pub fn cached_compile(pattern: &str) -> Result<Arc<Process>, Error> {
+    if let Some(process) = lock().get(pattern) {
+        return Ok(process);
+    }
+
+    let process = Arc::new(Process::new(pattern)?);
+    let mut cache = lock();
+    if cache.capacity > 0 {
+        cache.insert(pattern, Arc::clone(&process));
+    }
+    Ok(process)
+}
+
+// set the maximum number of the patterns in the cache, the least
+// recently used patterns are dropped if there are more, 0 disables
+// the cache.
+pub fn set_cache_capacity(capacity: usize) {
+    let mut cache = lock();
+    cache.capacity = capacity;
+    cache.shrink();
+}
+
+pub fn clear_cache() {
+    lock().entries.clear();
+}
+
+// the number of the patterns in the cache.
+pub fn cache_len() -> usize {
+    lock().entries.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::process::Process;
+
+    use super::{cached_compile, PatternCache};
+
+    // the tests use their own caches, since the global one is
+    // shared by the tests running in parallel.
+    #[test]
+    fn test_pattern_cache() {
+        let mut cache = PatternCache {
+            entries: vec![],
+            capacity: 2,
+        };
+
+        let a = Arc::new(Process::new("'a'").unwrap());
+        cache.insert("'a'", Arc::clone(&a));
+        cache.insert("'b'", Arc::new(Process::new("'b'").unwrap()));
+        assert!(Arc::ptr_eq(&cache.get("'a'").unwrap(), &a));
+
+        // the least recently used "'b'" is dropped
+        cache.insert("'c'", Arc::new(Process::new("'c'").unwrap()));
+        assert!(cache.get("'b'").is_none());
+        assert!(cache.get("'a'").is_some());
+        assert_eq!(cache.entries.len(), 2);
+
+        cache.capacity = 1;
+        cache.shrink();
+        let patterns: Vec<&str> = cache.entries.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(patterns, vec!["'a'"]);
+    }
+
+    #[test]
+    fn test_cached_compile() {
+        let first = cached_compile("'x', char_digit+, \"cache\"").unwrap();
+        let second = cached_compile("'x', char_digit+, \"cache\"").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let mut instance = second.new_instance("x12cache");
+        assert!(instance.is_match(0));
+
+        assert!(cached_compile("'x', char_digit+, (").is_err());
+
+        // shared across threads
+        let handle = std::thread::spawn(|| cached_compile("'x', char_digit+, \"cache\"").unwrap());
+        assert!(Arc::ptr_eq(&handle.join().unwrap(), &first));
+    }
+}
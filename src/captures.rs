@@ -0,0 +1,447 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{error::Error, process::MatchRange, state::CaptureGroup};
+
+// the texts and ranges of the capture groups of a match, the index 0 is
+// the whole match.
+//
+// a group that does not participate in the match (e.g. in the untaken
+// branch of an alternation) is `None`, which is different from a group
+// that matches an empty string, e.g. the pattern
+// `'x' || name('a'?, g)` gives:
+//
+// - "x": the group `g` is `None`.
+// - "y": the group `g` is `Some("")` at the range `0..0`.
+#[derive(Debug, PartialEq)]
+pub struct Captures {
+    names: Vec<Option<String>>,
+    ranges: Vec<Option<MatchRange>>,
+    values: Vec<Option<String>>,
+}
+
+impl Captures {
+    pub(crate) fn new(
+        names: Vec<Option<String>>,
+        ranges: Vec<Option<MatchRange>>,
+        values: Vec<Option<String>>,
+    ) -> Self {
+        Captures {
+            names,
+            ranges,
+            values,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // the text of the group by index, returns `None` if the
+    // group does not participate in the match.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.values.get(index)?.as_deref()
+    }
+
+    // the range of the group by index, returns `None` if the
+    // group does not participate in the match.
+    pub fn range(&self, index: usize) -> Option<MatchRange> {
+        *self.ranges.get(index)?
+    }
+
+    // the text of the group by name.
+    //
+    // the groups in the exclusive branches of alternation can
+    // share a name, the one that participates in the match is returned.
+    pub fn name(&self, name: &str) -> Option<&str> {
+        self.get(self.index_of(name)?)
+    }
+
+    // the range of the group by name.
+    pub fn name_range(&self, name: &str) -> Option<MatchRange> {
+        self.range(self.index_of(name)?)
+    }
+
+    // the index of the participating group with the name.
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .zip(self.ranges.iter())
+            .position(|(n, r)| n.as_deref() == Some(name) && r.is_some())
+    }
+
+    // the text of the group by name, returns an error if the group
+    // does not participate in the match.
+    pub fn get_str(&self, name: &str) -> Result<&str, Error> {
+        self.name(name)
+            .ok_or_else(|| Error::Message(format!("The capture group \"{}\" is missing.", name)))
+    }
+
+    // parse the text of the group by name, e.g.
+    // `let year: u32 = captures.parse("year")?;`
+    pub fn parse<T>(&self, name: &str) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let s = self.get_str(name)?;
+        s.parse::<T>().map_err(|e| {
+            Error::Message(format!(
+                "Failed to convert the capture group \"{}\" (\"{}\"): {}",
+                name, s, e
+            ))
+        })
+    }
+
+    // parse the text of the group by name if the group participates
+    // in the match.
+    pub fn parse_optional<T>(&self, name: &str) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.name(name) {
+            Some(_) => self.parse(name).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+// a match or a capture group that borrows the text, i.e. the matched
+// text is a slice of the text instead of a copy, e.g.
+//
+// ```rust
+// let groups = process.find_groups("v1.2").unwrap();
+// let major = groups[1].unwrap();
+// assert_eq!(major.as_str(), "1");
+// assert_eq!(major.name(), Some("major"));
+// ```
+//
+// the range is counted by chars like `MatchRange`, and the name is
+// `None` for the whole match and the groups created by `capture()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match<'t> {
+    text: &'t str,
+    range: MatchRange,
+    name: Option<&'t str>,
+}
+
+impl<'t> Match<'t> {
+    // the matched text.
+    pub fn as_str(&self) -> &'t str {
+        self.text
+    }
+
+    // the range of the match by chars.
+    pub fn range(&self) -> MatchRange {
+        self.range
+    }
+
+    // the name of the capture group.
+    pub fn name(&self) -> Option<&'t str> {
+        self.name
+    }
+}
+
+// slice the text by the ranges of the groups, the index 0 is the
+// whole match.
+pub(crate) fn borrow_matches<'t>(
+    text: &'t str,
+    ranges: &[Option<MatchRange>],
+    groups: &'t [CaptureGroup],
+) -> Vec<Option<Match<'t>>> {
+    // the byte offsets of the chars, up to the end of the last group
+    let max_end = ranges.iter().flatten().map(|r| r.end).max().unwrap_or(0);
+    let offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .take(max_end + 1)
+        .collect();
+
+    ranges
+        .iter()
+        .zip(groups.iter())
+        .map(|(range, group)| {
+            range.map(|range| Match {
+                text: &text[offsets[range.start]..offsets[range.end]],
+                range,
+                name: group.name.as_deref(),
+            })
+        })
+        .collect()
+}
+
+// split the ranges of the capture groups of a match (the index 0 is the
+// whole match) into the spans that do not overlap, each span has the
+// index of the innermost group that covers it, so that a UI can color
+// the nested groups, e.g. the pattern
+// `name(char_word+, user), '@', name((char_word+, name('.', dot), char_word+), host)`
+// and the text "me@a.io" gives:
+//
+// - 0..2: 1 (user)
+// - 2..3: 0
+// - 3..4: 3 (host)
+// - 4..5: 2 (dot)
+// - 5..7: 3 (host)
+//
+// the innermost group is the one that starts the latest, or the shorter
+// one if they start at the same position, or the earlier group if their
+// ranges are the same since the inner group is numbered first, e.g.
+// `name(name('a', x), y)`. the groups that
+// match empty strings are not included since they cover no chars, and
+// the groups of the look-arounds may be outside the whole match.
+//
+// the spans are in the order of the positions, and the adjacent spans
+// of the same group are merged.
+pub fn capture_spans(ranges: &[Option<MatchRange>]) -> Vec<(MatchRange, usize)> {
+    let mut boundaries: Vec<usize> = ranges
+        .iter()
+        .flatten()
+        .flat_map(|range| [range.start, range.end])
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut spans: Vec<(MatchRange, usize)> = vec![];
+    for pair in boundaries.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let innermost = ranges
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, range)| range.map(|r| (idx, r)))
+            .filter(|(_, r)| r.start <= start && end <= r.end)
+            .max_by_key(|(idx, r)| (r.start, std::cmp::Reverse(r.end), std::cmp::Reverse(*idx)));
+
+        let Some((index, _)) = innermost else {
+            continue;
+        };
+
+        match spans.last_mut() {
+            Some((last, last_index)) if *last_index == index && last.end == start => {
+                last.end = end;
+            }
+            _ => spans.push((MatchRange::new(start, end), index)),
+        }
+    }
+
+    spans
+}
+
+// convert the capture groups of a match into a user type, e.g.
+//
+// ```rust
+// struct Date {
+//     year: u32,
+//     month: u32,
+// }
+//
+// impl FromCaptures for Date {
+//     fn from_captures(captures: &Captures) -> Result<Self, Error> {
+//         Ok(Date {
+//             year: captures.parse("year")?,
+//             month: captures.parse("month")?,
+//         })
+//     }
+// }
+//
+// let date: Date = process.capture_into("2024-02")?;
+// ```
+pub trait FromCaptures: Sized {
+    fn from_captures(captures: &Captures) -> Result<Self, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        error::Error,
+        process::{MatchRange, Process},
+    };
+
+    use super::{capture_spans, Captures, FromCaptures};
+
+    #[derive(Debug, PartialEq)]
+    struct Version {
+        major: u32,
+        minor: u32,
+        patch: Option<u32>,
+    }
+
+    impl FromCaptures for Version {
+        fn from_captures(captures: &Captures) -> Result<Self, Error> {
+            Ok(Version {
+                major: captures.parse("major")?,
+                minor: captures.parse("minor")?,
+                patch: captures.parse_optional("patch")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_captures() {
+        let process = Process::new(
+            r#"name(char_digit+, major), '.', name(char_word+, minor), ('.', name(char_digit+, patch))?"#,
+        )
+        .unwrap();
+
+        let mut instance = process.new_instance("v1.2");
+        let captures = instance.captures(0).unwrap();
+        assert_eq!(captures.len(), 4);
+        assert_eq!(captures.get(0), Some("1.2"));
+        assert_eq!(captures.name("major"), Some("1"));
+        assert_eq!(captures.name("patch"), None);
+        assert!(captures.get_str("patch").is_err());
+        assert_eq!(captures.range(0), Some(MatchRange::new(1, 4)));
+        assert_eq!(captures.name_range("minor"), Some(MatchRange::new(3, 4)));
+        assert_eq!(captures.name_range("patch"), None);
+
+        assert_eq!(
+            process.capture_into::<Version>("v1.2.3").unwrap(),
+            Version {
+                major: 1,
+                minor: 2,
+                patch: Some(3)
+            }
+        );
+
+        assert_eq!(
+            process.capture_into::<Version>("v1.2").unwrap(),
+            Version {
+                major: 1,
+                minor: 2,
+                patch: None
+            }
+        );
+
+        // err: conversion
+        assert!(matches!(
+            process.capture_into::<Version>("v1.x"),
+            Err(Error::Message(m)) if m.contains("\"minor\"")
+        ));
+
+        // err: not match
+        assert!(process.capture_into::<Version>("abc").is_err());
+    }
+
+    #[test]
+    fn test_borrowed_matches() {
+        let process = Process::new(
+            r#"name(char_digit+, major), '.', capture(char_word+), ('.', name(char_digit+, patch))?"#,
+        )
+        .unwrap();
+
+        // the slices of the text, the ranges are counted by chars
+        let text = "版本 v1.b2 文字";
+        let m = process.find(text).unwrap();
+        assert_eq!(m.as_str(), "1.b2");
+        assert_eq!(m.range(), MatchRange::new(4, 8));
+        assert_eq!(m.name(), None);
+
+        let groups = process.find_groups(text).unwrap();
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0], Some(m));
+
+        let major = groups[1].unwrap();
+        assert_eq!(major.as_str(), "1");
+        assert_eq!(major.range(), MatchRange::new(4, 5));
+        assert_eq!(major.name(), Some("major"));
+
+        let minor = groups[2].unwrap();
+        assert_eq!(minor.as_str(), "b2");
+        assert_eq!(minor.name(), None);
+        assert_eq!(groups[3], None);
+
+        // the matches outlive the instance
+        let owned = String::from("1.2.3");
+        let patch = process.find_groups(&owned).unwrap()[3].unwrap();
+        assert_eq!(patch.as_str(), "3");
+        assert!(std::ptr::eq(patch.as_str(), &owned[4..]));
+
+        assert_eq!(process.find("x.y"), None);
+        assert_eq!(process.find_groups("x.y"), None);
+    }
+
+    #[test]
+    fn test_captures_participation() {
+        let process = Process::new(r#"'x' || name('a'?, g)"#).unwrap();
+
+        // the group does not participate in the match
+        let captures = process.new_instance("x").captures(0).unwrap();
+        assert_eq!(captures.name("g"), None);
+        assert_eq!(captures.name_range("g"), None);
+
+        // the group matches an empty string
+        let captures = process.new_instance("y").captures(0).unwrap();
+        assert_eq!(captures.name("g"), Some(""));
+        assert_eq!(captures.name_range("g"), Some(MatchRange::new(0, 0)));
+
+        // the last iteration that the group participates in
+        let process = Process::new(r#"(name('a'?, g), 'b')+"#).unwrap();
+        let captures = process.new_instance("abb").captures(0).unwrap();
+        assert_eq!(captures.name_range("g"), Some(MatchRange::new(2, 2)));
+
+        // the groups in the exclusive branches share the name
+        let process = Process::new(r#"name('a', g) || name('b'?, g)"#).unwrap();
+        let captures = process.new_instance("c").captures(0).unwrap();
+        assert_eq!(captures.len(), 2);
+        assert_eq!(captures.name_range("g"), Some(MatchRange::new(0, 0)));
+        assert_eq!(captures.range(2), None);
+    }
+
+    #[test]
+    fn test_capture_spans() {
+        let process = Process::new(
+            r#"name(char_word+, user), '@', name((char_word+, name('.', dot), char_word+), host)"#,
+        )
+        .unwrap();
+        let ranges = process
+            .new_instance("to: me@a.io")
+            .exec_with_captures(0)
+            .unwrap();
+        assert_eq!(
+            capture_spans(&ranges),
+            vec![
+                (MatchRange::new(4, 6), 1),
+                (MatchRange::new(6, 7), 0),
+                (MatchRange::new(7, 8), 3),
+                (MatchRange::new(8, 9), 2),
+                (MatchRange::new(9, 11), 3),
+            ]
+        );
+
+        // the groups with the same range, and the empty group
+        let process = Process::new(r#"name(name('a', x), y), name('b'?, z), 'c'"#).unwrap();
+        let ranges = process.new_instance("ac").exec_with_captures(0).unwrap();
+        assert_eq!(
+            capture_spans(&ranges),
+            vec![(MatchRange::new(0, 1), 1), (MatchRange::new(1, 2), 0)]
+        );
+
+        // the group of the look-around outside the match
+        let process = Process::new(r#"'a'.is_before(capture('b'))"#).unwrap();
+        let ranges = process.new_instance("ab").exec_with_captures(0).unwrap();
+        assert_eq!(
+            capture_spans(&ranges),
+            vec![(MatchRange::new(0, 1), 0), (MatchRange::new(1, 2), 1)]
+        );
+
+        // the byte offsets
+        let process = Process::new(r#"'文', capture(char_any)"#).unwrap();
+        assert_eq!(
+            process.find_capture_spans("x文字"),
+            Some(vec![(MatchRange::new(1, 4), 0), (MatchRange::new(4, 7), 1)])
+        );
+        assert_eq!(process.find_capture_spans("x"), None);
+    }
+}
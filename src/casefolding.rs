@@ -0,0 +1,122 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// Case folding closure of charsets.
+//
+// A char matches a case-insensitive charset if it has the same lowercase
+// as any char in the charset, the same as a case-insensitive char (see
+// `equals_ignore_case`), e.g. 'Ä' matches `['à'..'ÿ']` since its
+// lowercase 'ä' is in the range, and the Kelvin sign '\u{212a}' matches
+// `['K']` since both lowercases are 'k'.
+//
+// Only the single-char (simple) mappings are used, the chars whose
+// lowercase has multiple chars (e.g. 'İ' is "i\u{307}") match only
+// themselves, and 'ß' does not match `['S']` though its uppercase is "SS".
+//
+// Instead of mapping each input char at runtime, the chars that match
+// by case mapping are collected into ranges when the charset is compiled.
+
+use std::sync::OnceLock;
+
+// the pairs of (lowercase char, source char) of the single-char lowercase
+// mappings, sorted by the lowercase char, i.e. the chars with the same
+// lowercase are adjacent, and the lowercase char itself is not listed
+// as a source char.
+fn case_mapping_table() -> &'static [(char, char)] {
+    static TABLE: OnceLock<Vec<(char, char)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = vec![];
+        for c in ('\0'..='\u{d7ff}').chain('\u{e000}'..=char::MAX) {
+            let mut lowercase = c.to_lowercase();
+            if let (Some(lower), None) = (lowercase.next(), lowercase.next()) {
+                if lower != c {
+                    table.push((lower, c));
+                }
+            }
+        }
+        table.sort();
+        table
+    })
+}
+
+// the ranges of chars that are not accepted by the predicate, but have
+// the same lowercase as a char that is accepted.
+pub fn fold_closure(predicate: impl Fn(char) -> bool) -> Vec<(char, char)> {
+    let mut chars: Vec<char> = vec![];
+    for class in case_mapping_table().chunk_by(|a, b| a.0 == b.0) {
+        let lower = class[0].0;
+        let members = std::iter::once(lower).chain(class.iter().map(|(_, source)| *source));
+        if members.clone().any(&predicate) {
+            chars.extend(members.filter(|c| !predicate(*c)));
+        }
+    }
+
+    chars.sort();
+    chars.dedup();
+
+    // merge the adjacent chars into ranges
+    let mut ranges: Vec<(char, char)> = vec![];
+    for c in chars {
+        match ranges.last_mut() {
+            Some(range) if range.1 as u32 + 1 == c as u32 => range.1 = c,
+            _ => ranges.push((c, c)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::fold_closure;
+
+    #[test]
+    fn test_fold_closure() {
+        let in_ranges = |ranges: &[(char, char)]| {
+            let ranges = ranges.to_vec();
+            move |c: char| ranges.iter().any(|(s, e)| *s <= c && c <= *e)
+        };
+
+        let ranges = [('a', 'z')];
+        assert_eq!(
+            fold_closure(in_ranges(&ranges)),
+            vec![
+                ('A', 'Z'),
+                ('\u{212a}', '\u{212a}'), // Kelvin sign
+            ]
+        );
+
+        let ranges = [('à', 'ÿ')];
+        let closure = fold_closure(in_ranges(&ranges));
+        assert!(in_ranges(&closure)('Ä'));
+        assert!(in_ranges(&closure)('Ÿ'));
+        assert!(!in_ranges(&closure)('ä'));
+
+        // the chars that are accepted by the predicate are not included
+        let ranges = [('A', 'Z'), ('a', 'z')];
+        let closure = fold_closure(in_ranges(&ranges));
+        assert!(!in_ranges(&closure)('a'));
+        assert!(in_ranges(&closure)('\u{212a}'));
+
+        // the chars that differ from the ASCII letters in lowercase,
+        // the same as `equals_ignore_case`.
+        assert!(!in_ranges(&closure)('ı')); // the uppercase is 'I'
+        assert!(!in_ranges(&closure)('ſ')); // the uppercase is 'S'
+        assert!(!in_ranges(&closure)('ß')); // the uppercase is "SS"
+        assert!(!in_ranges(&closure)('İ')); // the lowercase is "i\u{307}"
+        assert!(fold_closure(|c| c == '\u{307}').is_empty());
+
+        // the uppercase 'ẞ' and the lowercase 'ß'
+        let closure = fold_closure(|c| c == 'ß');
+        assert_eq!(closure, vec![('ẞ', 'ẞ')]);
+
+        // the mapping is not symmetric
+        let closure = fold_closure(|c| c == 'σ');
+        assert!(in_ranges(&closure)('Σ'));
+        assert!(!in_ranges(&closure)('ς'));
+    }
+}
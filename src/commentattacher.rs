@@ -0,0 +1,244 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// attaches the comments to the nearest expressions and definitions
+// of the source map, the rules:
+//
+// 1. the comments inside a `define` statement belong to the definition.
+// 2. a comment that follows a token on the same line and ends the line is
+//    a trailing comment of the outermost expression (or the definition)
+//    ending at the token, e.g. `'a', 'b' // note` is attached to `'b'`.
+// 3. otherwise it is a leading comment of the outermost expression (or
+//    the documentation of the definition) starting at the next token, e.g.
+//    `// note \n 'a'+` is attached to `'a'+`.
+// 4. the comments that can not be attached, e.g. the ones before `)`
+//    and at the end of source, are kept in `SourceMap::comments`.
+
+use crate::{
+    ast::{NodeLocation, SourceComment, SourceMap},
+    location::Location,
+    token::{Comment, Token, TokenWithRange},
+};
+
+// split the comments from the tokens.
+fn extract_comments(tokens: &[TokenWithRange]) -> Vec<SourceComment> {
+    tokens
+        .iter()
+        .filter_map(|token_with_range| match &token_with_range.token {
+            Token::Comment(Comment::Line(text)) => Some(SourceComment {
+                text: text.to_owned(),
+                block: false,
+                location: token_with_range.range,
+            }),
+            Token::Comment(Comment::Block(text)) => Some(SourceComment {
+                text: text.to_owned(),
+                block: true,
+                location: token_with_range.range,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+// the tokens are the ones before removing the comments.
+pub fn attach_comments(tokens: &[TokenWithRange], source_map: &mut SourceMap) {
+    // the locations of the tokens except the comments, new lines and commas
+    let ranges: Vec<Location> = tokens
+        .iter()
+        .filter(|t| !matches!(t.token, Token::Comment(_) | Token::NewLine | Token::Comma))
+        .map(|t| t.range)
+        .collect();
+
+    for comment in extract_comments(tokens) {
+        let start = comment.location.index;
+        let end = start + comment.location.length;
+
+        // rule 1
+        if let Some(definition) = source_map
+            .definitions
+            .iter_mut()
+            .find(|d| d.location.index <= start && end <= d.location.index + d.location.length)
+        {
+            definition.comments.push(comment);
+            continue;
+        }
+
+        let previous = ranges.iter().rev().find(|r| r.index + r.length <= start);
+        let next = ranges.iter().find(|r| r.index >= end);
+
+        // rule 2
+        let end_line = comment.location.line + comment.text.matches('\n').count();
+        let ends_line = next.is_none_or(|r| r.line != end_line);
+
+        if let Some(previous) = previous.filter(|r| ends_line && r.line == comment.location.line) {
+            let previous_end = previous.index + previous.length;
+
+            if let Some(definition) = source_map
+                .definitions
+                .iter_mut()
+                .find(|d| d.location.index + d.location.length == previous_end)
+            {
+                definition.comments.push(comment);
+                continue;
+            }
+
+            if let Some(node) = find_node(&mut source_map.expressions, &|location| {
+                location.index + location.length == previous_end
+            }) {
+                node.trailing_comments.push(comment);
+                continue;
+            }
+        }
+
+        // rule 3
+        if let Some(next) = next {
+            if let Some(definition) = source_map
+                .definitions
+                .iter_mut()
+                .find(|d| d.location.index == next.index)
+            {
+                definition.doc_comments.push(comment);
+                continue;
+            }
+
+            if let Some(node) = find_node(&mut source_map.expressions, &|location| {
+                location.index == next.index && location.length > 0
+            }) {
+                node.leading_comments.push(comment);
+                continue;
+            }
+        }
+
+        // rule 4
+        source_map.comments.push(comment);
+    }
+}
+
+// find the outermost node that satisfies the predicate, the parents
+// are checked before their children.
+fn find_node<'a>(
+    nodes: &'a mut [NodeLocation],
+    predicate: &dyn Fn(&Location) -> bool,
+) -> Option<&'a mut NodeLocation> {
+    for node in nodes {
+        if predicate(&node.location) {
+            return Some(node);
+        }
+
+        if let Some(found) = find_node(&mut node.children, predicate) {
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{ast::NodeLocation, options::ParseOptions, parser::parse_from_str_with_options};
+
+    fn comment_texts(nodes: &[NodeLocation]) -> Vec<(Vec<String>, Vec<String>)> {
+        nodes
+            .iter()
+            .map(|node| {
+                (
+                    node.leading_comments
+                        .iter()
+                        .map(|c| c.text.clone())
+                        .collect(),
+                    node.trailing_comments
+                        .iter()
+                        .map(|c| c.text.clone())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_attach_comments() {
+        let options = ParseOptions {
+            keep_comments: true,
+            ..ParseOptions::default()
+        };
+
+        let (_, source_map) = parse_from_str_with_options(
+            r#"
+// the digits
+/* at least one */ char_digit+, // digits
+'.' /* dot */
+('a', /* the letter b */ 'b')
+// the end"#,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            comment_texts(&source_map.expressions),
+            vec![
+                (
+                    vec![" the digits".to_owned(), " at least one ".to_owned()],
+                    vec![" digits".to_owned()]
+                ),
+                (vec![], vec![" dot ".to_owned()]),
+                (vec![], vec![])
+            ]
+        );
+
+        let group = &source_map.expressions[2];
+        assert_eq!(
+            comment_texts(&group.children),
+            vec![
+                (vec![], vec![]),
+                (vec![" the letter b ".to_owned()], vec![])
+            ]
+        );
+
+        assert_eq!(source_map.comments.len(), 1);
+        assert_eq!(source_map.comments[0].text, " the end");
+        assert!(!source_map.comments[0].block);
+
+        // definitions
+        let (_, source_map) = parse_from_str_with_options(
+            r#"
+// the year
+// four digits
+define(year, /* digit */ char_digit{4}) // year
+year"#,
+            &options,
+        )
+        .unwrap();
+
+        let definition = &source_map.definitions[0];
+        assert_eq!(
+            definition
+                .doc_comments
+                .iter()
+                .map(|c| c.text.as_str())
+                .collect::<Vec<_>>(),
+            vec![" the year", " four digits"]
+        );
+        assert_eq!(
+            definition
+                .comments
+                .iter()
+                .map(|c| c.text.as_str())
+                .collect::<Vec<_>>(),
+            vec![" digit ", " year"]
+        );
+
+        // the comments are dropped by default
+        let (_, source_map) =
+            parse_from_str_with_options("// foo\n'a'", &ParseOptions::default()).unwrap();
+        assert_eq!(
+            comment_texts(&source_map.expressions),
+            vec![(vec![], vec![])]
+        );
+        assert!(source_map.comments.is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// prints the program along with the comments of the source map, i.e. the
+// comments collected by `parse_from_str_with_options` when
+// `ParseOptions::keep_comments` is set, see `commentattacher` for how
+// they are attached.
+//
+// - the leading comments are printed before the expression.
+// - the trailing comments are printed after the expression and
+//   the following comma.
+// - the unattached comments inside an expression (e.g. in a charset
+//   `['a', /* note */ 'b']`, or before the `)` of a group) are printed
+//   before the closing bracket of the innermost expression containing them.
+// - the other unattached comments are printed at the end of the program.
+//
+// the line comments always end the line, so the printed program can be
+// parsed again. the comments of the definitions are not printed since
+// the definitions are expanded in the program.
+
+use std::fmt::Display;
+
+use crate::{
+    ast::{Expression, FunctionCallArg, NodeLocation, Program, SourceComment, SourceMap},
+    location::Location,
+};
+
+pub struct ProgramWithComments<'a> {
+    pub(crate) program: &'a Program,
+    pub(crate) source_map: &'a SourceMap,
+}
+
+impl Display for ProgramWithComments<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let program = self.program;
+        if let Some(version) = program.syntax_version {
+            writeln!(f, "syntax_version({})", version.number())?;
+        }
+
+        let option_names = program.options.names();
+        if !option_names.is_empty() {
+            writeln!(f, "options({})", option_names.join(", "))?;
+        }
+
+        // the function calls are printed on their own lines,
+        // like `Program`'s `Display`.
+        let expressions = &program.expressions;
+        let mut text = String::new();
+        for (idx, expression) in expressions.iter().enumerate() {
+            let node = self.source_map.expressions.get(idx);
+            let separator = match expressions.get(idx + 1) {
+                None => ("", ""),
+                Some(next) if is_function_call(expression) || is_function_call(next) => ("", "\n"),
+                Some(_) => (",", " "),
+            };
+            self.push_expression(&mut text, expression, node, separator);
+        }
+
+        for test in &program.tests {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&test.to_string());
+        }
+
+        // the comments outside all expressions
+        for comment in &self.source_map.comments {
+            if !self
+                .source_map
+                .expressions
+                .iter()
+                .any(|node| contains(&node.location, comment))
+            {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                push_comment(&mut text, comment);
+            }
+        }
+
+        f.write_str(text.trim_end_matches('\n'))
+    }
+}
+
+impl ProgramWithComments<'_> {
+    // push the expression with its comments, followed by the separator,
+    // i.e. the punctuation (e.g. ",") and the space, the space is omitted
+    // if a trailing line comment ends the line.
+    fn push_expression(
+        &self,
+        text: &mut String,
+        expression: &Expression,
+        node: Option<&NodeLocation>,
+        (punctuation, space): (&str, &str),
+    ) {
+        let Some(node) = node else {
+            text.push_str(&expression.to_string());
+            text.push_str(punctuation);
+            text.push_str(space);
+            return;
+        };
+
+        for comment in &node.leading_comments {
+            push_comment(text, comment);
+            if comment.block {
+                text.push(' ');
+            }
+        }
+
+        let body = self.expression_text(expression, node);
+        let inner_comments: Vec<&SourceComment> = self
+            .source_map
+            .comments
+            .iter()
+            .filter(|comment| {
+                contains(&node.location, comment)
+                    && !node
+                        .children
+                        .iter()
+                        .any(|child| contains(&child.location, comment))
+            })
+            .collect();
+
+        match body.strip_suffix([')', ']']) {
+            Some(open) if !inner_comments.is_empty() => {
+                text.push_str(open);
+                for comment in inner_comments {
+                    text.push(' ');
+                    push_comment(text, comment);
+                }
+                text.push_str(&body[open.len()..]);
+            }
+            _ => {
+                text.push_str(&body);
+                for comment in inner_comments {
+                    text.push(' ');
+                    push_comment(text, comment);
+                }
+            }
+        }
+
+        text.push_str(punctuation);
+        for comment in &node.trailing_comments {
+            text.push(' ');
+            push_comment(text, comment);
+        }
+        if !text.ends_with('\n') {
+            text.push_str(space);
+        }
+    }
+
+    // the text of the expression without its own comments, the
+    // sub-expressions are printed with their comments.
+    fn expression_text(&self, expression: &Expression, node: &NodeLocation) -> String {
+        let mut text = String::new();
+        match expression {
+            Expression::Group(expressions) => {
+                text.push('(');
+                for (idx, e) in expressions.iter().enumerate() {
+                    let separator = if idx + 1 == expressions.len() {
+                        ("", "")
+                    } else {
+                        (",", " ")
+                    };
+                    self.push_expression(&mut text, e, node.children.get(idx), separator);
+                }
+                text.push(')');
+            }
+            Expression::FunctionCall(function_call) => {
+                text.push_str(&function_call.name.to_string());
+                text.push('(');
+
+                let mut children = node.children.iter();
+                let separator = if function_call.args.is_empty() {
+                    ("", "")
+                } else {
+                    (",", " ")
+                };
+                self.push_expression(
+                    &mut text,
+                    &function_call.expression,
+                    children.next(),
+                    separator,
+                );
+
+                for (idx, arg) in function_call.args.iter().enumerate() {
+                    let separator = if idx + 1 == function_call.args.len() {
+                        ("", "")
+                    } else {
+                        (",", " ")
+                    };
+                    match arg {
+                        FunctionCallArg::Expression(e) => {
+                            self.push_expression(&mut text, e, children.next(), separator)
+                        }
+                        _ => {
+                            text.push_str(&arg.to_string());
+                            text.push_str(separator.0);
+                            text.push_str(separator.1);
+                        }
+                    }
+                }
+                text.push(')');
+            }
+            Expression::OrList(alternatives) => {
+                for (idx, e) in alternatives.iter().enumerate() {
+                    let separator = if idx + 1 == alternatives.len() {
+                        ("", "")
+                    } else {
+                        (" ||", " ")
+                    };
+                    self.push_expression(&mut text, e, node.children.get(idx), separator);
+                }
+            }
+            Expression::Literal(_) | Expression::Identifier(_) => {
+                text.push_str(&expression.to_string())
+            }
+        }
+        text
+    }
+}
+
+fn is_function_call(expression: &Expression) -> bool {
+    matches!(expression, Expression::FunctionCall(_))
+}
+
+// whether the comment is inside the location.
+fn contains(location: &Location, comment: &SourceComment) -> bool {
+    location.index < comment.location.index
+        && comment.location.index + comment.location.length < location.index + location.length
+}
+
+fn push_comment(text: &mut String, comment: &SourceComment) {
+    if comment.block {
+        text.push_str("/*");
+        text.push_str(&comment.text);
+        text.push_str("*/");
+    } else {
+        text.push_str("//");
+        text.push_str(&comment.text);
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{options::ParseOptions, parser::parse_from_str_with_options};
+
+    fn format(s: &str) -> String {
+        let options = ParseOptions {
+            keep_comments: true,
+            ..ParseOptions::default()
+        };
+        let (program, source_map) = parse_from_str_with_options(s, &options).unwrap();
+        program.with_comments(&source_map).to_string()
+    }
+
+    #[test]
+    fn test_print_comments() {
+        assert_eq!(
+            format(
+                r#"
+// the digits
+/* at least one */ char_digit+, // digits
+'.' /* dot */
+('a', /* the letter b */ 'b')
+// the end"#
+            ),
+            "// the digits\n/* at least one */ one_or_more(char_digit) // digits\n\
+             '.', /* dot */ ('a', /* the letter b */ 'b')\n// the end"
+        );
+
+        // inside charsets and before the closing brackets
+        assert_eq!(
+            format(
+                r#"['a', /* letters */ 'b'..'z'], ('x' /* x */, 'y' // y
+)"#
+            ),
+            "['a', 'b'..'z' /* letters */], ('x', /* x */ 'y' // y\n)"
+        );
+
+        // the function calls and the logic or
+        assert_eq!(
+            format(r#"'a'.is_before(/* b */ 'b') || 'c' // c"#),
+            "is_before('a', /* b */ 'b') || 'c' // c"
+        );
+
+        // the printed program can be parsed again
+        let s = format(
+            r#"
+name(char_word+, key), // the key
+'=', char_digit+ // the value
+expect_match("a=1")"#,
+        );
+        assert_eq!(
+            s,
+            "name(one_or_more(char_word), key) // the key\n\
+             '='\none_or_more(char_digit) // the value\n\
+             expect_match(\"a=1\")"
+        );
+        assert_eq!(format(&s), s);
+
+        // no comments
+        assert_eq!(format(r#"'a', "bc""#), r#"'a', "bc""#);
+    }
+}
@@ -4,35 +4,147 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
+use std::collections::HashMap;
+
 use crate::{
-    ast::{Expression, Literal, Program},
+    ast::{
+        ByteRange, CharRange, CharSet, CharSetElement, Expression, FunctionCall, FunctionCallArg,
+        FunctionName, Literal, NodeLocation, NumberRange, Program,
+    },
     error::Error,
-    parser::parse_from_str,
-    state::StateSet,
-    transition::{CharTransition, JumpTransition, Transition},
+    location::Location,
+    options::CompileOptions,
+    parser::parse_from_str_with_vars_and_locations,
+    references::check_back_references,
+    state::{CaptureGroup, StateSet},
+    transition::{
+        BackReferenceTransition, ByteRangeTransition, ByteTransition, CaptureEndTransition,
+        CaptureStartTransition, CharSetItem, CharSetTransition, CharTransition,
+        CounterCheckTransition, CounterExitTransition, CounterIncTransition,
+        CounterResetTransition, JumpTransition, LookAroundTransition, MatchStartResetTransition,
+        NormalizedStringTransition, PresetCharSet, SpecialCharTransition, Status, StatusTransition,
+        StringTransition, TagTransition, Transition,
+    },
 };
 
-pub fn compile(program: &Program) -> Result<StateSet, Error> {
+pub fn compile(program: &Program, options: &CompileOptions) -> Result<StateSet, Error> {
+    check_back_references(program, None)?;
+    compile_checked(program, options, None)
+}
+
+pub fn compile_from_str(s: &str) -> Result<StateSet, Error> {
+    compile_from_str_with(s, &CompileOptions::default())
+}
+
+pub fn compile_from_str_with(s: &str, options: &CompileOptions) -> Result<StateSet, Error> {
+    let (program, locations) =
+        parse_from_str_with_vars_and_locations(s, &options.vars, &options.limits)?;
+
+    // the invalid back-references are reported with their locations
+    check_back_references(&program, Some(&locations))?;
+    compile_checked(&program, options, Some(&locations))
+}
+
+// compile the program whose back-references have been checked.
+fn compile_checked(
+    program: &Program,
+    options: &CompileOptions,
+    locations: Option<&[NodeLocation]>,
+) -> Result<StateSet, Error> {
+    // the options set by the pattern itself
+    let options = &program.options.apply(options);
+
+    // the duplicate capture names are reported with their locations
+    let mut name_locations = vec![];
+    if let Some(nodes) = locations {
+        for (expression, node) in program.expressions.iter().zip(nodes) {
+            collect_name_locations(expression, node, &mut name_locations);
+        }
+    }
+
     let mut state_set = StateSet::new();
-    let mut compiler = Compiler::new(program, &mut state_set);
+    let mut compiler = Compiler::new(program, &mut state_set, options, name_locations);
     compiler.compile()?;
 
     Ok(state_set)
 }
 
-pub fn compile_from_str(s: &str) -> Result<StateSet, Error> {
-    let program = parse_from_str(s)?;
-    compile(&program)
-}
+// the maximum number of chars of the string that a fixed repetition
+// of literals is collapsed into, the longer ones use the counter loop.
+const MAX_UNROLLED_STRING_LENGTH: usize = 256;
+
+// the minimum number of the branches of an alternation of literals
+// that is compiled into a trie, see `emit_literal_trie`.
+const MIN_TRIE_BRANCHES: usize = 3;
 
 pub struct Compiler<'a> {
     program: &'a Program,
     state_set: &'a mut StateSet,
+    options: &'a CompileOptions,
+    repetition_depth: usize,
+    look_around_depth: usize,
+
+    // the depth of `ignore_case(...)`, the literals inside it are
+    // case-insensitive, see `ignore_case`.
+    ignore_case_depth: usize,
+
+    // the nesting depth and the number of the emitted expressions,
+    // they are checked against the limits since the program may be
+    // constructed directly instead of parsed.
+    depth: usize,
+    node_count: usize,
+
+    // the alternation branches of the current expression
+    branch_path: Vec<Branch>,
+    logic_or_count: usize,
+
+    // the named captures, (name, capture index, branch path, location)
+    named_captures: Vec<(String, usize, Vec<Branch>, Option<Location>)>,
+
+    // the locations of the `name(...)` calls, see `collect_name_locations`
+    name_locations: Vec<(&'a FunctionCall, Location)>,
+
+    // the capture indices in the order that the groups are closed,
+    // the groups are renumbered by this order, see `emit_capture`.
+    closed_captures: Vec<usize>,
+
+    // the compiled lines of the look-around sub-expressions, indexed by
+    // the text of the expressions and whether they ignore case, the
+    // identical sub-expressions (e.g. the ones that come from the same
+    // macro) share one line.
+    look_around_lines: HashMap<(String, bool), EmitResult>,
 }
 
 impl<'a> Compiler<'a> {
-    fn new(program: &'a Program, state_set: &'a mut StateSet) -> Self {
-        Compiler { program, state_set }
+    fn new(
+        program: &'a Program,
+        state_set: &'a mut StateSet,
+        options: &'a CompileOptions,
+        name_locations: Vec<(&'a FunctionCall, Location)>,
+    ) -> Self {
+        Compiler {
+            program,
+            state_set,
+            options,
+            repetition_depth: 0,
+            look_around_depth: 0,
+            ignore_case_depth: 0,
+            depth: 0,
+            node_count: 0,
+            branch_path: vec![],
+            logic_or_count: 0,
+            named_captures: vec![],
+            name_locations,
+            closed_captures: vec![],
+            look_around_lines: HashMap::new(),
+        }
+    }
+
+    // whether the literals, charsets and back-references are
+    // case-insensitive, by the option `ignore_case` or inside
+    // `ignore_case(...)`, e.g. `"SELECT".ignore_case(), ' ', "*"`.
+    fn ignore_case(&self) -> bool {
+        self.options.ignore_case || self.ignore_case_depth > 0
     }
 
     fn compile(&mut self) -> Result<(), Error> {
@@ -40,19 +152,80 @@ impl<'a> Compiler<'a> {
         let result = self.emit_group(&self.program.expressions)?;
         self.state_set.start_node_index = result.in_state_index;
         self.state_set.end_node_index = result.out_state_index;
+
+        let mut new_indices = vec![0; self.state_set.capture_groups.len()];
+        for (idx, capture_index) in self.closed_captures.iter().enumerate() {
+            new_indices[*capture_index] = idx + 1;
+        }
+        self.state_set.renumber_capture_groups(&new_indices);
+
+        self.state_set.update_first_chars();
+        self.state_set.update_length_bounds();
+
+        if let Some(max) = self.options.max_capture_groups {
+            // the group 0 is not counted
+            let count = self.state_set.capture_groups.len() - 1;
+            if count > max {
+                return Err(Error::Message(format!(
+                    "The number of capture groups {} exceeds the limit {}.",
+                    count, max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // count a new expression and check the limit.
+    fn count_node(&mut self) -> Result<(), Error> {
+        self.node_count += 1;
+        if self.node_count > self.options.limits.max_nodes {
+            return Err(Error::Message(format!(
+                "The pattern exceeds the maximum number of expressions {}.",
+                self.options.limits.max_nodes
+            )));
+        }
         Ok(())
     }
 
+    // the count of a repetition, and check the limit.
+    fn get_repetition_count(
+        &self,
+        function_call: &FunctionCall,
+        index: usize,
+    ) -> Result<usize, Error> {
+        let n = get_number_arg(function_call, index)?;
+        if n > self.options.limits.max_repetition {
+            return Err(Error::Message(format!(
+                "The repetition count {} exceeds the limit {}.",
+                n, self.options.limits.max_repetition
+            )));
+        }
+        Ok(n)
+    }
+
     fn emit_expression(&mut self, expression: &Expression) -> Result<EmitResult, Error> {
+        self.count_node()?;
+
+        let limits = &self.options.limits;
+        if self.depth >= limits.max_nesting_depth {
+            return Err(Error::Message(format!(
+                "The expression is nested deeper than the maximum depth {}.",
+                limits.max_nesting_depth
+            )));
+        }
+
+        self.depth += 1;
         let result = match expression {
-            Expression::Literal(literal) => self.emit_literal(literal)?,
-            Expression::Identifier(_) => todo!(),
-            Expression::Group(expressions) => self.emit_group(expressions)?,
-            Expression::FunctionCall(_) => todo!(),
-            Expression::Or(left, right) => self.emit_logic_or(left, right)?,
+            Expression::Literal(literal) => self.emit_literal(literal),
+            Expression::Identifier(name) => self.emit_back_reference(name),
+            Expression::Group(expressions) => self.emit_group(expressions),
+            Expression::FunctionCall(function_call) => self.emit_function_call(function_call),
+            Expression::OrList(alternatives) => self.emit_logic_or(alternatives),
         };
+        self.depth -= 1;
 
-        Ok(result)
+        result
     }
 
     fn emit_group(&mut self, expressions: &[Expression]) -> Result<EmitResult, Error> {
@@ -65,11 +238,52 @@ impl<'a> Compiler<'a> {
         //
 
         let mut results = vec![];
-        for expression in expressions {
-            results.push(self.emit_expression(expression)?);
+        let mut idx = 0;
+        while idx < expressions.len() {
+            // the adjacent chars and strings are fused into one string
+            // transition, e.g. `'a', 'b', "cd"` is compiled as `"abcd"`.
+            let literal_count = expressions[idx..]
+                .iter()
+                .take_while(|e| {
+                    matches!(
+                        e,
+                        Expression::Literal(Literal::Char(_) | Literal::String(_))
+                    )
+                })
+                .count();
+
+            if literal_count > 1 {
+                for _ in 0..literal_count {
+                    self.count_node()?;
+                }
+
+                let s: String = expressions[idx..idx + literal_count]
+                    .iter()
+                    .filter_map(get_literal_string)
+                    .collect();
+                results.push(self.emit_literal_string(&s)?);
+                idx += literal_count;
+            } else {
+                results.push(self.emit_expression(&expressions[idx])?);
+                idx += 1;
+            }
         }
 
-        if results.len() == 1 {
+        if results.is_empty() {
+            // the empty group matches the empty string, e.g. `()` and
+            // the empty alternative of `('a' || )`
+            //
+            //  in           out
+            // --o==jump==>o--
+            let in_state_index = self.state_set.new_state();
+            let out_state_index = self.state_set.new_state();
+            self.state_set.append_transition(
+                in_state_index,
+                out_state_index,
+                Transition::Jump(JumpTransition),
+            );
+            Ok(EmitResult::new(in_state_index, out_state_index))
+        } else if results.len() == 1 {
             // eliminates the nested group, e.g. '(((...)))'
             let result = results.pop().unwrap();
             Ok(result)
@@ -94,288 +308,2368 @@ impl<'a> Compiler<'a> {
         }
     }
 
-    fn emit_logic_or(
-        &mut self,
-        left: &Expression,
-        right: &Expression,
-    ) -> Result<EmitResult, Error> {
-        //                    left
+    fn emit_logic_or(&mut self, alternatives: &[Expression]) -> Result<EmitResult, Error> {
+        //                alternative 0
         //                /-----------\
         //      /==jump==--o in  out o--==jump==\
-        //  in  |         \-----------/         |  out
-        // --o==|                               |==o--
-        //      |             right             |
-        //      |         /-----------\         |
+        //      |         \-----------/         |
+        //  in  |         alternative 1         |  out
+        // --o==|         /-----------\         |==o--
+        //      |==jump==--o in  out o--==jump==|
+        //      |         \-----------/         |
+        //      |              ...              |
         //      \==jump==--o in  out o--==jump==/
-        //                \-----------/
+        //
+        // the alternatives are tried in order, and they are all connected
+        // to the same pair of states, so the long alternation is not
+        // nested, e.g. `'a' || 'b' || 'c'`.
+
+        // the alternation of many chars and strings is compiled into a trie,
+        // e.g. `"GET" || "PUT" || "POST"`, so that the common prefixes are
+        // matched once instead of once per branch.
+        //
+        // the case-insensitive literals are excluded since the different
+        // chars may match the same text.
+        if !self.ignore_case() {
+            let mut branches = vec![];
+            if alternatives
+                .iter()
+                .all(|e| collect_literal_branches(e, &mut branches))
+                && branches.len() >= MIN_TRIE_BRANCHES
+            {
+                for _ in 0..branches.len() {
+                    self.count_node()?;
+                }
+
+                let branches: Vec<Vec<char>> =
+                    branches.iter().map(|s| s.chars().collect()).collect();
+                let branches: Vec<&[char]> = branches.iter().map(|b| b.as_slice()).collect();
+
+                let in_state_index = self.state_set.new_state();
+                let out_state_index = self.state_set.new_state();
+                self.emit_literal_trie(in_state_index, out_state_index, &branches);
+                return Ok(EmitResult::new(in_state_index, out_state_index));
+            }
+        }
+
+        let logic_or_number = self.logic_or_count;
+        self.logic_or_count += 1;
 
-        let left_result = self.emit_expression(left)?;
-        let right_result = self.emit_expression(right)?;
+        let mut results = vec![];
+        for (idx, alternative) in alternatives.iter().enumerate() {
+            self.branch_path.push((logic_or_number, idx));
+            let result = self.emit_expression(alternative);
+            self.branch_path.pop();
+            results.push(result?);
+        }
+
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+
+        for result in results {
+            self.state_set.append_transition(
+                in_state_index,
+                result.in_state_index,
+                Transition::Jump(JumpTransition),
+            );
+            self.state_set.append_transition(
+                result.out_state_index,
+                out_state_index,
+                Transition::Jump(JumpTransition),
+            );
+        }
+
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_trie(
+        &mut self,
+        state_index: usize,
+        out_state_index: usize,
+        branches: &[&[char]],
+    ) {
+        // the branches are grouped by their first chars, and the common
+        // prefix of each group is matched by one string transition, e.g.
+        // `"PUT" || "POST" || "PATCH" || "GET"` is compiled into:
+        //
+        //                 "UT"
+        //             /--------------\
+        //      'P'    |   "OST"      |
+        //  in /----o--|--------------|---\  out
+        // --o-|       |   "ATCH"     |   |==o--
+        //     |       \--------------/   |
+        //     |   "GET"                  |
+        //     \--------------------------/
+        //
+        // the branches that start with different chars never match the
+        // same text, so only the order of the branches in the same group
+        // matters. the branch that ends at the current node (i.e. the empty
+        // remainder) matches together with any group, so the groups before
+        // it are closed to keep the order of the branches, e.g. for
+        // `"ab" || "a" || "abc"`, the node after 'a' tries "b", then
+        // the end, then "bc".
+        enum Item<'b> {
+            End,
+            Group(Vec<&'b [char]>),
+        }
+
+        let mut items: Vec<Item> = vec![];
+        let mut open_start = 0; // the index of the first open group
+
+        for branch in branches {
+            match branch.first() {
+                None => {
+                    // the duplicated end is redundant
+                    if !matches!(items.last(), Some(Item::End)) {
+                        items.push(Item::End);
+                    }
+                    open_start = items.len();
+                }
+                Some(c) => {
+                    let group = items[open_start..].iter_mut().find_map(|item| match item {
+                        Item::Group(members) if members[0][0] == *c => Some(members),
+                        _ => None,
+                    });
+
+                    match group {
+                        Some(members) => members.push(branch),
+                        None => items.push(Item::Group(vec![branch])),
+                    }
+                }
+            }
+        }
+
+        for item in items {
+            match item {
+                Item::End => {
+                    self.state_set.append_transition(
+                        state_index,
+                        out_state_index,
+                        Transition::Jump(JumpTransition),
+                    );
+                }
+                Item::Group(members) => {
+                    let prefix_length = (1..members[0].len())
+                        .take_while(|idx| {
+                            members
+                                .iter()
+                                .all(|m| m.len() > *idx && m[*idx] == members[0][*idx])
+                        })
+                        .count()
+                        + 1;
+
+                    let prefix = &members[0][..prefix_length];
+                    let transition = if prefix_length == 1 {
+                        Transition::Char(CharTransition::new(prefix[0]))
+                    } else {
+                        Transition::String(StringTransition::new(
+                            &prefix.iter().collect::<String>(),
+                        ))
+                    };
+
+                    // the group of the same branches goes to the end directly
+                    if members.iter().all(|m| m.len() == prefix_length) {
+                        self.state_set
+                            .append_transition(state_index, out_state_index, transition);
+                        continue;
+                    }
+
+                    let next_state_index = self.state_set.new_state();
+                    self.state_set
+                        .append_transition(state_index, next_state_index, transition);
+
+                    let remainders: Vec<&[char]> =
+                        members.iter().map(|m| &m[prefix_length..]).collect();
+                    self.emit_literal_trie(next_state_index, out_state_index, &remainders);
+                }
+            }
+        }
+    }
+
+    fn emit_function_call(&mut self, function_call: &FunctionCall) -> Result<EmitResult, Error> {
+        let expression = &function_call.expression;
+
+        let result = match function_call.name {
+            FunctionName::Normalized => self.emit_function_normalized(expression)?,
+            FunctionName::IgnoreCase => {
+                self.ignore_case_depth += 1;
+                let result = self.emit_expression(expression);
+                self.ignore_case_depth -= 1;
+                result?
+            }
+            FunctionName::Line => self.emit_function_line(function_call)?,
+            FunctionName::Optional => self.emit_optional(expression, false)?,
+            FunctionName::OptionalLazy => self.emit_optional(expression, true)?,
+            FunctionName::OneOrMore => self.emit_repetition(expression, 1, None, false)?,
+            FunctionName::OneOrMoreLazy => self.emit_repetition(expression, 1, None, true)?,
+            FunctionName::ZeroOrMore => self.emit_repetition(expression, 0, None, false)?,
+            FunctionName::ZeroOrMoreLazy => self.emit_repetition(expression, 0, None, true)?,
+            FunctionName::Repeat | FunctionName::RepeatLazy => {
+                let n = self.get_repetition_count(function_call, 0)?;
+                let lazy = function_call.name == FunctionName::RepeatLazy;
+
+                // a fixed repetition of short literals is collapsed into
+                // a single string, e.g. `repeat("ab", 3)` is compiled
+                // into the string "ababab" instead of a counter loop.
+                match get_literal_string(expression) {
+                    Some(s)
+                        if n > 0
+                            && !s.is_empty()
+                            && s.chars().count() * n <= MAX_UNROLLED_STRING_LENGTH =>
+                    {
+                        self.emit_literal_string(&s.repeat(n))?
+                    }
+                    _ => self.emit_repetition(expression, n, Some(n), lazy)?,
+                }
+            }
+            FunctionName::RepeatRange | FunctionName::RepeatRangeLazy => {
+                let m = self.get_repetition_count(function_call, 0)?;
+                let n = self.get_repetition_count(function_call, 1)?;
+                let lazy = function_call.name == FunctionName::RepeatRangeLazy;
+                self.emit_repetition(expression, m, Some(n), lazy)?
+            }
+            FunctionName::AtLeast | FunctionName::AtLeastLazy => {
+                let n = self.get_repetition_count(function_call, 0)?;
+                let lazy = function_call.name == FunctionName::AtLeastLazy;
+                self.emit_repetition(expression, n, None, lazy)?
+            }
+            FunctionName::Name => {
+                let name = get_identifier_arg(function_call, 0)?;
+
+                // the calls are identified by their addresses in the program
+                let location = self
+                    .name_locations
+                    .iter()
+                    .find(|(call, _)| std::ptr::eq(*call, function_call))
+                    .map(|(_, location)| *location);
+                self.emit_capture(expression, Some(name), location)?
+            }
+            FunctionName::Capture => self.emit_capture(expression, None, None)?,
+            FunctionName::IsBefore => self.emit_look_around(function_call, false, false)?,
+            FunctionName::IsAfter => self.emit_look_around(function_call, true, false)?,
+            FunctionName::IsNotBefore => self.emit_look_around(function_call, false, true)?,
+            FunctionName::IsNotAfter => self.emit_look_around(function_call, true, true)?,
+            FunctionName::Tag => self.emit_tag(function_call)?,
+        };
+
+        Ok(result)
+    }
+
+    fn emit_capture(
+        &mut self,
+        expression: &Expression,
+        name: Option<String>,
+        location: Option<Location>,
+    ) -> Result<EmitResult, Error> {
+        //                        expression
+        //                      /-----------\
+        //  in                  |           |                out
+        // --o==capture start==--o in  out o--==capture end==o--
+        //                      \-----------/
+        //
+        // the capture groups are numbered by the order of their
+        // opening while compiling, since the back-references inside the
+        // group may refer to it, and they are renumbered by the order of
+        // their closing at the end, i.e. the order of their names in
+        // the source, see `StateSet::capture_groups`.
+        //
+        // the groups with the same name share one index if they are in
+        // different branches of an alternation (like the branch reset of
+        // PCRE), e.g. `name('a', x) || name('b', x)`, since only one of
+        // them can participate in a match.
+        // otherwise the duplicate name is an error.
+
+        let capture_index = match name {
+            Some(ref name) => self.get_named_capture_index(name, location)?,
+            None => None,
+        };
+
+        let capture_index = match capture_index {
+            Some(idx) => {
+                let group = &mut self.state_set.capture_groups[idx];
+                group.in_repetition |= self.repetition_depth > 0;
+                group.in_look_around |= self.look_around_depth > 0;
+                idx
+            }
+            None => {
+                let idx = self.state_set.capture_groups.len();
+                self.state_set.capture_groups.push(CaptureGroup {
+                    index: idx,
+                    name: name.clone(),
+                    in_repetition: self.repetition_depth > 0,
+                    in_look_around: self.look_around_depth > 0,
+                });
+                idx
+            }
+        };
+
+        if let Some(name) = name {
+            self.named_captures
+                .push((name, capture_index, self.branch_path.clone(), location));
+        }
+
+        let result = self.emit_expression(expression)?;
+        if !self.closed_captures.contains(&capture_index) {
+            self.closed_captures.push(capture_index);
+        }
 
         let in_state_index = self.state_set.new_state();
         let out_state_index = self.state_set.new_state();
 
         self.state_set.append_transition(
             in_state_index,
-            left_result.in_state_index,
-            Transition::Jump(JumpTransition),
-        );
-        self.state_set.append_transition(
-            in_state_index,
-            right_result.in_state_index,
-            Transition::Jump(JumpTransition),
+            result.in_state_index,
+            Transition::CaptureStart(CaptureStartTransition::new(
+                capture_index,
+                self.options.keep_first_capture,
+            )),
         );
-
         self.state_set.append_transition(
-            left_result.out_state_index,
+            result.out_state_index,
             out_state_index,
-            Transition::Jump(JumpTransition),
+            Transition::CaptureEnd(CaptureEndTransition::new(
+                capture_index,
+                self.options.keep_first_capture,
+            )),
         );
+
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_tag(&mut self, function_call: &FunctionCall) -> Result<EmitResult, Error> {
+        //      expression
+        //  /-------------\                 out
+        // --o in      out o--==tag==--o--
+        //  \-------------/
+        //
+        // the tags with the same name share one index, e.g.
+        // `tag("GET", "read") || tag("HEAD", "read")`.
+
+        let name = match function_call.args.first() {
+            Some(FunctionCallArg::Expression(e)) => match e.as_ref() {
+                Expression::Literal(Literal::String(s)) => s.to_owned(),
+                _ => return Err(tag_arg_error()),
+            },
+            _ => return Err(tag_arg_error()),
+        };
+
+        let tag_index = match self.state_set.tags.iter().position(|tag| *tag == name) {
+            Some(idx) => idx,
+            None => {
+                self.state_set.tags.push(name);
+                self.state_set.tags.len() - 1
+            }
+        };
+
+        let result = self.emit_expression(&function_call.expression)?;
+        let out_state_index = self.state_set.new_state();
         self.state_set.append_transition(
-            right_result.out_state_index,
+            result.out_state_index,
             out_state_index,
-            Transition::Jump(JumpTransition),
+            Transition::Tag(TagTransition { tag_index }),
         );
 
-        Ok(EmitResult::new(in_state_index, out_state_index))
+        Ok(EmitResult::new(result.in_state_index, out_state_index))
     }
 
-    fn emit_literal(&mut self, literal: &Literal) -> Result<EmitResult, Error> {
-        let result = match literal {
-            Literal::Char(character) => self.emit_literal_char(*character, false)?,
-            Literal::String(_) => todo!(),
-            Literal::Status(_) => todo!(),
-            Literal::CharSet(_) => todo!(),
-            Literal::PresetCharSet(_) => todo!(),
+    fn emit_look_around(
+        &mut self,
+        function_call: &FunctionCall,
+        is_behind: bool,
+        negative: bool,
+    ) -> Result<EmitResult, Error> {
+        // `A.is_before(B)`, `A.is_not_before(B)`:
+        //
+        //      A
+        //  /-------\                       out
+        // --o in  out o--==look ahead B==o--
+        //  \-------/
+        //
+        // `A.is_after(B)`, `A.is_not_after(B)`:
+        //
+        //  in                            A
+        // --o==look behind B==--o in  out o--
+        //                      \-------/
+        //
+        // the expression B is compiled into a separate line:
+        //
+        //      B
+        //  /-------\
+        //  |o in  out o|
+        //  \-------/
+
+        let result = self.emit_expression(&function_call.expression)?;
+
+        // the single identifier argument is a back-reference,
+        // e.g. `'a'.is_after(x)`.
+        let identifier;
+        let line_expression = match function_call.args.first() {
+            Some(FunctionCallArg::Expression(e)) => Some(e.as_ref()),
+            Some(FunctionCallArg::Identifier(name)) => {
+                identifier = Expression::Identifier(name.to_owned());
+                Some(&identifier)
+            }
+            _ => None,
         };
 
-        Ok(result)
+        let line_result = match line_expression {
+            Some(e) => {
+                // the lines with captures are not shared, since each
+                // capture has its own index.
+                let key = (!contains_capture(e)).then(|| (e.to_string(), self.ignore_case()));
+
+                match key.as_ref().and_then(|k| self.look_around_lines.get(k)) {
+                    Some(line_result) => *line_result,
+                    None => {
+                        if self.look_around_depth >= self.options.limits.max_look_around_depth {
+                            return Err(Error::Message(format!(
+                                "The look-around assertions are nested deeper than the limit {}.",
+                                self.options.limits.max_look_around_depth
+                            )));
+                        }
+
+                        self.look_around_depth += 1;
+                        let result = self.emit_expression(e);
+                        self.look_around_depth -= 1;
+                        let line_result = result?;
+
+                        if let Some(key) = key {
+                            self.look_around_lines.insert(key, line_result);
+                        }
+                        line_result
+                    }
+                }
+            }
+            _ => {
+                return Err(Error::Message(format!(
+                    "The function \"{}\" requires an expression as argument 1.",
+                    function_call.name
+                )));
+            }
+        };
+
+        let transition = Transition::LookAround(LookAroundTransition::new(
+            line_result.in_state_index,
+            line_result.out_state_index,
+            is_behind,
+            negative,
+        ));
+
+        let assertion_state_index = self.state_set.new_state();
+
+        if is_behind {
+            self.state_set.append_transition(
+                assertion_state_index,
+                result.in_state_index,
+                transition,
+            );
+            Ok(EmitResult::new(
+                assertion_state_index,
+                result.out_state_index,
+            ))
+        } else {
+            self.state_set.append_transition(
+                result.out_state_index,
+                assertion_state_index,
+                transition,
+            );
+            Ok(EmitResult::new(
+                result.in_state_index,
+                assertion_state_index,
+            ))
+        }
     }
 
-    fn emit_literal_char(&mut self, character: char, inverse: bool) -> Result<EmitResult, Error> {
-        let in_state_index = self.state_set.new_state();
-        let out_state_index = self.state_set.new_state();
-        let transition = Transition::Char(CharTransition::new(character /*, inverse */));
-        self.state_set
-            .append_transition(in_state_index, out_state_index, transition);
-        Ok(EmitResult::new(in_state_index, out_state_index))
+    // returns the index of the existing group with the same name,
+    // or None if the name is new.
+    fn get_named_capture_index(
+        &self,
+        name: &str,
+        location: Option<Location>,
+    ) -> Result<Option<usize>, Error> {
+        let mut capture_index = None;
+
+        for (existing_name, idx, branch_path, previous_location) in &self.named_captures {
+            if existing_name != name {
+                continue;
+            }
+
+            // two branch paths are exclusive if they go into different
+            // branches of the same alternation.
+            let exclusive = branch_path.iter().any(|(number, index)| {
+                self.branch_path
+                    .iter()
+                    .any(|(n, i)| n == number && i != index)
+            });
+
+            if !exclusive {
+                return Err(match (location, previous_location) {
+                    (Some(location), Some(previous)) => Error::MessageWithLocation(
+                        format!(
+                            "The capture name \"{}\" is duplicated, the previous one is at line {}, column {}.",
+                            name,
+                            previous.line + 1,
+                            previous.column + 1
+                        ),
+                        location,
+                    ),
+                    _ => Error::Message(format!("The capture name \"{}\" is duplicated.", name)),
+                });
+            }
+
+            capture_index = Some(*idx);
+        }
+
+        Ok(capture_index)
     }
-}
 
-struct EmitResult {
-    in_state_index: usize,
-    out_state_index: usize,
-}
+    fn emit_back_reference(&mut self, name: &str) -> Result<EmitResult, Error> {
+        // the identifier refers to a named capture group that is defined
+        // before it in the function form of the pattern, e.g.
+        // `name(char_word+, x), '=', x`.
+        //
+        // the back-reference may also cross the boundary of a look-around,
+        // e.g. `name(char_digit, x), 'a'.is_after(x)` and
+        // `name('a', x).is_before(char_any, x)`, since the sub-thread of the
+        // look-around starts with the captures of the main thread (and the
+        // captures of a positive look-around are kept), and the positions of
+        // the captures are absolute, the look-behind that runs from the
+        // start positions before the current position needs no adjustment.
+        //
+        // the back-reference to a group that has not been captured fails.
+        let capture_index = match self
+            .named_captures
+            .iter()
+            .find(|(existing_name, _, _, _)| existing_name == name)
+        {
+            Some((_, idx, _, _)) => *idx,
+            None => {
+                return Err(Error::Message(format!(
+                    "The back-reference \"{}\" does not refer to a capture group defined before it.",
+                    name
+                )));
+            }
+        };
 
-impl EmitResult {
-    fn new(in_state_index: usize, out_state_index: usize) -> Self {
-        EmitResult {
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        self.state_set.append_transition(
             in_state_index,
             out_state_index,
-        }
+            Transition::BackReference(BackReferenceTransition::new(
+                capture_index,
+                self.ignore_case(),
+            )),
+        );
+        Ok(EmitResult::new(in_state_index, out_state_index))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use pretty_assertions::assert_str_eq;
+    fn emit_optional(&mut self, expression: &Expression, lazy: bool) -> Result<EmitResult, Error> {
+        //                 expression
+        //               /-----------\
+        //     /==jump==--o in  out o--==jump==\
+        //  in |         \-----------/         | out
+        // --o=|                               |=o--
+        //     \==============jump=============/
+        //
+        // the jump to the expression is tried first if it is greedy.
 
-    use super::compile_from_str;
+        let result = self.emit_expression(expression)?;
 
-    #[test]
-    fn test_compile_char() {
-        {
-            let state_set = compile_from_str(r#"'a'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
 
-            assert_str_eq!(
-                s,
-                "\
-> 0
-  -> 1, Char 'a'
-< 1"
+        if lazy {
+            self.state_set.append_transition(
+                in_state_index,
+                out_state_index,
+                Transition::Jump(JumpTransition),
+            );
+            self.state_set.append_transition(
+                in_state_index,
+                result.in_state_index,
+                Transition::Jump(JumpTransition),
+            );
+        } else {
+            self.state_set.append_transition(
+                in_state_index,
+                result.in_state_index,
+                Transition::Jump(JumpTransition),
+            );
+            self.state_set.append_transition(
+                in_state_index,
+                out_state_index,
+                Transition::Jump(JumpTransition),
             );
         }
 
-        {
-            let state_set = compile_from_str(r#"'a', 'b', 'c'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+        self.state_set.append_transition(
+            result.out_state_index,
+            out_state_index,
+            Transition::Jump(JumpTransition),
+        );
 
-            assert_str_eq!(
-                s,
-                "\
-> 0
-  -> 1, Char 'a'
-- 1
-  -> 2, Jump
-- 2
-  -> 3, Char 'b'
-- 3
-  -> 4, Jump
-- 4
-  -> 5, Char 'c'
-< 5"
-            );
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_repetition(
+        &mut self,
+        expression: &Expression,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+    ) -> Result<EmitResult, Error> {
+        //                            expression
+        //                          /-----------\
+        //            /==check==>--o in  out o--==inc==\
+        //  in        |             \-----------/       |
+        // --o==reset==o head <=========================/
+        //            |
+        //            \==exit==> o out
+        //
+        // the counter is pushed by the "reset" transition and popped by
+        // the "exit" transition, it is carried by the backtracking frame.
+        //
+        // the "check" transition is tried first if it is greedy.
+
+        if matches!(max, Some(n) if n < min) {
+            return Err(Error::Message(format!(
+                "The repetition range {{{}, {}}} is invalid.",
+                min,
+                max.unwrap()
+            )));
         }
 
-        {
-            let state_set = compile_from_str(r#"'a',('b','c'), 'd'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+        self.repetition_depth += 1;
+        let result = self.emit_expression(expression);
+        self.repetition_depth -= 1;
+        let result = result?;
 
-            assert_str_eq!(
-                s,
-                "\
-> 0
-  -> 1, Char 'a'
-- 1
+        let in_state_index = self.state_set.new_state();
+        let head_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+
+        self.state_set.append_transition(
+            in_state_index,
+            head_state_index,
+            Transition::CounterReset(CounterResetTransition),
+        );
+
+        if lazy {
+            self.state_set.append_transition(
+                head_state_index,
+                out_state_index,
+                Transition::CounterExit(CounterExitTransition::new(min)),
+            );
+            self.state_set.append_transition(
+                head_state_index,
+                result.in_state_index,
+                Transition::CounterCheck(CounterCheckTransition::new(max)),
+            );
+        } else {
+            self.state_set.append_transition(
+                head_state_index,
+                result.in_state_index,
+                Transition::CounterCheck(CounterCheckTransition::new(max)),
+            );
+            self.state_set.append_transition(
+                head_state_index,
+                out_state_index,
+                Transition::CounterExit(CounterExitTransition::new(min)),
+            );
+        }
+
+        self.state_set.append_transition(
+            result.out_state_index,
+            head_state_index,
+            Transition::CounterInc(CounterIncTransition::new(min)),
+        );
+
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_function_normalized(&mut self, expression: &Expression) -> Result<EmitResult, Error> {
+        let s = match expression {
+            Expression::Literal(Literal::String(s)) => s.to_owned(),
+            Expression::Literal(Literal::Char(c)) => c.to_string(),
+            _ => {
+                return Err(Error::Message(
+                    "The function \"normalized\" only accepts a string or a char.".to_owned(),
+                ));
+            }
+        };
+
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = if self.ignore_case() {
+            Transition::NormalizedString(NormalizedStringTransition::new_ignore_case(&s))
+        } else {
+            Transition::NormalizedString(NormalizedStringTransition::new(&s))
+        };
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_function_line(&mut self, function_call: &FunctionCall) -> Result<EmitResult, Error> {
+        // `line(a, b, ...)` is compiled as `line_start, a, b, ..., line_end`
+        //
+        //  in                                        out
+        // --o==line start==o--a--o==jump==o--b--...--o==line end==o--
+        //
+        let mut results = vec![
+            self.emit_literal_status("line_start")?,
+            self.emit_expression(&function_call.expression)?,
+        ];
+
+        for arg in &function_call.args {
+            let result = match arg {
+                FunctionCallArg::Expression(expression) => self.emit_expression(expression)?,
+                FunctionCallArg::Identifier(name) => {
+                    self.emit_expression(&Expression::Identifier(name.to_owned()))?
+                }
+                FunctionCallArg::Number(_) => {
+                    return Err(Error::Message(
+                        "The function \"line\" only accepts expressions.".to_owned(),
+                    ));
+                }
+            };
+            results.push(result);
+        }
+
+        results.push(self.emit_literal_status("line_end")?);
+
+        for pair in results.windows(2) {
+            self.state_set.append_transition(
+                pair[0].out_state_index,
+                pair[1].in_state_index,
+                Transition::Jump(JumpTransition),
+            );
+        }
+
+        Ok(EmitResult::new(
+            results[0].in_state_index,
+            results[results.len() - 1].out_state_index,
+        ))
+    }
+
+    fn emit_literal(&mut self, literal: &Literal) -> Result<EmitResult, Error> {
+        let result = match literal {
+            Literal::Char(character) => self.emit_literal_char(*character)?,
+            Literal::String(s) => self.emit_literal_string(s)?,
+            Literal::Status(name) => self.emit_literal_status(name)?,
+            Literal::CharSet(charset) => self.emit_literal_charset(charset)?,
+            Literal::PresetCharSet(name) => self.emit_literal_preset_charset(name)?,
+            Literal::Special(name) => self.emit_literal_special_char(name)?,
+            Literal::Byte(byte) => self.emit_literal_byte(*byte)?,
+            Literal::ByteRange(byte_range) => self.emit_literal_byte_range(byte_range)?,
+            Literal::NumberRange(number_range) => self.emit_literal_number_range(number_range)?,
+        };
+
+        Ok(result)
+    }
+
+    fn emit_literal_char(
+        &mut self,
+        character: char, /*, inverse: bool */
+    ) -> Result<EmitResult, Error> {
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = if self.ignore_case() {
+            Transition::Char(CharTransition::new_ignore_case(character))
+        } else {
+            Transition::Char(CharTransition::new(character /*, inverse */))
+        };
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_string(&mut self, s: &str) -> Result<EmitResult, Error> {
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = if self.ignore_case() {
+            Transition::String(StringTransition::new_ignore_case(s))
+        } else {
+            Transition::String(StringTransition::new(s))
+        };
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_status(&mut self, name: &str) -> Result<EmitResult, Error> {
+        if name == "match_start_reset" {
+            let in_state_index = self.state_set.new_state();
+            let out_state_index = self.state_set.new_state();
+            self.state_set.append_transition(
+                in_state_index,
+                out_state_index,
+                Transition::MatchStartReset(MatchStartResetTransition),
+            );
+            return Ok(EmitResult::new(in_state_index, out_state_index));
+        }
+
+        // the line boundaries are matched regardless of the
+        // option `multiline`
+        let (status, multiline) = match name {
+            "line_start" => (Status::Start, true),
+            "line_end" => (Status::End, true),
+            _ => (status_from_str(name), self.options.multiline),
+        };
+
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = Transition::Status(StatusTransition::new(
+            status,
+            multiline,
+            multiline && self.options.crlf,
+            self.options.unicode,
+        ));
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_charset(&mut self, charset: &CharSet) -> Result<EmitResult, Error> {
+        let mut items = vec![];
+        for element in &charset.elements {
+            let item = match element {
+                CharSetElement::Char(c) => CharSetItem::Char(*c),
+                CharSetElement::CharRange(range) => {
+                    range.validate()?;
+                    CharSetItem::Range(range.start, range.end_included)
+                }
+                CharSetElement::PresetCharSet(name) => preset_charset_item_from_str(name),
+                CharSetElement::Status(name) => {
+                    return Err(Error::Message(format!(
+                        "The status \"{}\" can not be used in a charset.",
+                        name
+                    )));
+                }
+            };
+            items.push(item);
+        }
+
+        self.emit_charset_items(items, charset.negative)
+    }
+
+    fn emit_literal_preset_charset(&mut self, name: &str) -> Result<EmitResult, Error> {
+        self.emit_charset_items(vec![preset_charset_item_from_str(name)], false)
+    }
+
+    fn emit_charset_items(
+        &mut self,
+        items: Vec<CharSetItem>,
+        negative: bool,
+    ) -> Result<EmitResult, Error> {
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = Transition::CharSet(CharSetTransition::new(
+            items,
+            negative,
+            self.ignore_case(),
+            self.options.unicode,
+            self.options.grapheme,
+        ));
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_special_char(&mut self, name: &str) -> Result<EmitResult, Error> {
+        let (include_new_line, grapheme) = match name {
+            "char_any" => (self.options.dot_all, self.options.grapheme),
+            "char_any_including_newline" => (true, self.options.grapheme),
+            "grapheme_any" => (self.options.dot_all, true),
+            _ => unreachable!(),
+        };
+
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = Transition::SpecialChar(SpecialCharTransition::new(
+            include_new_line,
+            !include_new_line && self.options.crlf,
+            grapheme,
+        ));
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_byte(&mut self, byte: u8) -> Result<EmitResult, Error> {
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = Transition::Byte(ByteTransition::new(byte));
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_byte_range(&mut self, byte_range: &ByteRange) -> Result<EmitResult, Error> {
+        let in_state_index = self.state_set.new_state();
+        let out_state_index = self.state_set.new_state();
+        let transition = Transition::ByteRange(ByteRangeTransition::new(
+            byte_range.start,
+            byte_range.end_included,
+        ));
+        self.state_set
+            .append_transition(in_state_index, out_state_index, transition);
+        Ok(EmitResult::new(in_state_index, out_state_index))
+    }
+
+    fn emit_literal_number_range(
+        &mut self,
+        number_range: &NumberRange,
+    ) -> Result<EmitResult, Error> {
+        // the range is expanded to an alternation of digit sequences, e.g.
+        // `number_range(0, 255)` is expanded to:
+        //
+        // ('1', ['0'..'9'], ['0'..'9'])
+        // || ('2', ['0'..'4'], ['0'..'9'])
+        // || ('2', '5', ['0'..'5'])
+        // || (['1'..'9'], ['0'..'9'])
+        // || (['0'..'9'])
+        //
+        // the longer numbers are placed first so that the longest
+        // number is preferred.
+        let mut sequences = vec![];
+        let mut width = number_width(number_range.end_included);
+        loop {
+            let lower = if width == 1 { 0 } else { 10u32.pow(width - 1) };
+            let upper = 10u32.checked_pow(width).map_or(u32::MAX, |n| n - 1);
+            let start = number_range.start.max(lower);
+            let end_included = number_range.end_included.min(upper);
+            if start <= end_included {
+                sequences.extend(split_number_range(start, end_included, width));
+            }
+
+            if width == 1 || lower <= number_range.start {
+                break;
+            }
+            width -= 1;
+        }
+
+        let mut alternatives: Vec<Expression> = sequences
+            .into_iter()
+            .map(|sequence| {
+                let expressions = sequence
+                    .into_iter()
+                    .map(|(start, end_included)| {
+                        let literal = if start == end_included {
+                            Literal::Char(start)
+                        } else {
+                            Literal::CharSet(CharSet {
+                                negative: false,
+                                elements: vec![CharSetElement::CharRange(CharRange {
+                                    start,
+                                    end_included,
+                                })],
+                            })
+                        };
+                        Expression::Literal(literal)
+                    })
+                    .collect();
+                Expression::Group(expressions)
+            })
+            .collect();
+
+        let expression = if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Expression::OrList(alternatives)
+        };
+        self.emit_expression(&expression)
+    }
+}
+
+fn number_width(number: u32) -> u32 {
+    number.checked_ilog10().unwrap_or(0) + 1
+}
+
+// splits the range of numbers with the same width into sequences
+// of digit ranges, e.g. 130..=255 (width 3) is split into:
+//
+// - ['1'], ['3'..'9'], ['0'..'9']
+// - ['2'], ['0'..'4'], ['0'..'9']
+// - ['2'], ['5'], ['0'..'5']
+//
+// the leading digits of the sub-ranges can be zero.
+fn split_number_range(start: u32, end_included: u32, width: u32) -> Vec<Vec<(char, char)>> {
+    let digit = |n: u32| char::from_digit(n, 10).unwrap();
+
+    if width == 1 {
+        return vec![vec![(digit(start), digit(end_included))]];
+    }
+
+    let unit = 10u32.pow(width - 1);
+    let (start_head, start_rest) = (start / unit, start % unit);
+    let (end_head, end_rest) = (end_included / unit, end_included % unit);
+
+    let prefix = |head: u32, sequences: Vec<Vec<(char, char)>>| {
+        sequences
+            .into_iter()
+            .map(|mut sequence| {
+                sequence.insert(0, (digit(head), digit(head)));
+                sequence
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if start_head == end_head {
+        return prefix(
+            start_head,
+            split_number_range(start_rest, end_rest, width - 1),
+        );
+    }
+
+    let mut sequences = vec![];
+
+    let mut middle_start = start_head;
+    if start_rest != 0 {
+        sequences.extend(prefix(
+            start_head,
+            split_number_range(start_rest, unit - 1, width - 1),
+        ));
+        middle_start += 1;
+    }
+
+    let mut middle_end = end_head;
+    if end_rest != unit - 1 {
+        middle_end -= 1;
+    }
+
+    if middle_start <= middle_end {
+        let mut sequence = vec![(digit(middle_start), digit(middle_end))];
+        sequence.extend(std::iter::repeat_n(('0', '9'), width as usize - 1));
+        sequences.push(sequence);
+    }
+
+    if end_rest != unit - 1 {
+        sequences.extend(prefix(end_head, split_number_range(0, end_rest, width - 1)));
+    }
+
+    sequences
+}
+
+fn status_from_str(name: &str) -> Status {
+    match name {
+        "start" => Status::Start,
+        "end" => Status::End,
+        "bound" => Status::Bound,
+        "not_bound" => Status::NotBound,
+        "word_start" => Status::WordStart,
+        "word_end" => Status::WordEnd,
+        "continue_from_last" => Status::ContinueFromLast,
+        _ => unreachable!(),
+    }
+}
+
+fn preset_charset_item_from_str(name: &str) -> CharSetItem {
+    match name {
+        "char_space" => CharSetItem::Preset(PresetCharSet::Space, false),
+        "char_not_space" => CharSetItem::Preset(PresetCharSet::Space, true),
+        "char_space_ascii" => CharSetItem::Preset(PresetCharSet::AsciiSpace, false),
+        "char_not_space_ascii" => CharSetItem::Preset(PresetCharSet::AsciiSpace, true),
+        "char_word" => CharSetItem::Preset(PresetCharSet::Word, false),
+        "char_not_word" => CharSetItem::Preset(PresetCharSet::Word, true),
+        "char_digit" => CharSetItem::Preset(PresetCharSet::Digit, false),
+        "char_not_digit" => CharSetItem::Preset(PresetCharSet::Digit, true),
+        _ => unreachable!(),
+    }
+}
+
+// returns the text matched by the expression if it consists of
+// chars and strings only, e.g. `('a', "bc")` returns "abc".
+fn get_literal_string(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Literal(Literal::Char(c)) => Some(c.to_string()),
+        Expression::Literal(Literal::String(s)) => Some(s.to_owned()),
+        Expression::Group(expressions) => {
+            let mut s = String::new();
+            for expression in expressions {
+                s.push_str(&get_literal_string(expression)?);
+            }
+            Some(s)
+        }
+        Expression::FunctionCall(function_call)
+            if matches!(
+                function_call.name,
+                FunctionName::Repeat | FunctionName::RepeatLazy
+            ) =>
+        {
+            let n = get_number_arg(function_call, 0).ok()?;
+            let s = get_literal_string(&function_call.expression)?;
+
+            // the long strings are not built, see `MAX_UNROLLED_STRING_LENGTH`
+            if s.chars().count().saturating_mul(n) > MAX_UNROLLED_STRING_LENGTH {
+                return None;
+            }
+            Some(s.repeat(n))
+        }
+        _ => None,
+    }
+}
+
+// collect the texts of the branches of an alternation, returns false
+// if any branch is not a char or string, e.g. `'a' || ("bc" || 'd')`
+// collects "a", "bc" and "d".
+fn collect_literal_branches(expression: &Expression, branches: &mut Vec<String>) -> bool {
+    match expression {
+        Expression::OrList(alternatives) => alternatives
+            .iter()
+            .all(|e| collect_literal_branches(e, branches)),
+        Expression::Group(expressions) if expressions.len() == 1 => {
+            collect_literal_branches(&expressions[0], branches)
+        }
+        _ => match get_literal_string(expression) {
+            Some(s) => {
+                branches.push(s);
+                true
+            }
+            None => false,
+        },
+    }
+}
+
+fn contains_capture(expression: &Expression) -> bool {
+    match expression {
+        Expression::Group(expressions) => expressions.iter().any(contains_capture),
+        Expression::OrList(alternatives) => alternatives.iter().any(contains_capture),
+        Expression::FunctionCall(function_call) => {
+            matches!(
+                function_call.name,
+                FunctionName::Name | FunctionName::Capture
+            ) || contains_capture(&function_call.expression)
+                || function_call.args.iter().any(|arg| match arg {
+                    FunctionCallArg::Expression(e) => contains_capture(e),
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+// the locations of the `name(...)` calls of the expression, the children
+// of the node are the expression and the arguments that are expressions,
+// see `NodeLocation`, and the name takes the location of the function
+// call, the same as the back-references, see `find_back_references`.
+fn collect_name_locations<'a>(
+    expression: &'a Expression,
+    node: &NodeLocation,
+    name_locations: &mut Vec<(&'a FunctionCall, Location)>,
+) {
+    match expression {
+        Expression::Group(expressions) => {
+            for (e, child) in expressions.iter().zip(&node.children) {
+                collect_name_locations(e, child, name_locations);
+            }
+        }
+        Expression::OrList(alternatives) => {
+            for (e, child) in alternatives.iter().zip(&node.children) {
+                collect_name_locations(e, child, name_locations);
+            }
+        }
+        Expression::FunctionCall(function_call) => {
+            if function_call.name == FunctionName::Name {
+                name_locations.push((function_call, node.location));
+            }
+
+            let expressions = std::iter::once(function_call.expression.as_ref()).chain(
+                function_call.args.iter().filter_map(|arg| match arg {
+                    FunctionCallArg::Expression(e) => Some(e.as_ref()),
+                    _ => None,
+                }),
+            );
+            for (e, child) in expressions.zip(&node.children) {
+                collect_name_locations(e, child, name_locations);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn get_number_arg(function_call: &FunctionCall, index: usize) -> Result<usize, Error> {
+    match function_call.args.get(index) {
+        Some(FunctionCallArg::Number(n)) => Ok(*n as usize),
+        _ => Err(Error::Message(format!(
+            "The function \"{}\" requires a number as argument {}.",
+            function_call.name,
+            index + 1
+        ))),
+    }
+}
+
+fn get_identifier_arg(function_call: &FunctionCall, index: usize) -> Result<String, Error> {
+    match function_call.args.get(index) {
+        Some(FunctionCallArg::Identifier(s)) => Ok(s.to_owned()),
+        _ => Err(Error::Message(format!(
+            "The function \"{}\" requires an identifier as argument {}.",
+            function_call.name,
+            index + 1
+        ))),
+    }
+}
+
+fn tag_arg_error() -> Error {
+    Error::Message("The function \"tag\" requires a string as argument 1.".to_owned())
+}
+
+// the number of alternation and the index of the alternative
+type Branch = (usize, usize);
+
+#[derive(Clone, Copy)]
+struct EmitResult {
+    in_state_index: usize,
+    out_state_index: usize,
+}
+
+impl EmitResult {
+    fn new(in_state_index: usize, out_state_index: usize) -> Self {
+        EmitResult {
+            in_state_index,
+            out_state_index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_str_eq;
+
+    use crate::{
+        ast::{CharRange, CharSet, CharSetElement, Expression, Literal, PatternOptions, Program},
+        error::Error,
+        location::Location,
+        options::{CompileOptions, Limits},
+        parser::parse_from_str,
+    };
+
+    use super::{compile, compile_from_str, compile_from_str_with};
+
+    #[test]
+    fn test_compile_char() {
+        {
+            let state_set = compile_from_str(r#"'a'"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Char 'a'
+< 1"
+            );
+        }
+
+        {
+            // the adjacent chars are fused into a string
+            let state_set = compile_from_str(r#"'a', 'b', 'c'"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, String \"abc\"
+< 1"
+            );
+        }
+
+        {
+            let state_set = compile_from_str(r#"'a',('b','c'), 'd'"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Char 'a'
+- 1
+  -> 2, Jump
+- 2
+  -> 3, String \"bc\"
+- 3
+  -> 4, Jump
+- 4
+  -> 5, Char 'd'
+< 5"
+            );
+        }
+
+        {
+            let state_set = compile_from_str(r#"'a',('b', ('c', 'd'), 'e'), 'f'"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Char 'a'
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Char 'b'
+- 3
+  -> 4, Jump
+- 4
+  -> 5, String \"cd\"
+- 5
+  -> 6, Jump
+- 6
+  -> 7, Char 'e'
+- 7
+  -> 8, Jump
+- 8
+  -> 9, Char 'f'
+< 9"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_logic_or() {
+        {
+            let state_set = compile_from_str(r#"'a' || 'b'"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 5, Jump
+- 2
+  -> 3, Char 'b'
+- 3
+  -> 5, Jump
+> 4
+  -> 0, Jump
+  -> 2, Jump
+< 5"
+            );
+        }
+
+        {
+            // "'a', 'b' || 'c', 'd'" == "'a', ('b' || 'c'), 'd'"
+            let state_set = compile_from_str(r#"'a', 'b' || 'c', 'd'"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Char 'a'
+- 1
+  -> 6, Jump
+- 2
+  -> 3, Char 'b'
+- 3
+  -> 7, Jump
+- 4
+  -> 5, Char 'c'
+- 5
+  -> 7, Jump
+- 6
   -> 2, Jump
+  -> 4, Jump
+- 7
+  -> 8, Jump
+- 8
+  -> 9, Char 'd'
+< 9"
+            );
+        }
+
+        {
+            // "'a', 'b' || 'c', 'd'" == "'a', ('b' || 'c'), 'd'"
+            assert_str_eq!(
+                compile_from_str(r#"'a', 'b' || 'c', 'd'"#)
+                    .unwrap()
+                    .generate_states_and_transitions_text(),
+                compile_from_str(r#"'a', ('b' || 'c'), 'd'"#)
+                    .unwrap()
+                    .generate_states_and_transitions_text()
+            );
+        }
+
+        // associativity, the alternatives share the in and out states
+
+        {
+            let state_set = compile_from_str(r#"'a' || 'b' || char_digit"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 7, Jump
 - 2
   -> 3, Char 'b'
 - 3
-  -> 4, Jump
+  -> 7, Jump
+- 4
+  -> 5, Charset [char_digit]
+- 5
+  -> 7, Jump
+> 6
+  -> 0, Jump
+  -> 2, Jump
+  -> 4, Jump
+< 7"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_literal_trie() {
+        assert_str_eq!(
+            compile_from_str(r#"'a' || 'b' || 'c'"#)
+                .unwrap()
+                .generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 1, Char 'a'
+  -> 1, Char 'b'
+  -> 1, Char 'c'
+< 1"
+        );
+
+        assert_str_eq!(
+            compile_from_str(r#""PUT" || "POST" || ("PATCH" || "GET")"#)
+                .unwrap()
+                .generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 2, Char 'P'
+  -> 1, String \"GET\"
+< 1
+- 2
+  -> 1, String \"UT\"
+  -> 1, String \"OST\"
+  -> 1, String \"ATCH\""
+        );
+
+        // the order of the branches is kept, the end of "a" is tried
+        // between "ab" and "abc"
+        assert_str_eq!(
+            compile_from_str(r#""ab" || 'a' || "abc" || "" || "abd""#)
+                .unwrap()
+                .generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 2, Char 'a'
+  -> 1, Jump
+  -> 1, String \"abd\"
+< 1
+- 2
+  -> 1, Char 'b'
+  -> 1, Jump
+  -> 1, String \"bc\""
+        );
+
+        // the common prefix of a group
+        assert_str_eq!(
+            compile_from_str(r#""http://" || "https://" || "ftp://""#)
+                .unwrap()
+                .generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 2, String \"http\"
+  -> 1, String \"ftp://\"
+< 1
+- 2
+  -> 1, String \"://\"
+  -> 1, String \"s://\""
+        );
+
+        // not a trie: too few branches, case-insensitive, or non-literal
+        for (pattern, ignore_case) in [
+            (r#"'a' || 'b'"#, false),
+            (r#"'a' || 'b' || 'c'"#, true),
+            (r#"'a' || 'b' || char_digit"#, false),
+        ] {
+            let options = CompileOptions {
+                ignore_case,
+                ..CompileOptions::default()
+            };
+            let s = compile_from_str_with(pattern, &options)
+                .unwrap()
+                .generate_states_and_transitions_text();
+            assert!(s.contains("Jump"), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_compile_special_char() {
+        {
+            let state_set = compile_from_str(r#"'a', char_any"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Char 'a'
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Any char
+< 3"
+            );
+        }
+
+        {
+            let state_set = compile_from_str(r#"char_any_including_newline"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Any char including new line
+< 1"
+            );
+        }
+
+        {
+            let state_set = compile_from_str(r#"grapheme_any, options(grapheme), char_any"#);
+            assert!(state_set.is_err());
+
+            let state_set = compile_from_str(r#"options(grapheme), grapheme_any, !['a']"#).unwrap();
+            let s = state_set.generate_states_and_transitions_text();
+
+            assert_str_eq!(
+                s,
+                "\
+> 0
+  -> 1, Any char, grapheme
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Charset !['a'], grapheme
+< 3"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_string() {
+        let state_set = compile_from_str(r#""foo""#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, String \"foo\"
+< 1"
+        );
+
+        // the adjacent chars and strings are fused
+        let state_set = compile_from_str(r#"'a', "bc", char_digit, 'd', "", 'e'"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, String \"abc\"
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Charset [char_digit]
+- 3
+  -> 4, Jump
+- 4
+  -> 5, String \"de\"
+< 5"
+        );
+    }
+
+    #[test]
+    fn test_compile_function_normalized() {
+        let state_set = compile_from_str(r#"normalized("caf\u{e9}")"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, Normalized string \"cafe\\u{301}\"
+< 1"
+        );
+
+        // the option `ignore_case`
+        let options = CompileOptions {
+            ignore_case: true,
+            ..CompileOptions::default()
+        };
+        let state_set = compile_from_str_with(r#"normalized("caf\u{e9}")"#, &options).unwrap();
+        assert_str_eq!(
+            state_set.generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 1, Normalized string \"cafe\\u{301}\", ignore case
+< 1"
+        );
+
+        // err: not a string or char
+        assert!(compile_from_str(r#"normalized(('a', 'b'))"#).is_err());
+    }
+
+    #[test]
+    fn test_compile_byte() {
+        let state_set = compile_from_str(r#"byte(0x7f), byte_range(0x80..0xff)"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, Byte 0x7f
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Byte range 0x80..0xff
+< 3"
+        );
+    }
+
+    #[test]
+    fn test_compile_number_range() {
+        let state_set = compile_from_str(r#"number_range(5, 12)"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char '1'
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Charset ['0'..'2']
+- 3
+  -> 7, Jump
+- 4
+  -> 5, Charset ['5'..'9']
+- 5
+  -> 7, Jump
+> 6
+  -> 0, Jump
+  -> 4, Jump
+< 7"
+        );
+    }
+
+    #[test]
+    fn test_compile_empty_group() {
+        let state_set = compile_from_str(r#"'a' || ()"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 5, Jump
+- 2
+  -> 3, Jump
+- 3
+  -> 5, Jump
+> 4
+  -> 0, Jump
+  -> 2, Jump
+< 5"
+        );
+
+        // the empty program
+        let state_set = compile_from_str("").unwrap();
+        assert_str_eq!(
+            state_set.generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 1, Jump
+< 1"
+        );
+    }
+
+    #[test]
+    fn test_compile_first_chars() {
+        let get_first_chars = |s: &str| compile_from_str(s).unwrap().first_chars;
+
+        assert_eq!(get_first_chars(r#"'a', 'b'"#), Some(vec![('a', 'a')]));
+        assert_eq!(
+            get_first_chars(r#"start, ("foo" || ['x'..'z', '_']), 'b'"#),
+            Some(vec![('x', 'z'), ('_', '_'), ('f', 'f')])
+        );
+        assert_eq!(
+            get_first_chars(r#"name('a'{2,}, x).is_before('b')"#),
+            Some(vec![('a', 'a')])
+        );
+        assert_eq!(get_first_chars(r#"char_digit"#), Some(vec![('0', '9')]));
+
+        // any char can begin a match
+        assert_eq!(get_first_chars(r#"'a'*, 'b'?"#), None);
+        assert_eq!(get_first_chars(r#"'a' || char_any"#), None);
+        assert_eq!(get_first_chars(r#"!['a']"#), None);
+        assert_eq!(get_first_chars(r#"char_not_word"#), None);
+    }
+
+    #[test]
+    fn test_compile_optional() {
+        let state_set = compile_from_str(r#"'a'?"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 3, Jump
+> 2
+  -> 0, Jump
+  -> 3, Jump
+< 3"
+        );
+
+        // lazy
+        let state_set = compile_from_str(r#"'a'??"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 3, Jump
+> 2
+  -> 3, Jump
+  -> 0, Jump
+< 3"
+        );
+    }
+
+    #[test]
+    fn test_compile_repetition() {
+        let state_set = compile_from_str(r#"'a'+"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 3, Counter inc, min 1
+> 2
+  -> 3, Counter reset
+- 3
+  -> 0, Counter check, unbounded
+  -> 4, Counter exit, min 1
+< 4"
+        );
+
+        let state_set = compile_from_str(r#"'a'{2,3}?"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 3, Counter inc, min 2
+> 2
+  -> 3, Counter reset
+- 3
+  -> 4, Counter exit, min 2
+  -> 0, Counter check, max 3
+< 4"
+        );
+
+        // invalid range
+        assert!(compile_from_str(r#"'a'{3,2}"#).is_err());
+    }
+
+    #[test]
+    fn test_compile_repetition_of_literals() {
+        let state_set = compile_from_str(r#""abc"{3}"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, String \"abcabcabc\"
+< 1"
+        );
+
+        let state_set = compile_from_str(r#"('a', "bc", 'd'{2}){2}"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, String \"abcddabcdd\"
+< 1"
+        );
+
+        // not collapsed when the expression is not a pure literal
+        let state_set = compile_from_str(r#"char_digit{2}"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Charset [char_digit]
+- 1
+  -> 3, Counter inc, min 2
+> 2
+  -> 3, Counter reset
+- 3
+  -> 0, Counter check, max 2
+  -> 4, Counter exit, min 2
+< 4"
+        );
+    }
+
+    #[test]
+    fn test_compile_capture() {
+        let state_set = compile_from_str(r#"name('a', x), capture('b')"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 3, Capture end {1}
+> 2
+  -> 0, Capture start {1}
+- 3
+  -> 6, Jump
 - 4
-  -> 5, Char 'c'
+  -> 5, Char 'b'
 - 5
-  -> 6, Jump
+  -> 7, Capture end {2}
 - 6
-  -> 7, Char 'd'
+  -> 4, Capture start {2}
 < 7"
-            );
-        }
+        );
 
-        {
-            let state_set = compile_from_str(r#"'a',('b', ('c', 'd'), 'e'), 'f'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+        let names: Vec<Option<String>> = state_set
+            .capture_groups
+            .iter()
+            .map(|g| g.name.clone())
+            .collect();
+        assert_eq!(names, vec![None, Some("x".to_owned()), None]);
+    }
 
-            assert_str_eq!(
-                s,
-                "\
+    #[test]
+    fn test_compile_capture_numbering() {
+        let get_names = |s: &str| -> Vec<String> {
+            compile_from_str(s)
+                .unwrap()
+                .capture_groups
+                .iter()
+                .skip(1)
+                .map(|g| g.name.clone().unwrap())
+                .collect()
+        };
+
+        // left to right
+        assert_eq!(get_names(r#"name('a', x), name('b', y)"#), vec!["x", "y"]);
+
+        // inner before outer, by the order of the names
+        assert_eq!(
+            get_names(r#"name((name('a', x), name('b', y)), z)"#),
+            vec!["x", "y", "z"]
+        );
+
+        // the chained calls, the last call is the outermost
+        assert_eq!(get_names(r#"'a'.name(x).name(y)"#), vec!["x", "y"]);
+        assert_eq!(get_names(r#"name('a', x)+.name(y)"#), vec!["x", "y"]);
+        assert_eq!(
+            get_names(r#"name('a'.name(x), y), name('b', z).name(w)"#),
+            vec!["x", "y", "z", "w"]
+        );
+
+        // the transitions and the back-references are renumbered
+        let s = compile_from_str(r#"'a'.name(x).name(y), x"#)
+            .unwrap()
+            .generate_states_and_transitions_text();
+        assert!(s.contains("Capture start {1}") && s.contains("Capture end {2}"));
+        assert!(s.contains("Back reference {1}"));
+
+        // the subject before the look-around assertion
+        assert_eq!(
+            get_names(r#"name('a', x).is_after(name('b', y))"#),
+            vec!["x", "y"]
+        );
+        assert_eq!(
+            get_names(r#"name('a', x).is_before(name('b', y)), name('c', z)"#),
+            vec!["x", "y", "z"]
+        );
+
+        // the shared name takes the index of the first one
+        assert_eq!(
+            get_names(r#"(name('a', x) || (name('b', y), name('c', x))), name('d', z)"#),
+            vec!["x", "y", "z"]
+        );
+    }
+
+    #[test]
+    fn test_compile_look_around() {
+        let state_set = compile_from_str(r#"'a'.is_before('b')"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
 > 0
   -> 1, Char 'a'
 - 1
-  -> 2, Jump
+  -> 4, Look ahead (2, 3)
 - 2
   -> 3, Char 'b'
 - 3
-  -> 4, Jump
+< 4"
+        );
+
+        let state_set = compile_from_str(r#"'a'.is_not_after('b')"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+< 1
+- 2
+  -> 3, Char 'b'
+- 3
+> 4
+  -> 0, Negative look behind (2, 3)"
+        );
+
+        // the identical sub-expressions share one line
+        let state_set = compile_from_str(
+            r#"'a'.is_before("bc"), 'b'.is_not_after("bc"), 'c'.is_before(name("bc", x))"#,
+        )
+        .unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, Char 'a'
+- 1
+  -> 4, Look ahead (2, 3)
+- 2
+  -> 3, String \"bc\"
+- 3
 - 4
-  -> 5, Char 'c'
+  -> 7, Jump
 - 5
-  -> 6, Jump
+  -> 6, Char 'b'
 - 6
-  -> 7, Char 'd'
-- 7
   -> 8, Jump
+- 7
+  -> 5, Negative look behind (2, 3)
 - 8
-  -> 9, Char 'e'
+  -> 9, Char 'c'
 - 9
-  -> 10, Jump
+  -> 14, Look ahead (12, 13)
 - 10
-  -> 11, Char 'f'
-< 11"
-            );
-        }
+  -> 11, String \"bc\"
+- 11
+  -> 13, Capture end {1}
+- 12
+  -> 10, Capture start {1}
+- 13
+< 14"
+        );
     }
 
     #[test]
-    fn test_compile_logic_or() {
-        {
-            let state_set = compile_from_str(r#"'a' || 'b'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+    fn test_compile_charset_and_status() {
+        let state_set =
+            compile_from_str(r#"start, ['a'..'f', '_', char_not_space], char_digit"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
 
-            assert_str_eq!(
-                s,
-                "\
-- 0
-  -> 1, Char 'a'
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, Status start
 - 1
-  -> 5, Jump
+  -> 2, Jump
 - 2
-  -> 3, Char 'b'
+  -> 3, Charset ['a'..'f', '_', char_not_space]
 - 3
-  -> 5, Jump
-> 4
-  -> 0, Jump
-  -> 2, Jump
+  -> 4, Jump
+- 4
+  -> 5, Charset [char_digit]
 < 5"
-            );
-        }
+        );
 
-        {
-            // "'a', 'b' || 'c', 'd'" == "'a', ('b' || 'c'), 'd'"
-            let state_set = compile_from_str(r#"'a', 'b' || 'c', 'd'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+        // a status can not be used in a charset
+        assert!(compile_from_str(r#"['a', start]"#).is_err());
 
-            assert_str_eq!(
-                s,
-                "\
+        // the reversed range of the program that is constructed directly
+        let program = Program {
+            expressions: vec![Expression::Literal(Literal::CharSet(CharSet {
+                negative: false,
+                elements: vec![CharSetElement::CharRange(CharRange {
+                    start: 'z',
+                    end_included: 'a',
+                })],
+            }))],
+            tests: vec![],
+            options: PatternOptions::default(),
+            syntax_version: None,
+        };
+        assert_eq!(
+            compile(&program, &CompileOptions::default()).err(),
+            Some(Error::Message(
+                "The start of char range 'z'..'a' is greater than the end.".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compile_with_pattern_options() {
+        let state_set = compile_from_str(r#"options(ignore_case), 'a', end"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
 > 0
-  -> 1, Char 'a'
+  -> 1, Char 'a', ignore case
 - 1
-  -> 6, Jump
+  -> 2, Jump
 - 2
-  -> 3, Char 'b'
+  -> 3, Status end
+< 3"
+        );
+
+        // the options of the pattern are added to the passed ones
+        let options = CompileOptions {
+            multiline: true,
+            ..CompileOptions::default()
+        };
+        let state_set =
+            compile_from_str_with(r#"options(ignore_case), 'a', end"#, &options).unwrap();
+        assert_str_eq!(
+            state_set.generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 1, Char 'a', ignore case
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Status end, multiline
+< 3"
+        );
+    }
+
+    #[test]
+    fn test_compile_with_options() {
+        let options = CompileOptions {
+            ignore_case: true,
+            multiline: true,
+            ..CompileOptions::default()
+        };
+
+        let state_set = compile_from_str_with(r#"'a', !['x'], "bc", end"#, &options).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, Char 'a', ignore case
+- 1
+  -> 2, Jump
+- 2
+  -> 3, Charset !['x'], ignore case
 - 3
-  -> 7, Jump
+  -> 4, Jump
 - 4
-  -> 5, Char 'c'
+  -> 5, String \"bc\", ignore case
 - 5
-  -> 7, Jump
+  -> 6, Jump
 - 6
+  -> 7, Status end, multiline
+< 7"
+        );
+    }
+
+    #[test]
+    fn test_compile_ignore_case_function() {
+        let state_set = compile_from_str(r#"ignore_case("ab"), 'c'"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
+  -> 1, String \"ab\", ignore case
+- 1
   -> 2, Jump
-  -> 4, Jump
-- 7
-  -> 8, Jump
-- 8
-  -> 9, Char 'd'
-< 9"
-            );
-        }
+- 2
+  -> 3, Char 'c'
+< 3"
+        );
 
-        {
-            // "'a', 'b' || 'c', 'd'" == "'a', ('b' || 'c'), 'd'"
-            assert_str_eq!(
-                compile_from_str(r#"'a', 'b' || 'c', 'd'"#)
-                    .unwrap()
-                    .generate_states_and_transitions_text(),
-                compile_from_str(r#"'a', ('b' || 'c'), 'd'"#)
-                    .unwrap()
-                    .generate_states_and_transitions_text()
-            );
-        }
+        // the charsets, and the expressions in the look-arounds
+        let state_set = compile_from_str(r#"ignore_case(['a'..'f'].is_before('x')), 'x'"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+        assert!(s.contains("Charset ['a'..'f'], ignore case"));
+        assert!(s.contains("Char 'x', ignore case"));
+        assert!(s.contains("Char 'x'\n"));
 
-        // associativity
+        // the alternation of literals is not compiled into a trie
+        let state_set = compile_from_str(r#"ignore_case("GET" || "PUT" || "POST")"#).unwrap();
+        assert!(state_set
+            .generate_states_and_transitions_text()
+            .contains("String \"GET\", ignore case"));
 
-        {
-            let state_set = compile_from_str(r#"'a' || 'b' || 'c'"#).unwrap();
-            let s = state_set.generate_states_and_transitions_text();
+        // the normalized strings
+        let state_set = compile_from_str(r#"normalized("caf\u{e9}").ignore_case()"#).unwrap();
+        assert!(state_set
+            .generate_states_and_transitions_text()
+            .contains("Normalized string \"cafe\\u{301}\", ignore case"));
+    }
 
-            assert_str_eq!(
-                s,
-                "\
-- 0
+    #[test]
+    fn test_compile_duplicate_capture_names() {
+        // the groups in different branches share one index
+        let state_set =
+            compile_from_str(r#"name('a', x), (name('b', y) || 'c' || name('d', y))"#).unwrap();
+        let names: Vec<Option<String>> = state_set
+            .capture_groups
+            .iter()
+            .map(|g| g.name.clone())
+            .collect();
+        assert_eq!(
+            names,
+            vec![None, Some("x".to_owned()), Some("y".to_owned())]
+        );
+
+        // the groups may participate in the same match
+        assert!(compile_from_str(r#"name('a', x), name('b', x)"#).is_err());
+        assert!(compile_from_str(r#"name('a', x) || (name('b', y), name('c', y))"#).is_err());
+        assert!(compile_from_str(r#"name(name('a', x), x)"#).is_err());
+
+        // the error points to the second name
+        assert_eq!(
+            compile_from_str(
+                r#"'a'.name(x),
+'b' || ('c', 'd'.name(x))"#
+            )
+            .err(),
+            Some(Error::MessageWithLocation(
+                "The capture name \"x\" is duplicated, the previous one is at line 1, column 1."
+                    .to_owned(),
+                Location::new_range(0, 26, 1, 13, 11)
+            ))
+        );
+
+        // the program without locations
+        let program = parse_from_str(r#"name('a', x), name('b', x)"#).unwrap();
+        assert_eq!(
+            compile(&program, &CompileOptions::default()).err(),
+            Some(Error::Message(
+                "The capture name \"x\" is duplicated.".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compile_tag() {
+        let state_set = compile_from_str(r#"tag('a', "x"), 'b'"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+> 0
   -> 1, Char 'a'
 - 1
-  -> 9, Jump
+  -> 2, Tag {0}
 - 2
-  -> 3, Char 'b'
+  -> 3, Jump
+- 3
+  -> 4, Char 'b'
+< 4"
+        );
+        assert_eq!(state_set.tags, vec!["x".to_owned()]);
+
+        // the tags with the same name share one index
+        let state_set =
+            compile_from_str(r#"tag('a', "x") || tag('b', "y") || tag('c', "x")"#).unwrap();
+        assert_eq!(state_set.tags, vec!["x".to_owned(), "y".to_owned()]);
+
+        // err: the name is not a string
+        assert!(compile_from_str(r#"tag('a', 'x')"#).is_err());
+        assert!(compile_from_str(r#"tag('a', x)"#).is_err());
+        assert!(compile_from_str(r#"tag('a')"#).is_err());
+    }
+
+    #[test]
+    fn test_compile_back_reference() {
+        let state_set = compile_from_str(r#"name('a', x), x"#).unwrap();
+        let s = state_set.generate_states_and_transitions_text();
+
+        assert_str_eq!(
+            s,
+            "\
+- 0
+  -> 1, Char 'a'
+- 1
+  -> 3, Capture end {1}
+> 2
+  -> 0, Capture start {1}
 - 3
-  -> 7, Jump
-- 4
-  -> 5, Char 'c'
-- 5
-  -> 7, Jump
-- 6
-  -> 2, Jump
   -> 4, Jump
-- 7
-  -> 9, Jump
-> 8
-  -> 0, Jump
-  -> 6, Jump
-< 9"
-            );
+- 4
+  -> 5, Back reference {1}
+< 5"
+        );
+        assert!(state_set.has_back_references);
+
+        // the back-reference inside a look-around
+        let state_set = compile_from_str(r#"name('a', x), 'b'.is_after(x)"#).unwrap();
+        assert!(state_set
+            .generate_states_and_transitions_text()
+            .contains("Back reference {1}"));
+
+        // the groups with the same name in different branches
+        let state_set = compile_from_str(r#"(name('a', x) || name('b', x)), x"#).unwrap();
+        assert_eq!(state_set.capture_groups.len(), 2);
+
+        // err: the group is undefined or defined after the back-reference
+        assert!(compile_from_str(r#"'a', x"#).is_err());
+        assert!(compile_from_str(r#"x, name('a', x)"#).is_err());
+        assert!(compile_from_str(r#"name('a', x).is_after(x)"#).is_ok());
+    }
+
+    #[test]
+    fn test_compile_limits() {
+        let options = CompileOptions {
+            limits: Limits {
+                max_nesting_depth: 4,
+                max_nodes: 6,
+                ..Limits::default()
+            },
+            ..CompileOptions::default()
+        };
+
+        assert!(compile_from_str_with(r#"(('a', 'b'))"#, &options).is_ok());
+
+        // err: the parser rejects the pattern
+        assert!(compile_from_str_with(r#"(((('a'))))"#, &options).is_err());
+        assert!(compile_from_str_with(r#"'a', 'b', 'c', 'd', 'e', 'f', 'g'"#, &options).is_err());
+
+        // the program that is constructed directly
+        let mut expression = Expression::Literal(Literal::Char('a'));
+        for _ in 0..4 {
+            expression = Expression::Group(vec![expression]);
         }
+        let program = Program {
+            expressions: vec![expression],
+            tests: vec![],
+            options: PatternOptions::default(),
+            syntax_version: None,
+        };
+        assert!(compile(&program, &CompileOptions::default()).is_ok());
+        assert!(compile(&program, &options).is_err());
+
+        let program = Program {
+            expressions: (0..7)
+                .map(|_| Expression::Literal(Literal::Char('a')))
+                .collect(),
+            tests: vec![],
+            options: PatternOptions::default(),
+            syntax_version: None,
+        };
+        assert!(compile(&program, &options).is_err());
+
+        // the repetition counts
+        let options = CompileOptions {
+            limits: Limits {
+                max_repetition: 1000,
+                ..Limits::default()
+            },
+            ..CompileOptions::default()
+        };
+
+        assert!(compile_from_str_with(r#"'a'{1000}, 'b'{2,1000}, 'c'{1000,}"#, &options).is_ok());
+        assert!(compile_from_str_with(r#"'a'{1001}"#, &options).is_err());
+        assert!(compile_from_str_with(r#"'a'{2,1001}"#, &options).is_err());
+        assert!(compile_from_str_with(r#"'a'{1001,}"#, &options).is_err());
+        assert!(compile_from_str(r#"'a'{4000000000}"#).is_err());
+        assert!(compile_from_str(r#"repeat(char_digit, 10000000)"#).is_err());
+
+        // the nested look-arounds
+        let nested = |depth: usize| {
+            (0..depth).fold("'x'".to_owned(), |inner, _| {
+                format!("'a'.is_not_before({})", inner)
+            })
+        };
+
+        let options = CompileOptions {
+            limits: Limits {
+                max_look_around_depth: 3,
+                ..Limits::default()
+            },
+            ..CompileOptions::default()
+        };
+        assert!(compile_from_str_with(&nested(3), &options).is_ok());
+        assert!(compile_from_str_with(&nested(4), &options).is_err());
+        assert!(compile_from_str(&nested(32)).is_ok());
+        assert!(compile_from_str(&nested(33)).is_err());
+    }
+
+    #[test]
+    fn test_compile_unrolled_repetition() {
+        // the short string is unrolled
+        let state_set = compile_from_str(r#""ab"{3}"#).unwrap();
+        assert_str_eq!(
+            state_set.generate_states_and_transitions_text(),
+            "\
+> 0
+  -> 1, String \"ababab\"
+< 1"
+        );
+
+        // the long one uses the counter
+        let state_set = compile_from_str(r#""ab"{200}"#).unwrap();
+        assert!(state_set
+            .generate_states_and_transitions_text()
+            .contains("Counter check"));
+
+        // the nested repetitions
+        let state_set = compile_from_str(r#"("ab"{3}){2}"#).unwrap();
+        assert!(state_set
+            .generate_states_and_transitions_text()
+            .contains("String \"abababababab\""));
 
+        let state_set = compile_from_str(r#"('a'{50000}){50000}"#).unwrap();
+        assert_eq!(state_set.length_bounds.min, 2_500_000_000);
     }
 }
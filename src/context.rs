@@ -4,134 +4,236 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
+use std::cell::Cell;
+
+use crate::utf8reader::read_char;
+
 pub struct Context {
-    pub text: Vec<char>,      // the source text
-    pub length: usize,        // the length of source text
-    pub fixed_start: bool,    // it is true when the expression starts with `^`
-    pub fixed_end: bool,      // it is true when the expression ends with `$`
-    pub cursors: Vec<Cursor>, // the `Cursor` stack.
-    pub position: usize,      // it is sync to the position of the last cursor
-}
+    pub text: Vec<char>,  // the source text, it is empty in bytes mode
+    pub bytes: Vec<u8>,   // the source bytes, it is empty in text mode
+    pub bytes_mode: bool, // the position and length are counted by bytes in bytes mode
+    pub length: usize,    // the length of source text or bytes
+    pub position: usize,  // the position of the currently matching character
 
-// The `Cursor` can only be moved to left as a whole,
-// and cannot exceed the `position` of the previous `Cursor` (if it exists).
-// If the previous `Cursor` does not exist, it cannot be moved.
-pub struct Cursor {
-    pub start: usize, // the start poisition
-    pub end: usize,   // the end position, it is the length of source text.
-
-    // the position of the currently matched character.
-    // unlike the `start` position of `Cursor`, this value can only
-    // be increased (moved to right).
-    pub position: usize,
-    // // the position of the currently consumed character.
-    // // which is normally the same as `match_position`, but
-    // // is different only when an asserted match is encountered.
-    // pub text_position: usize,
-}
+    // the end position of the previous match, it is checked by
+    // the status `continue_from_last`.
+    pub last_match_end: usize,
 
-// the Cursor stack demo:
-//
-// 1. normal mode
-//
-// start                                 end
-// 0                                     len
-// |====*================================| <-- cursor 0
-//      ^__ position (move to right only)
-//
-// 2. backtracking
-//
-// start                                 end
-// 0                                     len
-// |-------------------------------------|
-// |======*==============================| <-- cursor 0
-// | pos__^     |===*====================| <-- cursor 1
-// |            ^   ^__ pos' move to right only
-// |            |__ start' move to left only
-// |                                     |
-// |-------------------------------------|
-//
-// 3. return to normal mode again, pop up upper frame
-//
-// start                                 end
-// 0                                     len
-// |=========*===========================| <-- cursor 0
-//           ^__ position (move to right only)
-//
-//
-// 4. backtracking within backtracking
-//
-// start                                 end
-// 0                                     len
-// |-------------------------------------|
-// |======*==============================| <-- cursor 0
-// | pos__^     |====*===================| <-- cursor 1
-// |    start'__^    ^__ pos' ^          |
-// |                          |===*======| <-- cursor 2
-// |                 start''__^   ^__ pos''
-// |                                     |
-// |-------------------------------------|
-//
-// 5. return to normal mode again, pop up all upper frames
-//
-// start                                 end
-// 0                                     len
-// |=============*=======================| <-- cursor 0
-//               ^__ position (move to right only)
+    // the range of positions that have been read, the end is exclusive,
+    // reading at the end of text counts as reading one position.
+    // it is used for finding the matches affected by an edit.
+    pub read_start: Cell<usize>,
+    pub read_end: Cell<usize>,
+
+    // whether a char or byte has been read beyond the end of text (or
+    // an incomplete UTF-8 char at the end of bytes), i.e. more input
+    // may change the result, it is used for finding partial matches.
+    pub reached_end: Cell<bool>,
+}
 
 impl Context {
-    #[inline]
-    pub fn get_current_char(&self) -> char {
-        self.get_char(self.position)
+    pub fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let length = chars.len();
+
+        Context {
+            text: chars,
+            bytes: vec![],
+            bytes_mode: false,
+            length,
+            position: 0,
+            last_match_end: 0,
+            read_start: Cell::new(usize::MAX),
+            read_end: Cell::new(0),
+            reached_end: Cell::new(false),
+        }
     }
 
-    #[inline]
-    pub fn is_first_char(&self) -> bool {
-        self.position == 0
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Context {
+            text: vec![],
+            bytes: bytes.to_vec(),
+            bytes_mode: true,
+            length: bytes.len(),
+            position: 0,
+            last_match_end: 0,
+            read_start: Cell::new(usize::MAX),
+            read_end: Cell::new(0),
+            reached_end: Cell::new(false),
+        }
+    }
+
+    // replace the whole source with the text, the allocated
+    // buffers are reused.
+    pub fn reset_text(&mut self, text: &str) {
+        self.text.clear();
+        self.text.extend(text.chars());
+        self.bytes.clear();
+        self.bytes_mode = false;
+        self.length = self.text.len();
+        self.reset_positions();
+    }
+
+    // replace the whole source with the bytes, the allocated
+    // buffers are reused.
+    pub fn reset_bytes(&mut self, bytes: &[u8]) {
+        self.text.clear();
+        self.bytes.clear();
+        self.bytes.extend_from_slice(bytes);
+        self.bytes_mode = true;
+        self.length = self.bytes.len();
+        self.reset_positions();
+    }
+
+    fn reset_positions(&mut self) {
+        self.position = 0;
+        self.last_match_end = 0;
+        self.reset_read_range();
+    }
+
+    // replace the chars from `start` to `end` (exclusive) with the text.
+    pub fn replace_text(&mut self, start: usize, end: usize, text: &str) {
+        self.text.splice(start..end, text.chars());
+        self.length = self.text.len();
+    }
+
+    // replace the bytes from `start` to `end` (exclusive).
+    pub fn replace_bytes(&mut self, start: usize, end: usize, bytes: &[u8]) {
+        self.bytes.splice(start..end, bytes.iter().copied());
+        self.length = self.bytes.len();
+    }
+
+    pub fn reset_read_range(&self) {
+        self.read_start.set(usize::MAX);
+        self.read_end.set(0);
     }
 
     #[inline]
-    pub fn is_last_char(&self) -> bool {
-        self.position == (self.length - 1)
+    pub fn record_read(&self, start: usize, end: usize) {
+        if start < self.read_start.get() {
+            self.read_start.set(start);
+        }
+        if end > self.read_end.get() {
+            self.read_end.set(end);
+        }
     }
 
-    pub fn is_word_bound(&self) -> bool {
-        let current_char = self.get_current_char();
+    // returns the char at the specified position and the number of
+    // positions it takes, i.e. 1 in text mode and 1 to 4 in bytes mode.
+    //
+    // returns None when reaching the end, or the bytes at the position
+    // are not a valid UTF-8 char.
+    pub fn read_char(&self, position: usize) -> Option<(char, usize)> {
+        // a UTF-8 char takes up to 4 bytes, the whole char is counted
+        // even if it is invalid.
+        let read_length = if self.bytes_mode { 4 } else { 1 };
+        self.record_read(position, (position + read_length).min(self.length + 1));
 
-        if is_word_char(current_char) {
-            !is_word_char(self.get_previous_char()) || !is_word_char(self.get_next_char())
+        if self.bytes_mode {
+            let result = read_char(&self.bytes, position);
+            if result.is_none() && is_incomplete_char(&self.bytes, position) {
+                self.reached_end.set(true);
+            }
+            result
+        } else if position < self.length {
+            Some((self.text[position], 1))
         } else {
-            is_word_char(self.get_previous_char()) || is_word_char(self.get_next_char())
+            self.reached_end.set(true);
+            None
         }
     }
 
+    // returns the byte at the specified position, or None
+    // when reaching the end or it is not in bytes mode.
     #[inline]
-    pub fn get_char(&self, position: usize) -> char {
-        self.text[position]
-    }
+    pub fn read_byte(&self, position: usize) -> Option<u8> {
+        self.record_read(position, position + 1);
 
-    #[inline]
-    fn get_previous_char(&self) -> char {
-        if self.is_first_char() {
-            '\0'
+        if self.bytes_mode {
+            let result = self.bytes.get(position).copied();
+            if result.is_none() {
+                self.reached_end.set(true);
+            }
+            result
         } else {
-            self.get_char(self.position - 1)
+            None
         }
     }
+}
 
-    #[inline]
-    fn get_next_char(&self) -> char {
-        if self.is_last_char() {
-            '\0'
+// whether the bytes from the position are empty or the beginning of
+// a UTF-8 char that is cut off by the end.
+fn is_incomplete_char(bytes: &[u8], position: usize) -> bool {
+    match bytes.get(position..) {
+        Some(rest) => match std::str::from_utf8(&rest[..rest.len().min(4)]) {
+            Ok(s) => s.is_empty(),
+            Err(e) => e.valid_up_to() == 0 && e.error_len().is_none(),
+        },
+        None => true,
+    }
+}
+
+impl Context {
+    // returns the char before the specified position, or None when
+    // the position is at the start, or the bytes before the position
+    // are not a valid UTF-8 char.
+    pub fn read_previous_char(&self, position: usize) -> Option<char> {
+        if position == 0 {
+            return None;
+        }
+
+        // a UTF-8 char takes up to 4 bytes
+        let read_length = if self.bytes_mode { 4 } else { 1 };
+        self.record_read(position.saturating_sub(read_length), position);
+
+        if self.bytes_mode {
+            // a UTF-8 char takes up to 4 bytes
+            (1..=4.min(position)).find_map(|length| {
+                match read_char(&self.bytes, position - length) {
+                    Some((c, l)) if l == length => Some(c),
+                    _ => None,
+                }
+            })
         } else {
-            self.get_char(self.position + 1)
+            Some(self.text[position - 1])
         }
     }
+
+    // the word boundary is the position between a word char and
+    // a non-word char (or the start/end of text).
+    pub fn is_word_bound(&self, position: usize, unicode: bool) -> bool {
+        let (is_previous_word, is_next_word) = self.get_word_chars_around(position, unicode);
+        is_previous_word != is_next_word
+    }
+
+    // whether the position is the start of a word, i.e. the previous
+    // char is not a word char and the next one is.
+    pub fn is_word_start(&self, position: usize, unicode: bool) -> bool {
+        self.get_word_chars_around(position, unicode) == (false, true)
+    }
+
+    // whether the position is the end of a word, i.e. the previous
+    // char is a word char and the next one is not.
+    pub fn is_word_end(&self, position: usize, unicode: bool) -> bool {
+        self.get_word_chars_around(position, unicode) == (true, false)
+    }
+
+    // whether the previous and the next chars are word chars.
+    fn get_word_chars_around(&self, position: usize, unicode: bool) -> (bool, bool) {
+        let is_previous_word = self
+            .read_previous_char(position)
+            .is_some_and(|c| is_word_char(c, unicode));
+        let is_next_word = self
+            .read_char(position)
+            .is_some_and(|(c, _)| is_word_char(c, unicode));
+        (is_previous_word, is_next_word)
+    }
 }
 
-fn is_word_char(c: char) -> bool {
-    ('a'..='z').any(|e| e == c)
-        || ('A'..='Z').any(|e| e == c)
-        || ('0'..='9').any(|e| e == c)
-        || c == '_'
+pub fn is_word_char(c: char, unicode: bool) -> bool {
+    if unicode {
+        c.is_alphanumeric() || c == '_'
+    } else {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
 }
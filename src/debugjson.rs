@@ -0,0 +1,302 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the JSON form of the compiled states, for the visualizers and the
+// tests that inspect the compilation, e.g.
+//
+// ```json
+// {"start":0,"end":1,
+//  "states":[{"index":0,"transitions":[
+//      {"kind":"char","target":1,"character":"a","ignore_case":false}]},
+//      {"index":1,"transitions":[]}],
+//  "capture_groups":[{"index":0,"name":null,"in_repetition":false,"in_look_around":false}],
+//  "look_arounds":[]}
+// ```
+//
+// (the actual output has no white spaces.)
+//
+// each transition has the "kind" (see `Transition::kind`) and the
+// "target" state index, followed by the parameters of the kind.
+// the "look_arounds" are the routes of the look-around assertions,
+// see `StateSet::look_around_routes`.
+
+use crate::{
+    state::StateSet,
+    transition::{
+        BackReferenceTransition, ByteRangeTransition, ByteTransition, CaptureEndTransition,
+        CaptureStartTransition, CharSetItem, CharSetTransition, CharTransition,
+        CounterCheckTransition, CounterExitTransition, CounterIncTransition, LookAroundTransition,
+        NormalizedStringTransition, SpecialCharTransition, StatusTransition, StringTransition,
+        TagTransition, Transition,
+    },
+};
+
+pub fn generate_debug_json(state_set: &StateSet) -> String {
+    let states = (0..state_set.state_count())
+        .map(|state_index| {
+            let mut transitions = vec![];
+            let mut next_link_index = state_set.get_first_link_index(state_index);
+            while let Some(link_index) = next_link_index {
+                let (transition, target_state_index, next) = state_set.get_link(link_index);
+                transitions.push(transition_json(transition, target_state_index));
+                next_link_index = next;
+            }
+
+            object(&[
+                ("index", state_index.to_string()),
+                ("transitions", array(transitions)),
+            ])
+        })
+        .collect();
+
+    let capture_groups = state_set
+        .capture_groups
+        .iter()
+        .map(|group| {
+            object(&[
+                ("index", group.index.to_string()),
+                ("name", optional(group.name.as_deref().map(string))),
+                ("in_repetition", group.in_repetition.to_string()),
+                ("in_look_around", group.in_look_around.to_string()),
+            ])
+        })
+        .collect();
+
+    let look_arounds = state_set
+        .look_around_routes()
+        .iter()
+        .map(|route| {
+            object(&[
+                ("state_index", route.state_index.to_string()),
+                ("start_state_index", route.start_state_index.to_string()),
+                ("end_state_index", route.end_state_index.to_string()),
+                ("is_behind", route.is_behind.to_string()),
+                ("negative", route.negative.to_string()),
+                ("parent", optional(route.parent.map(|p| p.to_string()))),
+            ])
+        })
+        .collect();
+
+    object(&[
+        ("start", state_set.start_node_index.to_string()),
+        ("end", state_set.end_node_index.to_string()),
+        ("states", array(states)),
+        ("capture_groups", array(capture_groups)),
+        ("look_arounds", array(look_arounds)),
+    ])
+}
+
+fn transition_json(transition: &Transition, target_state_index: usize) -> String {
+    let mut fields = vec![
+        ("kind", string(transition.kind())),
+        ("target", target_state_index.to_string()),
+    ];
+
+    match transition {
+        Transition::Jump(_) | Transition::CounterReset(_) | Transition::MatchStartReset(_) => {}
+        Transition::Tag(TagTransition { tag_index }) => {
+            fields.push(("tag_index", tag_index.to_string()))
+        }
+        Transition::Char(CharTransition {
+            character,
+            ignore_case,
+        }) => fields.extend([
+            ("character", string(&character.to_string())),
+            ("ignore_case", ignore_case.to_string()),
+        ]),
+        Transition::SpecialChar(SpecialCharTransition {
+            include_new_line,
+            crlf,
+            grapheme,
+        }) => fields.extend([
+            ("include_new_line", include_new_line.to_string()),
+            ("crlf", crlf.to_string()),
+            ("grapheme", grapheme.to_string()),
+        ]),
+        Transition::String(StringTransition { chars, ignore_case }) => fields.extend([
+            ("chars", string(&chars.iter().collect::<String>())),
+            ("ignore_case", ignore_case.to_string()),
+        ]),
+        Transition::CharSet(CharSetTransition {
+            items,
+            negative,
+            ignore_case,
+            unicode,
+            grapheme,
+            ..
+        }) => fields.extend([
+            (
+                "items",
+                array(items.iter().map(charset_item_json).collect()),
+            ),
+            ("negative", negative.to_string()),
+            ("ignore_case", ignore_case.to_string()),
+            ("unicode", unicode.to_string()),
+            ("grapheme", grapheme.to_string()),
+        ]),
+        Transition::Status(StatusTransition {
+            status,
+            multiline,
+            crlf,
+            unicode,
+        }) => fields.extend([
+            ("status", string(&status.to_string())),
+            ("multiline", multiline.to_string()),
+            ("crlf", crlf.to_string()),
+            ("unicode", unicode.to_string()),
+        ]),
+        Transition::NormalizedString(NormalizedStringTransition { chars, ignore_case }) => fields
+            .extend([
+                ("chars", string(&chars.iter().collect::<String>())),
+                ("ignore_case", ignore_case.to_string()),
+            ]),
+        Transition::Byte(ByteTransition { byte }) => fields.push(("byte", byte.to_string())),
+        Transition::ByteRange(ByteRangeTransition {
+            start,
+            end_included,
+        }) => fields.extend([
+            ("start", start.to_string()),
+            ("end_included", end_included.to_string()),
+        ]),
+        Transition::CounterCheck(CounterCheckTransition { max }) => {
+            fields.push(("max", optional(max.map(|m| m.to_string()))))
+        }
+        Transition::CounterInc(CounterIncTransition { min })
+        | Transition::CounterExit(CounterExitTransition { min }) => {
+            fields.push(("min", min.to_string()))
+        }
+        Transition::CaptureStart(CaptureStartTransition {
+            capture_index,
+            keep_first,
+        })
+        | Transition::CaptureEnd(CaptureEndTransition {
+            capture_index,
+            keep_first,
+        }) => fields.extend([
+            ("capture_index", capture_index.to_string()),
+            ("keep_first", keep_first.to_string()),
+        ]),
+        Transition::LookAround(LookAroundTransition {
+            start_state_index,
+            end_state_index,
+            is_behind,
+            negative,
+        }) => fields.extend([
+            ("start_state_index", start_state_index.to_string()),
+            ("end_state_index", end_state_index.to_string()),
+            ("is_behind", is_behind.to_string()),
+            ("negative", negative.to_string()),
+        ]),
+        Transition::BackReference(BackReferenceTransition {
+            capture_index,
+            ignore_case,
+        }) => fields.extend([
+            ("capture_index", capture_index.to_string()),
+            ("ignore_case", ignore_case.to_string()),
+        ]),
+    }
+
+    object(&fields)
+}
+
+fn charset_item_json(item: &CharSetItem) -> String {
+    match item {
+        CharSetItem::Char(c) => {
+            object(&[("type", string("char")), ("char", string(&c.to_string()))])
+        }
+        CharSetItem::Range(start, end_included) => object(&[
+            ("type", string("range")),
+            ("start", string(&start.to_string())),
+            ("end_included", string(&end_included.to_string())),
+        ]),
+        CharSetItem::Preset(preset, negative) => object(&[
+            ("type", string("preset")),
+            ("name", string(preset.name())),
+            ("negative", negative.to_string()),
+        ]),
+    }
+}
+
+// the values below are the JSON texts
+
+fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn array(items: Vec<String>) -> String {
+    format!("[{}]", items.join(","))
+}
+
+fn optional(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_owned())
+}
+
+fn string(s: &str) -> String {
+    let mut text = String::with_capacity(s.len() + 2);
+    text.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\n' => text.push_str("\\n"),
+            '\r' => text.push_str("\\r"),
+            '\t' => text.push_str("\\t"),
+            '\u{0}'..='\u{1f}' => text.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => text.push(c),
+        }
+    }
+    text.push('"');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::compiler::compile_from_str;
+
+    use super::{generate_debug_json, string};
+
+    #[test]
+    fn test_generate_debug_json() {
+        let json = generate_debug_json(&compile_from_str(r#"'a'"#).unwrap());
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"start":0,"end":1,"#,
+                r#""states":[{"index":0,"transitions":["#,
+                r#"{"kind":"char","target":1,"character":"a","ignore_case":false}]},"#,
+                r#"{"index":1,"transitions":[]}],"#,
+                r#""capture_groups":[{"index":0,"name":null,"in_repetition":false,"in_look_around":false}],"#,
+                r#""look_arounds":[]}"#
+            )
+        );
+
+        let json = generate_debug_json(
+            &compile_from_str(r#"name(['a'..'z', char_digit]{2,}, x), 'b'.is_before(!['"'])"#)
+                .unwrap(),
+        );
+        assert!(json.contains(r#""name":"x","in_repetition":false"#));
+        assert!(json.contains(
+            r#""items":[{"type":"range","start":"a","end_included":"z"},{"type":"preset","name":"digit","negative":false}]"#
+        ));
+        assert!(json.contains(r#"{"type":"char","char":"\""}"#));
+        assert!(json.contains(r#""kind":"counter_check","#));
+        assert!(json.contains(r#""max":null"#));
+        assert!(json.contains(r#""kind":"capture_start","#));
+        assert!(json.contains(r#""kind":"look_around","#));
+        assert!(json.contains(r#""is_behind":false,"negative":false,"parent":null}]"#));
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(string("a\"b\\c\n\u{1}文"), r#""a\"b\\c\n\u0001文""#);
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// compares two programs structurally, e.g. for reviewing the changes of
+// the shared patterns, the differences are reported by the positions of
+// the expressions instead of the lines of the source, e.g. the patterns
+//
+// ```anreg
+// name(char_digit{4}, year), '-', ("jan" || "feb")
+// ```
+//
+// and
+//
+// ```anreg
+// name(char_digit{2,4}, yyyy), '-', ("jan" || "feb" || "mar")
+// ```
+//
+// give the differences:
+//
+// ```text
+// 0: capture renamed: year -> yyyy
+// 0.0: repetition changed: {4} -> {2,4}
+// 2.0.2: alternative added: "mar"
+// ```
+//
+// the position is the indices of the expressions from the top level,
+// joined by ".", the indices of a function call are 0 for the expression
+// and 1, 2, ... for the arguments, and the alternatives of a logic or
+// (e.g. `'a' || 'b' || 'c'`) are indexed as a list.
+//
+// the sequences (the expressions of the program or a group, and the
+// alternatives) are matched by their longest common subsequence, the
+// remaining items in the same gap are compared one by one, and the extra
+// ones are reported as added or removed.
+
+use std::fmt::Display;
+
+use crate::ast::{Expression, FunctionCall, FunctionCallArg, FunctionName, Program};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub position: Vec<usize>, // empty for the options of the program
+    pub kind: DifferenceKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DifferenceKind {
+    Added(String),
+    Removed(String),
+    AlternativeAdded(String),
+    AlternativeRemoved(String),
+    RepetitionChanged { from: String, to: String },
+    CaptureRenamed { from: String, to: String },
+    Changed { from: String, to: String },
+    OptionsChanged { from: String, to: String },
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.position.is_empty() {
+            f.write_str("options")?;
+        } else {
+            let indices: Vec<String> = self.position.iter().map(|i| i.to_string()).collect();
+            f.write_str(&indices.join("."))?;
+        }
+
+        match &self.kind {
+            DifferenceKind::Added(s) => write!(f, ": added: {}", s),
+            DifferenceKind::Removed(s) => write!(f, ": removed: {}", s),
+            DifferenceKind::AlternativeAdded(s) => write!(f, ": alternative added: {}", s),
+            DifferenceKind::AlternativeRemoved(s) => write!(f, ": alternative removed: {}", s),
+            DifferenceKind::RepetitionChanged { from, to } => {
+                write!(f, ": repetition changed: {} -> {}", from, to)
+            }
+            DifferenceKind::CaptureRenamed { from, to } => {
+                write!(f, ": capture renamed: {} -> {}", from, to)
+            }
+            DifferenceKind::Changed { from, to } => write!(f, ": changed: {} -> {}", from, to),
+            DifferenceKind::OptionsChanged { from, to } => write!(f, ": {} -> {}", from, to),
+        }
+    }
+}
+
+// the differences from the program `a` to the program `b`, they are
+// empty if the programs are the same, the test cases are not compared.
+pub fn diff_programs(a: &Program, b: &Program) -> Vec<Difference> {
+    let mut differences = vec![];
+
+    if a.options != b.options {
+        let names = |program: &Program| format!("options({})", program.options.names().join(", "));
+        differences.push(Difference {
+            position: vec![],
+            kind: DifferenceKind::OptionsChanged {
+                from: names(a),
+                to: names(b),
+            },
+        });
+    }
+
+    let a_items: Vec<&Expression> = a.expressions.iter().collect();
+    let b_items: Vec<&Expression> = b.expressions.iter().collect();
+    diff_sequences(&mut differences, &[], &a_items, &b_items, false);
+    differences
+}
+
+fn diff_sequences(
+    differences: &mut Vec<Difference>,
+    position: &[usize],
+    a: &[&Expression],
+    b: &[&Expression],
+    alternatives: bool,
+) {
+    let child = |index: usize| [position, &[index]].concat();
+
+    // the gaps between the common items, and the gap after the last one
+    let mut pairs = common_subsequence(a, b);
+    pairs.push((a.len(), b.len()));
+
+    let (mut a_start, mut b_start) = (0, 0);
+    for (a_end, b_end) in pairs {
+        let paired = (a_end - a_start).min(b_end - b_start);
+        for offset in 0..paired {
+            let index = b_start + offset;
+            diff_expressions(differences, &child(index), a[a_start + offset], b[index]);
+        }
+
+        for (index, expression) in a.iter().enumerate().take(a_end).skip(a_start + paired) {
+            let text = expression.to_string();
+            differences.push(Difference {
+                position: child(index),
+                kind: if alternatives {
+                    DifferenceKind::AlternativeRemoved(text)
+                } else {
+                    DifferenceKind::Removed(text)
+                },
+            });
+        }
+
+        for (index, expression) in b.iter().enumerate().take(b_end).skip(b_start + paired) {
+            let text = expression.to_string();
+            differences.push(Difference {
+                position: child(index),
+                kind: if alternatives {
+                    DifferenceKind::AlternativeAdded(text)
+                } else {
+                    DifferenceKind::Added(text)
+                },
+            });
+        }
+
+        (a_start, b_start) = (a_end + 1, b_end + 1);
+    }
+}
+
+fn diff_expressions(
+    differences: &mut Vec<Difference>,
+    position: &[usize],
+    a: &Expression,
+    b: &Expression,
+) {
+    if a == b {
+        return;
+    }
+
+    match (a, b) {
+        (Expression::OrList(..), _) | (_, Expression::OrList(..)) => {
+            diff_sequences(
+                differences,
+                position,
+                &alternatives_of(a),
+                &alternatives_of(b),
+                true,
+            );
+        }
+        (Expression::Group(a_items), Expression::Group(b_items)) => {
+            let a_items: Vec<&Expression> = a_items.iter().collect();
+            let b_items: Vec<&Expression> = b_items.iter().collect();
+            diff_sequences(differences, position, &a_items, &b_items, false);
+        }
+        (Expression::FunctionCall(a_call), Expression::FunctionCall(b_call))
+            if is_same_function(a_call, b_call) =>
+        {
+            diff_function_calls(differences, position, a_call, b_call);
+        }
+        _ => differences.push(Difference {
+            position: position.to_vec(),
+            kind: DifferenceKind::Changed {
+                from: a.to_string(),
+                to: b.to_string(),
+            },
+        }),
+    }
+}
+
+fn diff_function_calls(
+    differences: &mut Vec<Difference>,
+    position: &[usize],
+    a: &FunctionCall,
+    b: &FunctionCall,
+) {
+    let child = |index: usize| [position, &[index]].concat();
+
+    if let (Some(a_bounds), Some(b_bounds)) = (repetition_bounds(a), repetition_bounds(b)) {
+        if a_bounds != b_bounds {
+            differences.push(Difference {
+                position: position.to_vec(),
+                kind: DifferenceKind::RepetitionChanged {
+                    from: a_bounds,
+                    to: b_bounds,
+                },
+            });
+        }
+        diff_expressions(differences, &child(0), &a.expression, &b.expression);
+        return;
+    }
+
+    // the names of capture groups
+    if a.name == FunctionName::Name && a.args != b.args {
+        differences.push(Difference {
+            position: position.to_vec(),
+            kind: DifferenceKind::CaptureRenamed {
+                from: args_text(&a.args),
+                to: args_text(&b.args),
+            },
+        });
+        diff_expressions(differences, &child(0), &a.expression, &b.expression);
+        return;
+    }
+
+    diff_expressions(differences, &child(0), &a.expression, &b.expression);
+
+    if a.args.len() != b.args.len() {
+        differences.push(Difference {
+            position: position.to_vec(),
+            kind: DifferenceKind::Changed {
+                from: a.to_string(),
+                to: b.to_string(),
+            },
+        });
+        return;
+    }
+
+    for (idx, (a_arg, b_arg)) in a.args.iter().zip(b.args.iter()).enumerate() {
+        match (a_arg, b_arg) {
+            (
+                FunctionCallArg::Expression(a_expression),
+                FunctionCallArg::Expression(b_expression),
+            ) => diff_expressions(differences, &child(idx + 1), a_expression, b_expression),
+            _ if a_arg != b_arg => differences.push(Difference {
+                position: child(idx + 1),
+                kind: DifferenceKind::Changed {
+                    from: a_arg.to_string(),
+                    to: b_arg.to_string(),
+                },
+            }),
+            _ => {}
+        }
+    }
+}
+
+// whether the function calls are compared by their parts, i.e. the same
+// function, or both are repetitions.
+fn is_same_function(a: &FunctionCall, b: &FunctionCall) -> bool {
+    a.name == b.name || (repetition_bounds(a).is_some() && repetition_bounds(b).is_some())
+}
+
+// the alternatives of the logic or, e.g. `'a' || 'b' || 'c'` has three,
+// and the other expressions are one alternative.
+fn alternatives_of(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::OrList(alternatives) => alternatives.iter().collect(),
+        _ => vec![expression],
+    }
+}
+
+// the bounds of the repetition in the notation form, e.g. `{2,5}`, `{1,}`
+// and `{0,1}?` (lazy), or None if it is not a repetition.
+fn repetition_bounds(function_call: &FunctionCall) -> Option<String> {
+    let numbers: Vec<u32> = function_call
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+            FunctionCallArg::Number(n) => Some(*n),
+            _ => None,
+        })
+        .collect();
+
+    let (bounds, lazy) = match (&function_call.name, numbers.as_slice()) {
+        (FunctionName::Optional, _) => ("{0,1}".to_owned(), false),
+        (FunctionName::OneOrMore, _) => ("{1,}".to_owned(), false),
+        (FunctionName::ZeroOrMore, _) => ("{0,}".to_owned(), false),
+        (FunctionName::Repeat, [n]) => (format!("{{{}}}", n), false),
+        (FunctionName::RepeatRange, [m, n]) => (format!("{{{},{}}}", m, n), false),
+        (FunctionName::AtLeast, [n]) => (format!("{{{},}}", n), false),
+        (FunctionName::OptionalLazy, _) => ("{0,1}".to_owned(), true),
+        (FunctionName::OneOrMoreLazy, _) => ("{1,}".to_owned(), true),
+        (FunctionName::ZeroOrMoreLazy, _) => ("{0,}".to_owned(), true),
+        (FunctionName::RepeatLazy, [n]) => (format!("{{{}}}", n), true),
+        (FunctionName::RepeatRangeLazy, [m, n]) => (format!("{{{},{}}}", m, n), true),
+        (FunctionName::AtLeastLazy, [n]) => (format!("{{{},}}", n), true),
+        _ => return None,
+    };
+
+    Some(if lazy { bounds + "?" } else { bounds })
+}
+
+fn args_text(args: &[FunctionCallArg]) -> String {
+    let texts: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    texts.join(", ")
+}
+
+// the index pairs of the longest common subsequence of the equal items.
+fn common_subsequence(a: &[&Expression], b: &[&Expression]) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the length of the common subsequence of
+    // `a[i..]` and `b[j..]`
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::parser::parse_from_str;
+
+    use super::diff_programs;
+
+    fn diff(a: &str, b: &str) -> Vec<String> {
+        diff_programs(&parse_from_str(a).unwrap(), &parse_from_str(b).unwrap())
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_programs() {
+        assert_eq!(
+            diff(
+                r#"name(char_digit{4}, year), '-', ("jan" || "feb")"#,
+                r#"name(char_digit{2,4}, yyyy), '-', ("jan" || "feb" || "mar")"#
+            ),
+            vec![
+                "0: capture renamed: year -> yyyy",
+                "0.0: repetition changed: {4} -> {2,4}",
+                "2.0.2: alternative added: \"mar\"",
+            ]
+        );
+
+        // the same programs
+        assert!(diff(r#"'a', ('b' || 'c')+"#, r#"'a', ('b' || 'c')+"#).is_empty());
+
+        // the added, removed and changed expressions
+        assert_eq!(
+            diff(r#"start, 'a', 'b', end"#, r#"'a', 'x', end, "tail""#),
+            vec![
+                "0: removed: start",
+                "1: changed: 'b' -> 'x'",
+                "3: added: \"tail\"",
+            ]
+        );
+
+        // the alternatives and the lazy repetitions
+        assert_eq!(
+            diff(r#"'a' || 'b' || 'c', 'x'+?"#, r#"'a' || 'c', 'x'{2,}?"#),
+            vec![
+                "0.1: alternative removed: 'b'",
+                "1: repetition changed: {1,}? -> {2,}?",
+            ]
+        );
+
+        // the options and the function calls
+        assert_eq!(
+            diff(
+                r#"options(multiline), 'a'.is_before('b')"#,
+                r#"'a'.is_before(['b', 'c']), capture('d')"#
+            ),
+            vec![
+                "options: options(multiline) -> options()",
+                "0.1: changed: 'b' -> ['b', 'c']",
+                "1: added: capture('d')",
+            ]
+        );
+        assert_eq!(
+            diff(r#"'a'.is_before('b')"#, r#"'a'.is_after('b')"#),
+            vec!["0: changed: is_before('a', 'b') -> is_after('a', 'b')"]
+        );
+    }
+}
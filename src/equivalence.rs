@@ -0,0 +1,542 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// compares the languages of two compiled patterns, i.e. the sets of
+// texts that are matched entirely by the patterns.
+//
+// the states of a pattern are treated as a NFA, the configuration is
+// the state index along with the values of the repetition counters,
+// the values are saturated at the largest bound of the pattern, so the
+// number of configurations is finite.
+// the two NFAs are then walked in lockstep (the product of the
+// subset constructions) over a partition of chars, in which all
+// chars of a part are accepted by the same transitions.
+//
+// the captures do not change the language and are ignored, the
+// patterns with assertions, look-arounds, normalized strings and
+// bytes are not supported.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use crate::{
+    error::Error,
+    state::StateSet,
+    transition::{
+        equals_ignore_case, CharSetItem, CharSetTransition, CharTransition, CounterCheckTransition,
+        CounterExitTransition, CounterIncTransition, PresetCharSet, SpecialCharTransition,
+        StringTransition, Transition,
+    },
+};
+
+// the maximum number of the pairs of subsets to visit
+const MAX_VISITED_PAIRS: usize = 100_000;
+
+// whether every text matched entirely by `a` is also matched by `b`.
+pub fn is_subset(a: &StateSet, b: &StateSet) -> Result<bool, Error> {
+    compare(a, b, |accept_a, accept_b| !accept_a || accept_b)
+}
+
+// whether `a` and `b` match the same texts.
+pub fn is_equivalent(a: &StateSet, b: &StateSet) -> Result<bool, Error> {
+    compare(a, b, |accept_a, accept_b| accept_a == accept_b)
+}
+
+fn compare(a: &StateSet, b: &StateSet, check: fn(bool, bool) -> bool) -> Result<bool, Error> {
+    let automaton_a = Automaton::new(a)?;
+    let automaton_b = Automaton::new(b)?;
+    let chars = get_representative_chars(&[&automaton_a, &automaton_b]);
+
+    let start = (automaton_a.start(), automaton_b.start());
+    let mut visited = HashSet::new();
+    let mut pending = VecDeque::new();
+    visited.insert(start.clone());
+    pending.push_back(start);
+
+    while let Some((configs_a, configs_b)) = pending.pop_front() {
+        if !check(
+            automaton_a.is_accepted(&configs_a),
+            automaton_b.is_accepted(&configs_b),
+        ) {
+            return Ok(false);
+        }
+
+        for c in &chars {
+            let next = (
+                automaton_a.step(&configs_a, *c),
+                automaton_b.step(&configs_b, *c),
+            );
+            if next.0.is_empty() && next.1.is_empty() {
+                continue;
+            }
+
+            if visited.insert(next.clone()) {
+                if visited.len() > MAX_VISITED_PAIRS {
+                    return Err(Error::Message(
+                        "The patterns are too complex to compare.".to_owned(),
+                    ));
+                }
+                pending.push_back(next);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+// the chars that begin each part of the partition.
+fn get_representative_chars(automata: &[&Automaton]) -> Vec<char> {
+    let mut points = BTreeSet::new();
+    points.insert('\0');
+
+    for automaton in automata {
+        for edges in &automaton.edges {
+            for (edge, _) in edges {
+                let ranges_list = match edge {
+                    Edge::Chars(ranges) => std::slice::from_ref(ranges),
+                    Edge::String(ranges_list) => ranges_list.as_slice(),
+                    _ => continue,
+                };
+
+                for ranges in ranges_list {
+                    for (start, end_included) in ranges {
+                        points.insert(*start);
+                        if let Some(c) = next_char(*end_included) {
+                            points.insert(c);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    points.into_iter().collect()
+}
+
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{d7ff}' => Some('\u{e000}'),
+        char::MAX => None,
+        _ => char::from_u32(c as u32 + 1),
+    }
+}
+
+fn previous_char(c: char) -> Option<char> {
+    match c {
+        '\u{e000}' => Some('\u{d7ff}'),
+        '\0' => None,
+        _ => char::from_u32(c as u32 - 1),
+    }
+}
+
+enum Edge {
+    Epsilon,
+    CounterReset,
+    CounterCheck(Option<usize>),
+    CounterInc,
+    CounterExit(usize),
+    Chars(Vec<(char, char)>),
+    String(Vec<Vec<(char, char)>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Config {
+    state_index: usize,
+    string: Option<(usize, usize)>, // the edge index and the offset of the matching string
+    counters: Vec<usize>,
+}
+
+struct Automaton {
+    start_state_index: usize,
+    end_state_index: usize,
+    edges: Vec<Vec<(Edge, usize)>>, // the edges and target states of each state
+    counter_limit: usize,           // the counter values are saturated at this value
+}
+
+impl Automaton {
+    fn new(state_set: &StateSet) -> Result<Self, Error> {
+        let mut edges = vec![];
+        let mut counter_limit = 0;
+
+        for state_index in 0..state_set.state_count() {
+            let mut state_edges = vec![];
+            let mut next_link_index = state_set.get_first_link_index(state_index);
+            while let Some(link_index) = next_link_index {
+                let (transition, target_state_index, next) = state_set.get_link(link_index);
+                next_link_index = next;
+
+                let edge = match transition {
+                    Transition::Jump(_)
+                    | Transition::CaptureStart(_)
+                    | Transition::CaptureEnd(_)
+                    | Transition::MatchStartReset(_)
+                    | Transition::Tag(_) => Edge::Epsilon,
+                    Transition::CounterReset(_) => Edge::CounterReset,
+                    Transition::CounterCheck(CounterCheckTransition { max }) => {
+                        counter_limit = counter_limit.max(max.unwrap_or(0));
+                        Edge::CounterCheck(*max)
+                    }
+                    Transition::CounterInc(CounterIncTransition { min }) => {
+                        counter_limit = counter_limit.max(*min);
+                        Edge::CounterInc
+                    }
+                    Transition::CounterExit(CounterExitTransition { min }) => {
+                        counter_limit = counter_limit.max(*min);
+                        Edge::CounterExit(*min)
+                    }
+                    Transition::Char(CharTransition {
+                        character,
+                        ignore_case,
+                    }) => Edge::Chars(char_ranges(*character, *ignore_case)),
+                    Transition::String(StringTransition { chars, ignore_case }) => {
+                        if chars.is_empty() {
+                            Edge::Epsilon
+                        } else {
+                            Edge::String(
+                                chars
+                                    .iter()
+                                    .map(|c| char_ranges(*c, *ignore_case))
+                                    .collect(),
+                            )
+                        }
+                    }
+                    Transition::CharSet(charset) if !(charset.negative && charset.grapheme) => {
+                        Edge::Chars(charset_ranges(charset))
+                    }
+                    Transition::SpecialChar(SpecialCharTransition {
+                        include_new_line,
+                        crlf,
+                        grapheme: false,
+                    }) => {
+                        if *include_new_line {
+                            Edge::Chars(vec![('\0', char::MAX)])
+                        } else if *crlf {
+                            Edge::Chars(complement(&[('\n', '\n'), ('\r', '\r')]))
+                        } else {
+                            Edge::Chars(complement(&[('\n', '\n')]))
+                        }
+                    }
+                    _ => {
+                        return Err(Error::Message(format!(
+                            "The transition \"{}\" is not supported for comparing patterns.",
+                            transition
+                        )));
+                    }
+                };
+
+                state_edges.push((edge, target_state_index));
+            }
+            edges.push(state_edges);
+        }
+
+        Ok(Automaton {
+            start_state_index: state_set.start_node_index,
+            end_state_index: state_set.end_node_index,
+            edges,
+            counter_limit: counter_limit + 1,
+        })
+    }
+
+    fn start(&self) -> Vec<Config> {
+        self.closure(vec![Config {
+            state_index: self.start_state_index,
+            string: None,
+            counters: vec![],
+        }])
+    }
+
+    fn is_accepted(&self, configs: &[Config]) -> bool {
+        configs
+            .iter()
+            .any(|config| config.state_index == self.end_state_index && config.string.is_none())
+    }
+
+    // the configurations after consuming the char.
+    fn step(&self, configs: &[Config], c: char) -> Vec<Config> {
+        let contains = |ranges: &[(char, char)]| ranges.iter().any(|(s, e)| *s <= c && c <= *e);
+
+        let mut next_configs = vec![];
+        for config in configs {
+            let state_edges = &self.edges[config.state_index];
+            match config.string {
+                Some((edge_index, offset)) => {
+                    let (edge, target_state_index) = &state_edges[edge_index];
+                    if let Edge::String(ranges_list) = edge {
+                        if contains(&ranges_list[offset]) {
+                            next_configs.push(Config {
+                                state_index: config.state_index,
+                                string: Some((edge_index, offset + 1)),
+                                counters: config.counters.clone(),
+                            });
+                            if offset + 1 == ranges_list.len() {
+                                next_configs.last_mut().unwrap().state_index = *target_state_index;
+                                next_configs.last_mut().unwrap().string = None;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    for (edge_index, (edge, target_state_index)) in state_edges.iter().enumerate() {
+                        let (matched, string) = match edge {
+                            Edge::Chars(ranges) => (contains(ranges), None),
+                            Edge::String(ranges_list) if ranges_list.len() == 1 => {
+                                (contains(&ranges_list[0]), None)
+                            }
+                            Edge::String(ranges_list) => {
+                                (contains(&ranges_list[0]), Some((edge_index, 1)))
+                            }
+                            _ => (false, None),
+                        };
+
+                        if matched {
+                            next_configs.push(Config {
+                                state_index: if string.is_some() {
+                                    config.state_index
+                                } else {
+                                    *target_state_index
+                                },
+                                string,
+                                counters: config.counters.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        self.closure(next_configs)
+    }
+
+    // the configurations that are reachable without consuming chars,
+    // sorted and deduplicated.
+    fn closure(&self, configs: Vec<Config>) -> Vec<Config> {
+        let mut result = BTreeSet::new();
+        let mut pending = configs;
+
+        while let Some(config) = pending.pop() {
+            if config.string.is_none() {
+                for (edge, target_state_index) in &self.edges[config.state_index] {
+                    let mut counters = config.counters.clone();
+                    let passed = match edge {
+                        Edge::Epsilon => true,
+                        Edge::CounterReset => {
+                            counters.push(0);
+                            true
+                        }
+                        Edge::CounterCheck(max) => match counters.last() {
+                            Some(value) => max.is_none_or(|m| *value < m),
+                            None => false,
+                        },
+                        Edge::CounterInc => match counters.last_mut() {
+                            Some(value) => {
+                                *value = (*value + 1).min(self.counter_limit);
+                                true
+                            }
+                            None => false,
+                        },
+                        Edge::CounterExit(min) => match counters.last() {
+                            Some(value) if value >= min => {
+                                counters.pop();
+                                true
+                            }
+                            _ => false,
+                        },
+                        Edge::Chars(_) | Edge::String(_) => false,
+                    };
+
+                    if passed {
+                        let next_config = Config {
+                            state_index: *target_state_index,
+                            string: None,
+                            counters,
+                        };
+                        if !result.contains(&next_config) {
+                            pending.push(next_config);
+                        }
+                    }
+                }
+            }
+
+            result.insert(config);
+        }
+
+        result.into_iter().collect()
+    }
+}
+
+fn char_ranges(c: char, ignore_case: bool) -> Vec<(char, char)> {
+    if ignore_case {
+        collect_ranges(|e| e == c || equals_ignore_case(e, c))
+    } else {
+        vec![(c, c)]
+    }
+}
+
+fn charset_ranges(charset: &CharSetTransition) -> Vec<(char, char)> {
+    // the case mapping and the Unicode presets are not simple ranges,
+    // so all chars are checked in these cases.
+    if charset.ignore_case || charset.unicode {
+        return collect_ranges(|c| charset.matches_char(c));
+    }
+
+    let mut ranges = vec![];
+    for item in &charset.items {
+        match item {
+            CharSetItem::Char(c) => ranges.push((*c, *c)),
+            CharSetItem::Range(start, end_included) => ranges.push((*start, *end_included)),
+            CharSetItem::Preset(preset, negative) => {
+                let preset_ranges = match preset {
+                    PresetCharSet::Space | PresetCharSet::AsciiSpace => {
+                        vec![('\t', '\n'), ('\x0c', '\r'), (' ', ' ')]
+                    }
+                    PresetCharSet::Word => vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+                    PresetCharSet::Digit => vec![('0', '9')],
+                };
+
+                if *negative {
+                    ranges.extend(complement(&preset_ranges));
+                } else {
+                    ranges.extend(preset_ranges);
+                }
+            }
+        }
+    }
+
+    if charset.negative {
+        complement(&ranges)
+    } else {
+        ranges
+    }
+}
+
+// the ranges of all chars that satisfy the predicate.
+fn collect_ranges(predicate: impl Fn(char) -> bool) -> Vec<(char, char)> {
+    let mut ranges: Vec<(char, char)> = vec![];
+    let mut last: Option<char> = None;
+
+    for c in ('\0'..='\u{d7ff}').chain('\u{e000}'..=char::MAX) {
+        if predicate(c) {
+            match ranges.last_mut() {
+                Some(range) if last.and_then(next_char) == Some(c) => range.1 = c,
+                _ => ranges.push((c, c)),
+            }
+            last = Some(c);
+        }
+    }
+
+    ranges
+}
+
+fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut sorted = ranges.to_vec();
+    sorted.sort();
+
+    let mut result = vec![];
+    let mut next_start = Some('\0');
+    for (start, end_included) in sorted {
+        if let Some(s) = next_start {
+            if s < start {
+                result.push((s, previous_char(start).unwrap()));
+            }
+            if end_included >= s {
+                next_start = next_char(end_included);
+            }
+        }
+    }
+
+    if let Some(s) = next_start {
+        result.push((s, char::MAX));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::{compile_from_str, compile_from_str_with};
+    use crate::options::CompileOptions;
+
+    use super::{complement, is_equivalent, is_subset};
+
+    #[test]
+    fn test_complement() {
+        assert_eq!(
+            complement(&[('b', 'c'), ('a', 'a'), ('x', 'y')]),
+            vec![('\0', '`'), ('d', 'w'), ('z', char::MAX)]
+        );
+        assert_eq!(complement(&[('\0', char::MAX)]), vec![]);
+    }
+
+    #[test]
+    fn test_is_subset_and_is_equivalent() {
+        let check = |a: &str, b: &str| -> (bool, bool) {
+            let a = compile_from_str(a).unwrap();
+            let b = compile_from_str(b).unwrap();
+            (is_subset(&a, &b).unwrap(), is_equivalent(&a, &b).unwrap())
+        };
+
+        // (subset, equivalent)
+        assert_eq!(check(r#"'a'"#, r#"'a' || 'b'"#), (true, false));
+        assert_eq!(check(r#"'a' || 'b'"#, r#"'a'"#), (false, false));
+        assert_eq!(check(r#"'a' || 'b'"#, r#"['a'..'b']"#), (true, true));
+        assert_eq!(check(r#""abc""#, r#"'a', 'b', 'c'"#), (true, true));
+        assert_eq!(check(r#""ab" || "ac""#, r#"'a', ['b', 'c']"#), (true, true));
+        assert_eq!(check(r#"'a'+"#, r#"'a', 'a'*"#), (true, true));
+        assert_eq!(check(r#"('a'+)+"#, r#"'a'+"#), (true, true));
+        assert_eq!(check(r#"'a'{2,3}"#, r#"'a'{2,}"#), (true, false));
+        assert_eq!(check(r#"'a'{2,3}"#, r#""aa", 'a'?"#), (true, true));
+        assert_eq!(check(r#"('a', 'b'?){2}"#, r#"('a', 'b'?)+"#), (true, false));
+        assert_eq!(check(r#"char_digit+"#, r#"['0'..'9']+"#), (true, true));
+        assert_eq!(check(r#"char_word"#, r#"![char_not_word]"#), (true, true));
+        assert_eq!(check(r#"char_any"#, r#"!['\n']"#), (true, true));
+        assert_eq!(
+            check(r#"char_any"#, r#"char_any_including_newline"#),
+            (true, false)
+        );
+        assert_eq!(check(r#"name('a', x)"#, r#"capture('a')"#), (true, true));
+        assert_eq!(check(r#"'a'?"#, r#"()"#), (false, false));
+        assert_eq!(check(r#"()"#, r#"'a'*"#), (true, false));
+
+        // ignore case
+        let options = CompileOptions {
+            ignore_case: true,
+            ..CompileOptions::default()
+        };
+        let a = compile_from_str_with(r#"'a', ['b'..'c']"#, &options).unwrap();
+        let b = compile_from_str(r#"['a', 'A'], ['b', 'c', 'B', 'C']"#).unwrap();
+        assert!(is_equivalent(&a, &b).unwrap());
+
+        // the Kelvin sign is also matched when ignoring case
+        let a = compile_from_str_with(r#"'k'"#, &options).unwrap();
+        let b = compile_from_str(r#"['k', 'K']"#).unwrap();
+        let c = compile_from_str(r#"['k', 'K', '\u{212a}']"#).unwrap();
+        assert!(!is_equivalent(&a, &b).unwrap());
+        assert!(is_equivalent(&a, &c).unwrap());
+
+        // the Unicode white spaces
+        let options = CompileOptions {
+            unicode: true,
+            ..CompileOptions::default()
+        };
+        let a = compile_from_str(r#"char_space"#).unwrap();
+        let b = compile_from_str_with(r#"char_space"#, &options).unwrap();
+        let c = compile_from_str(r#"[char_space, '\u{a0}', '\u{3000}']"#).unwrap();
+        assert!(is_subset(&a, &b).unwrap());
+        assert!(!is_subset(&b, &a).unwrap());
+        assert!(is_subset(&c, &b).unwrap());
+
+        // the ASCII white spaces regardless of the option
+        let d = compile_from_str_with(r#"char_space_ascii"#, &options).unwrap();
+        assert!(is_equivalent(&a, &d).unwrap());
+        let e = compile_from_str_with(r#"char_not_space_ascii"#, &options).unwrap();
+        let f = compile_from_str(r#"char_not_space"#).unwrap();
+        assert!(is_equivalent(&e, &f).unwrap());
+
+        // err: not supported
+        let a = compile_from_str(r#"start, 'a'"#).unwrap();
+        let b = compile_from_str(r#"'a'"#).unwrap();
+        assert!(is_subset(&a, &b).is_err());
+    }
+}
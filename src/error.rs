@@ -17,12 +17,16 @@ pub enum Error {
     // the last index of string, for example, the "char incomplete" error raised by a string `'a`,
     // which index is 2.
     MessageWithLocation(String, Location),
+
+    // a limit of the resources used by matching is exceeded, see
+    // `MatchLimits`, it is distinct from "no match".
+    LimitExceeded(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::Message(msg) => f.write_str(msg),
+            Error::Message(msg) | Error::LimitExceeded(msg) => f.write_str(msg),
             Error::UnexpectedEndOfDocument(detail) => {
                 writeln!(f, "Unexpected to reach the end of document.")?;
                 write!(f, "{}", detail)
@@ -114,6 +114,22 @@ fn generate_snippet_and_indented_detail(
     (snippet, indented_detail)
 }
 
+// print the source snippet and mark the specified range of chars
+// with the detail, e.g.
+//
+// ```text
+// | abcdefg
+// |   ^^^ detail
+// ```
+#[cfg(any(test, feature = "testing"))]
+pub fn format_snippet(source: &str, start: usize, length: usize, detail: &str) -> String {
+    let source_total_length = source.chars().count();
+    let snippet_range = calculate_snippet_range(start, length, source_total_length);
+    let (snippet, indented_detail) =
+        generate_snippet_and_indented_detail(&mut source.chars(), &snippet_range, detail);
+    format!("{}\n{}", snippet, indented_detail)
+}
+
 impl Error {
     pub fn with_source(&self, source: &str) -> String {
         // print human readable error message with the source
@@ -130,7 +146,7 @@ impl Error {
         // | snippet length
 
         match self {
-            Error::Message(msg) => msg.to_owned(),
+            Error::Message(msg) | Error::LimitExceeded(msg) => msg.to_owned(),
             Error::UnexpectedEndOfDocument(detail) => {
                 let msg = "Unexpected to reach the end of document.";
                 let snippet_range =
@@ -0,0 +1,391 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// generates the sample texts that are matched entirely by a pattern.
+//
+// the generator walks the states from the start node to the end node,
+// the transitions of each state are tried in a random order, and the
+// walk goes back to the previous state when all transitions fail,
+// just like the process does.
+//
+// the repetitions are bounded by `max_repetitions`, i.e. an iteration
+// beyond the bound is only tried after leaving the repetition fails.
+// the assertions and look-arounds are assumed to succeed during the
+// walk, and the generated text is checked by the process at the end,
+// the walk starts over with other random chars if all of its choices
+// fail the check, e.g. the `char_any` of `'a'.is_before('b'), char_any`.
+
+use crate::{
+    context::Context,
+    process::Instance,
+    state::StateSet,
+    transition::{
+        equals_ignore_case, CaptureSlot, CharSetItem, CharSetTransition, CharTransition,
+        FrameState, NormalizedStringTransition, PresetCharSet, SpecialCharTransition,
+        StringTransition, Transition,
+    },
+};
+
+const DEFAULT_MAX_REPETITIONS: usize = 4;
+
+// the maximum number of transitions to try for generating one text
+const MAX_STEPS: usize = 100_000;
+
+// the chars for the transitions that accept (almost) any char
+const PRINTABLE_CHARS: (char, char) = (' ', '~');
+
+pub struct Generator<'a> {
+    state_set: &'a StateSet,
+    random: Random,
+    max_repetitions: usize,
+}
+
+struct Frame {
+    state_index: usize,
+    length: usize,            // the length of text when entering this state
+    link_indices: Vec<usize>, // the transitions to try, the last one is tried first
+    state: FrameState,
+}
+
+impl<'a> Generator<'a> {
+    pub(crate) fn new(state_set: &'a StateSet, seed: u64) -> Self {
+        Generator {
+            state_set,
+            random: Random::new(seed),
+            max_repetitions: DEFAULT_MAX_REPETITIONS,
+        }
+    }
+
+    // the number of iterations of a repetition is at most
+    // `max(min, max_repetitions)` unless more are required to match.
+    pub fn set_max_repetitions(&mut self, max_repetitions: usize) {
+        self.max_repetitions = max_repetitions;
+    }
+
+    // generate a text that is matched entirely by the pattern, returns
+    // `None` if no text is found within `MAX_STEPS` transitions, e.g.
+    // the pattern `'a', start` that matches nothing, and the rare one
+    // like `'a'.is_before("bcd"), char_any{3}`.
+    pub fn generate(&mut self) -> Option<String> {
+        let state_set = self.state_set;
+        let mut text: Vec<char> = vec![];
+
+        let mut initial_state = FrameState::default();
+        initial_state
+            .captures
+            .resize(state_set.capture_groups.len(), CaptureSlot::default());
+        let mut frames = vec![];
+
+        for _ in 0..MAX_STEPS {
+            if frames.is_empty() {
+                // start a new walk
+                let state = initial_state.clone();
+                frames.push(self.new_frame(state_set.start_node_index, 0, state));
+            }
+
+            let frame = frames.last_mut().unwrap();
+            text.truncate(frame.length);
+
+            if frame.state_index == state_set.end_node_index {
+                let s: String = text.iter().collect();
+                if self.is_exact_match(&s) {
+                    return Some(s);
+                }
+
+                frames.pop();
+                continue;
+            }
+
+            let Some(link_index) = frame.link_indices.pop() else {
+                frames.pop();
+                continue;
+            };
+
+            let (transition, target_state_index, _) = state_set.get_link(link_index);
+            let position = frame.length;
+            let mut state = frame.state.clone();
+
+            if !transition.update_frame_state(&mut state, position) {
+                continue;
+            }
+
+            // the back-reference repeats the captured text
+            let chars = match transition {
+                Transition::BackReference(back_reference) => {
+                    match state.captures[back_reference.capture_index] {
+                        CaptureSlot {
+                            start: Some(start),
+                            end: Some(end),
+                            ..
+                        } => Some(text[start..end].to_vec()),
+                        _ => None,
+                    }
+                }
+                _ => self.generate_chars(transition),
+            };
+
+            match chars {
+                Some(chars) => text.extend(chars),
+                None => continue,
+            }
+
+            let length = text.len();
+            frames.push(self.new_frame(target_state_index, length, state));
+        }
+
+        None
+    }
+
+    fn new_frame(&mut self, state_index: usize, length: usize, state: FrameState) -> Frame {
+        let mut link_indices = vec![];
+        let mut next_link_index = self.state_set.get_first_link_index(state_index);
+        while let Some(link_index) = next_link_index {
+            link_indices.push(link_index);
+            next_link_index = self.state_set.get_link(link_index).2;
+        }
+
+        self.random.shuffle(&mut link_indices);
+
+        // try leaving the repetition before exceeding the bound, the
+        // iterations are only tried after all other transitions fail.
+        if matches!(state.counters.last(), Some(counter) if counter.value >= self.max_repetitions) {
+            link_indices.sort_by_key(|link_index| {
+                !matches!(
+                    self.state_set.get_link(*link_index).0,
+                    Transition::CounterCheck(_)
+                )
+            });
+        }
+
+        Frame {
+            state_index,
+            length,
+            link_indices,
+            state,
+        }
+    }
+
+    // the chars consumed by the transition, returns `None` if
+    // no chars can be generated for it.
+    fn generate_chars(&mut self, transition: &Transition) -> Option<Vec<char>> {
+        let chars = match transition {
+            Transition::Char(CharTransition {
+                character,
+                ignore_case,
+            }) => vec![self.vary_case(*character, *ignore_case)],
+            Transition::String(StringTransition { chars, ignore_case }) => chars
+                .iter()
+                .map(|c| self.vary_case(*c, *ignore_case))
+                .collect(),
+            Transition::SpecialChar(SpecialCharTransition { .. }) => {
+                vec![self.random.char_in(PRINTABLE_CHARS.0, PRINTABLE_CHARS.1)]
+            }
+            Transition::CharSet(charset) => vec![self.generate_charset_char(charset)?],
+            Transition::NormalizedString(NormalizedStringTransition { chars, ignore_case }) => {
+                chars
+                    .iter()
+                    .map(|c| self.vary_case(*c, *ignore_case))
+                    .collect()
+            }
+            Transition::Byte(_) | Transition::ByteRange(_) => {
+                // the bytes can not be represented by text
+                return None;
+            }
+            _ => vec![],
+        };
+
+        Some(chars)
+    }
+
+    fn vary_case(&mut self, c: char, ignore_case: bool) -> char {
+        if ignore_case && self.random.next_bool() {
+            let mut upper = c.to_uppercase();
+            let mut lower = c.to_lowercase();
+            let other = if c.is_lowercase() {
+                upper.next().filter(|_| upper.next().is_none())
+            } else {
+                lower.next().filter(|_| lower.next().is_none())
+            };
+
+            match other {
+                Some(o) if equals_ignore_case(o, c) => o,
+                _ => c,
+            }
+        } else {
+            c
+        }
+    }
+
+    fn generate_charset_char(&mut self, charset: &CharSetTransition) -> Option<char> {
+        // pick a char from a random item first
+        if !charset.negative && !charset.items.is_empty() {
+            for _ in 0..16 {
+                let item = &charset.items[self.random.next_index(charset.items.len())];
+                let c = match item {
+                    CharSetItem::Char(c) => *c,
+                    CharSetItem::Range(start, end_included) => {
+                        self.random.char_in(*start, *end_included)
+                    }
+                    CharSetItem::Preset(preset, false) => {
+                        let samples: &[char] = match preset {
+                            PresetCharSet::Space | PresetCharSet::AsciiSpace => &[' ', '\t', '\n'],
+                            PresetCharSet::Word => &['a', 'z', 'A', 'Z', '0', '9', '_'],
+                            PresetCharSet::Digit => &['0', '1', '2', '5', '7', '9'],
+                        };
+                        samples[self.random.next_index(samples.len())]
+                    }
+                    CharSetItem::Preset(_, true) => {
+                        self.random.char_in(PRINTABLE_CHARS.0, PRINTABLE_CHARS.1)
+                    }
+                };
+
+                if charset.matches_char(c) {
+                    return Some(c);
+                }
+            }
+        }
+
+        // then try the printable chars
+        for _ in 0..16 {
+            let c = self.random.char_in(PRINTABLE_CHARS.0, PRINTABLE_CHARS.1);
+            if charset.matches_char(c) {
+                return Some(c);
+            }
+        }
+
+        ('\0'..='\u{ff}').find(|c| charset.matches_char(*c))
+    }
+
+    fn is_exact_match(&self, text: &str) -> bool {
+        let mut instance = Instance::new(self.state_set, Context::new(text));
+        instance.exec_exact()
+    }
+}
+
+// a simple pseudo random number generator (xorshift64*), the
+// same seed always produces the same texts.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // the state must not be zero
+        let state = match seed ^ 0x9e37_79b9_7f4a_7c15 {
+            0 => 1,
+            n => n,
+        };
+        Random { state }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+
+    fn next_index(&mut self, length: usize) -> usize {
+        (self.next_u64() % length as u64) as usize
+    }
+
+    // a random char between `start` and `end_included`, the chars
+    // that are not valid (i.e. the surrogates) are replaced by `start`.
+    fn char_in(&mut self, start: char, end_included: char) -> char {
+        let count = end_included as u64 - start as u64 + 1;
+        let n = start as u64 + self.next_u64() % count;
+        char::from_u32(n as u32).unwrap_or(start)
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.next_index(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::process::Process;
+
+    fn generate(pattern: &str, count: usize) -> Vec<String> {
+        let process = Process::new(pattern).unwrap();
+        let mut generator = process.new_generator(42);
+        (0..count)
+            .map(|_| {
+                let text = generator.generate().expect(pattern);
+                assert!(
+                    process.new_instance(&text).exec_exact(),
+                    "{:?} does not match {}",
+                    text,
+                    pattern
+                );
+                text
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generate() {
+        assert_eq!(generate(r#""abc""#, 2), vec!["abc", "abc"]);
+        assert_eq!(generate(r#"()"#, 1), vec![""]);
+
+        // all samples match the pattern, it is checked by `generate`
+        generate(r#"'a' || 'b' || "cd""#, 20);
+        generate(r#"char_digit{4}, '-', ['0'..'1'], char_digit"#, 20);
+        generate(r#"!['a'..'z'], char_word+, char_space*"#, 20);
+        generate(r#"('a', 'b'?){2,}, char_any+?"#, 20);
+        generate(r#"(('x'?)*, 'y'){3}"#, 20);
+        generate(r#"number_range(10, 255)"#, 20);
+        generate(r#"start, char_word+, end"#, 20);
+        generate(r#"char_word+, bound, '!'?"#, 20);
+        generate(r#"char_digit+.is_before('!'), '!'"#, 20);
+        generate(r#"'a'.is_after('x'?), char_word"#, 20);
+        generate(r#"date_iso8601()"#, 20);
+        generate(r#"name(char_word{1,3}, x), '=', x"#, 20);
+
+        // the bound of repetitions
+        let process = Process::new(r#"'a'*"#).unwrap();
+        let mut generator = process.new_generator(7);
+        generator.set_max_repetitions(2);
+        for _ in 0..20 {
+            assert!(generator.generate().unwrap().len() <= 2);
+        }
+
+        let process = Process::new(r#"'a'{5}"#).unwrap();
+        let mut generator = process.new_generator(7);
+        generator.set_max_repetitions(2);
+        assert_eq!(generator.generate(), Some("aaaaa".to_owned()));
+
+        // the same seed generates the same texts
+        assert_eq!(
+            generate(r#"char_word{1,8}"#, 5),
+            generate(r#"char_word{1,8}"#, 5)
+        );
+
+        // the walk starts over if the look-around fails
+        for seed in 0..50 {
+            let process = Process::new(r#"start, 'a'.is_before('b'), char_any, end"#).unwrap();
+            assert_eq!(
+                process.new_generator(seed).generate(),
+                Some("ab".to_owned())
+            );
+        }
+
+        // no text matches
+        let process = Process::new(r#"'a', start"#).unwrap();
+        assert_eq!(process.new_generator(1).generate(), None);
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// converts the shell globs into ANREG programs, so the tools that
+// support both globs and patterns can share one engine, e.g. the glob
+// `*.{log,txt}` is converted into
+// `start, zero_or_more(!['/']), '.', ("log" || "txt"), end`.
+//
+// the wildcards:
+//
+// - `*` matches any chars except '/', i.e. it does not cross the
+//   directories.
+// - `?` matches one char except '/'.
+// - `[abc]`, `[a-z]` match one of the chars, and `[!a-z]` (or `[^a-z]`)
+//   matches one char except the listed ones and '/'. the char ']' is
+//   listed literally if it comes first, e.g. `[]a]`, and a '[' without
+//   the closing ']' is an ordinary char.
+// - `{a,b}` matches any of the alternatives, the alternatives are globs
+//   too and can be nested, e.g. `{*.rs,src/{a,b}}`.
+// - `\` escapes the next char, also inside the brackets, e.g. `\*`
+//   matches '*', and `[\]\-]` matches ']' or '-'.
+//
+// the program is anchored, i.e. the glob matches the whole text.
+
+use crate::{
+    ast::{
+        CharRange, CharSet, CharSetElement, Expression, FunctionCall, FunctionName, Literal,
+        PatternOptions, Program,
+    },
+    error::Error,
+};
+
+pub fn from_glob(glob: &str) -> Result<Program, Error> {
+    let mut converter = GlobConverter {
+        chars: glob.chars().collect(),
+        position: 0,
+    };
+
+    let mut expressions = vec![Expression::Literal(Literal::Status("start".to_owned()))];
+    expressions.extend(converter.convert_sequence(false)?);
+    expressions.push(Expression::Literal(Literal::Status("end".to_owned())));
+
+    Ok(Program {
+        expressions,
+        tests: vec![],
+        options: PatternOptions::default(),
+        syntax_version: None,
+    })
+}
+
+struct GlobConverter {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl GlobConverter {
+    // convert the glob until the end, or the ',' and '}' of the
+    // alternatives if it is inside braces.
+    fn convert_sequence(&mut self, in_braces: bool) -> Result<Vec<Expression>, Error> {
+        let mut expressions = vec![];
+        let mut literal = String::new();
+
+        while let Some(&c) = self.chars.get(self.position) {
+            let expression = match c {
+                ',' | '}' if in_braces => break,
+                '*' => {
+                    // the consecutive stars are the same as one
+                    while self.chars.get(self.position) == Some(&'*') {
+                        self.position += 1;
+                    }
+                    Expression::FunctionCall(Box::new(FunctionCall {
+                        name: FunctionName::ZeroOrMore,
+                        expression: Box::new(not_separator()),
+                        args: vec![],
+                    }))
+                }
+                '?' => {
+                    self.position += 1;
+                    not_separator()
+                }
+                '[' => match self.convert_bracket() {
+                    Some(charset) => Expression::Literal(Literal::CharSet(charset)),
+                    None => {
+                        // the '[' without the closing ']'
+                        self.position += 1;
+                        literal.push(c);
+                        continue;
+                    }
+                },
+                '{' => {
+                    self.position += 1;
+                    self.convert_braces()?
+                }
+                '\\' => {
+                    self.position += 1;
+                    if let Some(&escaped) = self.chars.get(self.position) {
+                        self.position += 1;
+                        literal.push(escaped);
+                    } else {
+                        literal.push(c);
+                    }
+                    continue;
+                }
+                _ => {
+                    self.position += 1;
+                    literal.push(c);
+                    continue;
+                }
+            };
+
+            push_literal(&mut expressions, &mut literal);
+            expressions.push(expression);
+        }
+
+        push_literal(&mut expressions, &mut literal);
+        Ok(expressions)
+    }
+
+    // convert the alternatives, the '{' has been consumed.
+    fn convert_braces(&mut self) -> Result<Expression, Error> {
+        let mut alternatives = vec![];
+        loop {
+            let mut expressions = self.convert_sequence(true)?;
+            alternatives.push(if expressions.len() == 1 {
+                expressions.pop().unwrap()
+            } else {
+                Expression::Group(expressions)
+            });
+
+            match self.chars.get(self.position) {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    break;
+                }
+                _ => {
+                    return Err(Error::Message(
+                        "Incomplete alternatives in the glob, expect \"}\".".to_owned(),
+                    ));
+                }
+            }
+        }
+
+        let expression = if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Expression::OrList(alternatives)
+        };
+
+        // the alternatives are grouped, so they do not take the
+        // expressions around them as their operands.
+        Ok(Expression::Group(vec![expression]))
+    }
+
+    // convert the bracket expression at the current position, returns
+    // `None` (and the position is unchanged) if there is no closing ']'.
+    fn convert_bracket(&mut self) -> Option<CharSet> {
+        let mut position = self.position + 1; // skip '['
+
+        let negative = matches!(self.chars.get(position), Some('!' | '^'));
+        if negative {
+            position += 1;
+        }
+
+        let mut elements = vec![];
+        let mut first = true;
+        loop {
+            let c = *self.chars.get(position)?;
+            if c == ']' && !first {
+                position += 1;
+                break;
+            }
+            first = false;
+
+            let (start, length) = self.bracket_char(position);
+            position += length;
+
+            match (self.chars.get(position), self.chars.get(position + 1)) {
+                (Some('-'), Some(&next)) if next != ']' => {
+                    let (end_included, length) = self.bracket_char(position + 1);
+                    elements.push(CharSetElement::CharRange(CharRange {
+                        start,
+                        end_included,
+                    }));
+                    position += 1 + length;
+                }
+                _ => elements.push(CharSetElement::Char(start)),
+            }
+        }
+
+        if negative {
+            elements.push(CharSetElement::Char('/'));
+        }
+
+        self.position = position;
+        Some(CharSet { negative, elements })
+    }
+
+    // the char of the bracket expression at the position and the number
+    // of chars it takes, i.e. 2 for the escaped one.
+    fn bracket_char(&self, position: usize) -> (char, usize) {
+        match (self.chars[position], self.chars.get(position + 1)) {
+            ('\\', Some(&escaped)) => (escaped, 2),
+            (c, _) => (c, 1),
+        }
+    }
+}
+
+// any char except the directory separator
+fn not_separator() -> Expression {
+    Expression::Literal(Literal::CharSet(CharSet {
+        negative: true,
+        elements: vec![CharSetElement::Char('/')],
+    }))
+}
+
+fn push_literal(expressions: &mut Vec<Expression>, literal: &mut String) {
+    let mut chars = literal.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => {}
+        (Some(c), None) => expressions.push(Expression::Literal(Literal::Char(c))),
+        _ => expressions.push(Expression::Literal(Literal::String(literal.to_owned()))),
+    }
+    literal.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{options::CompileOptions, process::Process};
+
+    use super::from_glob;
+
+    fn is_match(glob: &str, text: &str) -> bool {
+        let program = from_glob(glob).unwrap();
+        let process = Process::from_program(&program, &CompileOptions::default()).unwrap();
+        let mut instance = process.new_instance(text);
+        instance.exec(0).is_some()
+    }
+
+    #[test]
+    fn test_from_glob() {
+        assert_eq!(
+            from_glob("*.{log,txt}").unwrap().to_string(),
+            "start\nzero_or_more(!['/'])\n'.', (\"log\" || \"txt\"), end"
+        );
+        assert_eq!(
+            from_glob("[!a-c]?[]x]\\*").unwrap().to_string(),
+            "start, !['a'..'c', '/'], !['/'], [']', 'x'], '*', end"
+        );
+        assert_eq!(
+            from_glob("[\\]a-\\z]").unwrap().to_string(),
+            "start, [']', 'a'..'z'], end"
+        );
+        assert!(from_glob("{a,b").is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        // star
+        assert!(is_match("*.log", "app.log"));
+        assert!(is_match("*.log", ".log"));
+        assert!(!is_match("*.log", "app.log.1"));
+        assert!(!is_match("*.log", "logs/app.log"));
+        assert!(is_match("logs/**.log", "logs/app.log"));
+        assert!(is_match("a*b*c", "aXbYbZc"));
+
+        // question mark
+        assert!(is_match("file?.txt", "file1.txt"));
+        assert!(!is_match("file?.txt", "file.txt"));
+        assert!(!is_match("a?b", "a/b"));
+
+        // brackets
+        assert!(is_match("[a-c]x", "bx"));
+        assert!(!is_match("[a-c]x", "dx"));
+        assert!(is_match("[!a-c]x", "dx"));
+        assert!(!is_match("[!a-c]x", "/x"));
+        assert!(is_match("[]]", "]"));
+        assert!(is_match("[a-]", "-"));
+        assert!(is_match("a[b", "a[b"));
+
+        // escapes inside brackets
+        assert!(is_match("[\\]]", "]"));
+        assert!(is_match("[a\\-z]", "-"));
+        assert!(!is_match("[a\\-z]", "b"));
+        assert!(is_match("[\\!a]", "!"));
+        assert!(is_match("[+-\\]]", "]"));
+
+        // the escaped ']' does not close the brackets
+        assert!(is_match("[\\]", "[]"));
+        assert!(is_match("[\\\\]", "\\"));
+
+        // braces
+        assert!(is_match("*.{rs,toml}", "Cargo.toml"));
+        assert!(is_match("{src/{a,b},test}/*.rs", "src/b/lib.rs"));
+        assert!(!is_match("{src/{a,b},test}/*.rs", "src/c/lib.rs"));
+        assert!(is_match("a{,b}c", "ac"));
+        assert!(is_match("a{,b}c", "abc"));
+
+        // escapes and the chars that are special in ANREG
+        assert!(is_match("\\*\\?", "*?"));
+        assert!(!is_match("\\*", "a"));
+        assert!(is_match("'\"()||.txt", "'\"()||.txt"));
+        assert!(is_match("文字-*", "文字-1"));
+    }
+}
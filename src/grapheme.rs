@@ -0,0 +1,248 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the boundaries of the extended grapheme clusters, i.e. the units that
+// are perceived as one char by the users, e.g. "e\u{301}" (é), the emoji
+// with a skin tone modifier "\u{1f44d}\u{1f3fd}" (👍🏽) and the family
+// emoji joined by ZWJ "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}".
+//
+// the rules of UAX #29 are followed except the "Prepend" and
+// "SpacingMark" ones (GB9a and GB9b), and the properties are
+// approximated by the Unicode blocks:
+//
+// - GB3, GB4, GB5: "\r\n" is one cluster, the other control chars
+//   are always alone.
+// - GB6, GB7, GB8: the Hangul syllable sequences.
+// - GB9: the combining marks, the variation selectors, the emoji
+//   modifiers, the tags and ZWJ extend the previous char.
+// - GB11: the pictographs joined by ZWJ.
+// - GB12, GB13: the regional indicators (flags) are paired.
+
+use crate::context::Context;
+
+const ZWJ: char = '\u{200d}';
+
+// returns the length of the extended grapheme cluster that begins with
+// the char at the position, the char has been read by the caller.
+pub fn grapheme_length(
+    context: &Context,
+    position: usize,
+    first: char,
+    first_length: usize,
+) -> usize {
+    let mut end = position + first_length;
+    let mut previous = first;
+
+    // the number of the regional indicators before the current char
+    let mut regional_indicators = usize::from(is_regional_indicator(first));
+
+    // whether the cluster is a pictograph followed by the extending chars
+    let mut pictographic = is_pictographic(first);
+
+    while let Some((c, length)) = context.read_char(end) {
+        if !is_joined(previous, c, regional_indicators, pictographic) {
+            break;
+        }
+
+        if is_regional_indicator(c) {
+            regional_indicators += 1;
+        }
+        pictographic = (pictographic && (is_extend(c) || c == ZWJ)) || is_pictographic(c);
+
+        previous = c;
+        end += length;
+    }
+
+    end - position
+}
+
+// whether there is no boundary between the two chars.
+fn is_joined(
+    previous: char,
+    current: char,
+    regional_indicators: usize,
+    pictographic: bool,
+) -> bool {
+    // GB3, GB4, GB5
+    if previous == '\r' && current == '\n' {
+        return true;
+    }
+    if previous.is_control() || current.is_control() {
+        return false;
+    }
+
+    // GB6, GB7, GB8
+    let previous_hangul = get_hangul_type(previous);
+    let current_hangul = get_hangul_type(current);
+    match (previous_hangul, current_hangul) {
+        (Some(Hangul::L), Some(Hangul::L | Hangul::V | Hangul::Lv | Hangul::Lvt))
+        | (Some(Hangul::Lv | Hangul::V), Some(Hangul::V | Hangul::T))
+        | (Some(Hangul::Lvt | Hangul::T), Some(Hangul::T)) => return true,
+        _ => {}
+    }
+
+    // GB9
+    if is_extend(current) || current == ZWJ {
+        return true;
+    }
+
+    // GB11
+    if previous == ZWJ && pictographic && is_pictographic(current) {
+        return true;
+    }
+
+    // GB12, GB13
+    is_regional_indicator(previous)
+        && is_regional_indicator(current)
+        && regional_indicators % 2 == 1
+}
+
+fn is_extend(c: char) -> bool {
+    matches!(c,
+        '\u{300}'..='\u{36f}'         // combining diacritical marks
+        | '\u{483}'..='\u{489}'       // Cyrillic combining marks
+        | '\u{591}'..='\u{5bd}'       // Hebrew points
+        | '\u{610}'..='\u{61a}'       // Arabic marks
+        | '\u{64b}'..='\u{65f}'
+        | '\u{900}'..='\u{902}'       // Devanagari signs
+        | '\u{93c}'
+        | '\u{941}'..='\u{948}'
+        | '\u{94d}'
+        | '\u{e31}'                   // Thai vowels and tone marks
+        | '\u{e34}'..='\u{e3a}'
+        | '\u{e47}'..='\u{e4e}'
+        | '\u{1ab0}'..='\u{1aff}'     // combining diacritical marks extended
+        | '\u{1dc0}'..='\u{1dff}'     // combining diacritical marks supplement
+        | '\u{200c}'                  // ZWNJ
+        | '\u{20d0}'..='\u{20ff}'     // combining marks for symbols, e.g. the keycap
+        | '\u{3099}'..='\u{309a}'     // Japanese voiced sound marks
+        | '\u{fe00}'..='\u{fe0f}'     // variation selectors
+        | '\u{fe20}'..='\u{fe2f}'     // combining half marks
+        | '\u{1f3fb}'..='\u{1f3ff}'   // emoji modifiers (skin tones)
+        | '\u{e0020}'..='\u{e007f}'   // tags, e.g. the subdivision flags
+        | '\u{e0100}'..='\u{e01ef}'   // variation selectors supplement
+    )
+}
+
+fn is_pictographic(c: char) -> bool {
+    matches!(c,
+        '\u{a9}'
+        | '\u{ae}'
+        | '\u{203c}'
+        | '\u{2049}'
+        | '\u{2122}'
+        | '\u{2139}'
+        | '\u{2194}'..='\u{21ff}'
+        | '\u{2300}'..='\u{23ff}'
+        | '\u{2600}'..='\u{27bf}'
+        | '\u{2b00}'..='\u{2bff}'
+        | '\u{3030}'
+        | '\u{303d}'
+        | '\u{3297}'
+        | '\u{3299}'
+        | '\u{1f000}'..='\u{1f1e5}'
+        | '\u{1f200}'..='\u{1f3fa}'
+        | '\u{1f400}'..='\u{1faff}'
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Hangul {
+    L,   // leading consonant (choseong)
+    V,   // vowel (jungseong)
+    T,   // trailing consonant (jongseong)
+    Lv,  // syllable without the trailing consonant
+    Lvt, // syllable with the trailing consonant
+}
+
+fn get_hangul_type(c: char) -> Option<Hangul> {
+    match c {
+        '\u{1100}'..='\u{115f}' | '\u{a960}'..='\u{a97c}' => Some(Hangul::L),
+        '\u{1160}'..='\u{11a7}' | '\u{d7b0}'..='\u{d7c6}' => Some(Hangul::V),
+        '\u{11a8}'..='\u{11ff}' | '\u{d7cb}'..='\u{d7fb}' => Some(Hangul::T),
+        '\u{ac00}'..='\u{d7a3}' => {
+            if (c as u32 - 0xac00).is_multiple_of(28) {
+                Some(Hangul::Lv)
+            } else {
+                Some(Hangul::Lvt)
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::context::Context;
+
+    use super::grapheme_length;
+
+    // split the text into the clusters
+    fn split(s: &str) -> Vec<String> {
+        let context = Context::new(s);
+        let mut clusters = vec![];
+        let mut position = 0;
+        while let Some((c, length)) = context.read_char(position) {
+            let cluster_length = grapheme_length(&context, position, c, length);
+            clusters.push(
+                context.text[position..position + cluster_length]
+                    .iter()
+                    .collect(),
+            );
+            position += cluster_length;
+        }
+        clusters
+    }
+
+    #[test]
+    fn test_grapheme_length() {
+        assert_eq!(split("abc"), vec!["a", "b", "c"]);
+        assert_eq!(split("e\u{301}x"), vec!["e\u{301}", "x"]);
+        assert_eq!(split("a\r\n\n"), vec!["a", "\r\n", "\n"]);
+        assert_eq!(split("\n\u{301}"), vec!["\n", "\u{301}"]);
+
+        // emoji
+        assert_eq!(
+            split("\u{1f44d}\u{1f3fd}!"),
+            vec!["\u{1f44d}\u{1f3fd}", "!"]
+        );
+        assert_eq!(
+            split("\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}a"),
+            vec!["\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}", "a"]
+        );
+        assert_eq!(split("1\u{fe0f}\u{20e3}"), vec!["1\u{fe0f}\u{20e3}"]);
+
+        // the ZWJ between non-pictographs does not join them
+        assert_eq!(split("a\u{200d}b"), vec!["a\u{200d}", "b"]);
+
+        // flags
+        assert_eq!(
+            split("\u{1f1e8}\u{1f1f3}\u{1f1ef}\u{1f1f5}\u{1f1fa}"),
+            vec!["\u{1f1e8}\u{1f1f3}", "\u{1f1ef}\u{1f1f5}", "\u{1f1fa}"]
+        );
+
+        // Hangul
+        assert_eq!(
+            split("\u{1100}\u{1161}\u{11a8}\u{ac00}\u{11a8}\u{ac01}\u{1161}"),
+            vec![
+                "\u{1100}\u{1161}\u{11a8}",
+                "\u{ac00}\u{11a8}",
+                "\u{ac01}",
+                "\u{1161}"
+            ]
+        );
+
+        // bytes mode
+        let context = Context::from_bytes("e\u{301}".as_bytes());
+        assert_eq!(grapheme_length(&context, 0, 'e', 1), 3);
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::ops::Range;
+
+use crate::process::{Instance, MatchRange, Process};
+
+// keeps all matches of a text, and updates only the matches affected
+// by an edit instead of rescanning the whole text.
+//
+// it is designed for editors, e.g. highlighting the matches while typing.
+pub struct IncrementalMatcher<'a> {
+    instance: Instance<'a>,
+    matches: Vec<TrackedMatch>,
+}
+
+// a match and the range of positions that have been read
+// while searching it, the match depends on the text within the range.
+struct TrackedMatch {
+    range: MatchRange,
+    read_start: usize,
+    read_end: usize, // exclusive
+}
+
+impl<'a> IncrementalMatcher<'a> {
+    pub fn new(process: &'a Process, text: &str) -> Self {
+        Self::from_instance(process.new_instance(text))
+    }
+
+    pub fn from_bytes(process: &'a Process, bytes: &[u8]) -> Self {
+        Self::from_instance(process.new_instance_from_bytes(bytes))
+    }
+
+    fn from_instance(instance: Instance<'a>) -> Self {
+        let mut matcher = IncrementalMatcher {
+            instance,
+            matches: vec![],
+        };
+        matcher.update(0, 0, 0);
+        matcher
+    }
+
+    pub fn matches(&self) -> impl Iterator<Item = &MatchRange> {
+        self.matches.iter().map(|m| &m.range)
+    }
+
+    // replace the chars from `start` to `end` (exclusive) with the text,
+    // returns the index range of matches that have been recomputed.
+    pub fn replace_text(&mut self, start: usize, end: usize, text: &str) -> Range<usize> {
+        self.instance.replace_text(start, end, text);
+        self.update(start, end, text.chars().count())
+    }
+
+    // replace the bytes from `start` to `end` (exclusive),
+    // returns the index range of matches that have been recomputed.
+    pub fn replace_bytes(&mut self, start: usize, end: usize, bytes: &[u8]) -> Range<usize> {
+        self.instance.replace_bytes(start, end, bytes);
+        self.update(start, end, bytes.len())
+    }
+
+    fn update(&mut self, start: usize, end: usize, inserted_length: usize) -> Range<usize> {
+        let delta = inserted_length as isize - (end - start) as isize;
+        let shift = |position: usize| (position as isize + delta) as usize;
+
+        // the matches before the first affected one are kept
+        let first_affected = self
+            .matches
+            .iter()
+            .position(|m| m.read_end > start)
+            .unwrap_or(self.matches.len());
+
+        // the matches that depend on the text after the edit only can
+        // be reused if the scan reaches them again.
+        let mut reused = self.matches[first_affected..]
+            .iter()
+            .position(|m| m.read_start >= end)
+            .map_or(self.matches.len(), |idx| first_affected + idx);
+
+        let mut position = match first_affected {
+            0 => 0,
+            idx => next_scan_position(&self.matches[idx - 1].range),
+        };
+
+        let mut new_matches = vec![];
+        let mut converged = false;
+        while let Some((range, read_start, read_end)) = self.instance.exec_with_read_range(position)
+        {
+            // skip the reusable matches that the scan has passed
+            while reused < self.matches.len()
+                && shift(self.matches[reused].range.start) < range.start
+            {
+                reused += 1;
+            }
+
+            if reused < self.matches.len() {
+                let old_range = &self.matches[reused].range;
+                if shift(old_range.start) == range.start && shift(old_range.end) == range.end {
+                    // the following matches are the same as before
+                    converged = true;
+                    break;
+                }
+            }
+
+            position = next_scan_position(&range);
+            new_matches.push(TrackedMatch {
+                range,
+                read_start,
+                read_end,
+            });
+
+            if position > self.instance.length() {
+                break;
+            }
+        }
+
+        if !converged {
+            reused = self.matches.len();
+        }
+
+        let reused_matches: Vec<TrackedMatch> = self
+            .matches
+            .drain(reused..)
+            .map(|m| TrackedMatch {
+                range: MatchRange::new(shift(m.range.start), shift(m.range.end)),
+                read_start: shift(m.read_start),
+                read_end: shift(m.read_end),
+            })
+            .collect();
+
+        let new_count = new_matches.len();
+        self.matches.truncate(first_affected);
+        self.matches.extend(new_matches);
+        self.matches.extend(reused_matches);
+
+        first_affected..(first_affected + new_count)
+    }
+}
+
+// the position to continue scanning after a match,
+// it moves forward one position after an empty match.
+fn next_scan_position(range: &MatchRange) -> usize {
+    if range.start == range.end {
+        range.end + 1
+    } else {
+        range.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::process::{MatchRange, Process};
+
+    use super::IncrementalMatcher;
+
+    fn get_matches(matcher: &IncrementalMatcher) -> Vec<(usize, usize)> {
+        matcher.matches().map(|m| (m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn test_incremental_matcher() {
+        let process = Process::new(r#""ab""#).unwrap();
+        let mut matcher = IncrementalMatcher::new(&process, "ab xab ab");
+        assert_eq!(get_matches(&matcher), vec![(0, 2), (4, 6), (7, 9)]);
+
+        // break the second match
+        let changed = matcher.replace_text(4, 5, "x");
+        assert_eq!(get_matches(&matcher), vec![(0, 2), (7, 9)]);
+        assert_eq!(changed, 1..1);
+
+        // insert a new match, the last match is shifted
+        let changed = matcher.replace_text(2, 3, " ab ");
+        assert_eq!(get_matches(&matcher), vec![(0, 2), (3, 5), (10, 12)]);
+        assert_eq!(changed, 1..2);
+
+        // edit after all matches
+        let changed = matcher.replace_text(12, 12, "ab");
+        assert_eq!(
+            get_matches(&matcher),
+            vec![(0, 2), (3, 5), (10, 12), (12, 14)]
+        );
+        assert_eq!(changed, 3..4);
+    }
+
+    #[test]
+    fn test_incremental_matcher_look_around() {
+        // the match depends on the char after it
+        let process = Process::new(r#"'a'.is_before('b')"#).unwrap();
+        let mut matcher = IncrementalMatcher::new(&process, "ab ac ab");
+        assert_eq!(get_matches(&matcher), vec![(0, 1), (6, 7)]);
+
+        matcher.replace_text(4, 5, "b");
+        assert_eq!(get_matches(&matcher), vec![(0, 1), (3, 4), (6, 7)]);
+
+        matcher.replace_text(1, 2, "");
+        assert_eq!(get_matches(&matcher), vec![(2, 3), (5, 6)]);
+    }
+
+    #[test]
+    fn test_incremental_matcher_text_boundaries() {
+        // the match depends on the end of text
+        let process = Process::new(r#"'a', end"#).unwrap();
+        let mut matcher = IncrementalMatcher::new(&process, "a b a");
+        assert_eq!(get_matches(&matcher), vec![(4, 5)]);
+
+        matcher.replace_text(5, 5, "x");
+        assert_eq!(get_matches(&matcher), vec![]);
+
+        matcher.replace_text(5, 6, "");
+        assert_eq!(get_matches(&matcher), vec![(4, 5)]);
+
+        // the match depends on the start of text
+        let process = Process::new(r#"start"#).unwrap();
+        let mut matcher = IncrementalMatcher::new(&process, "ab");
+        assert_eq!(get_matches(&matcher), vec![(0, 0)]);
+
+        matcher.replace_text(0, 0, "x");
+        assert_eq!(get_matches(&matcher), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_incremental_matcher_same_as_full_scan() {
+        let process = Process::new(r#"char_word+"#).unwrap();
+        let edits = [
+            (0, 0, "x"),
+            (3, 5, " "),
+            (2, 2, "yy zz"),
+            (8, 12, ""),
+            (1, 4, "-"),
+        ];
+
+        let mut text = "foo bar baz".to_owned();
+        let mut matcher = IncrementalMatcher::new(&process, &text);
+
+        for (start, end, replacement) in edits {
+            matcher.replace_text(start, end, replacement);
+
+            let mut chars: Vec<char> = text.chars().collect();
+            chars.splice(start..end, replacement.chars());
+            text = chars.into_iter().collect();
+
+            let expected = IncrementalMatcher::new(&process, &text);
+            assert_eq!(get_matches(&matcher), get_matches(&expected));
+        }
+    }
+
+    #[test]
+    fn test_incremental_matcher_bytes() {
+        let process = Process::new(r#""文""#).unwrap();
+        let mut matcher = IncrementalMatcher::from_bytes(&process, "a文b".as_bytes());
+        assert_eq!(
+            matcher.matches().collect::<Vec<_>>(),
+            vec![&MatchRange::new(1, 4)]
+        );
+
+        matcher.replace_bytes(0, 1, "文".as_bytes());
+        assert_eq!(
+            matcher.matches().collect::<Vec<_>>(),
+            vec![&MatchRange::new(0, 3), &MatchRange::new(3, 6)]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// a vector that stores up to `N` items inline and spills to the heap
+// when it grows beyond that.
+//
+// the frame states of the process are created and cloned very often,
+// and most patterns have only a few repetition counters and capture
+// groups, so keeping them inline avoids the allocations of the small
+// vectors.
+//
+// the heap buffer is kept when the vector is cleared, so a spilled
+// vector that is reused (e.g. the spare frame states) does not
+// allocate again.
+
+use std::ops::{Deref, DerefMut};
+
+pub struct InlineVec<T: Copy + Default, const N: usize> {
+    inline: [T; N],
+    len: usize, // the number of the inline items
+    heap: Vec<T>,
+    spilled: bool, // the items are stored in `heap` rather than `inline`
+}
+
+impl<T: Copy + Default, const N: usize> InlineVec<T, N> {
+    pub fn new() -> Self {
+        InlineVec {
+            inline: [T::default(); N],
+            len: 0,
+            heap: vec![],
+            spilled: false,
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.spilled {
+            self.heap.push(value);
+        } else if self.len < N {
+            self.inline[self.len] = value;
+            self.len += 1;
+        } else {
+            self.spill();
+            self.heap.push(value);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.spilled {
+            self.heap.pop()
+        } else if self.len > 0 {
+            self.len -= 1;
+            Some(self.inline[self.len])
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.heap.clear();
+        self.spilled = false;
+    }
+
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        if !self.spilled {
+            if new_len <= N {
+                if new_len > self.len {
+                    self.inline[self.len..new_len].fill(value);
+                }
+                self.len = new_len;
+                return;
+            }
+            self.spill();
+        }
+        self.heap.resize(new_len, value);
+    }
+
+    // the capacity of the heap buffer, it is 0 if the vector
+    // has never spilled.
+    pub fn heap_capacity(&self) -> usize {
+        self.heap.capacity()
+    }
+
+    // moves the inline items to the heap buffer.
+    fn spill(&mut self) {
+        self.heap.clear();
+        self.heap.extend_from_slice(&self.inline[..self.len]);
+        self.len = 0;
+        self.spilled = true;
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for InlineVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Deref for InlineVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        if self.spilled {
+            &self.heap
+        } else {
+            &self.inline[..self.len]
+        }
+    }
+}
+
+impl<T: Copy + Default, const N: usize> DerefMut for InlineVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        if self.spilled {
+            &mut self.heap
+        } else {
+            &mut self.inline[..self.len]
+        }
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Clone for InlineVec<T, N> {
+    fn clone(&self) -> Self {
+        let mut vec = Self::new();
+        vec.clone_from(self);
+        vec
+    }
+
+    // reuses the heap buffer of `self`
+    fn clone_from(&mut self, source: &Self) {
+        self.clear();
+        if source.len() <= N {
+            self.inline[..source.len()].copy_from_slice(source);
+            self.len = source.len();
+        } else {
+            self.heap.extend_from_slice(source);
+            self.spilled = true;
+        }
+    }
+}
+
+impl<T: Copy + Default + std::fmt::Debug, const N: usize> std::fmt::Debug for InlineVec<T, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Copy + Default + PartialEq, const N: usize> PartialEq for InlineVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::InlineVec;
+
+    #[test]
+    fn test_inline_vec() {
+        let mut vec: InlineVec<u32, 2> = InlineVec::new();
+        vec.push(1);
+        vec.push(2);
+        assert_eq!(&*vec, &[1, 2]);
+        assert_eq!(vec.heap_capacity(), 0);
+
+        // spill
+        vec.push(3);
+        assert_eq!(&*vec, &[1, 2, 3]);
+        assert!(vec.heap_capacity() >= 3);
+
+        vec[0] = 10;
+        *vec.last_mut().unwrap() = 30;
+        assert_eq!(vec.pop(), Some(30));
+        assert_eq!(&*vec, &[10, 2]);
+
+        // the heap buffer is kept
+        let capacity = vec.heap_capacity();
+        vec.clear();
+        assert!(vec.is_empty());
+        assert_eq!(vec.heap_capacity(), capacity);
+        assert_eq!(vec.pop(), None);
+
+        vec.resize(2, 7);
+        assert_eq!(&*vec, &[7, 7]);
+        vec.resize(4, 8);
+        assert_eq!(&*vec, &[7, 7, 8, 8]);
+        vec.resize(1, 0);
+        assert_eq!(&*vec, &[7]);
+
+        // clone
+        let mut other: InlineVec<u32, 2> = InlineVec::new();
+        other.push(5);
+        other.clone_from(&vec);
+        assert_eq!(other, vec);
+        vec.resize(3, 9);
+        other.clone_from(&vec);
+        assert_eq!(&*other, &[7, 9, 9]);
+        assert_eq!(format!("{:?}", other.clone()), "[7, 9, 9]");
+    }
+}
@@ -9,9 +9,14 @@ use crate::{
     error::Error,
     location::Location,
     peekableiter::PeekableIter,
-    token::{Comment, Token, TokenWithRange},
+    symbol::Interner,
+    token::{
+        Comment, Token, TokenWithRange, PRESET_CHARSET_NAMES, SPECIAL_CHAR_NAMES, STATUS_NAMES,
+    },
 };
 
+// the first stage of parsing, it splits the pattern into tokens, the
+// comments and the new lines are kept as tokens.
 pub fn lex_from_str(s: &str) -> Result<Vec<TokenWithRange>, Error> {
     let mut chars = s.chars();
     let mut char_position_iter = CharsWithPositionIter::new(0, &mut chars);
@@ -24,6 +29,14 @@ struct Lexer<'a> {
     upstream: &'a mut PeekableIter<'a, CharWithPosition>,
     last_position: Location,
     saved_positions: Vec<Location>,
+
+    // the names of the identifiers and keywords are interned, so the
+    // repeated names (e.g. in a big file with many definitions) are
+    // allocated once.
+    interner: Interner,
+
+    // the buffer for reading a name, it is reused by all names.
+    name_buffer: String,
 }
 
 impl<'a> Lexer<'a> {
@@ -32,6 +45,8 @@ impl<'a> Lexer<'a> {
             upstream,
             last_position: Location::new_position(0, 0, 0, 0),
             saved_positions: vec![],
+            interner: Interner::new(),
+            name_buffer: String::new(),
         }
     }
 
@@ -279,6 +294,10 @@ impl<'a> Lexer<'a> {
                         1,
                     ))
                 }
+                '0' if self.peek_char_and_equals(1, 'x') => {
+                    // hexadecimal number
+                    token_ranges.push(self.lex_hex_number()?);
+                }
                 '0'..='9' => {
                     // number
                     token_ranges.push(self.lex_number()?);
@@ -291,6 +310,10 @@ impl<'a> Lexer<'a> {
                     // char
                     token_ranges.push(self.lex_char()?);
                 }
+                '`' => {
+                    // quoted identifier
+                    token_ranges.push(self.lex_quoted_identifier()?);
+                }
                 '/' if self.peek_char_and_equals(1, '/') => {
                     // line comment
                     token_ranges.push(self.lex_line_comment()?);
@@ -323,7 +346,8 @@ impl<'a> Lexer<'a> {
         // current char = the character of `iter.upstream.peek(0)``
         // T = terminator chars || EOF
 
-        let mut name_string = String::new();
+        let mut name_string = std::mem::take(&mut self.name_buffer);
+        name_string.clear();
 
         self.push_peek_position();
 
@@ -388,16 +412,99 @@ impl<'a> Lexer<'a> {
             &self.last_position,
         );
 
-        let token = match name_string.as_str() {
-            "start" | "end" | "bound" | "not_bound" => Token::Status(name_string),
-            "char_space" | "char_not_space" | "char_word" | "char_not_word" | "char_digit"
-            | "char_not_digit" => Token::PresetCharSet(name_string),
-            _ => Token::Identifier(name_string),
+        let symbol = self.interner.intern(&name_string);
+        let name = name_string.as_str();
+        let token = if STATUS_NAMES.contains(&name) {
+            Token::Status(symbol)
+        } else if PRESET_CHARSET_NAMES.contains(&name) {
+            Token::PresetCharSet(symbol)
+        } else if SPECIAL_CHAR_NAMES.contains(&name) {
+            Token::Special(symbol)
+        } else {
+            Token::Identifier(symbol)
         };
+        self.name_buffer = name_string;
 
         Ok(TokenWithRange::new(token, name_range))
     }
 
+    fn lex_quoted_identifier(&mut self) -> Result<TokenWithRange, Error> {
+        // `tag name`?  //
+        // ^         ^__// to here
+        // |____________// current char, validated
+        //
+        // the name can contain any chars, the backtick, the backslash,
+        // the new line, the carriage return and the tab are escaped
+        // by backslash, e.g. `a\`b` is the name "a`b".
+
+        let mut name_string = String::new();
+
+        self.push_peek_position();
+
+        self.next_char(); // consume '`'
+
+        loop {
+            match self.next_char() {
+                Some('`') => {
+                    break;
+                }
+                Some('\\') => match self.next_char() {
+                    Some(previous_char @ ('`' | '\\')) => {
+                        name_string.push(previous_char);
+                    }
+                    Some('n') => name_string.push('\n'),
+                    Some('r') => name_string.push('\r'),
+                    Some('t') => name_string.push('\t'),
+                    Some(previous_char) => {
+                        return Err(Error::MessageWithLocation(
+                            format!("Unexpected escape char '{}'.", previous_char),
+                            self.last_position,
+                        ));
+                    }
+                    None => {
+                        // `\` + EOF
+                        return Err(Error::UnexpectedEndOfDocument(
+                            "Incomplete escape character sequence.".to_owned(),
+                        ));
+                    }
+                },
+                Some('\n' | '\r') => {
+                    return Err(Error::MessageWithLocation(
+                        "The new line in the quoted identifier should be escaped.".to_owned(),
+                        self.last_position,
+                    ));
+                }
+                Some(previous_char) => {
+                    name_string.push(previous_char);
+                }
+                None => {
+                    // `abcEOF
+                    return Err(Error::UnexpectedEndOfDocument(
+                        "Incomplete quoted identifier.".to_owned(),
+                    ));
+                }
+            }
+        }
+
+        let name_range = Location::from_position_pair_with_end_included(
+            &self.pop_saved_position(),
+            &self.last_position,
+        );
+
+        if name_string.is_empty() {
+            return Err(Error::MessageWithLocation(
+                "Empty quoted identifier.".to_owned(),
+                name_range,
+            ));
+        }
+
+        let symbol = self.interner.intern(&name_string);
+        Ok(TokenWithRange::new(
+            Token::QuotedIdentifier(symbol),
+            name_range,
+        ))
+    }
+
     fn lex_number(&mut self) -> Result<TokenWithRange, Error> {
         // 123456T  //
         // ^     ^__// to here
@@ -405,7 +512,9 @@ impl<'a> Lexer<'a> {
         //
         // T = terminator chars || EOF
 
-        let mut num_string = String::new();
+        // the digits are collected in the reused buffer
+        let mut num_string = std::mem::take(&mut self.name_buffer);
+        num_string.clear();
 
         self.push_peek_position();
 
@@ -445,12 +554,77 @@ impl<'a> Lexer<'a> {
                 num_range,
             )
         })?;
+        self.name_buffer = num_string;
 
         let num_token = Token::Number(num);
 
         Ok(TokenWithRange::new(num_token, num_range))
     }
 
+    fn lex_hex_number(&mut self) -> Result<TokenWithRange, Error> {
+        // 0xaabbT  //
+        // ^     ^__// to here
+        // |________// current char, validated
+        //
+        // T = terminator chars || EOF
+
+        // the digits are collected in the reused buffer
+        let mut num_string = std::mem::take(&mut self.name_buffer);
+        num_string.clear();
+
+        self.push_peek_position();
+
+        self.next_char(); // consume '0'
+        self.next_char(); // consume 'x'
+
+        while let Some(current_char) = self.peek_char(0) {
+            match current_char {
+                '0'..='9' | 'a'..='f' | 'A'..='F' => {
+                    // valid digits for hex number
+                    num_string.push(*current_char);
+
+                    self.next_char(); // consume digit
+                }
+                '_' => {
+                    self.next_char(); // consume '_'
+                }
+                ' ' | '\t' | '\r' | '\n' | ',' | '|' | '!' | '[' | ']' | '(' | ')' | '/' | '\''
+                | '"' | '.' | '?' | '+' | '*' | '{' | '}' => {
+                    // terminator chars
+                    break;
+                }
+                _ => {
+                    return Err(Error::MessageWithLocation(
+                        format!("Invalid char '{}' for hexadecimal number.", current_char),
+                        *self.peek_position(0).unwrap(),
+                    ));
+                }
+            }
+        }
+
+        let num_range = Location::from_position_pair_with_end_included(
+            &self.pop_saved_position(),
+            &self.last_position,
+        );
+
+        if num_string.is_empty() {
+            return Err(Error::MessageWithLocation(
+                "Incomplete hexadecimal number.".to_owned(),
+                num_range,
+            ));
+        }
+
+        let num = u32::from_str_radix(&num_string, 16).map_err(|_| {
+            Error::MessageWithLocation(
+                format!("Can not convert \"0x{}\" to integer number.", num_string),
+                num_range,
+            )
+        })?;
+        self.name_buffer = num_string;
+
+        Ok(TokenWithRange::new(Token::Number(num), num_range))
+    }
+
     fn lex_char(&mut self) -> Result<TokenWithRange, Error> {
         // 'a'?  //
         // ^  ^__// to here
@@ -491,6 +665,22 @@ impl<'a> Lexer<'a> {
                                         // null char
                                         '\0'
                                     }
+                                    'e' => {
+                                        // escape (ESC, ascii 27)
+                                        '\u{1b}'
+                                    }
+                                    'v' => {
+                                        // vertical tabulation (VT, ascii 11)
+                                        '\u{0b}'
+                                    }
+                                    'f' => {
+                                        // form feed (FF, ascii 12)
+                                        '\u{0c}'
+                                    }
+                                    'x' => {
+                                        // hex byte, e.g. '\x7f'
+                                        self.unescape_hex()?
+                                    }
                                     'u' => {
                                         if self.peek_char_and_equals(0, '{') {
                                             // unicode code point, e.g. '\u{2d}', '\u{6587}'
@@ -637,14 +827,58 @@ impl<'a> Lexer<'a> {
             // ref:
             // https://doc.rust-lang.org/std/primitive.char.html
             Ok(c)
+        } else if (0xd800..=0xdfff).contains(&codepoint) {
+            Err(Error::MessageWithLocation(
+                "Invalid unicode code point, the surrogates (from U+D800 to U+DFFF) are not chars."
+                    .to_owned(),
+                codepoint_range,
+            ))
         } else {
             Err(Error::MessageWithLocation(
-                "Invalid unicode code point.".to_owned(),
+                "Invalid unicode code point, it is greater than U+10FFFF.".to_owned(),
                 codepoint_range,
             ))
         }
     }
 
+    fn unescape_hex(&mut self) -> Result<char, Error> {
+        // \x7f?  //
+        //   ^ ^__// to here
+        //   |____// current char
+
+        // the hex escape sequence consists of exactly two hex digits,
+        // and the value is the code point of the char, i.e. from
+        // '\x00' to '\xff' (U+0000 to U+00FF).
+
+        let mut codepoint_string = String::new();
+
+        for _ in 0..2 {
+            match self.next_char() {
+                Some(previous_char) => match previous_char {
+                    '0'..='9' | 'a'..='f' | 'A'..='F' => codepoint_string.push(previous_char),
+                    _ => {
+                        return Err(Error::MessageWithLocation(
+                            format!(
+                                "Invalid character '{}' for hex escape sequence.",
+                                previous_char
+                            ),
+                            self.last_position,
+                        ));
+                    }
+                },
+                None => {
+                    // EOF
+                    return Err(Error::UnexpectedEndOfDocument(
+                        "Incomplete hex escape sequence.".to_owned(),
+                    ));
+                }
+            }
+        }
+
+        let codepoint = u32::from_str_radix(&codepoint_string, 16).unwrap();
+        Ok(char::from_u32(codepoint).unwrap())
+    }
+
     fn lex_string(&mut self) -> Result<TokenWithRange, Error> {
         // "abc"?  //
         // ^    ^__// to here
@@ -692,6 +926,23 @@ impl<'a> Lexer<'a> {
                                             // null char
                                             final_string.push('\0');
                                         }
+                                        'e' => {
+                                            // escape (ESC, ascii 27)
+                                            final_string.push('\u{1b}');
+                                        }
+                                        'v' => {
+                                            // vertical tabulation (VT, ascii 11)
+                                            final_string.push('\u{0b}');
+                                        }
+                                        'f' => {
+                                            // form feed (FF, ascii 12)
+                                            final_string.push('\u{0c}');
+                                        }
+                                        'x' => {
+                                            // hex byte, e.g. "\x7f"
+                                            let ch = self.unescape_hex()?;
+                                            final_string.push(ch);
+                                        }
                                         'u' => {
                                             if self.peek_char_and_equals(0, '{') {
                                                 // unicode code point, e.g. '\u{2d}', '\u{6587}'
@@ -877,21 +1128,30 @@ mod tests {
         error::Error,
         lexer::{lex_from_str, Comment, TokenWithRange},
         location::Location,
+        symbol::Symbol,
     };
 
     use super::Token;
 
     impl Token {
         pub fn new_identifier(s: &str) -> Self {
-            Token::Identifier(s.to_owned())
+            Token::Identifier(Symbol::new(s))
+        }
+
+        pub fn new_quoted_identifier(s: &str) -> Self {
+            Token::QuotedIdentifier(Symbol::new(s))
         }
 
         pub fn new_symbol(s: &str) -> Self {
-            Token::Status(s.to_owned())
+            Token::Status(Symbol::new(s))
         }
 
         pub fn new_preset_charset(s: &str) -> Self {
-            Token::PresetCharSet(s.to_owned())
+            Token::PresetCharSet(Symbol::new(s))
+        }
+
+        pub fn new_special(s: &str) -> Self {
+            Token::Special(Symbol::new(s))
         }
 
         pub fn new_string(s: &str) -> Self {
@@ -1122,15 +1382,85 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_lex_quoted_identifier() {
+        assert_eq!(
+            lex_from_str_without_location(r#"`tag name` `start`, `a\`b\\c\n`(`文字`)"#).unwrap(),
+            vec![
+                Token::new_quoted_identifier("tag name"),
+                Token::new_quoted_identifier("start"),
+                Token::Comma,
+                Token::new_quoted_identifier("a`b\\c\n"),
+                Token::LeftParen,
+                Token::new_quoted_identifier("文字"),
+                Token::RightParen,
+            ]
+        );
+
+        // location
+        assert_eq!(
+            lex_from_str("a `b c`").unwrap(),
+            vec![
+                TokenWithRange::from_position_and_length(
+                    Token::new_identifier("a"),
+                    &Location::new_position(0, 0, 0, 0),
+                    1
+                ),
+                TokenWithRange::from_position_and_length(
+                    Token::new_quoted_identifier("b c"),
+                    &Location::new_position(0, 2, 0, 2),
+                    5
+                )
+            ]
+        );
+
+        // err: empty
+        assert!(matches!(
+            lex_from_str_without_location("``"),
+            Err(Error::MessageWithLocation(
+                _,
+                Location {
+                    index: 0,
+                    length: 2,
+                    ..
+                }
+            ))
+        ));
+
+        // err: unsupported escape char
+        assert!(matches!(
+            lex_from_str_without_location(r#"`a\x`"#),
+            Err(Error::MessageWithLocation(_, Location { index: 3, .. }))
+        ));
+
+        // err: the new line is not escaped
+        assert!(matches!(
+            lex_from_str_without_location("`a\nb`"),
+            Err(Error::MessageWithLocation(_, Location { index: 2, .. }))
+        ));
+
+        // err: incomplete
+        assert!(matches!(
+            lex_from_str_without_location("`abc"),
+            Err(Error::UnexpectedEndOfDocument(_))
+        ));
+    }
+
     #[test]
     fn test_lex_symbol() {
         assert_eq!(
-            lex_from_str_without_location("start end bound not_bound").unwrap(),
+            lex_from_str_without_location(
+                "start end bound not_bound word_start word_end continue_from_last"
+            )
+            .unwrap(),
             vec![
                 Token::new_symbol("start"),
                 Token::new_symbol("end"),
                 Token::new_symbol("bound"),
                 Token::new_symbol("not_bound"),
+                Token::new_symbol("word_start"),
+                Token::new_symbol("word_end"),
+                Token::new_symbol("continue_from_last"),
             ]
         );
 
@@ -1189,6 +1519,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lex_special() {
+        assert_eq!(
+            lex_from_str_without_location("char_any char_any_including_newline").unwrap(),
+            vec![
+                Token::new_special("char_any"),
+                Token::new_special("char_any_including_newline"),
+            ]
+        );
+
+        // location
+
+        assert_eq!(
+            lex_from_str("char_any").unwrap(),
+            vec![TokenWithRange::from_position_and_length(
+                Token::new_special("char_any"),
+                &Location::new_position(0, 0, 0, 0),
+                8
+            )]
+        );
+    }
+
     #[test]
     fn test_lex_number() {
         assert_eq!(
@@ -1260,6 +1612,80 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_lex_hex_number() {
+        assert_eq!(
+            lex_from_str_without_location("0x7f").unwrap(),
+            vec![Token::Number(0x7f)]
+        );
+
+        assert_eq!(
+            lex_from_str_without_location("0xAB_cd 0x0").unwrap(),
+            vec![Token::Number(0xabcd), Token::Number(0)]
+        );
+
+        assert_eq!(
+            lex_from_str_without_location("0x80..0xff").unwrap(),
+            vec![Token::Number(0x80), Token::Interval, Token::Number(0xff)]
+        );
+
+        // location
+
+        assert_eq!(
+            lex_from_str("0x7f").unwrap(),
+            vec![TokenWithRange::from_position_and_length(
+                Token::Number(0x7f),
+                &Location::new_position(0, 0, 0, 0,),
+                4
+            )]
+        );
+
+        // err: invalid char for hex number
+        assert!(matches!(
+            lex_from_str_without_location("0x1g"),
+            Err(Error::MessageWithLocation(
+                _,
+                Location {
+                    unit: 0,
+                    index: 3,
+                    line: 0,
+                    column: 3,
+                    length: 0
+                }
+            ))
+        ));
+
+        // err: incomplete hex number
+        assert!(matches!(
+            lex_from_str_without_location("0x"),
+            Err(Error::MessageWithLocation(
+                _,
+                Location {
+                    unit: 0,
+                    index: 0,
+                    line: 0,
+                    column: 0,
+                    length: 2
+                }
+            ))
+        ));
+
+        // err: integer number overflow
+        assert!(matches!(
+            lex_from_str_without_location("0x1_0000_0000"),
+            Err(Error::MessageWithLocation(
+                _,
+                Location {
+                    unit: 0,
+                    index: 0,
+                    line: 0,
+                    column: 0,
+                    length: 13
+                }
+            ))
+        ));
+    }
+
     #[test]
     fn test_lex_char() {
         assert_eq!(
@@ -1331,6 +1757,38 @@ mod tests {
             vec![Token::Char('\0')]
         );
 
+        // escape char, control chars
+        assert_eq!(
+            lex_from_str_without_location("'\\e' '\\v' '\\f'").unwrap(),
+            vec![
+                Token::Char('\u{1b}'),
+                Token::Char('\u{0b}'),
+                Token::Char('\u{0c}')
+            ]
+        );
+
+        // escape char, hex
+        assert_eq!(
+            lex_from_str_without_location("'\\x33' '\\x7f' '\\xFF'").unwrap(),
+            vec![
+                Token::Char('3'),
+                Token::Char('\u{7f}'),
+                Token::Char('\u{ff}')
+            ]
+        );
+
+        // err: invalid hex escape
+        assert!(matches!(
+            lex_from_str_without_location("'\\x7g'"),
+            Err(Error::MessageWithLocation(_, _))
+        ));
+
+        // err: incomplete hex escape
+        assert!(matches!(
+            lex_from_str_without_location("'\\x7"),
+            Err(Error::UnexpectedEndOfDocument(_))
+        ));
+
         // escape char, unicode
         assert_eq!(
             lex_from_str_without_location("'\\u{2d}'").unwrap(),
@@ -1343,6 +1801,18 @@ mod tests {
             vec![Token::Char('文')]
         );
 
+        // err: surrogate
+        assert!(matches!(
+            lex_from_str_without_location("'\\u{d800}'"),
+            Err(Error::MessageWithLocation(message, _)) if message.contains("surrogates")
+        ));
+
+        // err: out of range
+        assert!(matches!(
+            lex_from_str_without_location("'\\u{110000}'"),
+            Err(Error::MessageWithLocation(message, _)) if message.contains("U+10FFFF")
+        ));
+
         // location
 
         assert_eq!(
@@ -1436,9 +1906,9 @@ mod tests {
             ))
         ));
 
-        // err: unsupported escape char \v
+        // err: unsupported escape char \q
         assert!(matches!(
-            lex_from_str_without_location("'\\v'"),
+            lex_from_str_without_location("'\\q'"),
             Err(Error::MessageWithLocation(
                 _,
                 Location {
@@ -1451,16 +1921,16 @@ mod tests {
             ))
         ));
 
-        // err: unsupported hex escape "\x.."
+        // err: invalid hex escape "\x.."
         assert!(matches!(
-            lex_from_str_without_location("'\\x33'"),
+            lex_from_str_without_location("'\\x3z'"),
             Err(Error::MessageWithLocation(
                 _,
                 Location {
                     unit: 0,
-                    index: 2,
+                    index: 4,
                     line: 0,
-                    column: 2,
+                    column: 4,
                     length: 0
                 }
             ))
@@ -1625,13 +2095,13 @@ mod tests {
         assert_eq!(
             lex_from_str_without_location(
                 r#"
-                "\\\'\"\t\r\n\0\u{2d}\u{6587}"
+                "\\\'\"\t\r\n\0\u{2d}\u{6587}\e\v\f\x41\x7f"
                 "#
             )
             .unwrap(),
             vec![
                 Token::NewLine,
-                Token::new_string("\\\'\"\t\r\n\0-文"),
+                Token::new_string("\\\'\"\t\r\n\0-文\u{1b}\u{0b}\u{0c}A\u{7f}"),
                 Token::NewLine,
             ]
         );
@@ -1674,9 +2144,9 @@ mod tests {
             Err(Error::UnexpectedEndOfDocument(_))
         ));
 
-        // err: unsupported escape char \v
+        // err: unsupported escape char \q
         assert!(matches!(
-            lex_from_str_without_location(r#""abc\vxyz""#),
+            lex_from_str_without_location(r#""abc\qxyz""#),
             Err(Error::MessageWithLocation(
                 _,
                 Location {
@@ -1689,16 +2159,16 @@ mod tests {
             ))
         ));
 
-        // err: unsupported hex escape "\x.."
+        // err: invalid hex escape "\x.."
         assert!(matches!(
-            lex_from_str_without_location(r#""abc\x33xyz""#),
+            lex_from_str_without_location(r#""abc\x3zxyz""#),
             Err(Error::MessageWithLocation(
                 _,
                 Location {
                     unit: 0,
-                    index: 5,
+                    index: 7,
                     line: 0,
-                    column: 5,
+                    column: 7,
                     length: 0
                 }
             ))
@@ -1842,7 +2312,7 @@ mod tests {
             lex_from_str("foo // bar").unwrap(),
             vec![
                 TokenWithRange::from_position_and_length(
-                    Token::Identifier("foo".to_owned()),
+                    Token::new_identifier("foo"),
                     &Location::new_position(0, 0, 0, 0),
                     3
                 ),
@@ -1858,7 +2328,7 @@ mod tests {
             lex_from_str("abc // def\n// xyz\n").unwrap(),
             vec![
                 TokenWithRange::from_position_and_length(
-                    Token::Identifier("abc".to_owned()),
+                    Token::new_identifier("abc"),
                     &Location::new_position(0, 0, 0, 0),
                     3
                 ),
@@ -1944,7 +2414,7 @@ mod tests {
             lex_from_str("foo /* hello */ bar").unwrap(),
             vec![
                 TokenWithRange::from_position_and_length(
-                    Token::Identifier("foo".to_owned()),
+                    Token::new_identifier("foo"),
                     &Location::new_position(0, 0, 0, 0),
                     3
                 ),
@@ -1954,7 +2424,7 @@ mod tests {
                     11
                 ),
                 TokenWithRange::from_position_and_length(
-                    Token::Identifier("bar".to_owned()),
+                    Token::new_identifier("bar"),
                     &Location::new_position(0, 16, 0, 16),
                     3
                 ),
@@ -4,21 +4,113 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
+mod analyzer;
+#[cfg(any(test, feature = "testing"))]
+mod assertions;
 mod ast;
+mod batch;
+#[cfg(feature = "bench")]
+mod bench;
+#[cfg(feature = "cache")]
+mod cache;
+mod captures;
+mod casefolding;
 mod charposition;
+mod commentattacher;
 mod commentcleaner;
+mod commentprinter;
 mod compiler;
 mod context;
+mod debugjson;
+mod diff;
+mod equivalence;
 mod error;
 mod errorprinter;
+mod generator;
+mod glob;
+mod grapheme;
+mod incremental;
+mod inlinevec;
 mod lexer;
+mod lint;
 mod location;
 mod macroexpander;
+mod matchlength;
+mod normalization;
 mod normalizer;
+mod options;
 mod parser;
+mod patterninfo;
 mod peekableiter;
+mod process;
+mod references;
+mod serialization;
+mod simplifier;
 mod state;
+mod symbol;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
 mod token;
+mod tokenizer;
 mod transition;
+mod utf8reader;
+mod variables;
+mod window;
 
-pub use compiler::compile_from_str;
\ No newline at end of file
+pub use analyzer::{analyze_from_str, Warning};
+pub use ast::{
+    quote, DefinitionLocation, NodeLocation, PatternOptions, Program, SelfTest, SourceComment,
+    SourceMap, SyntaxVersion,
+};
+pub use batch::MatchAll;
+pub use captures::{capture_spans, Captures, FromCaptures, Match};
+pub use commentcleaner::clean as clean_comments;
+pub use commentprinter::ProgramWithComments;
+pub use compiler::{compile, compile_from_str, compile_from_str_with};
+pub use diff::{diff_programs, Difference, DifferenceKind};
+pub use error::Error;
+pub use generator::Generator;
+pub use glob::from_glob;
+pub use incremental::IncrementalMatcher;
+pub use lexer::lex_from_str;
+pub use lint::{lint_from_str, LintWarning};
+pub use location::Location;
+pub use macroexpander::expand as expand_macros;
+pub use normalizer::normalize as normalize_tokens;
+pub use options::{CompileOptions, Limits, MatchLimits, ParseOptions};
+pub use parser::{
+    parse_from_str, parse_from_str_with_limits, parse_from_str_with_locations,
+    parse_from_str_with_options, parse_from_str_with_vars, parse_from_tokens,
+    parse_stages_from_str, ParseStages,
+};
+pub use patterninfo::{pattern_info, pattern_info_with, PatternInfo};
+pub use process::{
+    ExecCheckpoint, ExecProgress, FailedTransition, FailureInfo, Instance, MatchRange, MatchStats,
+    PartialMatch, Process,
+};
+pub use references::{BackReference, BackReferenceStatus};
+pub use serialization::generate_rust_source;
+pub use state::{CaptureGroup, LookAroundRoute};
+pub use symbol::Symbol;
+pub use token::{Comment, Token, TokenWithRange};
+pub use tokenizer::{TokenMatch, Tokenizer, Tokens};
+pub use variables::substitute_variables;
+pub use window::{windows, WindowMatches, Windows};
+
+#[cfg(feature = "bench")]
+pub use bench::{scenarios, Corpus, Scenario, CORPUS_SIZE};
+
+#[cfg(feature = "cache")]
+pub use cache::{
+    cache_len, cached_compile, clear_cache, set_cache_capacity, DEFAULT_CACHE_CAPACITY,
+};
+
+#[cfg(feature = "testing")]
+pub use assertions::{assert_captures, assert_matches, check_captures, check_matches};
+#[cfg(feature = "testing")]
+pub use ast::{
+    ByteRange, CharRange, CharSet, CharSetElement, Expression, FunctionCall, FunctionCallArg,
+    FunctionName, Literal, NumberRange,
+};
+#[cfg(feature = "testing")]
+pub use testing::{arbitrary_program, assert_round_trip, check_round_trip, Unstructured};
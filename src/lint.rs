@@ -0,0 +1,499 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::fmt::Display;
+
+use crate::{
+    analyzer::{get_first_chars, FirstChars},
+    ast::{Expression, Literal},
+    commentcleaner::clean,
+    error::Error,
+    lexer::lex_from_str,
+    location::Location,
+    normalizer::normalize,
+    parser::parse_from_str,
+    token::{Token, TokenWithRange},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct LintWarning {
+    pub message: String,
+    pub location: Location,
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message,
+            self.location.line + 1,
+            self.location.column + 1
+        )
+    }
+}
+
+// check the pattern and reports the suspicious constructs which are valid
+// but probably mistakes, the warnings are sorted by location.
+//
+// the checks work on the tokens before the macros are expanded, so that
+// the locations refer to the source text and the constructs in a
+// definition are reported only once.
+pub fn lint_from_str(s: &str) -> Result<Vec<LintWarning>, Error> {
+    // make sure the pattern is valid
+    parse_from_str(s)?;
+
+    let tokens = normalize(clean(lex_from_str(s)?));
+
+    let mut warnings = vec![];
+    check_unused_definitions(&tokens, &mut warnings);
+    check_duplicate_charset_members(&tokens, &mut warnings);
+    check_subsumed_alternatives(&tokens, &mut warnings);
+    check_unreferenced_captures(&tokens, &mut warnings);
+
+    warnings.sort_by_key(|w| w.location.index);
+    Ok(warnings)
+}
+
+fn is_identifier(token_with_range: &TokenWithRange, name: &str) -> bool {
+    matches!(&token_with_range.token, Token::Identifier(id) if id == name)
+}
+
+// whether the token is the name, either quoted or not.
+fn is_name(token_with_range: &TokenWithRange, name: &str) -> bool {
+    token_with_range
+        .token
+        .get_identifier()
+        .is_some_and(|id| id == name)
+}
+
+// returns the indices of the name tokens of definitions, i.e.
+// `define(name, ...)` and `define_charset(name, ...)`.
+fn find_definition_names(tokens: &[TokenWithRange]) -> Vec<usize> {
+    let mut indices = vec![];
+    for idx in 0..tokens.len() {
+        if (is_identifier(&tokens[idx], "define") || is_identifier(&tokens[idx], "define_charset"))
+            && matches!(tokens.get(idx + 1), Some(t) if t.token == Token::LeftParen)
+        {
+            let mut name_idx = idx + 2;
+            if matches!(tokens.get(name_idx), Some(t) if t.token == Token::NewLine) {
+                name_idx += 1;
+            }
+            if tokens
+                .get(name_idx)
+                .is_some_and(|t| t.token.get_identifier().is_some())
+            {
+                indices.push(name_idx);
+            }
+        }
+    }
+    indices
+}
+
+// returns the indices of the name tokens of named captures, i.e.
+// the last identifier of `name(..., name)` and `.name(name)`.
+fn find_capture_names(tokens: &[TokenWithRange]) -> Vec<usize> {
+    let mut indices = vec![];
+    for idx in 0..tokens.len() {
+        if !(is_identifier(&tokens[idx], "name")
+            && matches!(tokens.get(idx + 1), Some(t) if t.token == Token::LeftParen))
+        {
+            continue;
+        }
+
+        // find the matching ')'
+        let mut depth: usize = 0;
+        let mut end_option = None;
+        for (offset, token_with_range) in tokens[(idx + 1)..].iter().enumerate() {
+            match token_with_range.token {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end_option = Some(idx + 1 + offset);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(end) = end_option {
+            let mut name_idx = end - 1;
+            if tokens[name_idx].token == Token::NewLine {
+                name_idx -= 1;
+            }
+            if tokens[name_idx].token.get_identifier().is_some() {
+                indices.push(name_idx);
+            }
+        }
+    }
+    indices
+}
+
+fn is_referenced(tokens: &[TokenWithRange], name_idx: usize, excludes: &[usize]) -> bool {
+    let name = get_identifier(&tokens[name_idx]);
+
+    tokens
+        .iter()
+        .enumerate()
+        .any(|(idx, t)| !excludes.contains(&idx) && is_name(t, name))
+}
+
+fn check_unused_definitions(tokens: &[TokenWithRange], warnings: &mut Vec<LintWarning>) {
+    let definition_names = find_definition_names(tokens);
+    for name_idx in &definition_names {
+        if !is_referenced(tokens, *name_idx, &definition_names) {
+            warnings.push(LintWarning {
+                message: format!(
+                    "The definition \"{}\" is never used.",
+                    get_identifier(&tokens[*name_idx])
+                ),
+                location: tokens[*name_idx].range,
+            });
+        }
+    }
+}
+
+fn check_unreferenced_captures(tokens: &[TokenWithRange], warnings: &mut Vec<LintWarning>) {
+    let capture_names = find_capture_names(tokens);
+    let definition_names = find_definition_names(tokens);
+    let excludes: Vec<usize> = capture_names
+        .iter()
+        .chain(definition_names.iter())
+        .copied()
+        .collect();
+
+    for name_idx in &capture_names {
+        if !is_referenced(tokens, *name_idx, &excludes) {
+            warnings.push(LintWarning {
+                message: format!(
+                    "The capture group \"{}\" is never back-referenced.",
+                    get_identifier(&tokens[*name_idx])
+                ),
+                location: tokens[*name_idx].range,
+            });
+        }
+    }
+}
+
+fn get_identifier(token_with_range: &TokenWithRange) -> &str {
+    token_with_range.token.get_identifier().unwrap()
+}
+
+fn get_preset_chars(name: &str) -> FirstChars {
+    get_first_chars(&Expression::Literal(Literal::PresetCharSet(
+        name.to_owned(),
+    )))
+}
+
+fn check_duplicate_charset_members(tokens: &[TokenWithRange], warnings: &mut Vec<LintWarning>) {
+    let mut idx = 0;
+    while idx < tokens.len() {
+        if tokens[idx].token != Token::LeftBracket {
+            idx += 1;
+            continue;
+        }
+
+        // the members of the charset, (chars, location)
+        let mut members: Vec<(FirstChars, Location)> = vec![];
+
+        idx += 1;
+        while idx < tokens.len() && tokens[idx].token != Token::RightBracket {
+            let range = tokens[idx].range;
+            let member = match &tokens[idx].token {
+                Token::Char(start) => {
+                    if let (
+                        Some(TokenWithRange {
+                            token: Token::Interval,
+                            ..
+                        }),
+                        Some(TokenWithRange {
+                            token: Token::Char(end_included),
+                            range: end_range,
+                        }),
+                    ) = (tokens.get(idx + 1), tokens.get(idx + 2))
+                    {
+                        idx += 2;
+                        Some((
+                            FirstChars {
+                                any: false,
+                                ranges: vec![(*start, *end_included)],
+                            },
+                            Location::from_range_pair(&range, end_range),
+                        ))
+                    } else {
+                        Some((
+                            FirstChars {
+                                any: false,
+                                ranges: vec![(*start, *start)],
+                            },
+                            range,
+                        ))
+                    }
+                }
+                Token::PresetCharSet(name) if !name.starts_with("char_not_") => {
+                    Some((get_preset_chars(name), range))
+                }
+                _ => None,
+            };
+
+            if let Some((chars, location)) = member {
+                if members.iter().any(|(c, _)| c.overlaps(&chars)) {
+                    warnings.push(LintWarning {
+                        message: "The charset member overlaps an earlier member.".to_owned(),
+                        location,
+                    });
+                }
+                members.push((chars, location));
+            }
+
+            idx += 1;
+        }
+    }
+}
+
+// the operand of alternation which matches a fixed text or a single char.
+enum Alternative {
+    Text(String),
+    Chars(FirstChars),
+    Other,
+}
+
+// reads an alternative from the index, returns the alternative and
+// the index of the last token.
+fn read_alternative(tokens: &[TokenWithRange], start: usize) -> Option<(Alternative, usize)> {
+    let result = match &tokens.get(start)?.token {
+        Token::Char(c) => (Alternative::Text(c.to_string()), start),
+        Token::String(s) => (Alternative::Text(s.to_owned()), start),
+        Token::PresetCharSet(name) if !name.starts_with("char_not_") => {
+            (Alternative::Chars(get_preset_chars(name)), start)
+        }
+        Token::PresetCharSet(_) | Token::Special(_) => (Alternative::Other, start),
+        Token::LeftBracket | Token::Exclamation => {
+            let negative = tokens[start].token == Token::Exclamation;
+            let end = start
+                + tokens[start..]
+                    .iter()
+                    .position(|t| t.token == Token::RightBracket)?;
+
+            let mut chars = FirstChars::default();
+            let mut idx = if negative { start + 2 } else { start + 1 };
+            let mut exact = !negative;
+            while idx < end {
+                match &tokens[idx].token {
+                    Token::Char(c) => {
+                        if tokens[idx + 1].token == Token::Interval {
+                            if let Token::Char(end_included) = tokens[idx + 2].token {
+                                chars.ranges.push((*c, end_included));
+                            }
+                            idx += 2;
+                        } else {
+                            chars.ranges.push((*c, *c));
+                        }
+                    }
+                    Token::PresetCharSet(name) if !name.starts_with("char_not_") => {
+                        chars.ranges.extend(get_preset_chars(name).ranges);
+                    }
+                    Token::Comma | Token::NewLine => {}
+                    _ => exact = false,
+                }
+                idx += 1;
+            }
+
+            if exact {
+                (Alternative::Chars(chars), end)
+            } else {
+                (Alternative::Other, end)
+            }
+        }
+        _ => return None,
+    };
+
+    // the alternative should not be followed by notations, e.g. `'a'+`
+    match tokens.get(result.1 + 1).map(|t| &t.token) {
+        None | Some(Token::LogicOr | Token::Comma | Token::NewLine | Token::RightParen) => {
+            Some(result)
+        }
+        _ => None,
+    }
+}
+
+// whether the later alternative can never be matched because the
+// earlier one matches the same text.
+fn is_subsumed(earlier: &Alternative, later: &Alternative) -> bool {
+    match (earlier, later) {
+        (Alternative::Text(a), Alternative::Text(b)) => a == b,
+        (Alternative::Chars(chars), Alternative::Text(s)) => {
+            let mut iter = s.chars();
+            match (iter.next(), iter.next()) {
+                (Some(c), None) => chars.contains(c),
+                _ => false,
+            }
+        }
+        (Alternative::Chars(a), Alternative::Chars(b)) => {
+            b.ranges.iter().all(|(start, end_included)| {
+                // check each char since the ranges of `a` may be discontinuous
+                (*start..=*end_included).all(|c| a.contains(c))
+            })
+        }
+        _ => false,
+    }
+}
+
+fn check_subsumed_alternatives(tokens: &[TokenWithRange], warnings: &mut Vec<LintWarning>) {
+    let mut idx = 0;
+    while idx < tokens.len() {
+        // find the first alternative of an alternation
+        let is_chain_start = (idx == 0 || tokens[idx - 1].token != Token::LogicOr)
+            && !(idx > 0 && tokens[idx - 1].token == Token::Exclamation);
+        let first = if is_chain_start {
+            read_alternative(tokens, idx)
+        } else {
+            None
+        };
+
+        let (alternative, mut end) = match first {
+            Some((alternative, end)) if matches!(tokens.get(end + 1), Some(t) if t.token == Token::LogicOr) => {
+                (alternative, end)
+            }
+            _ => {
+                idx += 1;
+                continue;
+            }
+        };
+
+        let mut alternatives = vec![alternative];
+        while matches!(tokens.get(end + 1), Some(t) if t.token == Token::LogicOr) {
+            let start = end + 2;
+            match read_alternative(tokens, start) {
+                Some((alternative, alternative_end)) => {
+                    if alternatives.iter().any(|a| is_subsumed(a, &alternative)) {
+                        warnings.push(LintWarning {
+                            message: "The alternative is unreachable because an earlier alternative matches the same text.".to_owned(),
+                            location: Location::from_range_pair(
+                                &tokens[start].range,
+                                &tokens[alternative_end].range,
+                            ),
+                        });
+                    }
+                    alternatives.push(alternative);
+                    end = alternative_end;
+                }
+                None => break,
+            }
+        }
+
+        idx = end + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::lint_from_str;
+
+    // returns (message, index, length)
+    fn lint(s: &str) -> Vec<(String, usize, usize)> {
+        lint_from_str(s)
+            .unwrap()
+            .into_iter()
+            .map(|w| (w.message, w.location.index, w.location.length))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_unused_definitions() {
+        assert_eq!(
+            lint(
+                r#"define(a, 'a')
+define(b, 'b')
+a"#
+            ),
+            vec![("The definition \"b\" is never used.".to_owned(), 22, 1)]
+        );
+
+        // used by another definition
+        assert!(lint(
+            r#"define(a, 'a')
+define(b, (a, 'b'))
+b"#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_lint_duplicate_charset_members() {
+        assert_eq!(
+            lint(r#"['a', 'a'..'c', 'x']"#),
+            vec![(
+                "The charset member overlaps an earlier member.".to_owned(),
+                6,
+                8
+            )]
+        );
+
+        assert_eq!(
+            lint(r#"![char_digit, '5']"#),
+            vec![(
+                "The charset member overlaps an earlier member.".to_owned(),
+                14,
+                3
+            )]
+        );
+
+        assert!(lint(r#"['a'..'c', 'd'..'f', char_space]"#).is_empty());
+    }
+
+    #[test]
+    fn test_lint_subsumed_alternatives() {
+        assert_eq!(
+            lint(r#"char_word || 'a' || "ab" || "ab""#),
+            vec![
+                (
+                    "The alternative is unreachable because an earlier alternative matches the same text.".to_owned(),
+                    13,
+                    3
+                ),
+                (
+                    "The alternative is unreachable because an earlier alternative matches the same text.".to_owned(),
+                    28,
+                    4
+                )
+            ]
+        );
+
+        assert_eq!(lint(r#"['a'..'z'] || ['x'..'y', 'c']"#).len(), 1);
+
+        // not subsumed
+        assert!(lint(r#"'a' || char_word"#).is_empty());
+        assert!(lint(r#"['a'..'c'] || ['c'..'e']"#).is_empty());
+        assert!(lint(r#"'a' || 'a'+"#).is_empty());
+        assert!(lint(r#"!['a'] || 'b'"#).is_empty());
+    }
+
+    #[test]
+    fn test_lint_unreferenced_captures() {
+        assert_eq!(
+            lint(r#"name('a', first), name('b', second), second"#),
+            vec![(
+                "The capture group \"first\" is never back-referenced.".to_owned(),
+                10,
+                5
+            )]
+        );
+
+        assert_eq!(
+            lint(r#"'a'.name(x)"#),
+            vec![(
+                "The capture group \"x\" is never back-referenced.".to_owned(),
+                9,
+                1
+            )]
+        );
+    }
+}
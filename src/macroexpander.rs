@@ -5,10 +5,14 @@
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
 use crate::{
+    ast::DefinitionLocation,
     error::Error,
+    lexer::lex_from_str,
     location::Location,
+    options::Limits,
     peekableiter::PeekableIter,
-    token::{Token, TokenWithRange},
+    symbol::Symbol,
+    token::{get_reserved_word_kind, Token, TokenWithRange},
 };
 
 fn remove_comments(tokens: Vec<TokenWithRange>) -> Vec<TokenWithRange> {
@@ -33,10 +37,9 @@ fn extract_definitions(
 ) -> Result<(Vec<TokenWithRange>, Vec<Definition>), Error> {
     let mut definitions: Vec<Definition> = vec![];
     loop {
-        let pos = tokens.iter().position(|token_with_range| {
-            matches!(token_with_range, TokenWithRange {
-                token: Token::Identifier(id),
-                ..  } if id == "define" )
+        // the statement `define(...)` or `define_charset(...)`
+        let pos = tokens.windows(2).position(|pair| {
+            is_definition_keyword(&pair[0].token) && pair[1].token == Token::LeftParen
         });
 
         if pos.is_none() {
@@ -94,52 +97,528 @@ fn extract_definitions(
 fn replace_identifiers(
     mut program_tokens: Vec<TokenWithRange>,
     mut definitions: Vec<Definition>,
-) -> Vec<TokenWithRange> {
+    relocate: bool,
+    max_tokens: usize,
+) -> Result<Vec<TokenWithRange>, Error> {
     definitions.reverse();
-    while !definitions.is_empty() {
-        let definition = definitions.pop().unwrap();
-
+    while let Some(definition) = definitions.pop() {
         for idx in (0..definitions.len()).rev() {
             find_and_replace_identifiers(
                 &mut definitions[idx].tokens,
-                &definition.name,
-                &definition.tokens,
-            );
+                &definition,
+                relocate,
+                max_tokens,
+            )?;
         }
 
-        find_and_replace_identifiers(&mut program_tokens, &definition.name, &definition.tokens);
+        find_and_replace_identifiers(&mut program_tokens, &definition, relocate, max_tokens)?;
     }
 
-    program_tokens
+    Ok(program_tokens)
 }
 
 fn find_and_replace_identifiers(
     source_tokens: &mut Vec<TokenWithRange>,
-    find_id: &str,
-    replace_with: &[TokenWithRange],
-) {
+    definition: &Definition,
+    relocate: bool,
+    max_tokens: usize,
+) -> Result<(), Error> {
     for idx in (0..source_tokens.len()).rev() {
-        if let Token::Identifier(id) = &source_tokens[idx].token {
-            if id == find_id {
+        if let Some(id) = source_tokens[idx].token.get_identifier() {
+            if id == &definition.name && is_macro_reference(source_tokens, idx) {
+                let range = source_tokens[idx].range;
+
+                let inside_charset = is_inside_charset(source_tokens, idx);
+                if inside_charset && !definition.charset {
+                    check_charset_elements(definition, range)?;
+                }
+
+                // the charset macro is expanded to its elements when it is
+                // used inside another charset, e.g. `hex` in `[hex, '_']`
+                // is expanded to `'0'..'9', 'a'..'f'` instead of `['0'..'9', 'a'..'f']`.
+                let replace_with = if definition.charset && inside_charset {
+                    if definition.tokens[0].token == Token::Exclamation {
+                        return Err(Error::MessageWithLocation(
+                            format!(
+                                "The negative charset macro \"{}\" can not be used inside another charset.",
+                                definition.name
+                            ),
+                            range,
+                        ));
+                    }
+                    &definition.tokens[1..(definition.tokens.len() - 1)]
+                } else {
+                    &definition.tokens[..]
+                };
+
+                // the definitions that refer to each other may expand
+                // exponentially, so the size is checked before each replacement.
+                if source_tokens.len() - 1 + replace_with.len() > max_tokens {
+                    return Err(too_many_tokens_error(max_tokens, range));
+                }
+
                 // remove the identifier token, and insert the target tokens
-                source_tokens.splice(idx..(idx + 1), replace_with.iter().cloned());
+                source_tokens.splice(
+                    idx..(idx + 1),
+                    replace_with.iter().map(|token_with_range| TokenWithRange {
+                        token: token_with_range.token.clone(),
+                        range: if relocate {
+                            range
+                        } else {
+                            token_with_range.range
+                        },
+                    }),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// whether the identifier at the index refers to a definition.
+//
+// the identifiers with the same name as a definition are left untouched
+// if they are:
+// - the names of functions, e.g. `optional` in `optional('a')` and
+//   `'a'.optional()`.
+// - the names of capture groups, e.g. `x` in `name('a', x)` and
+//   `'a'.name(x)`.
+fn is_macro_reference(tokens: &[TokenWithRange], idx: usize) -> bool {
+    let is_token = |idx: Option<usize>, expected: Token| {
+        idx.and_then(|i| tokens.get(i))
+            .is_some_and(|t| t.token == expected)
+    };
+
+    if is_token(idx.checked_sub(1), Token::Dot) || is_token(Some(idx + 1), Token::LeftParen) {
+        return false;
+    }
+
+    !is_capture_name(tokens, idx)
+}
+
+// whether the token at the index is between the brackets of a charset.
+fn is_inside_charset(tokens: &[TokenWithRange], idx: usize) -> bool {
+    tokens[..idx]
+        .iter()
+        .rev()
+        .find(|t| matches!(t.token, Token::LeftBracket | Token::RightBracket))
+        .is_some_and(|t| t.token == Token::LeftBracket)
+}
+
+// the text macro that is used inside a charset can only expand to the
+// charset elements, otherwise the error is reported at the reference
+// with the location of the element in the definition, instead of the
+// parser reporting the element as if it were written in the charset.
+//
+// the identifiers in the definition are the macros that are not
+// expanded yet, they are checked when they are expanded.
+fn check_charset_elements(definition: &Definition, range: Location) -> Result<(), Error> {
+    let element = definition.tokens.iter().find(|t| {
+        !matches!(
+            t.token,
+            Token::Char(_)
+                | Token::Interval
+                | Token::PresetCharSet(_)
+                | Token::Status(_)
+                | Token::Comma
+                | Token::NewLine
+                | Token::Identifier(_)
+                | Token::QuotedIdentifier(_)
+        )
+    });
+
+    let Some(element) = element else {
+        return Ok(());
+    };
+
+    let hint = if matches!(element.token, Token::LeftBracket | Token::Exclamation) {
+        " Use \"define_charset\" to define a charset that can be used inside other charsets."
+    } else {
+        ""
+    };
+
+    Err(Error::MessageWithLocation(
+        format!(
+            "The macro \"{}\" can not be used inside a charset, it expands to the {} at line {}, column {}.{}",
+            definition.name,
+            element.token.get_description(),
+            element.range.line + 1,
+            element.range.column + 1,
+            hint
+        ),
+        range,
+    ))
+}
+
+fn is_definition_keyword(token: &Token) -> bool {
+    matches!(token, Token::Identifier(id) if id == "define" || id == "define_charset")
+}
+
+// whether the identifier at the index is the name of a capture group,
+// i.e. the last argument of the function `name`, e.g. `x` in
+// `name('a', x)` and `'a'.name(x)`.
+fn is_capture_name(tokens: &[TokenWithRange], idx: usize) -> bool {
+    if !matches!(tokens.get(idx + 1), Some(t) if t.token == Token::RightParen) {
+        return false;
+    }
+
+    // find the '(' of the enclosing function call
+    let mut depth: usize = 0;
+    for open_idx in (0..idx).rev() {
+        match tokens[open_idx].token {
+            Token::RightParen => depth += 1,
+            Token::LeftParen if depth > 0 => depth -= 1,
+            Token::LeftParen => {
+                return matches!(
+                    open_idx.checked_sub(1).map(|i| &tokens[i].token),
+                    Some(Token::Identifier(name)) if name == "name"
+                );
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+// a name can only be defined once, the later definition would otherwise
+// be ignored silently since the references are expanded with the first one.
+fn check_duplicate_definitions(definitions: &[Definition]) -> Result<(), Error> {
+    for (idx, definition) in definitions.iter().enumerate() {
+        if let Some(previous) = definitions[..idx]
+            .iter()
+            .find(|d| d.name == definition.name)
+        {
+            return Err(Error::MessageWithLocation(
+                format!(
+                    "The macro \"{}\" is defined more than once, the previous definition is at line {}, column {}.",
+                    definition.name,
+                    previous.name_range.line + 1,
+                    previous.name_range.column + 1
+                ),
+                definition.name_range,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// the name of a capture group can not be the name of a macro, otherwise
+// the identifier would be expanded as the macro instead of being the
+// back-reference to the group, e.g. `name('a', x), define(x, 'b'), x`.
+fn check_capture_names(tokens: &[TokenWithRange], names: &[Symbol]) -> Result<(), Error> {
+    for (idx, token_with_range) in tokens.iter().enumerate() {
+        if let Some(id) = token_with_range.token.get_identifier() {
+            if names.contains(id) && is_capture_name(tokens, idx) {
+                return Err(Error::MessageWithLocation(
+                    format!(
+                        "The capture group name \"{}\" conflicts with the macro of the same name.",
+                        id
+                    ),
+                    token_with_range.range,
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the definitions that refer to themselves, directly or through other
+// definitions, e.g. `define(a, (b, 'x'))` and `define(b, a?)`, can never
+// be expanded completely, so they are reported with the chain of names
+// (e.g. "a → b → a") at the reference that closes the cycle.
+fn check_definition_cycles(definitions: &[Definition]) -> Result<(), Error> {
+    // the references of each definition, (target definition index, location)
+    let references: Vec<Vec<(usize, Location)>> = definitions
+        .iter()
+        .map(|definition| {
+            let tokens = &definition.tokens;
+            tokens
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, t)| match t.token.get_identifier() {
+                    Some(id) if is_macro_reference(tokens, idx) => definitions
+                        .iter()
+                        .position(|d| &d.name == id)
+                        .map(|target| (target, t.range)),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    // the depth-first search, the definitions on the current path are
+    // `Visiting`, and a reference to one of them closes a cycle.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Unvisited,
+        Visiting,
+        Visited,
+    }
+
+    let mut marks = vec![Mark::Unvisited; definitions.len()];
+
+    for root in 0..definitions.len() {
+        if marks[root] != Mark::Unvisited {
+            continue;
+        }
+
+        // the path of (definition index, index of the next reference)
+        let mut path: Vec<(usize, usize)> = vec![(root, 0)];
+        marks[root] = Mark::Visiting;
+
+        while let Some((current, next)) = path.last_mut() {
+            let Some((target, range)) = references[*current].get(*next).copied() else {
+                marks[*current] = Mark::Visited;
+                path.pop();
+                continue;
+            };
+            *next += 1;
+
+            match marks[target] {
+                Mark::Unvisited => {
+                    marks[target] = Mark::Visiting;
+                    path.push((target, 0));
+                }
+                Mark::Visiting => {
+                    let start = path.iter().position(|(idx, _)| *idx == target).unwrap();
+                    let names: Vec<&str> = path[start..]
+                        .iter()
+                        .map(|(idx, _)| definitions[*idx].name.as_str())
+                        .chain([definitions[target].name.as_str()])
+                        .collect();
+                    return Err(Error::MessageWithLocation(
+                        format!("Macro cycle detected: {}.", names.join(" → ")),
+                        range,
+                    ));
+                }
+                Mark::Visited => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn too_many_tokens_error(max_tokens: usize, range: Location) -> Error {
+    Error::MessageWithLocation(
+        format!(
+            "The expanded pattern exceeds the maximum number of tokens {}.",
+            max_tokens
+        ),
+        range,
+    )
+}
+
+// the built-in macros, they are invoked with empty parentheses,
+// e.g. `date_iso8601()`, and each one is expanded to a group
+// with named captures.
+const BUILTIN_MACROS: [(&str, &str); 3] = [
+    (
+        "date_iso8601",
+        r#"(
+            name(['0'..'9']{4}, year), '-',
+            name(('0', ['1'..'9']) || ('1', ['0'..'2']), month), '-',
+            name(('0', ['1'..'9']) || (['1'..'2'], ['0'..'9']) || ('3', ['0'..'1']), day)
+        )"#,
+    ),
+    (
+        "time_hh_mm_ss",
+        r#"(
+            name((['0'..'1'], ['0'..'9']) || ('2', ['0'..'3']), hour), ':',
+            name((['0'..'5'], ['0'..'9']), minute), ':',
+            name((['0'..'5'], ['0'..'9']), second)
+        )"#,
+    ),
+    (
+        "timezone_offset",
+        r#"(
+            name('Z' || (
+                ['+', '-'],
+                (['0'..'1'], ['0'..'9']) || ('2', ['0'..'3']),
+                ':'?,
+                ['0'..'5'], ['0'..'9']
+            ), timezone)
+        )"#,
+    ),
+];
+
+fn replace_builtin_macros(
+    mut tokens: Vec<TokenWithRange>,
+    max_tokens: usize,
+) -> Result<Vec<TokenWithRange>, Error> {
+    for idx in (0..tokens.len()).rev() {
+        let source = match &tokens[idx].token {
+            Token::Identifier(id)
+                if !(idx > 0 && tokens[idx - 1].token == Token::Dot)
+                    && matches!(
+                        tokens.get(idx + 1),
+                        Some(TokenWithRange {
+                            token: Token::LeftParen,
+                            ..
+                        })
+                    )
+                    && matches!(
+                        tokens.get(idx + 2),
+                        Some(TokenWithRange {
+                            token: Token::RightParen,
+                            ..
+                        })
+                    ) =>
+            {
+                match BUILTIN_MACROS.iter().find(|(name, _)| id == name) {
+                    Some((_, source)) => *source,
+                    None => continue,
+                }
             }
+            _ => continue,
+        };
+
+        // the expanded tokens take the location of the macro name, so that
+        // the errors are reported at the invocation.
+        let range = tokens[idx].range;
+        let macro_tokens: Vec<TokenWithRange> = remove_comments(lex_from_str(source)?)
+            .into_iter()
+            .map(|token_with_range| TokenWithRange {
+                token: token_with_range.token,
+                range,
+            })
+            .collect();
+
+        if tokens.len() - 3 + macro_tokens.len() > max_tokens {
+            return Err(too_many_tokens_error(max_tokens, range));
         }
+
+        tokens.splice(idx..(idx + 3), macro_tokens);
     }
+
+    Ok(tokens)
+}
+
+// the stage before parsing, it extracts the `define` (and `define_charset`)
+// statements and
+// replaces the identifiers that refer to them with their tokens, then
+// expands the built-in macros, e.g. `date_iso8601()`.
+pub fn expand(tokens: Vec<TokenWithRange>, limits: &Limits) -> Result<Vec<TokenWithRange>, Error> {
+    expand_with(tokens, false, limits.max_expanded_tokens)
 }
 
-pub fn expand(tokens: Vec<TokenWithRange>) -> Result<Vec<TokenWithRange>, Error> {
+// like `expand`, but the expanded tokens of a definition take the
+// location of the identifier that refers to it, instead of the
+// locations inside the definition.
+pub fn expand_at_references(
+    tokens: Vec<TokenWithRange>,
+    limits: &Limits,
+) -> Result<Vec<TokenWithRange>, Error> {
+    expand_with(tokens, true, limits.max_expanded_tokens)
+}
+
+fn expand_with(
+    tokens: Vec<TokenWithRange>,
+    relocate: bool,
+    max_tokens: usize,
+) -> Result<Vec<TokenWithRange>, Error> {
     let clean_tokens = remove_comments(tokens);
     let (program_tokens, definitions) = extract_definitions(clean_tokens)?;
-    let expand_tokens = replace_identifiers(program_tokens, definitions);
+    check_duplicate_definitions(&definitions)?;
+    check_definition_cycles(&definitions)?;
+
+    let names: Vec<Symbol> = definitions.iter().map(|d| d.name.clone()).collect();
+    let expand_tokens = replace_identifiers(program_tokens, definitions, relocate, max_tokens)?;
+    let expand_tokens = replace_builtin_macros(expand_tokens, max_tokens)?;
+    check_capture_names(&expand_tokens, &names)?;
 
     Ok(expand_tokens)
 }
 
+// collect the locations of the `define` statements and the identifiers
+// that refer to them, the tokens are the ones before expanding.
+//
+// like the expanding, the identifiers with the name of a definition are
+// counted as references, except the name of the definition itself, and
+// the names of functions and capture groups, see `is_macro_reference`.
+pub fn collect_definition_locations(tokens: &[TokenWithRange]) -> Vec<DefinitionLocation> {
+    let mut definitions = vec![];
+    let mut name_indices = vec![];
+
+    for (idx, token_with_range) in tokens.iter().enumerate() {
+        if !is_definition_keyword(&token_with_range.token)
+            || !matches!(
+                tokens.get(idx + 1),
+                Some(TokenWithRange {
+                    token: Token::LeftParen,
+                    ..
+                })
+            )
+        {
+            continue;
+        }
+
+        let name_idx = match tokens[idx + 2..]
+            .iter()
+            .position(|t| t.token != Token::NewLine)
+        {
+            Some(offset) => idx + 2 + offset,
+            None => continue,
+        };
+
+        let name = match tokens[name_idx].token.get_identifier() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        // find the ending ')'
+        let mut depth: usize = 0;
+        let mut end_range = tokens[tokens.len() - 1].range;
+        for t in &tokens[idx + 1..] {
+            match t.token {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end_range = t.range;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        name_indices.push(name_idx);
+        definitions.push(DefinitionLocation {
+            name,
+            name_location: tokens[name_idx].range,
+            location: Location::from_range_pair(&token_with_range.range, &end_range),
+            references: vec![],
+            doc_comments: vec![],
+            comments: vec![],
+        });
+    }
+
+    for (idx, token_with_range) in tokens.iter().enumerate() {
+        if let Some(id) = token_with_range.token.get_identifier() {
+            if name_indices.contains(&idx) || !is_macro_reference(tokens, idx) {
+                continue;
+            }
+
+            if let Some(definition) = definitions.iter_mut().find(|d| id == d.name.as_str()) {
+                definition.references.push(token_with_range.range);
+            }
+        }
+    }
+
+    definitions
+}
+
 #[derive(Debug, PartialEq)]
 struct Definition {
-    name: String,
+    name: Symbol,
+    name_range: Location,
     tokens: Vec<TokenWithRange>,
+    charset: bool, // defined by `define_charset`
 }
 
 pub struct DefinitionExtractor<'a> {
@@ -225,10 +704,10 @@ impl<'a> DefinitionExtractor<'a> {
         }
     }
 
-    fn expect_identifier(&mut self) -> Result<String, Error> {
+    fn expect_identifier(&mut self) -> Result<Symbol, Error> {
         match self.peek_token(0) {
-            Some(Token::Identifier(s)) => {
-                let id = s.to_owned();
+            Some(Token::Identifier(s) | Token::QuotedIdentifier(s)) => {
+                let id = s.clone();
                 self.next_token();
                 Ok(id)
             }
@@ -249,10 +728,41 @@ impl<'a> DefinitionExtractor<'a> {
         // |        |__ validated
         // | current validated
 
-        self.next_token(); // consume "define"
+        let charset =
+            matches!(self.next_token(), Some(Token::Identifier(id)) if id == "define_charset"); // consume "define" or "define_charset"
         self.next_token(); // consume '('
         self.consume_new_line_if_exist(); // consume trailing new-line
 
+        let name_range = self.peek_range(0).copied().unwrap_or(self.last_range);
+
+        // the macro can not take the name of a keyword, a preset charset,
+        // an assertion, a function or a built-in macro, since the
+        // identifiers with these names are not expanded.
+        if let Some(
+            Token::Identifier(name)
+            | Token::Status(name)
+            | Token::PresetCharSet(name)
+            | Token::Special(name),
+        ) = self.peek_token(0)
+        {
+            let kind = get_reserved_word_kind(name).or_else(|| {
+                BUILTIN_MACROS
+                    .iter()
+                    .any(|(builtin, _)| name == builtin)
+                    .then_some("built-in macro")
+            });
+
+            if let Some(kind) = kind {
+                return Err(Error::MessageWithLocation(
+                    format!(
+                        "The macro name \"{}\" conflicts with the {} \"{}\".",
+                        name, kind, name
+                    ),
+                    name_range,
+                ));
+            }
+        }
+
         let name = self.expect_identifier()?;
         self.expect_new_line_or_comma()?;
 
@@ -265,9 +775,41 @@ impl<'a> DefinitionExtractor<'a> {
             }
         }
 
+        if charset {
+            // the body of `define_charset` is a charset, e.g. `['0'..'9']`
+            // or `!['0'..'9']`, so that it can be expanded to the elements
+            // inside other charsets.
+            while token_with_ranges
+                .last()
+                .is_some_and(|t| t.token == Token::NewLine)
+            {
+                token_with_ranges.pop();
+            }
+
+            let start = match token_with_ranges.first() {
+                Some(t) if t.token == Token::Exclamation => 1,
+                _ => 0,
+            };
+            let is_charset = token_with_ranges.len() >= start + 2
+                && token_with_ranges[start].token == Token::LeftBracket
+                && token_with_ranges.last().unwrap().token == Token::RightBracket
+                && !token_with_ranges[(start + 1)..(token_with_ranges.len() - 1)]
+                    .iter()
+                    .any(|t| matches!(t.token, Token::LeftBracket | Token::RightBracket));
+
+            if !is_charset {
+                return Err(Error::MessageWithLocation(
+                    format!("The charset macro \"{}\" only accepts a charset.", name),
+                    name_range,
+                ));
+            }
+        }
+
         let definition = Definition {
             name,
+            name_range,
             tokens: token_with_ranges,
+            charset,
         };
 
         Ok(definition)
@@ -283,6 +825,8 @@ mod tests {
         error::Error,
         lexer::lex_from_str,
         normalizer::normalize,
+        options::Limits,
+        parser::parse_from_str,
         token::{Token, TokenWithRange},
     };
 
@@ -292,7 +836,7 @@ mod tests {
         let tokens = lex_from_str(s)?;
         let clean_tokens = clean(tokens);
         let normalized_tokens = normalize(clean_tokens);
-        let expanded_tokens = expand(normalized_tokens)?;
+        let expanded_tokens = expand(normalized_tokens, &Limits::default())?;
         let expanded_and_normalized_tokens = normalize(expanded_tokens);
         Ok(expanded_and_normalized_tokens)
     }
@@ -425,4 +969,313 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_expand_builtin_macros() {
+        let tokens = expanded_lex_from_str_without_location(
+            r#"
+            define(d, date_iso8601())
+            start, d, 'T', time_hh_mm_ss(), end
+            "#,
+        )
+        .unwrap();
+
+        assert!(tokens.contains(&Token::new_identifier("name")));
+        assert!(!tokens.contains(&Token::new_identifier("date_iso8601")));
+        assert!(!tokens.contains(&Token::new_identifier("time_hh_mm_ss")));
+
+        // the rear function call is not expanded
+        assert_eq!(
+            expanded_lex_from_str_without_location(r#"'a'.date_iso8601()"#).unwrap(),
+            vec![
+                Token::Char('a'),
+                Token::Dot,
+                Token::new_identifier("date_iso8601"),
+                Token::LeftParen,
+                Token::RightParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_limits() {
+        // each definition doubles the size
+        let mut source = "define(a0, \"ab\")\n".to_owned();
+        for i in 1..30 {
+            source.push_str(&format!("define(a{}, (a{}, a{}))\n", i, i - 1, i - 1));
+        }
+        source.push_str("a29");
+
+        let tokens = normalize(clean(lex_from_str(&source).unwrap()));
+        assert!(matches!(
+            expand(tokens, &Limits::default()),
+            Err(Error::MessageWithLocation(..))
+        ));
+
+        let limits = Limits {
+            max_expanded_tokens: 10,
+            ..Limits::default()
+        };
+
+        let tokens = normalize(clean(lex_from_str(r#"define(a, ('a', 'b')), a"#).unwrap()));
+        assert!(expand(tokens, &limits).is_ok());
+
+        let tokens = normalize(clean(
+            lex_from_str(r#"define(a, ('a', 'b')), a, a"#).unwrap(),
+        ));
+        assert!(expand(tokens, &limits).is_err());
+
+        let tokens = normalize(clean(lex_from_str(r#"date_iso8601()"#).unwrap()));
+        assert!(expand(tokens, &limits).is_err());
+    }
+
+    #[test]
+    fn test_expand_hygiene() {
+        // the identifiers in the position of function names are not expanded
+        assert_eq!(
+            expanded_lex_from_str_without_location(
+                r#"
+            define(opt, 'a')
+            define(x, 'b')
+            opt(x), name(x, y), x.name(y), opt
+            "#,
+            )
+            .unwrap(),
+            vec![
+                Token::new_identifier("opt"),
+                Token::LeftParen,
+                Token::Char('b'),
+                Token::RightParen,
+                Token::Comma,
+                Token::new_identifier("name"),
+                Token::LeftParen,
+                Token::Char('b'),
+                Token::Comma,
+                Token::new_identifier("y"),
+                Token::RightParen,
+                Token::Comma,
+                Token::Char('b'),
+                Token::Dot,
+                Token::new_identifier("name"),
+                Token::LeftParen,
+                Token::new_identifier("y"),
+                Token::RightParen,
+                Token::Comma,
+                Token::Char('a'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_cycles() {
+        let cycle_error = |s: &str| match expanded_lex_from_str(s) {
+            Err(Error::MessageWithLocation(message, location)) => (message, location.index),
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        // the self-reference
+        assert_eq!(
+            cycle_error(r#"define(a, (a, 'b')), a"#),
+            ("Macro cycle detected: a → a.".to_owned(), 11)
+        );
+
+        // the mutual references
+        assert_eq!(
+            cycle_error(
+                r#"define(a, (b, 'x'))
+define(b, ('y', c?))
+define(c, a+)
+a"#
+            ),
+            ("Macro cycle detected: a → b → c → a.".to_owned(), 51)
+        );
+
+        // the cycle that is not referenced by the program
+        assert_eq!(
+            cycle_error(r#"define(a, 'a'), define(b, (a, b)), a"#).0,
+            "Macro cycle detected: b → b."
+        );
+
+        // the diamond is not a cycle
+        assert!(expanded_lex_from_str(
+            r#"define(a, 'a')
+define(b, (a, a))
+define(c, (a, b))
+c, b"#
+        )
+        .is_ok());
+
+        // the definition inside a capture group
+        assert!(expanded_lex_from_str(r#"define(a, 'a'), name(a, x), x"#).is_ok());
+    }
+
+    #[test]
+    fn test_expand_duplicate_definitions() {
+        let error = |s: &str| match expanded_lex_from_str(s) {
+            Err(Error::MessageWithLocation(message, location)) => (message, location.index),
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert_eq!(
+            error(
+                r#"define(a, 'a')
+define(a, 'b')
+a"#
+            ),
+            (
+                "The macro \"a\" is defined more than once, the previous definition is at line 1, column 8."
+                    .to_owned(),
+                22
+            )
+        );
+
+        // the capture group with the name of a macro
+        assert_eq!(
+            error(r#"define(x, 'b'), name('a', x), x"#),
+            (
+                "The capture group name \"x\" conflicts with the macro of the same name."
+                    .to_owned(),
+                26
+            )
+        );
+        assert!(expanded_lex_from_str(r#"define(a, name('x', a)), a"#).is_err());
+        assert!(expanded_lex_from_str(r#"define(month, 'b'), date_iso8601()"#).is_err());
+
+        // the back-reference without the macro
+        assert!(expanded_lex_from_str(r#"define(y, 'b'), name(y, x), x"#).is_ok());
+    }
+
+    #[test]
+    fn test_expand_reserved_names() {
+        let error = |s: &str| match expanded_lex_from_str(s) {
+            Err(Error::MessageWithLocation(message, location)) => (message, location.index),
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert_eq!(
+            error(r#"define(start, 'a'), start"#),
+            (
+                "The macro name \"start\" conflicts with the assertion \"start\".".to_owned(),
+                7
+            )
+        );
+        assert_eq!(
+            error(r#"define(char_digit, ['0'..'7'])"#).0,
+            "The macro name \"char_digit\" conflicts with the preset charset \"char_digit\"."
+        );
+        assert_eq!(
+            error(r#"define(repeat, 'a')"#).0,
+            "The macro name \"repeat\" conflicts with the function \"repeat\"."
+        );
+        assert_eq!(
+            error(r#"define(var, 'a')"#).0,
+            "The macro name \"var\" conflicts with the keyword \"var\"."
+        );
+        assert_eq!(
+            error(r#"define(date_iso8601, 'a')"#).0,
+            "The macro name \"date_iso8601\" conflicts with the built-in macro \"date_iso8601\"."
+        );
+    }
+
+    #[test]
+    fn test_expand_charset() {
+        let format = |s: &str| parse_from_str(s).map(|program| program.to_string());
+
+        // inside and outside other charsets
+        assert_eq!(
+            format(
+                r#"
+            define_charset(hex, ['0'..'9', 'a'..'f'])
+            [hex, '_'], hex, !hex, ![hex, '-']
+            "#
+            )
+            .unwrap(),
+            "['0'..'9', 'a'..'f', '_'], ['0'..'9', 'a'..'f'], !['0'..'9', 'a'..'f'], !['0'..'9', 'a'..'f', '-']"
+        );
+
+        // the charset macros refer to each other, in any order
+        assert_eq!(
+            format(
+                r#"
+            define_charset(word, [alpha, char_digit, '_'])
+            define_charset(alpha, ['a'..'z', 'A'..'Z'])
+            define(ident, ([alpha, '_'], word*))
+            ident
+            "#
+            )
+            .unwrap(),
+            "(['a'..'z', 'A'..'Z', '_'], zero_or_more(['a'..'z', 'A'..'Z', char_digit, '_']))"
+        );
+
+        // the text macro of a charset can not be used inside charsets
+        assert!(format(r#"define(hex, ['0'..'9']), [hex, '_']"#).is_err());
+
+        let error = |s: &str| match expanded_lex_from_str(s) {
+            Err(Error::MessageWithLocation(message, location)) => (message, location.index),
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert_eq!(
+            error(r#"define_charset(not_hex, !['0'..'9']), ['x', not_hex]"#),
+            (
+                "The negative charset macro \"not_hex\" can not be used inside another charset."
+                    .to_owned(),
+                44
+            )
+        );
+        assert_eq!(
+            error(r#"define_charset(hex, '0'..'9')"#),
+            (
+                "The charset macro \"hex\" only accepts a charset.".to_owned(),
+                15
+            )
+        );
+        assert!(expanded_lex_from_str(r#"define_charset(ab, ['a'], ['b'])"#).is_err());
+        assert!(
+            expanded_lex_from_str(r#"define_charset(a, [b]), define_charset(b, [a])"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_expand_inside_charset() {
+        let error = |s: &str| match expanded_lex_from_str(s) {
+            Err(Error::MessageWithLocation(message, location)) => (message, location.index),
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        // the macros of the charset elements
+        assert!(
+            expanded_lex_from_str(r#"define(d, '0'..'9'), define(x, (d, 'x')), ['a', d]"#).is_ok()
+        );
+        assert!(expanded_lex_from_str(r#"define(a, b), define(b, 'b'), ['x', a]"#).is_ok());
+
+        assert_eq!(
+            error(
+                r#"define(hex, ['0'..'9', 'a'..'f'])
+['x', hex]"#
+            ),
+            (
+                "The macro \"hex\" can not be used inside a charset, it expands to the \
+                 left bracket \"[\" at line 1, column 13. Use \"define_charset\" to define \
+                 a charset that can be used inside other charsets."
+                    .to_owned(),
+                40
+            )
+        );
+        assert_eq!(
+            error(r#"define(a, ('a', "bc")), ['x', a]"#),
+            (
+                "The macro \"a\" can not be used inside a charset, it expands to the \
+                 left parenthese \"(\" at line 1, column 11."
+                    .to_owned(),
+                30
+            )
+        );
+
+        // the macro inside another macro
+        assert_eq!(
+            error(r#"define(s, "bc"), define(a, ['x', s]), a"#).0,
+            "The macro \"s\" can not be used inside a charset, it expands to the string at line 1, column 11."
+        );
+    }
 }
@@ -0,0 +1,515 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the command line tool, e.g.
+//
+// ```text
+// $ anreg repl
+// pattern> name(char_word+, key), '=', name(char_digit+, value)
+// text> a=1, b=23
+// a=1, b=23
+// ^^^  ^^^^
+// #0 0..3 "a=1"
+//   key 0..1 "a"
+//   value 2..3 "1"
+// #1 5..9 "b=23"
+//   key 5..6 "b"
+//   value 7..9 "23"
+// ```
+//
+// the commands in the REPL:
+//
+// - `:pattern PATTERN` replaces the pattern.
+// - `:quit` exits, as well as the end of input (Ctrl+D).
+//
+// the command `anreg test DIR` runs the test cases of the pattern files
+// (i.e. the `*.anreg` files in the directory and its subdirectories),
+// which are the directives `expect_match(text)` and `expect_no_match(text)`
+// in the patterns, and prints a JUnit XML report for the CI, e.g.
+//
+// ```text
+// $ anreg test patterns
+// <?xml version="1.0" encoding="UTF-8"?>
+// <testsuites tests="2" failures="1" errors="0">
+//   <testsuite name="email.anreg" tests="2" failures="1" errors="0">
+//     <testcase name="expect_match(&quot;a@b.c&quot;)" classname="email.anreg"/>
+//     <testcase name="expect_no_match(&quot;a@b&quot;)" classname="email.anreg">
+//       <failure message="the text matches the pattern"/>
+//     </testcase>
+//   </testsuite>
+// </testsuites>
+// ```
+//
+// each file is a test suite, a file that can not be read or compiled is
+// reported as an error. the exit code is non-zero if any test fails.
+
+use std::{
+    env, fs,
+    io::{self, BufRead, IsTerminal, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anreg::{parse_from_str, CompileOptions, MatchRange, Process};
+
+const USAGE: &str = "\
+Usage: anreg <command>
+
+Commands:
+  repl        read a pattern, then match the following lines of text with it
+  test <dir>  run the test cases of the *.anreg files in the directory,
+              and print a JUnit XML report
+";
+
+// the extension of the pattern files
+const PATTERN_FILE_EXTENSION: &str = "anreg";
+
+// the ANSI escape codes for highlighting the matched spans.
+const COLOR_MATCH: &str = "\x1b[1;32m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(|s| s.as_str()) {
+        Some("repl") => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+
+            // the colors are disabled when the output is redirected,
+            // or the environment variable `NO_COLOR` is set.
+            let color = stdout.is_terminal() && env::var_os("NO_COLOR").is_none();
+
+            match repl(stdin.lock(), stdout.lock(), color) {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some("test") if args.len() == 2 => {
+            let stdout = io::stdout();
+            match run_tests(Path::new(&args[1]), stdout.lock()) {
+                Ok(true) => ExitCode::SUCCESS,
+                Ok(false) => ExitCode::FAILURE,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        _ => {
+            eprint!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn repl(mut input: impl BufRead, mut output: impl Write, color: bool) -> io::Result<()> {
+    let mut process: Option<Process> = None;
+    let mut line = String::new();
+
+    loop {
+        let prompt = if process.is_some() {
+            "text> "
+        } else {
+            "pattern> "
+        };
+        write!(output, "{}", prompt)?;
+        output.flush()?;
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(());
+        }
+
+        let text = line.trim_end_matches(['\n', '\r']);
+
+        let pattern = match text.strip_prefix(':') {
+            Some("quit") => return Ok(()),
+            Some(command) => match command.strip_prefix("pattern ") {
+                Some(pattern) => pattern,
+                None => {
+                    writeln!(output, "Unknown command \":{}\".", command)?;
+                    continue;
+                }
+            },
+            None => match &process {
+                Some(process) => {
+                    print_matches(&mut output, process, text, color)?;
+                    continue;
+                }
+                None => text,
+            },
+        };
+
+        match Process::new(pattern) {
+            Ok(p) => process = Some(p),
+            Err(e) => writeln!(output, "{}", e.with_source(pattern))?,
+        }
+    }
+}
+
+// the result of a test suite, i.e. a pattern file.
+struct TestSuite {
+    name: String,
+
+    // the test cases and their failures, i.e. (name, failure message)
+    cases: Vec<(String, Option<&'static str>)>,
+
+    // the error of reading or compiling the file
+    error: Option<String>,
+}
+
+impl TestSuite {
+    fn failures(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|(_, failure)| failure.is_some())
+            .count()
+    }
+}
+
+// run the test cases of the pattern files in the directory, and write
+// the report, returns whether all tests pass.
+fn run_tests(dir: &Path, mut output: impl Write) -> io::Result<bool> {
+    let mut paths = vec![];
+    find_pattern_files(dir, &mut paths)?;
+    paths.sort();
+
+    let suites: Vec<TestSuite> = paths
+        .iter()
+        .map(|path| {
+            let name = path
+                .strip_prefix(dir)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned();
+            run_test_suite(name, path)
+        })
+        .collect();
+
+    let tests: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+    let failures: usize = suites.iter().map(TestSuite::failures).sum();
+    let errors = suites.iter().filter(|suite| suite.error.is_some()).count();
+
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        output,
+        r#"<testsuites tests="{}" failures="{}" errors="{}">"#,
+        tests, failures, errors
+    )?;
+
+    for suite in &suites {
+        let name = escape_xml(&suite.name);
+        writeln!(
+            output,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}">"#,
+            name,
+            suite.cases.len(),
+            suite.failures(),
+            usize::from(suite.error.is_some())
+        )?;
+
+        for (case_name, failure) in &suite.cases {
+            let case_name = escape_xml(case_name);
+            match failure {
+                None => writeln!(
+                    output,
+                    r#"    <testcase name="{}" classname="{}"/>"#,
+                    case_name, name
+                )?,
+                Some(message) => {
+                    writeln!(
+                        output,
+                        r#"    <testcase name="{}" classname="{}">"#,
+                        case_name, name
+                    )?;
+                    writeln!(output, r#"      <failure message="{}"/>"#, message)?;
+                    writeln!(output, "    </testcase>")?;
+                }
+            }
+        }
+
+        if let Some(error) = &suite.error {
+            writeln!(
+                output,
+                r#"    <testcase name="compile" classname="{}">"#,
+                name
+            )?;
+            writeln!(output, r#"      <error message="{}"/>"#, escape_xml(error))?;
+            writeln!(output, "    </testcase>")?;
+        }
+
+        writeln!(output, "  </testsuite>")?;
+    }
+
+    writeln!(output, "</testsuites>")?;
+    Ok(failures == 0 && errors == 0)
+}
+
+fn run_test_suite(name: String, path: &Path) -> TestSuite {
+    let mut suite = TestSuite {
+        name,
+        cases: vec![],
+        error: None,
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            suite.error = Some(e.to_string());
+            return suite;
+        }
+    };
+
+    let program = match parse_from_str(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            suite.error = Some(e.with_source(&source));
+            return suite;
+        }
+    };
+
+    let failed_tests = match program.run_self_tests(&CompileOptions::default()) {
+        Ok(failed_tests) => failed_tests,
+        Err(e) => {
+            suite.error = Some(e.with_source(&source));
+            return suite;
+        }
+    };
+
+    suite.cases = program
+        .tests
+        .iter()
+        .map(|test| {
+            let failed = failed_tests
+                .iter()
+                .any(|failed| std::ptr::eq(*failed, test));
+            let failure = match (failed, test.should_match) {
+                (false, _) => None,
+                (true, true) => Some("the text does not match the pattern"),
+                (true, false) => Some("the text matches the pattern"),
+            };
+            (test.to_string(), failure)
+        })
+        .collect();
+
+    suite
+}
+
+// find the pattern files in the directory and its subdirectories.
+fn find_pattern_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_pattern_files(&path, paths)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == PATTERN_FILE_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+// escape the text for the XML attributes.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn print_matches(
+    output: &mut impl Write,
+    process: &Process,
+    text: &str,
+    color: bool,
+) -> io::Result<()> {
+    let chars: Vec<char> = text.chars().collect();
+    let slice = |range: &MatchRange| chars[range.start..range.end].iter().collect::<String>();
+
+    let mut matches: Vec<Vec<Option<MatchRange>>> = vec![];
+    let mut instance = process.new_instance(text);
+    instance.scan(0, |match_ranges| {
+        matches.push(match_ranges.to_vec());
+        ControlFlow::Continue(())
+    });
+
+    if matches.is_empty() {
+        return writeln!(output, "no match");
+    }
+
+    // the text with the matched spans highlighted, or marked
+    // by '^' in the next line when the colors are disabled.
+    let mut highlighted = String::new();
+    let mut marks = String::new();
+    let mut position = 0;
+    for match_range in matches.iter().filter_map(|m| m[0]) {
+        highlighted.extend(&chars[position..match_range.start]);
+        marks.push_str(&" ".repeat(match_range.start - position));
+
+        if color {
+            highlighted.push_str(COLOR_MATCH);
+            highlighted.push_str(&slice(&match_range));
+            highlighted.push_str(COLOR_RESET);
+        } else {
+            highlighted.push_str(&slice(&match_range));
+        }
+        marks.push_str(&"^".repeat(match_range.end - match_range.start));
+        position = match_range.end;
+    }
+    highlighted.extend(&chars[position..]);
+
+    writeln!(output, "{}", highlighted)?;
+    if !color {
+        writeln!(output, "{}", marks.trim_end())?;
+    }
+
+    for (index, match_ranges) in matches.iter().enumerate() {
+        for (group, match_range) in match_ranges.iter().enumerate() {
+            let label = match (group, &process.capture_groups()[group].name) {
+                (0, _) => format!("#{}", index),
+                (_, Some(name)) => format!("  {}", name),
+                (_, None) => format!("  ${}", group),
+            };
+
+            match match_range {
+                Some(range) => writeln!(
+                    output,
+                    "{} {}..{} {:?}",
+                    label,
+                    range.start,
+                    range.end,
+                    slice(range)
+                )?,
+                None => writeln!(output, "{} -", label)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use std::fs;
+
+    use super::{repl, run_tests};
+
+    fn run(input: &str, color: bool) -> String {
+        let mut output = vec![];
+        repl(input.as_bytes(), &mut output, color).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_repl() {
+        assert_eq!(
+            run(
+                "name(char_word+, key), '=', capture(char_digit+)?\na=1, b=\nxyz\n",
+                false
+            ),
+            "\
+pattern> text> a=1, b=
+^^^  ^^
+#0 0..3 \"a=1\"
+  key 0..1 \"a\"
+  $2 2..3 \"1\"
+#1 5..7 \"b=\"
+  key 5..6 \"b\"
+  $2 -
+text> no match
+text> \n"
+        );
+
+        // the colors
+        assert_eq!(
+            run("'b'\nabc\n:quit\n", true),
+            "pattern> text> a\x1b[1;32mb\x1b[0mc\n#0 1..2 \"b\"\ntext> "
+        );
+
+        // the commands
+        assert_eq!(
+            run("'a'\n:pattern 'b'\nab\n:foo\n", false),
+            "pattern> text> text> ab\n ^\n#0 1..2 \"b\"\ntext> Unknown command \":foo\".\ntext> \n"
+        );
+
+        // err: invalid pattern
+        let output = run("'a\n'a'\na\n", false);
+        assert!(output.starts_with("pattern> "));
+        assert!(output.ends_with("pattern> text> a\n^\n#0 0..1 \"a\"\ntext> \n"));
+    }
+
+    #[test]
+    fn test_run_tests() {
+        let dir = std::env::temp_dir().join(format!("anreg-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("net")).unwrap();
+        fs::write(
+            dir.join("digits.anreg"),
+            "start, char_digit+, end\nexpect_match(\"123\")\nexpect_no_match(\"1a\")",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("net/host.anreg"),
+            "char_word+, '.', \"com\"\nexpect_match(\"a.com\")\nexpect_no_match(\"b.com\")",
+        )
+        .unwrap();
+        fs::write(dir.join("net/notes.txt"), "not a pattern").unwrap();
+
+        let mut output = vec![];
+        assert!(!run_tests(&dir, &mut output).unwrap());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="4" failures="1" errors="0">
+  <testsuite name="digits.anreg" tests="2" failures="0" errors="0">
+    <testcase name="expect_match(&quot;123&quot;)" classname="digits.anreg"/>
+    <testcase name="expect_no_match(&quot;1a&quot;)" classname="digits.anreg"/>
+  </testsuite>
+  <testsuite name="net/host.anreg" tests="2" failures="1" errors="0">
+    <testcase name="expect_match(&quot;a.com&quot;)" classname="net/host.anreg"/>
+    <testcase name="expect_no_match(&quot;b.com&quot;)" classname="net/host.anreg">
+      <failure message="the text matches the pattern"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+
+        // the file that can not be compiled
+        fs::write(dir.join("net/host.anreg"), "'a', x\nexpect_match(\"a\")").unwrap();
+        let mut output = vec![];
+        assert!(!run_tests(&dir, &mut output).unwrap());
+        let report = String::from_utf8(output).unwrap();
+        assert!(report.contains(r#"<testsuites tests="2" failures="0" errors="1">"#));
+        assert!(report.contains(
+            r#"<testcase name="compile" classname="net/host.anreg">
+      <error message="The back-reference &quot;x&quot; does not refer to any capture group."/>"#
+        ));
+
+        fs::remove_file(dir.join("net/host.anreg")).unwrap();
+        assert!(run_tests(&dir, &mut vec![]).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the analysis of the length of the texts matched by the states, e.g.
+// the pattern `"foo", char_digit{2,}` matches at least 5 chars, so the
+// process does not try the start positions that are less than 5 chars
+// from the end of text, and the pattern `char_digit{2,4}` matches at
+// most 4 chars.
+//
+// the states are walked from the start state to the end state, each
+// repetition (from the "reset" transition to the "exit" transition) is
+// treated as a single edge, whose length is the length of the body
+// times the number of repetitions, e.g.
+//
+// ```text
+//                            body
+//                          /-----------\
+//            /==check==>--o in  out o--==inc==\
+//  in        |             \-----------/       |
+// --o==reset==o head <=========================/
+//            |
+//            \==exit==> o out
+// ```
+//
+// the length is counted by the transitions, i.e. each char and each byte
+// takes one position. the minimum length is a lower bound of the positions
+// in both the text mode and the bytes mode (where a char takes 1 to 4
+// positions), and the maximum length is the upper bound in text mode,
+// see `match_byte_length_bounds` for the upper bound in bytes mode.
+
+use std::collections::HashMap;
+
+use crate::{
+    state::StateSet,
+    transition::{CounterCheckTransition, CounterExitTransition, Transition},
+};
+
+// the minimum and maximum length of a match, the maximum length is
+// `None` if it is unbounded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthBounds {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl LengthBounds {
+    fn exact(length: usize) -> Self {
+        LengthBounds {
+            min: length,
+            max: Some(length),
+        }
+    }
+
+    // the bounds of `self` followed by `other`
+    fn then(&self, other: &LengthBounds) -> Self {
+        LengthBounds {
+            min: self.min.saturating_add(other.min),
+            max: match (self.max, other.max) {
+                (Some(a), Some(b)) => a.checked_add(b),
+                _ => None,
+            },
+        }
+    }
+
+    // the bounds of either `self` or `other`
+    fn either(&self, other: &LengthBounds) -> Self {
+        LengthBounds {
+            min: self.min.min(other.min),
+            max: match (self.max, other.max) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            },
+        }
+    }
+}
+
+// the length bounds of the texts matched by the states, it is `0..`
+// if the states are not compiled by the compiler (e.g. loaded from
+// invalid bytes) and can not be analyzed.
+pub fn match_length_bounds(state_set: &StateSet) -> LengthBounds {
+    analyze_length_bounds(state_set, false)
+}
+
+// like `match_length_bounds`, but the maximum length is the number of
+// bytes in bytes mode, e.g. the char '文' takes 3 bytes, and `char_any`
+// takes up to 4 bytes.
+pub fn match_byte_length_bounds(state_set: &StateSet) -> LengthBounds {
+    analyze_length_bounds(state_set, true)
+}
+
+fn analyze_length_bounds(state_set: &StateSet, bytes_mode: bool) -> LengthBounds {
+    if state_set.is_empty() {
+        return LengthBounds { min: 0, max: None };
+    }
+
+    route_length_bounds(
+        state_set,
+        state_set.start_node_index,
+        state_set.end_node_index,
+        bytes_mode,
+    )
+}
+
+// the length bounds of the texts matched by the route from the start
+// state to the end state, e.g. the route of a look-behind assertion,
+// the maximum length is the number of bytes in bytes mode.
+pub fn route_length_bounds(
+    state_set: &StateSet,
+    start_state_index: usize,
+    end_state_index: usize,
+    bytes_mode: bool,
+) -> LengthBounds {
+    let mut analyzer = Analyzer {
+        state_set,
+        bytes_mode,
+        repetitions: HashMap::new(),
+    };
+
+    analyzer
+        .route_bounds(start_state_index, end_state_index)
+        .ok()
+        .flatten()
+        .unwrap_or(LengthBounds { min: 0, max: None })
+}
+
+// the states can not be analyzed, e.g. there is a loop which is
+// not made by a repetition.
+struct Unanalyzable;
+
+struct Analyzer<'a> {
+    state_set: &'a StateSet,
+    bytes_mode: bool,
+
+    // the bounds of the repetition bodies, indexed by the head state
+    repetitions: HashMap<usize, Option<LengthBounds>>,
+}
+
+impl Analyzer<'_> {
+    // the bounds of the route from the start state to the end state,
+    // it is `None` if the end state is unreachable.
+    fn route_bounds(
+        &mut self,
+        start_state_index: usize,
+        end_state_index: usize,
+    ) -> Result<Option<LengthBounds>, Unanalyzable> {
+        // the edges of the visited states, and the states ordered
+        // by their completion, i.e. the targets come first.
+        let mut edges: HashMap<usize, Vec<(usize, LengthBounds)>> = HashMap::new();
+        let mut completed_states = vec![];
+
+        // whether the visited states are completed
+        let mut visited: HashMap<usize, bool> = HashMap::new();
+
+        // the depth-first walk without recursion, the routes may
+        // be very long, e.g. a long string of chars.
+        let mut stack = vec![(start_state_index, false)];
+        while let Some((state_index, children_done)) = stack.pop() {
+            if children_done {
+                visited.insert(state_index, true);
+                completed_states.push(state_index);
+                continue;
+            }
+
+            match visited.get(&state_index) {
+                Some(true) => continue,
+                Some(false) => {
+                    // the state is still being walked, i.e. a loop
+                    return Err(Unanalyzable);
+                }
+                None => {}
+            }
+            visited.insert(state_index, false);
+
+            let state_edges = if state_index == end_state_index {
+                vec![]
+            } else {
+                self.state_edges(state_index, end_state_index)?
+            };
+
+            stack.push((state_index, true));
+            for (target_state_index, _) in &state_edges {
+                if visited.get(target_state_index) != Some(&true) {
+                    stack.push((*target_state_index, false));
+                }
+            }
+            edges.insert(state_index, state_edges);
+        }
+
+        let mut bounds: HashMap<usize, Option<LengthBounds>> = HashMap::new();
+        for state_index in completed_states {
+            let state_bounds = if state_index == end_state_index {
+                Some(LengthBounds::exact(0))
+            } else {
+                edges[&state_index]
+                    .iter()
+                    .filter_map(|(target_state_index, edge_bounds)| {
+                        bounds[target_state_index].map(|b| edge_bounds.then(&b))
+                    })
+                    .reduce(|a, b| a.either(&b))
+            };
+            bounds.insert(state_index, state_bounds);
+        }
+
+        Ok(bounds[&start_state_index])
+    }
+
+    // the edges of the state, i.e. (target state index, bounds),
+    // a repetition is an edge from its "in" state to its "out" state.
+    fn state_edges(
+        &mut self,
+        state_index: usize,
+        end_state_index: usize,
+    ) -> Result<Vec<(usize, LengthBounds)>, Unanalyzable> {
+        let mut edges = vec![];
+
+        let mut next_link_index = self.state_set.get_first_link_index(state_index);
+        while let Some(link_index) = next_link_index {
+            let (transition, target_state_index, next) = self.state_set.get_link(link_index);
+            next_link_index = next;
+
+            let edge = match transition {
+                Transition::CounterReset(_) => self.repetition_edge(target_state_index)?,
+                // the end of the body of a repetition
+                Transition::CounterInc(_) if target_state_index == end_state_index => {
+                    Some((target_state_index, LengthBounds::exact(0)))
+                }
+                Transition::CounterCheck(_)
+                | Transition::CounterInc(_)
+                | Transition::CounterExit(_) => return Err(Unanalyzable),
+                _ => Some((
+                    target_state_index,
+                    transition_bounds(transition, self.bytes_mode),
+                )),
+            };
+
+            if let Some(edge) = edge {
+                edges.push(edge);
+            }
+        }
+
+        Ok(edges)
+    }
+
+    // the edge from the head of the repetition to its "out" state, it is
+    // `None` if the repetition can not complete.
+    fn repetition_edge(
+        &mut self,
+        head_state_index: usize,
+    ) -> Result<Option<(usize, LengthBounds)>, Unanalyzable> {
+        let mut body = None;
+        let mut exit = None;
+
+        let mut next_link_index = self.state_set.get_first_link_index(head_state_index);
+        while let Some(link_index) = next_link_index {
+            let (transition, target_state_index, next) = self.state_set.get_link(link_index);
+            next_link_index = next;
+
+            match transition {
+                Transition::CounterCheck(CounterCheckTransition { max }) => {
+                    body = Some((target_state_index, *max))
+                }
+                Transition::CounterExit(CounterExitTransition { min }) => {
+                    exit = Some((target_state_index, *min))
+                }
+                _ => return Err(Unanalyzable),
+            }
+        }
+
+        let (Some((body_state_index, max)), Some((out_state_index, min))) = (body, exit) else {
+            return Err(Unanalyzable);
+        };
+
+        let body_bounds = match self.repetitions.get(&head_state_index) {
+            Some(bounds) => *bounds,
+            None => {
+                // the body ends with the "inc" transition to the head
+                let bounds = self.route_bounds(body_state_index, head_state_index)?;
+                self.repetitions.insert(head_state_index, bounds);
+                bounds
+            }
+        };
+
+        let bounds = match body_bounds {
+            Some(body_bounds) => LengthBounds {
+                min: body_bounds.min.saturating_mul(min),
+                max: match (body_bounds.max, max) {
+                    (Some(0), _) => Some(0),
+                    (Some(length), Some(count)) => length.checked_mul(count),
+                    _ => None,
+                },
+            },
+            None if min == 0 => LengthBounds::exact(0),
+            None => return Ok(None), // the body can not complete
+        };
+
+        Ok(Some((out_state_index, bounds)))
+    }
+}
+
+// the bounds of the text matched by the transition, the counter
+// transitions are handled by the analyzer.
+fn transition_bounds(transition: &Transition, bytes_mode: bool) -> LengthBounds {
+    let bounds = match transition {
+        // a grapheme cluster may consist of any number of chars
+        Transition::SpecialChar(t) if t.grapheme => LengthBounds { min: 1, max: None },
+        Transition::CharSet(t) if t.negative && t.grapheme => LengthBounds { min: 1, max: None },
+        Transition::Char(_)
+        | Transition::SpecialChar(_)
+        | Transition::CharSet(_)
+        | Transition::Byte(_)
+        | Transition::ByteRange(_) => LengthBounds::exact(1),
+        Transition::String(t) => LengthBounds::exact(t.chars.len()),
+        // a text char may be decomposed into many chars
+        Transition::NormalizedString(t) => LengthBounds {
+            min: t.chars.len().min(1),
+            max: Some(t.chars.len()),
+        },
+        // the length of the captured text is unknown
+        Transition::BackReference(_) => LengthBounds { min: 0, max: None },
+        _ => LengthBounds::exact(0),
+    };
+
+    if !bytes_mode || bounds.max.is_none() {
+        return bounds;
+    }
+
+    // the chars that ignore case may match the chars of other lengths,
+    // e.g. 'k' matches the Kelvin sign '\u{212a}' which takes 3 bytes.
+    let max = match transition {
+        Transition::Char(t) if !t.ignore_case => t.character.len_utf8(),
+        Transition::String(t) if !t.ignore_case => t.chars.iter().map(|c| c.len_utf8()).sum(),
+        Transition::Byte(_) | Transition::ByteRange(_) => 1,
+        _ => bounds.max.map_or(0, |max| max * 4),
+    };
+
+    LengthBounds {
+        min: bounds.min,
+        max: Some(max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::compiler::compile_from_str;
+
+    use super::{match_byte_length_bounds, match_length_bounds};
+
+    #[test]
+    fn test_match_length_bounds() {
+        let bounds = |s: &str| {
+            let bounds = match_length_bounds(&compile_from_str(s).unwrap());
+            (bounds.min, bounds.max)
+        };
+
+        assert_eq!(bounds(r#"'a'"#), (1, Some(1)));
+        assert_eq!(bounds(r#"'a', "foo", char_digit"#), (5, Some(5)));
+        assert_eq!(bounds(r#"start, 'a'.is_before("bcd"), end"#), (1, Some(1)));
+        assert_eq!(bounds(r#""foo" || ('a', 'b') || "barz""#), (2, Some(4)));
+        assert_eq!(bounds(r#"'a'?, 'b'*"#), (0, None));
+        assert_eq!(bounds(r#"'a'?, 'b'??"#), (0, Some(2)));
+        assert_eq!(bounds(r#"name('a', x), x"#), (1, None));
+        assert_eq!(bounds(r#"'a'+, "bc"{3}, 'd'{2,5}"#), (9, None));
+        assert_eq!(bounds(r#""bc"{3}, 'd'{2,5}?"#), (8, Some(11)));
+        assert_eq!(bounds(r#"('a', 'b'{2,}){3,}?"#), (9, None));
+        assert_eq!(bounds(r#"(('a' || "bc"){2}, "xyz"?){2}"#), (4, Some(14)));
+        assert_eq!(
+            bounds(r#"name(char_word+, x), '@', capture(char_word+)"#),
+            (3, None)
+        );
+        assert_eq!(bounds(r#"normalized("cafe\u{301}")"#), (1, Some(5)));
+        assert_eq!(bounds(r#"number_range(10, 255)"#), (2, Some(3)));
+        assert_eq!(bounds(r#"()"#), (0, Some(0)));
+        assert_eq!(bounds(r#"grapheme_any, char_any"#), (2, None));
+        assert_eq!(bounds(r#"options(grapheme), ['a'], !['b']"#), (2, None));
+
+        // the repetition of empty body
+        assert_eq!(bounds(r#"(start)*"#), (0, Some(0)));
+
+        // the long route
+        let source = vec!["'a'"; 5000].join(",");
+        assert_eq!(bounds(&source), (5000, Some(5000)));
+    }
+
+    #[test]
+    fn test_match_byte_length_bounds() {
+        let bounds = |s: &str| {
+            let bounds = match_byte_length_bounds(&compile_from_str(s).unwrap());
+            (bounds.min, bounds.max)
+        };
+
+        assert_eq!(bounds(r#"'a', "文字""#), (3, Some(7)));
+        assert_eq!(
+            bounds(r#"byte(0x41){2,3}, byte_range(0x80..0xff)"#),
+            (3, Some(4))
+        );
+        assert_eq!(bounds(r#"char_any, char_digit{2}"#), (3, Some(12)));
+        assert_eq!(bounds(r#""ab" || 'x'+"#), (1, None));
+        assert_eq!(bounds(r#"start, end"#), (0, Some(0)));
+        assert_eq!(bounds(r#"name('a', x), x"#), (1, None));
+    }
+}
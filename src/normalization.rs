@@ -0,0 +1,565 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// Canonical decomposition (NFD) of the precomposed Latin letters.
+//
+// The table covers the blocks "Latin-1 Supplement", "Latin Extended-A",
+// "Latin Extended-B" and "Latin Extended Additional", each entry is
+// decomposed recursively, e.g. 'ǻ' -> 'a' + '\u{30a}' + '\u{301}'.
+//
+// Note that the canonical reordering of combining marks is not performed,
+// the combining marks are compared in the order they are stored.
+//
+// ref:
+// https://www.unicode.org/reports/tr15/
+// https://www.unicode.org/Public/UCD/latest/ucd/UnicodeData.txt
+
+// return the decomposed chars of the specified char,
+// or None if the char has no canonical decomposition.
+pub fn decompose_char(c: char) -> Option<&'static str> {
+    DECOMPOSITION_TABLE
+        .binary_search_by(|(precomposed, _)| precomposed.cmp(&c))
+        .ok()
+        .map(|idx| DECOMPOSITION_TABLE[idx].1)
+}
+
+pub fn decompose_str(s: &str) -> Vec<char> {
+    let mut chars = vec![];
+    for c in s.chars() {
+        match decompose_char(c) {
+            Some(decomposed) => chars.extend(decomposed.chars()),
+            None => chars.push(c),
+        }
+    }
+    chars
+}
+
+// the block "Combining Diacritical Marks"
+pub fn is_combining_mark(c: char) -> bool {
+    ('\u{300}'..='\u{36f}').contains(&c)
+}
+
+const DECOMPOSITION_TABLE: [(char, &str); 497] = [
+    ('\u{c0}', "A\u{300}"),          // À
+    ('\u{c1}', "A\u{301}"),          // Á
+    ('\u{c2}', "A\u{302}"),          // Â
+    ('\u{c3}', "A\u{303}"),          // Ã
+    ('\u{c4}', "A\u{308}"),          // Ä
+    ('\u{c5}', "A\u{30a}"),          // Å
+    ('\u{c7}', "C\u{327}"),          // Ç
+    ('\u{c8}', "E\u{300}"),          // È
+    ('\u{c9}', "E\u{301}"),          // É
+    ('\u{ca}', "E\u{302}"),          // Ê
+    ('\u{cb}', "E\u{308}"),          // Ë
+    ('\u{cc}', "I\u{300}"),          // Ì
+    ('\u{cd}', "I\u{301}"),          // Í
+    ('\u{ce}', "I\u{302}"),          // Î
+    ('\u{cf}', "I\u{308}"),          // Ï
+    ('\u{d1}', "N\u{303}"),          // Ñ
+    ('\u{d2}', "O\u{300}"),          // Ò
+    ('\u{d3}', "O\u{301}"),          // Ó
+    ('\u{d4}', "O\u{302}"),          // Ô
+    ('\u{d5}', "O\u{303}"),          // Õ
+    ('\u{d6}', "O\u{308}"),          // Ö
+    ('\u{d9}', "U\u{300}"),          // Ù
+    ('\u{da}', "U\u{301}"),          // Ú
+    ('\u{db}', "U\u{302}"),          // Û
+    ('\u{dc}', "U\u{308}"),          // Ü
+    ('\u{dd}', "Y\u{301}"),          // Ý
+    ('\u{e0}', "a\u{300}"),          // à
+    ('\u{e1}', "a\u{301}"),          // á
+    ('\u{e2}', "a\u{302}"),          // â
+    ('\u{e3}', "a\u{303}"),          // ã
+    ('\u{e4}', "a\u{308}"),          // ä
+    ('\u{e5}', "a\u{30a}"),          // å
+    ('\u{e7}', "c\u{327}"),          // ç
+    ('\u{e8}', "e\u{300}"),          // è
+    ('\u{e9}', "e\u{301}"),          // é
+    ('\u{ea}', "e\u{302}"),          // ê
+    ('\u{eb}', "e\u{308}"),          // ë
+    ('\u{ec}', "i\u{300}"),          // ì
+    ('\u{ed}', "i\u{301}"),          // í
+    ('\u{ee}', "i\u{302}"),          // î
+    ('\u{ef}', "i\u{308}"),          // ï
+    ('\u{f1}', "n\u{303}"),          // ñ
+    ('\u{f2}', "o\u{300}"),          // ò
+    ('\u{f3}', "o\u{301}"),          // ó
+    ('\u{f4}', "o\u{302}"),          // ô
+    ('\u{f5}', "o\u{303}"),          // õ
+    ('\u{f6}', "o\u{308}"),          // ö
+    ('\u{f9}', "u\u{300}"),          // ù
+    ('\u{fa}', "u\u{301}"),          // ú
+    ('\u{fb}', "u\u{302}"),          // û
+    ('\u{fc}', "u\u{308}"),          // ü
+    ('\u{fd}', "y\u{301}"),          // ý
+    ('\u{ff}', "y\u{308}"),          // ÿ
+    ('\u{100}', "A\u{304}"),         // Ā
+    ('\u{101}', "a\u{304}"),         // ā
+    ('\u{102}', "A\u{306}"),         // Ă
+    ('\u{103}', "a\u{306}"),         // ă
+    ('\u{104}', "A\u{328}"),         // Ą
+    ('\u{105}', "a\u{328}"),         // ą
+    ('\u{106}', "C\u{301}"),         // Ć
+    ('\u{107}', "c\u{301}"),         // ć
+    ('\u{108}', "C\u{302}"),         // Ĉ
+    ('\u{109}', "c\u{302}"),         // ĉ
+    ('\u{10a}', "C\u{307}"),         // Ċ
+    ('\u{10b}', "c\u{307}"),         // ċ
+    ('\u{10c}', "C\u{30c}"),         // Č
+    ('\u{10d}', "c\u{30c}"),         // č
+    ('\u{10e}', "D\u{30c}"),         // Ď
+    ('\u{10f}', "d\u{30c}"),         // ď
+    ('\u{112}', "E\u{304}"),         // Ē
+    ('\u{113}', "e\u{304}"),         // ē
+    ('\u{114}', "E\u{306}"),         // Ĕ
+    ('\u{115}', "e\u{306}"),         // ĕ
+    ('\u{116}', "E\u{307}"),         // Ė
+    ('\u{117}', "e\u{307}"),         // ė
+    ('\u{118}', "E\u{328}"),         // Ę
+    ('\u{119}', "e\u{328}"),         // ę
+    ('\u{11a}', "E\u{30c}"),         // Ě
+    ('\u{11b}', "e\u{30c}"),         // ě
+    ('\u{11c}', "G\u{302}"),         // Ĝ
+    ('\u{11d}', "g\u{302}"),         // ĝ
+    ('\u{11e}', "G\u{306}"),         // Ğ
+    ('\u{11f}', "g\u{306}"),         // ğ
+    ('\u{120}', "G\u{307}"),         // Ġ
+    ('\u{121}', "g\u{307}"),         // ġ
+    ('\u{122}', "G\u{327}"),         // Ģ
+    ('\u{123}', "g\u{327}"),         // ģ
+    ('\u{124}', "H\u{302}"),         // Ĥ
+    ('\u{125}', "h\u{302}"),         // ĥ
+    ('\u{128}', "I\u{303}"),         // Ĩ
+    ('\u{129}', "i\u{303}"),         // ĩ
+    ('\u{12a}', "I\u{304}"),         // Ī
+    ('\u{12b}', "i\u{304}"),         // ī
+    ('\u{12c}', "I\u{306}"),         // Ĭ
+    ('\u{12d}', "i\u{306}"),         // ĭ
+    ('\u{12e}', "I\u{328}"),         // Į
+    ('\u{12f}', "i\u{328}"),         // į
+    ('\u{130}', "I\u{307}"),         // İ
+    ('\u{134}', "J\u{302}"),         // Ĵ
+    ('\u{135}', "j\u{302}"),         // ĵ
+    ('\u{136}', "K\u{327}"),         // Ķ
+    ('\u{137}', "k\u{327}"),         // ķ
+    ('\u{139}', "L\u{301}"),         // Ĺ
+    ('\u{13a}', "l\u{301}"),         // ĺ
+    ('\u{13b}', "L\u{327}"),         // Ļ
+    ('\u{13c}', "l\u{327}"),         // ļ
+    ('\u{13d}', "L\u{30c}"),         // Ľ
+    ('\u{13e}', "l\u{30c}"),         // ľ
+    ('\u{143}', "N\u{301}"),         // Ń
+    ('\u{144}', "n\u{301}"),         // ń
+    ('\u{145}', "N\u{327}"),         // Ņ
+    ('\u{146}', "n\u{327}"),         // ņ
+    ('\u{147}', "N\u{30c}"),         // Ň
+    ('\u{148}', "n\u{30c}"),         // ň
+    ('\u{14c}', "O\u{304}"),         // Ō
+    ('\u{14d}', "o\u{304}"),         // ō
+    ('\u{14e}', "O\u{306}"),         // Ŏ
+    ('\u{14f}', "o\u{306}"),         // ŏ
+    ('\u{150}', "O\u{30b}"),         // Ő
+    ('\u{151}', "o\u{30b}"),         // ő
+    ('\u{154}', "R\u{301}"),         // Ŕ
+    ('\u{155}', "r\u{301}"),         // ŕ
+    ('\u{156}', "R\u{327}"),         // Ŗ
+    ('\u{157}', "r\u{327}"),         // ŗ
+    ('\u{158}', "R\u{30c}"),         // Ř
+    ('\u{159}', "r\u{30c}"),         // ř
+    ('\u{15a}', "S\u{301}"),         // Ś
+    ('\u{15b}', "s\u{301}"),         // ś
+    ('\u{15c}', "S\u{302}"),         // Ŝ
+    ('\u{15d}', "s\u{302}"),         // ŝ
+    ('\u{15e}', "S\u{327}"),         // Ş
+    ('\u{15f}', "s\u{327}"),         // ş
+    ('\u{160}', "S\u{30c}"),         // Š
+    ('\u{161}', "s\u{30c}"),         // š
+    ('\u{162}', "T\u{327}"),         // Ţ
+    ('\u{163}', "t\u{327}"),         // ţ
+    ('\u{164}', "T\u{30c}"),         // Ť
+    ('\u{165}', "t\u{30c}"),         // ť
+    ('\u{168}', "U\u{303}"),         // Ũ
+    ('\u{169}', "u\u{303}"),         // ũ
+    ('\u{16a}', "U\u{304}"),         // Ū
+    ('\u{16b}', "u\u{304}"),         // ū
+    ('\u{16c}', "U\u{306}"),         // Ŭ
+    ('\u{16d}', "u\u{306}"),         // ŭ
+    ('\u{16e}', "U\u{30a}"),         // Ů
+    ('\u{16f}', "u\u{30a}"),         // ů
+    ('\u{170}', "U\u{30b}"),         // Ű
+    ('\u{171}', "u\u{30b}"),         // ű
+    ('\u{172}', "U\u{328}"),         // Ų
+    ('\u{173}', "u\u{328}"),         // ų
+    ('\u{174}', "W\u{302}"),         // Ŵ
+    ('\u{175}', "w\u{302}"),         // ŵ
+    ('\u{176}', "Y\u{302}"),         // Ŷ
+    ('\u{177}', "y\u{302}"),         // ŷ
+    ('\u{178}', "Y\u{308}"),         // Ÿ
+    ('\u{179}', "Z\u{301}"),         // Ź
+    ('\u{17a}', "z\u{301}"),         // ź
+    ('\u{17b}', "Z\u{307}"),         // Ż
+    ('\u{17c}', "z\u{307}"),         // ż
+    ('\u{17d}', "Z\u{30c}"),         // Ž
+    ('\u{17e}', "z\u{30c}"),         // ž
+    ('\u{1a0}', "O\u{31b}"),         // Ơ
+    ('\u{1a1}', "o\u{31b}"),         // ơ
+    ('\u{1af}', "U\u{31b}"),         // Ư
+    ('\u{1b0}', "u\u{31b}"),         // ư
+    ('\u{1cd}', "A\u{30c}"),         // Ǎ
+    ('\u{1ce}', "a\u{30c}"),         // ǎ
+    ('\u{1cf}', "I\u{30c}"),         // Ǐ
+    ('\u{1d0}', "i\u{30c}"),         // ǐ
+    ('\u{1d1}', "O\u{30c}"),         // Ǒ
+    ('\u{1d2}', "o\u{30c}"),         // ǒ
+    ('\u{1d3}', "U\u{30c}"),         // Ǔ
+    ('\u{1d4}', "u\u{30c}"),         // ǔ
+    ('\u{1d5}', "U\u{308}\u{304}"),  // Ǖ
+    ('\u{1d6}', "u\u{308}\u{304}"),  // ǖ
+    ('\u{1d7}', "U\u{308}\u{301}"),  // Ǘ
+    ('\u{1d8}', "u\u{308}\u{301}"),  // ǘ
+    ('\u{1d9}', "U\u{308}\u{30c}"),  // Ǚ
+    ('\u{1da}', "u\u{308}\u{30c}"),  // ǚ
+    ('\u{1db}', "U\u{308}\u{300}"),  // Ǜ
+    ('\u{1dc}', "u\u{308}\u{300}"),  // ǜ
+    ('\u{1de}', "A\u{308}\u{304}"),  // Ǟ
+    ('\u{1df}', "a\u{308}\u{304}"),  // ǟ
+    ('\u{1e0}', "A\u{307}\u{304}"),  // Ǡ
+    ('\u{1e1}', "a\u{307}\u{304}"),  // ǡ
+    ('\u{1e2}', "\u{c6}\u{304}"),    // Ǣ
+    ('\u{1e3}', "\u{e6}\u{304}"),    // ǣ
+    ('\u{1e6}', "G\u{30c}"),         // Ǧ
+    ('\u{1e7}', "g\u{30c}"),         // ǧ
+    ('\u{1e8}', "K\u{30c}"),         // Ǩ
+    ('\u{1e9}', "k\u{30c}"),         // ǩ
+    ('\u{1ea}', "O\u{328}"),         // Ǫ
+    ('\u{1eb}', "o\u{328}"),         // ǫ
+    ('\u{1ec}', "O\u{328}\u{304}"),  // Ǭ
+    ('\u{1ed}', "o\u{328}\u{304}"),  // ǭ
+    ('\u{1ee}', "\u{1b7}\u{30c}"),   // Ǯ
+    ('\u{1ef}', "\u{292}\u{30c}"),   // ǯ
+    ('\u{1f0}', "j\u{30c}"),         // ǰ
+    ('\u{1f4}', "G\u{301}"),         // Ǵ
+    ('\u{1f5}', "g\u{301}"),         // ǵ
+    ('\u{1f8}', "N\u{300}"),         // Ǹ
+    ('\u{1f9}', "n\u{300}"),         // ǹ
+    ('\u{1fa}', "A\u{30a}\u{301}"),  // Ǻ
+    ('\u{1fb}', "a\u{30a}\u{301}"),  // ǻ
+    ('\u{1fc}', "\u{c6}\u{301}"),    // Ǽ
+    ('\u{1fd}', "\u{e6}\u{301}"),    // ǽ
+    ('\u{1fe}', "\u{d8}\u{301}"),    // Ǿ
+    ('\u{1ff}', "\u{f8}\u{301}"),    // ǿ
+    ('\u{200}', "A\u{30f}"),         // Ȁ
+    ('\u{201}', "a\u{30f}"),         // ȁ
+    ('\u{202}', "A\u{311}"),         // Ȃ
+    ('\u{203}', "a\u{311}"),         // ȃ
+    ('\u{204}', "E\u{30f}"),         // Ȅ
+    ('\u{205}', "e\u{30f}"),         // ȅ
+    ('\u{206}', "E\u{311}"),         // Ȇ
+    ('\u{207}', "e\u{311}"),         // ȇ
+    ('\u{208}', "I\u{30f}"),         // Ȉ
+    ('\u{209}', "i\u{30f}"),         // ȉ
+    ('\u{20a}', "I\u{311}"),         // Ȋ
+    ('\u{20b}', "i\u{311}"),         // ȋ
+    ('\u{20c}', "O\u{30f}"),         // Ȍ
+    ('\u{20d}', "o\u{30f}"),         // ȍ
+    ('\u{20e}', "O\u{311}"),         // Ȏ
+    ('\u{20f}', "o\u{311}"),         // ȏ
+    ('\u{210}', "R\u{30f}"),         // Ȑ
+    ('\u{211}', "r\u{30f}"),         // ȑ
+    ('\u{212}', "R\u{311}"),         // Ȓ
+    ('\u{213}', "r\u{311}"),         // ȓ
+    ('\u{214}', "U\u{30f}"),         // Ȕ
+    ('\u{215}', "u\u{30f}"),         // ȕ
+    ('\u{216}', "U\u{311}"),         // Ȗ
+    ('\u{217}', "u\u{311}"),         // ȗ
+    ('\u{218}', "S\u{326}"),         // Ș
+    ('\u{219}', "s\u{326}"),         // ș
+    ('\u{21a}', "T\u{326}"),         // Ț
+    ('\u{21b}', "t\u{326}"),         // ț
+    ('\u{21e}', "H\u{30c}"),         // Ȟ
+    ('\u{21f}', "h\u{30c}"),         // ȟ
+    ('\u{226}', "A\u{307}"),         // Ȧ
+    ('\u{227}', "a\u{307}"),         // ȧ
+    ('\u{228}', "E\u{327}"),         // Ȩ
+    ('\u{229}', "e\u{327}"),         // ȩ
+    ('\u{22a}', "O\u{308}\u{304}"),  // Ȫ
+    ('\u{22b}', "o\u{308}\u{304}"),  // ȫ
+    ('\u{22c}', "O\u{303}\u{304}"),  // Ȭ
+    ('\u{22d}', "o\u{303}\u{304}"),  // ȭ
+    ('\u{22e}', "O\u{307}"),         // Ȯ
+    ('\u{22f}', "o\u{307}"),         // ȯ
+    ('\u{230}', "O\u{307}\u{304}"),  // Ȱ
+    ('\u{231}', "o\u{307}\u{304}"),  // ȱ
+    ('\u{232}', "Y\u{304}"),         // Ȳ
+    ('\u{233}', "y\u{304}"),         // ȳ
+    ('\u{1e00}', "A\u{325}"),        // Ḁ
+    ('\u{1e01}', "a\u{325}"),        // ḁ
+    ('\u{1e02}', "B\u{307}"),        // Ḃ
+    ('\u{1e03}', "b\u{307}"),        // ḃ
+    ('\u{1e04}', "B\u{323}"),        // Ḅ
+    ('\u{1e05}', "b\u{323}"),        // ḅ
+    ('\u{1e06}', "B\u{331}"),        // Ḇ
+    ('\u{1e07}', "b\u{331}"),        // ḇ
+    ('\u{1e08}', "C\u{327}\u{301}"), // Ḉ
+    ('\u{1e09}', "c\u{327}\u{301}"), // ḉ
+    ('\u{1e0a}', "D\u{307}"),        // Ḋ
+    ('\u{1e0b}', "d\u{307}"),        // ḋ
+    ('\u{1e0c}', "D\u{323}"),        // Ḍ
+    ('\u{1e0d}', "d\u{323}"),        // ḍ
+    ('\u{1e0e}', "D\u{331}"),        // Ḏ
+    ('\u{1e0f}', "d\u{331}"),        // ḏ
+    ('\u{1e10}', "D\u{327}"),        // Ḑ
+    ('\u{1e11}', "d\u{327}"),        // ḑ
+    ('\u{1e12}', "D\u{32d}"),        // Ḓ
+    ('\u{1e13}', "d\u{32d}"),        // ḓ
+    ('\u{1e14}', "E\u{304}\u{300}"), // Ḕ
+    ('\u{1e15}', "e\u{304}\u{300}"), // ḕ
+    ('\u{1e16}', "E\u{304}\u{301}"), // Ḗ
+    ('\u{1e17}', "e\u{304}\u{301}"), // ḗ
+    ('\u{1e18}', "E\u{32d}"),        // Ḙ
+    ('\u{1e19}', "e\u{32d}"),        // ḙ
+    ('\u{1e1a}', "E\u{330}"),        // Ḛ
+    ('\u{1e1b}', "e\u{330}"),        // ḛ
+    ('\u{1e1c}', "E\u{327}\u{306}"), // Ḝ
+    ('\u{1e1d}', "e\u{327}\u{306}"), // ḝ
+    ('\u{1e1e}', "F\u{307}"),        // Ḟ
+    ('\u{1e1f}', "f\u{307}"),        // ḟ
+    ('\u{1e20}', "G\u{304}"),        // Ḡ
+    ('\u{1e21}', "g\u{304}"),        // ḡ
+    ('\u{1e22}', "H\u{307}"),        // Ḣ
+    ('\u{1e23}', "h\u{307}"),        // ḣ
+    ('\u{1e24}', "H\u{323}"),        // Ḥ
+    ('\u{1e25}', "h\u{323}"),        // ḥ
+    ('\u{1e26}', "H\u{308}"),        // Ḧ
+    ('\u{1e27}', "h\u{308}"),        // ḧ
+    ('\u{1e28}', "H\u{327}"),        // Ḩ
+    ('\u{1e29}', "h\u{327}"),        // ḩ
+    ('\u{1e2a}', "H\u{32e}"),        // Ḫ
+    ('\u{1e2b}', "h\u{32e}"),        // ḫ
+    ('\u{1e2c}', "I\u{330}"),        // Ḭ
+    ('\u{1e2d}', "i\u{330}"),        // ḭ
+    ('\u{1e2e}', "I\u{308}\u{301}"), // Ḯ
+    ('\u{1e2f}', "i\u{308}\u{301}"), // ḯ
+    ('\u{1e30}', "K\u{301}"),        // Ḱ
+    ('\u{1e31}', "k\u{301}"),        // ḱ
+    ('\u{1e32}', "K\u{323}"),        // Ḳ
+    ('\u{1e33}', "k\u{323}"),        // ḳ
+    ('\u{1e34}', "K\u{331}"),        // Ḵ
+    ('\u{1e35}', "k\u{331}"),        // ḵ
+    ('\u{1e36}', "L\u{323}"),        // Ḷ
+    ('\u{1e37}', "l\u{323}"),        // ḷ
+    ('\u{1e38}', "L\u{323}\u{304}"), // Ḹ
+    ('\u{1e39}', "l\u{323}\u{304}"), // ḹ
+    ('\u{1e3a}', "L\u{331}"),        // Ḻ
+    ('\u{1e3b}', "l\u{331}"),        // ḻ
+    ('\u{1e3c}', "L\u{32d}"),        // Ḽ
+    ('\u{1e3d}', "l\u{32d}"),        // ḽ
+    ('\u{1e3e}', "M\u{301}"),        // Ḿ
+    ('\u{1e3f}', "m\u{301}"),        // ḿ
+    ('\u{1e40}', "M\u{307}"),        // Ṁ
+    ('\u{1e41}', "m\u{307}"),        // ṁ
+    ('\u{1e42}', "M\u{323}"),        // Ṃ
+    ('\u{1e43}', "m\u{323}"),        // ṃ
+    ('\u{1e44}', "N\u{307}"),        // Ṅ
+    ('\u{1e45}', "n\u{307}"),        // ṅ
+    ('\u{1e46}', "N\u{323}"),        // Ṇ
+    ('\u{1e47}', "n\u{323}"),        // ṇ
+    ('\u{1e48}', "N\u{331}"),        // Ṉ
+    ('\u{1e49}', "n\u{331}"),        // ṉ
+    ('\u{1e4a}', "N\u{32d}"),        // Ṋ
+    ('\u{1e4b}', "n\u{32d}"),        // ṋ
+    ('\u{1e4c}', "O\u{303}\u{301}"), // Ṍ
+    ('\u{1e4d}', "o\u{303}\u{301}"), // ṍ
+    ('\u{1e4e}', "O\u{303}\u{308}"), // Ṏ
+    ('\u{1e4f}', "o\u{303}\u{308}"), // ṏ
+    ('\u{1e50}', "O\u{304}\u{300}"), // Ṑ
+    ('\u{1e51}', "o\u{304}\u{300}"), // ṑ
+    ('\u{1e52}', "O\u{304}\u{301}"), // Ṓ
+    ('\u{1e53}', "o\u{304}\u{301}"), // ṓ
+    ('\u{1e54}', "P\u{301}"),        // Ṕ
+    ('\u{1e55}', "p\u{301}"),        // ṕ
+    ('\u{1e56}', "P\u{307}"),        // Ṗ
+    ('\u{1e57}', "p\u{307}"),        // ṗ
+    ('\u{1e58}', "R\u{307}"),        // Ṙ
+    ('\u{1e59}', "r\u{307}"),        // ṙ
+    ('\u{1e5a}', "R\u{323}"),        // Ṛ
+    ('\u{1e5b}', "r\u{323}"),        // ṛ
+    ('\u{1e5c}', "R\u{323}\u{304}"), // Ṝ
+    ('\u{1e5d}', "r\u{323}\u{304}"), // ṝ
+    ('\u{1e5e}', "R\u{331}"),        // Ṟ
+    ('\u{1e5f}', "r\u{331}"),        // ṟ
+    ('\u{1e60}', "S\u{307}"),        // Ṡ
+    ('\u{1e61}', "s\u{307}"),        // ṡ
+    ('\u{1e62}', "S\u{323}"),        // Ṣ
+    ('\u{1e63}', "s\u{323}"),        // ṣ
+    ('\u{1e64}', "S\u{301}\u{307}"), // Ṥ
+    ('\u{1e65}', "s\u{301}\u{307}"), // ṥ
+    ('\u{1e66}', "S\u{30c}\u{307}"), // Ṧ
+    ('\u{1e67}', "s\u{30c}\u{307}"), // ṧ
+    ('\u{1e68}', "S\u{323}\u{307}"), // Ṩ
+    ('\u{1e69}', "s\u{323}\u{307}"), // ṩ
+    ('\u{1e6a}', "T\u{307}"),        // Ṫ
+    ('\u{1e6b}', "t\u{307}"),        // ṫ
+    ('\u{1e6c}', "T\u{323}"),        // Ṭ
+    ('\u{1e6d}', "t\u{323}"),        // ṭ
+    ('\u{1e6e}', "T\u{331}"),        // Ṯ
+    ('\u{1e6f}', "t\u{331}"),        // ṯ
+    ('\u{1e70}', "T\u{32d}"),        // Ṱ
+    ('\u{1e71}', "t\u{32d}"),        // ṱ
+    ('\u{1e72}', "U\u{324}"),        // Ṳ
+    ('\u{1e73}', "u\u{324}"),        // ṳ
+    ('\u{1e74}', "U\u{330}"),        // Ṵ
+    ('\u{1e75}', "u\u{330}"),        // ṵ
+    ('\u{1e76}', "U\u{32d}"),        // Ṷ
+    ('\u{1e77}', "u\u{32d}"),        // ṷ
+    ('\u{1e78}', "U\u{303}\u{301}"), // Ṹ
+    ('\u{1e79}', "u\u{303}\u{301}"), // ṹ
+    ('\u{1e7a}', "U\u{304}\u{308}"), // Ṻ
+    ('\u{1e7b}', "u\u{304}\u{308}"), // ṻ
+    ('\u{1e7c}', "V\u{303}"),        // Ṽ
+    ('\u{1e7d}', "v\u{303}"),        // ṽ
+    ('\u{1e7e}', "V\u{323}"),        // Ṿ
+    ('\u{1e7f}', "v\u{323}"),        // ṿ
+    ('\u{1e80}', "W\u{300}"),        // Ẁ
+    ('\u{1e81}', "w\u{300}"),        // ẁ
+    ('\u{1e82}', "W\u{301}"),        // Ẃ
+    ('\u{1e83}', "w\u{301}"),        // ẃ
+    ('\u{1e84}', "W\u{308}"),        // Ẅ
+    ('\u{1e85}', "w\u{308}"),        // ẅ
+    ('\u{1e86}', "W\u{307}"),        // Ẇ
+    ('\u{1e87}', "w\u{307}"),        // ẇ
+    ('\u{1e88}', "W\u{323}"),        // Ẉ
+    ('\u{1e89}', "w\u{323}"),        // ẉ
+    ('\u{1e8a}', "X\u{307}"),        // Ẋ
+    ('\u{1e8b}', "x\u{307}"),        // ẋ
+    ('\u{1e8c}', "X\u{308}"),        // Ẍ
+    ('\u{1e8d}', "x\u{308}"),        // ẍ
+    ('\u{1e8e}', "Y\u{307}"),        // Ẏ
+    ('\u{1e8f}', "y\u{307}"),        // ẏ
+    ('\u{1e90}', "Z\u{302}"),        // Ẑ
+    ('\u{1e91}', "z\u{302}"),        // ẑ
+    ('\u{1e92}', "Z\u{323}"),        // Ẓ
+    ('\u{1e93}', "z\u{323}"),        // ẓ
+    ('\u{1e94}', "Z\u{331}"),        // Ẕ
+    ('\u{1e95}', "z\u{331}"),        // ẕ
+    ('\u{1e96}', "h\u{331}"),        // ẖ
+    ('\u{1e97}', "t\u{308}"),        // ẗ
+    ('\u{1e98}', "w\u{30a}"),        // ẘ
+    ('\u{1e99}', "y\u{30a}"),        // ẙ
+    ('\u{1e9b}', "\u{17f}\u{307}"),  // ẛ
+    ('\u{1ea0}', "A\u{323}"),        // Ạ
+    ('\u{1ea1}', "a\u{323}"),        // ạ
+    ('\u{1ea2}', "A\u{309}"),        // Ả
+    ('\u{1ea3}', "a\u{309}"),        // ả
+    ('\u{1ea4}', "A\u{302}\u{301}"), // Ấ
+    ('\u{1ea5}', "a\u{302}\u{301}"), // ấ
+    ('\u{1ea6}', "A\u{302}\u{300}"), // Ầ
+    ('\u{1ea7}', "a\u{302}\u{300}"), // ầ
+    ('\u{1ea8}', "A\u{302}\u{309}"), // Ẩ
+    ('\u{1ea9}', "a\u{302}\u{309}"), // ẩ
+    ('\u{1eaa}', "A\u{302}\u{303}"), // Ẫ
+    ('\u{1eab}', "a\u{302}\u{303}"), // ẫ
+    ('\u{1eac}', "A\u{323}\u{302}"), // Ậ
+    ('\u{1ead}', "a\u{323}\u{302}"), // ậ
+    ('\u{1eae}', "A\u{306}\u{301}"), // Ắ
+    ('\u{1eaf}', "a\u{306}\u{301}"), // ắ
+    ('\u{1eb0}', "A\u{306}\u{300}"), // Ằ
+    ('\u{1eb1}', "a\u{306}\u{300}"), // ằ
+    ('\u{1eb2}', "A\u{306}\u{309}"), // Ẳ
+    ('\u{1eb3}', "a\u{306}\u{309}"), // ẳ
+    ('\u{1eb4}', "A\u{306}\u{303}"), // Ẵ
+    ('\u{1eb5}', "a\u{306}\u{303}"), // ẵ
+    ('\u{1eb6}', "A\u{323}\u{306}"), // Ặ
+    ('\u{1eb7}', "a\u{323}\u{306}"), // ặ
+    ('\u{1eb8}', "E\u{323}"),        // Ẹ
+    ('\u{1eb9}', "e\u{323}"),        // ẹ
+    ('\u{1eba}', "E\u{309}"),        // Ẻ
+    ('\u{1ebb}', "e\u{309}"),        // ẻ
+    ('\u{1ebc}', "E\u{303}"),        // Ẽ
+    ('\u{1ebd}', "e\u{303}"),        // ẽ
+    ('\u{1ebe}', "E\u{302}\u{301}"), // Ế
+    ('\u{1ebf}', "e\u{302}\u{301}"), // ế
+    ('\u{1ec0}', "E\u{302}\u{300}"), // Ề
+    ('\u{1ec1}', "e\u{302}\u{300}"), // ề
+    ('\u{1ec2}', "E\u{302}\u{309}"), // Ể
+    ('\u{1ec3}', "e\u{302}\u{309}"), // ể
+    ('\u{1ec4}', "E\u{302}\u{303}"), // Ễ
+    ('\u{1ec5}', "e\u{302}\u{303}"), // ễ
+    ('\u{1ec6}', "E\u{323}\u{302}"), // Ệ
+    ('\u{1ec7}', "e\u{323}\u{302}"), // ệ
+    ('\u{1ec8}', "I\u{309}"),        // Ỉ
+    ('\u{1ec9}', "i\u{309}"),        // ỉ
+    ('\u{1eca}', "I\u{323}"),        // Ị
+    ('\u{1ecb}', "i\u{323}"),        // ị
+    ('\u{1ecc}', "O\u{323}"),        // Ọ
+    ('\u{1ecd}', "o\u{323}"),        // ọ
+    ('\u{1ece}', "O\u{309}"),        // Ỏ
+    ('\u{1ecf}', "o\u{309}"),        // ỏ
+    ('\u{1ed0}', "O\u{302}\u{301}"), // Ố
+    ('\u{1ed1}', "o\u{302}\u{301}"), // ố
+    ('\u{1ed2}', "O\u{302}\u{300}"), // Ồ
+    ('\u{1ed3}', "o\u{302}\u{300}"), // ồ
+    ('\u{1ed4}', "O\u{302}\u{309}"), // Ổ
+    ('\u{1ed5}', "o\u{302}\u{309}"), // ổ
+    ('\u{1ed6}', "O\u{302}\u{303}"), // Ỗ
+    ('\u{1ed7}', "o\u{302}\u{303}"), // ỗ
+    ('\u{1ed8}', "O\u{323}\u{302}"), // Ộ
+    ('\u{1ed9}', "o\u{323}\u{302}"), // ộ
+    ('\u{1eda}', "O\u{31b}\u{301}"), // Ớ
+    ('\u{1edb}', "o\u{31b}\u{301}"), // ớ
+    ('\u{1edc}', "O\u{31b}\u{300}"), // Ờ
+    ('\u{1edd}', "o\u{31b}\u{300}"), // ờ
+    ('\u{1ede}', "O\u{31b}\u{309}"), // Ở
+    ('\u{1edf}', "o\u{31b}\u{309}"), // ở
+    ('\u{1ee0}', "O\u{31b}\u{303}"), // Ỡ
+    ('\u{1ee1}', "o\u{31b}\u{303}"), // ỡ
+    ('\u{1ee2}', "O\u{31b}\u{323}"), // Ợ
+    ('\u{1ee3}', "o\u{31b}\u{323}"), // ợ
+    ('\u{1ee4}', "U\u{323}"),        // Ụ
+    ('\u{1ee5}', "u\u{323}"),        // ụ
+    ('\u{1ee6}', "U\u{309}"),        // Ủ
+    ('\u{1ee7}', "u\u{309}"),        // ủ
+    ('\u{1ee8}', "U\u{31b}\u{301}"), // Ứ
+    ('\u{1ee9}', "u\u{31b}\u{301}"), // ứ
+    ('\u{1eea}', "U\u{31b}\u{300}"), // Ừ
+    ('\u{1eeb}', "u\u{31b}\u{300}"), // ừ
+    ('\u{1eec}', "U\u{31b}\u{309}"), // Ử
+    ('\u{1eed}', "u\u{31b}\u{309}"), // ử
+    ('\u{1eee}', "U\u{31b}\u{303}"), // Ữ
+    ('\u{1eef}', "u\u{31b}\u{303}"), // ữ
+    ('\u{1ef0}', "U\u{31b}\u{323}"), // Ự
+    ('\u{1ef1}', "u\u{31b}\u{323}"), // ự
+    ('\u{1ef2}', "Y\u{300}"),        // Ỳ
+    ('\u{1ef3}', "y\u{300}"),        // ỳ
+    ('\u{1ef4}', "Y\u{323}"),        // Ỵ
+    ('\u{1ef5}', "y\u{323}"),        // ỵ
+    ('\u{1ef6}', "Y\u{309}"),        // Ỷ
+    ('\u{1ef7}', "y\u{309}"),        // ỷ
+    ('\u{1ef8}', "Y\u{303}"),        // Ỹ
+    ('\u{1ef9}', "y\u{303}"),        // ỹ
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{decompose_char, decompose_str, DECOMPOSITION_TABLE};
+
+    #[test]
+    fn test_decompose() {
+        assert_eq!(decompose_char('é'), Some("e\u{301}"));
+        assert_eq!(decompose_char('ǻ'), Some("a\u{30a}\u{301}"));
+        assert_eq!(decompose_char('e'), None);
+        assert_eq!(decompose_char('文'), None);
+
+        assert_eq!(decompose_str("café"), vec!['c', 'a', 'f', 'e', '\u{301}']);
+        assert_eq!(
+            decompose_str("cafe\u{301}"),
+            vec!['c', 'a', 'f', 'e', '\u{301}']
+        );
+
+        // the table must be sorted for binary searching
+        assert!(DECOMPOSITION_TABLE.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+}
@@ -10,6 +10,9 @@ use crate::{
     token::{Token, TokenWithRange},
 };
 
+// the stage after cleaning, it merges the continuous new lines and
+// the commas, so the parser sees at most one separator between
+// two expressions.
 pub fn normalize(tokens: Vec<TokenWithRange>) -> Vec<TokenWithRange> {
     // combine multiple continuous newlines into one newline.
     // rules:
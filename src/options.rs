@@ -0,0 +1,159 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::collections::HashMap;
+
+// the options for compiling a pattern, e.g.
+//
+// ```
+// let options = CompileOptions {
+//     ignore_case: true,
+//     ..CompileOptions::default()
+// };
+// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompileOptions {
+    // chars, strings and charsets match case-insensitively.
+    pub ignore_case: bool,
+
+    // the statuses `start` and `end` also match at the start and
+    // the end of each line.
+    pub multiline: bool,
+
+    // `char_any` also matches the new line char.
+    pub dot_all: bool,
+
+    // the "\r\n" is treated as one new line, i.e. `start` and `end` in
+    // multiline mode match before and after "\r\n" (and a single '\r')
+    // but not between '\r' and '\n', and `char_any` does not match '\r',
+    // so the texts from Windows behave the same as the ones from Unix.
+    pub crlf: bool,
+
+    // the preset charsets `char_word`, `char_digit`, `char_space` and
+    // the status `bound` match Unicode chars, instead of ASCII chars only,
+    // e.g. `char_space` matches the chars with the Unicode property
+    // `White_Space` (including NBSP U+00A0 and the ideographic space
+    // U+3000) instead of the ASCII white spaces ' ', '\t', '\n', '\x0c'
+    // and '\r'.
+    //
+    // the preset charsets `char_space_ascii` and `char_not_space_ascii`
+    // match the ASCII white spaces (or the other chars) regardless of
+    // this option, e.g. `char_word+, char_space_ascii` matches the
+    // Unicode words followed by an ASCII white space.
+    pub unicode: bool,
+
+    // `char_any` and the negative charsets consume a whole extended
+    // grapheme cluster instead of a single char, e.g. `char_any{2}`
+    // matches the emoji with a skin tone modifier "\u{1f44d}\u{1f3fd}"
+    // followed by "e\u{301}" (é), the literal `grapheme_any` always
+    // consumes a cluster regardless of this option.
+    pub grapheme: bool,
+
+    // a capture group inside a repetition keeps the first iteration
+    // instead of the last one, e.g. `name(char_word, x)+` matches "abc"
+    // and captures "a" instead of "c" (the default, same as PCRE).
+    pub keep_first_capture: bool,
+
+    // the compiler reports an error if the number of capture groups
+    // (not including the group 0) exceeds the limit.
+    pub max_capture_groups: Option<usize>,
+
+    // the limits of the size of the pattern, they are applied to both
+    // parsing and compiling.
+    pub limits: Limits,
+
+    // the external variables that are referenced by `var(name)` in the
+    // pattern, the values are compiled as literal chars or strings.
+    pub vars: HashMap<String, String>,
+}
+
+// the options for parsing a pattern with `parse_from_str_with_options`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+    // keep the comments and attach them to the nearest expressions or
+    // definitions in the source map, e.g. for formatters and
+    // documentation generators.
+    pub keep_comments: bool,
+
+    // the limits of the size of the pattern.
+    pub limits: Limits,
+}
+
+// the limits that protect the parser and the compiler from the
+// untrusted patterns, e.g. the deeply nested groups that overflow
+// the stack, or the definitions that expand exponentially:
+//
+// ```text
+// define(a, "aa")
+// define(b, (a, a))
+// define(c, (b, b))
+// ...
+// ```
+//
+// an error is reported when any limit is exceeded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    // the maximum nesting depth of the expressions, i.e. the elements
+    // of groups, the arguments of function calls and the alternatives.
+    pub max_nesting_depth: usize,
+
+    // the maximum number of the expressions.
+    pub max_nodes: usize,
+
+    // the maximum number of the tokens after expanding the
+    // definitions and the built-in macros.
+    pub max_expanded_tokens: usize,
+
+    // the maximum count of a repetition, e.g. the numbers of `{m, n}`,
+    // `repeat(x, n)` and `at_least(x, n)`, it is checked by the compiler.
+    pub max_repetition: usize,
+
+    // the maximum nesting depth of the look-around assertions, e.g.
+    // `'a'.is_before('b'.is_before('c'))` is 2, the matcher checks the
+    // nested assertions recursively, so the depth is limited more
+    // strictly than `max_nesting_depth`, it is checked by the compiler.
+    pub max_look_around_depth: usize,
+}
+
+// the limits of the resources used by an instance for matching, they
+// bound the memory when matching the untrusted texts with the patterns
+// that backtrack heavily, e.g. `(char_any*)*, 'x'` on a long text.
+//
+// the match attempt stops when any limit is exceeded, `Instance::try_exec`
+// reports it as `Error::LimitExceeded`, and the other methods treat it
+// as no match. there is no limit by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchLimits {
+    // the maximum number of the backtracking frames of all threads
+    // that exist at the same time.
+    pub max_frames: usize,
+
+    // the maximum number of the threads that run at the same time,
+    // i.e. the thread of the match attempt and the nested threads
+    // of the look-around assertions.
+    pub max_threads: usize,
+}
+
+impl Default for MatchLimits {
+    fn default() -> Self {
+        Self {
+            max_frames: usize::MAX,
+            max_threads: usize::MAX,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_nesting_depth: 128,
+            max_nodes: 100_000,
+            max_expanded_tokens: 1_000_000,
+            max_repetition: 100_000,
+            max_look_around_depth: 32,
+        }
+    }
+}
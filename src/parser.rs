@@ -4,32 +4,72 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
+use std::collections::HashMap;
+
 use crate::{
     ast::{
-        CharRange, CharSet, CharSetElement, Expression, FunctionCall, FunctionCallArg,
-        FunctionName, Literal, Program,
+        ByteRange, CharRange, CharSet, CharSetElement, Expression, FunctionCall, FunctionCallArg,
+        FunctionName, Literal, NodeLocation, NumberRange, PatternOptions, Program, SelfTest,
+        SourceMap, SyntaxVersion,
     },
+    commentattacher::attach_comments,
     commentcleaner::clean,
     error::Error,
     lexer::lex_from_str,
     location::Location,
-    macroexpander::expand,
+    macroexpander::{collect_definition_locations, expand, expand_at_references},
     normalizer::normalize,
+    options::{Limits, ParseOptions},
     peekableiter::PeekableIter,
-    token::{Token, TokenWithRange},
+    token::{get_reserved_word_kind, Token, TokenWithRange},
+    variables::substitute_variables,
 };
 
 pub struct Parser<'a> {
     upstream: &'a mut PeekableIter<'a, TokenWithRange>,
     last_range: Location,
+
+    // the locations of the parsed expressions, the locations of the
+    // children are replaced by the one of their parent when the parent
+    // is complete, see `wrap_locations`.
+    locations: Vec<NodeLocation>,
+
+    limits: &'a Limits,
+    depth: usize,
+    node_count: usize,
 }
 
 impl<'a> Parser<'a> {
-    fn new(upstream: &'a mut PeekableIter<'a, TokenWithRange>) -> Self {
+    fn new(upstream: &'a mut PeekableIter<'a, TokenWithRange>, limits: &'a Limits) -> Self {
         Self {
             upstream,
             last_range: Location::new_range(0, 0, 0, 0, 0),
+            locations: vec![],
+            limits,
+            depth: 0,
+            node_count: 0,
+        }
+    }
+
+    fn current_position(&self) -> Location {
+        self.peek_range(0)
+            .map_or(self.last_range, |r| *r)
+            .get_position_by_range_start()
+    }
+
+    // count a new expression and check the limit.
+    fn count_node(&mut self) -> Result<(), Error> {
+        self.node_count += 1;
+        if self.node_count > self.limits.max_nodes {
+            return Err(Error::MessageWithLocation(
+                format!(
+                    "The pattern exceeds the maximum number of expressions {}.",
+                    self.limits.max_nodes
+                ),
+                self.current_position(),
+            ));
         }
+        Ok(())
     }
 
     fn next_token(&mut self) -> Option<Token> {
@@ -55,12 +95,29 @@ impl<'a> Parser<'a> {
             Some(TokenWithRange { token, .. }) if token == expected_token)
     }
 
-    // fn peek_range(&self, offset: usize) -> Option<&Location> {
-    //     match self.upstream.peek(offset) {
-    //         Some(TokenWithRange { range, .. }) => Some(range),
-    //         None => None,
-    //     }
-    // }
+    fn peek_range(&self, offset: usize) -> Option<&Location> {
+        match self.upstream.peek(offset) {
+            Some(TokenWithRange { range, .. }) => Some(range),
+            None => None,
+        }
+    }
+
+    // combine the locations pushed since `mark` into the location
+    // of their parent, which starts at `start` and ends at the last token.
+    fn wrap_locations(&mut self, mark: usize, start: Location) {
+        let children = self.locations.split_off(mark);
+
+        // the tokens of a defined identifier have the locations of
+        // the definition, which may be anywhere in the source.
+        let end = self.last_range;
+        let location = if end.unit == start.unit && end.index >= start.index {
+            Location::from_range_pair(&start, &end)
+        } else {
+            start
+        };
+
+        self.locations.push(NodeLocation::new(location, children));
+    }
 
     // consume '\n' if it exists.
     fn consume_new_line_if_exist(&mut self) -> bool {
@@ -121,8 +178,8 @@ impl<'a> Parser<'a> {
 
     fn expect_identifier(&mut self) -> Result<String, Error> {
         match self.peek_token(0) {
-            Some(Token::Identifier(s)) => {
-                let id = s.to_owned();
+            Some(Token::Identifier(s) | Token::QuotedIdentifier(s)) => {
+                let id = s.to_string();
                 self.next_token();
                 Ok(id)
             }
@@ -136,6 +193,35 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // the name of a capture group can not be a keyword, a preset charset,
+    // an assertion or a function, e.g. `name('a', start)`, since they are
+    // not identifiers, or they can not be the back-references.
+    fn check_capture_name(&self) -> Result<(), Error> {
+        let (
+            Some(
+                Token::Identifier(name)
+                | Token::Status(name)
+                | Token::PresetCharSet(name)
+                | Token::Special(name),
+            ),
+            Some(Token::RightParen | Token::Comma | Token::NewLine),
+        ) = (self.peek_token(0), self.peek_token(1))
+        else {
+            return Ok(());
+        };
+
+        match get_reserved_word_kind(name) {
+            Some(kind) => Err(Error::MessageWithLocation(
+                format!(
+                    "The capture group name \"{}\" conflicts with the {} \"{}\".",
+                    name, kind, name
+                ),
+                *self.peek_range(0).unwrap(),
+            )),
+            None => Ok(()),
+        }
+    }
+
     fn expect_number(&mut self) -> Result<u32, Error> {
         match self.peek_token(0) {
             Some(Token::Number(n)) => {
@@ -153,6 +239,18 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn expect_byte(&mut self) -> Result<u8, Error> {
+        let num = self.expect_number()?;
+        if num > 0xff {
+            Err(Error::MessageWithLocation(
+                "Byte value exceeds 0xff.".to_owned(),
+                self.last_range,
+            ))
+        } else {
+            Ok(num as u8)
+        }
+    }
+
     fn expect_char(&mut self) -> Result<char, Error> {
         match self.peek_token(0) {
             Some(Token::Char(c)) => {
@@ -173,14 +271,59 @@ impl<'a> Parser<'a> {
     pub fn parse_program(&mut self) -> Result<Program, Error> {
         // let mut definitions = vec![];
         let mut expressions = vec![];
+        let mut tests = vec![];
+        let mut options = PatternOptions::default();
+        let mut syntax_version = None;
+        let mut is_first_statement = true;
 
-        while let Some(_) = self.peek_token(0) {
-            let expression = self.parse_expression()?;
-            expressions.push(expression);
+        while let Some(token) = self.peek_token(0) {
+            if matches!(token, Token::Identifier(id) if id == "syntax_version")
+                && self.peek_token_and_equals(1, &Token::LeftParen)
+            {
+                if !is_first_statement {
+                    return Err(Error::MessageWithLocation(
+                        "The syntax version must be declared at the beginning of the pattern."
+                            .to_owned(),
+                        self.current_position(),
+                    ));
+                }
+                syntax_version = Some(self.parse_syntax_version()?);
+            } else if matches!(token, Token::Identifier(id) if id == "options")
+                && self.peek_token_and_equals(1, &Token::LeftParen)
+            {
+                if !expressions.is_empty() || !tests.is_empty() {
+                    return Err(Error::MessageWithLocation(
+                        "The options must be set at the top of the pattern.".to_owned(),
+                        self.current_position(),
+                    ));
+                }
+                self.parse_options(&mut options)?;
+            } else if matches!(token, Token::Identifier(id) if id == "expect_match" || id == "expect_no_match")
+                && self.peek_token_and_equals(1, &Token::LeftParen)
+            {
+                let test = self.parse_self_test()?;
+                tests.push(test);
+            } else {
+                let expression = self.parse_expression()?;
+                expressions.push(expression);
+            }
+
+            is_first_statement = false;
 
             // consume separator
             let found_sep = self.consume_new_line_or_comma_if_exist();
             if !found_sep {
+                // the leftover tokens are not ignored, e.g. the `)` of
+                // `'a' )` and the `'b'` of `'a' 'b'`.
+                if let Some(token) = self.peek_token(0) {
+                    return Err(Error::MessageWithLocation(
+                        format!(
+                            "Expect a comma or a new line, found {}.",
+                            token.get_description()
+                        ),
+                        *self.peek_range(0).unwrap(),
+                    ));
+                }
                 break;
             }
         }
@@ -188,11 +331,111 @@ impl<'a> Parser<'a> {
         let program = Program {
             // definitions,
             expressions,
+            tests,
+            options,
+            syntax_version,
         };
 
         Ok(program)
     }
 
+    fn parse_syntax_version(&mut self) -> Result<SyntaxVersion, Error> {
+        // "syntax_version" "(" number ")" ?
+        // ---------------- ---        -
+        // ^                ^__ validated ^__ to here
+        // | current, validated
+
+        self.next_token(); // consume 'syntax_version'
+        self.next_token(); // consume '('
+        self.consume_new_line_if_exist(); // consume trailing new-line
+
+        let number = self.expect_number()?;
+        let Some(version) = SyntaxVersion::from_number(number) else {
+            return Err(Error::MessageWithLocation(
+                format!(
+                    "Unsupported syntax version {}, the latest version is {}.",
+                    number,
+                    SyntaxVersion::LATEST.number()
+                ),
+                self.last_range,
+            ));
+        };
+
+        self.consume_new_line_if_exist();
+        self.expect_token(&Token::RightParen)?; // consume ')'
+        Ok(version)
+    }
+
+    fn parse_options(&mut self, options: &mut PatternOptions) -> Result<(), Error> {
+        // "options" "(" {identifier} ")" ?
+        // --------- ---              -
+        // ^         ^__ validated    ^__ to here
+        // | current, validated
+
+        self.next_token(); // consume 'options'
+        self.next_token(); // consume '('
+        self.consume_new_line_if_exist(); // consume trailing new-line
+
+        while let Some(token) = self.peek_token(0) {
+            if token == &Token::RightParen {
+                break;
+            }
+
+            let name = self.expect_identifier()?;
+            if !options.set(&name) {
+                return Err(Error::MessageWithLocation(
+                    format!("Unknown option \"{}\".", name),
+                    self.last_range,
+                ));
+            }
+
+            let found_sep = self.consume_new_line_or_comma_if_exist();
+            if !found_sep {
+                break;
+            }
+        }
+
+        self.expect_token(&Token::RightParen)?; // consume ')'
+        Ok(())
+    }
+
+    fn parse_self_test(&mut self) -> Result<SelfTest, Error> {
+        // "expect_match" "(" string ")" ?
+        // "expect_no_match" "(" string ")" ?
+        // ----------------- ---        -
+        // ^                 ^          ^__ to here
+        // | current,        |__ validated
+        // | validated
+
+        let name = self.expect_identifier()?;
+
+        self.next_token(); // consume '('
+        self.consume_new_line_if_exist(); // consume trailing new-line
+
+        let text = match self.next_token() {
+            Some(Token::String(text)) => text,
+            Some(_) => {
+                return Err(Error::MessageWithLocation(
+                    "Expect a string for the test case.".to_owned(),
+                    self.last_range,
+                ));
+            }
+            None => {
+                return Err(Error::UnexpectedEndOfDocument(
+                    "Expect a string for the test case.".to_owned(),
+                ));
+            }
+        };
+
+        self.consume_new_line_if_exist();
+        self.expect_token(&Token::RightParen)?; // consume ')'
+
+        Ok(SelfTest {
+            text,
+            should_match: name == "expect_match",
+        })
+    }
+
     fn parse_expression(&mut self) -> Result<Expression, Error> {
         // token ...
         // -----
@@ -204,49 +447,86 @@ impl<'a> Parser<'a> {
         // 2. unary expressions
         // 3. base expression
 
-        self.parse_logic_or()
+        self.enter_nesting()?;
+        let result = self.parse_logic_or();
+        self.depth -= 1;
+        result
+    }
+
+    // the parser is recursive, and the other passes (e.g. the analyzer
+    // and `Display`) walk the AST recursively, so the depth is limited
+    // to avoid overflowing the stack.
+    fn enter_nesting(&mut self) -> Result<(), Error> {
+        if self.depth >= self.limits.max_nesting_depth {
+            return Err(Error::MessageWithLocation(
+                format!(
+                    "The expression is nested deeper than the maximum depth {}.",
+                    self.limits.max_nesting_depth
+                ),
+                self.current_position(),
+            ));
+        }
+
+        self.depth += 1;
+        Ok(())
     }
 
     // binary expression (login or, etc.)   | precedence low
     // |-- unary expression
     // |   |-- simple expression            | precedence high
     fn parse_logic_or(&mut self) -> Result<Expression, Error> {
-        // token ... [ "||" expression ]
+        // token ... { "||" expression }
         // -----
         // ^
         // | current, not None
 
-        let mut left = self.parse_simple_expression()?;
+        // the alternatives are collected into a flat list by a loop
+        // instead of recursion, so the patterns with thousands of
+        // alternatives (e.g. the generated ones) do not exceed the
+        // nesting depth, e.g. `'a' || 'b' || 'c'` is parsed as
+        // `OrList(['a', 'b', 'c'])`.
+        let mark = self.locations.len();
+        let start = self.peek_range(0).map_or(self.last_range, |r| *r);
+
+        // the empty alternative matches the empty string, e.g. the
+        // left side of `( || 'a')` and the right side of `('a' || )`.
+        let first = if let Some(Token::LogicOr) = self.peek_token(0) {
+            self.push_empty_alternative(start.get_position_by_range_start())?
+        } else {
+            self.parse_simple_expression()?
+        };
+
+        let mut alternatives = vec![first];
 
         while let Some(Token::LogicOr) = self.peek_token(0) {
             self.next_token(); // consume "||"
             self.consume_new_line_if_exist(); // consume trailing new-line
 
-            // Operator associativity
-            // - https://en.wikipedia.org/wiki/Operator_associativity
-            // - https://en.wikipedia.org/wiki/Operators_in_C_and_C%2B%2B#Operator_precedence
-            //
-            // left-associative, left-to-right associative
-            // a || b || c -> (a || b) || c
-            //
-            // right-associative, righ-to-left associative
-            // a || b || c -> a || (b || c)
-            //
-            // note:
-            // using `parse_expression` for right-to-left associative, e.g.
-            // `let right = self.parse_expression()?;`
-            // or
-            // using `parse_simple_expression` for left-to-right associative, e.g.
-            // `let right = self.parse_simple_expression()?;`
-            //
-            // for the current interpreter, it is more efficient by using right-associative.
+            let alternative = if matches!(
+                self.peek_token(0),
+                None | Some(Token::RightParen | Token::Comma | Token::NewLine | Token::LogicOr)
+            ) {
+                self.push_empty_alternative(self.last_range.get_position_by_range_end())?
+            } else {
+                self.parse_simple_expression()?
+            };
 
-            let right = self.parse_expression()?;
-            let expression = Expression::Or(Box::new(left), Box::new(right));
-            left = expression;
+            alternatives.push(alternative);
         }
 
-        Ok(left)
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+
+        self.wrap_locations(mark, start);
+        self.count_node()?;
+        Ok(Expression::OrList(alternatives))
+    }
+
+    fn push_empty_alternative(&mut self, position: Location) -> Result<Expression, Error> {
+        self.locations.push(NodeLocation::new(position, vec![]));
+        self.count_node()?;
+        Ok(Expression::Group(vec![]))
     }
 
     // binary expression (login or, etc.)
@@ -267,8 +547,15 @@ impl<'a> Parser<'a> {
         // ^
         // | current, may be None
 
+        let mark = self.locations.len();
+        let start = self.peek_range(0).map_or(self.last_range, |r| *r);
         let mut left = self.parse_base_expression()?;
 
+        // the chain is parsed by a loop, but each notation and function
+        // call wraps the expression, so it is counted as a level of
+        // nesting, e.g. `'a'.optional().optional()` is nested 2 levels.
+        let depth = self.depth;
+
         while let Some(token) = self.peek_token(0) {
             match token {
                 Token::Question
@@ -277,7 +564,8 @@ impl<'a> Parser<'a> {
                 | Token::QuestionLazy
                 | Token::PlusLazy
                 | Token::AsteriskLazy => {
-                    let name = function_name_from_notation_token(&token, &self.last_range)?;
+                    let name = function_name_from_notation_token(token, &self.last_range)?;
+                    self.enter_nesting()?;
                     let function_call = FunctionCall {
                         name,
                         expression: Box::new(left),
@@ -286,8 +574,11 @@ impl<'a> Parser<'a> {
                     left = Expression::FunctionCall(Box::new(function_call));
 
                     self.next_token(); // consume notation
+                    self.wrap_locations(mark, start);
+                    self.count_node()?;
                 }
                 Token::LeftBrace => {
+                    self.enter_nesting()?;
                     let (notation_quantifier, lazy) = self.continue_parse_notation_quantifier()?;
 
                     let mut args = vec![];
@@ -326,13 +617,18 @@ impl<'a> Parser<'a> {
                         args,
                     };
                     left = Expression::FunctionCall(Box::new(function_call));
+                    self.wrap_locations(mark, start);
+                    self.count_node()?;
                 }
                 Token::Dot
                     if matches!(self.peek_token(1), Some(Token::Identifier(_)))
                         && self.peek_token_and_equals(2, &Token::LeftParen) =>
                 {
+                    self.enter_nesting()?;
                     let function_call = self.continue_parse_rear_function_call(left)?;
                     left = Expression::FunctionCall(Box::new(function_call));
+                    self.wrap_locations(mark, start);
+                    self.count_node()?;
                 }
                 _ => {
                     break;
@@ -340,6 +636,7 @@ impl<'a> Parser<'a> {
             }
         }
 
+        self.depth = depth;
         Ok(left)
     }
 
@@ -461,37 +758,7 @@ impl<'a> Parser<'a> {
         self.next_token(); // consume '('
         self.consume_new_line_if_exist(); // consume trailing new-line
 
-        let mut args = vec![];
-
-        while let Some(token) = self.peek_token(0) {
-            if token == &Token::RightParen {
-                break;
-            }
-
-            match token {
-                Token::Number(num_ref) => {
-                    let num = *num_ref;
-                    self.next_token(); // consume number
-                    args.push(FunctionCallArg::Number(num));
-                }
-                Token::Identifier(id_ref) => {
-                    let id = id_ref.to_owned();
-                    self.next_token(); // consume identifier
-                    args.push(FunctionCallArg::Identifier(id));
-                }
-                _ => {
-                    return Err(Error::MessageWithLocation(
-                        "Unsupported argument value.".to_owned(),
-                        self.last_range,
-                    ));
-                }
-            }
-
-            let found_sep = self.consume_new_line_or_comma_if_exist();
-            if !found_sep {
-                break;
-            }
-        }
+        let args = self.parse_function_args(&name, None)?;
 
         self.expect_token(&Token::RightParen)?; // consume ')'
 
@@ -515,6 +782,9 @@ impl<'a> Parser<'a> {
         // - identifier
         // - group
         // - function call
+        let mark = self.locations.len();
+        let start = self.peek_range(0).map_or(self.last_range, |r| *r);
+
         let expression = match self.peek_token(0) {
             Some(token) => {
                 match token {
@@ -522,11 +792,27 @@ impl<'a> Parser<'a> {
                         // group
                         self.parse_group()?
                     }
+                    Token::Identifier(id)
+                        if (id == "byte" || id == "byte_range")
+                            && self.peek_token_and_equals(1, &Token::LeftParen) =>
+                    {
+                        // byte literal
+                        let literal = self.parse_byte()?;
+                        Expression::Literal(literal)
+                    }
+                    Token::Identifier(id)
+                        if id == "number_range"
+                            && self.peek_token_and_equals(1, &Token::LeftParen) =>
+                    {
+                        // number range literal
+                        let literal = self.parse_number_range()?;
+                        Expression::Literal(literal)
+                    }
                     Token::Identifier(_) if self.peek_token_and_equals(1, &Token::LeftParen) => {
                         // function call
                         self.parse_function_call()?
                     }
-                    Token::Identifier(_) => {
+                    Token::Identifier(_) | Token::QuotedIdentifier(_) => {
                         // identifier
                         let id = self.expect_identifier()?;
                         Expression::Identifier(id)
@@ -544,6 +830,8 @@ impl<'a> Parser<'a> {
             }
         };
 
+        self.wrap_locations(mark, start);
+        self.count_node()?;
         Ok(expression)
     }
 
@@ -590,7 +878,48 @@ impl<'a> Parser<'a> {
         self.consume_new_line_if_exist(); // consume trailing new-line
 
         let expression = self.parse_expression()?;
-        self.consume_new_line_or_comma_if_exist(); // consume trailing new-line
+
+        // the separator between the expression and the arguments
+        let separator = if self.consume_new_line_or_comma_if_exist() {
+            Some(self.last_range)
+        } else {
+            None
+        };
+
+        let args = self.parse_function_args(&name, separator)?;
+
+        self.expect_token(&Token::RightParen)?; // consume ')'
+
+        let function_call = FunctionCall {
+            name,
+            expression: Box::new(expression),
+            args,
+        };
+
+        Ok(Expression::FunctionCall(Box::new(function_call)))
+    }
+
+    fn parse_function_args(
+        &mut self,
+        name: &FunctionName,
+        mut separator: Option<Location>,
+    ) -> Result<Vec<FunctionCallArg>, Error> {
+        // arg ["," arg ...] ")"
+        // ---               -
+        // ^                 ^__ to here, not consumed
+        // | current
+        //
+        // the arguments are separated by commas (or new-lines), and each
+        // argument is a single expression, the logic or binds tighter
+        // than the comma like in a group, e.g. `'a'.is_before('b' || 'c')`
+        // takes the alternation as the argument, and a sequence must be
+        // wrapped in parentheses, e.g. `'a'.is_before(('b', 'c'))`.
+        //
+        // the functions take a fixed number of arguments (except `line`),
+        // so an extra argument is an error pointing at the comma before
+        // it, rather than being ignored, e.g. `'a'.is_before('b', 'c')`.
+
+        let max_args = max_function_args(name);
 
         let mut args = vec![];
         while let Some(token) = self.peek_token(0) {
@@ -598,40 +927,60 @@ impl<'a> Parser<'a> {
                 break;
             }
 
+            if let Some(max) = max_args.filter(|max| args.len() >= *max) {
+                return Err(Error::MessageWithLocation(
+                    format!(
+                        "Unexpected argument {} of the function \"{}\", it takes {} besides \
+                        the expression. The comma separates the arguments, wrap the \
+                        expressions in parentheses to pass a sequence as one expression, \
+                        e.g. `('a', 'b')`.",
+                        args.len() + 1,
+                        name,
+                        match max {
+                            0 => "no argument".to_owned(),
+                            1 => "1 argument".to_owned(),
+                            _ => format!("{} arguments", max),
+                        },
+                    ),
+                    separator.unwrap_or(*self.peek_range(0).unwrap()),
+                ));
+            }
+
+            if name == &FunctionName::Name {
+                self.check_capture_name()?;
+            }
+
+            let token = self.peek_token(0).unwrap();
             match token {
                 Token::Number(num_ref) => {
                     let num = *num_ref;
                     self.next_token(); // consume number
                     args.push(FunctionCallArg::Number(num));
                 }
-                Token::Identifier(id_ref) => {
-                    let id = id_ref.to_owned();
+                Token::Identifier(id_ref) | Token::QuotedIdentifier(id_ref)
+                    if matches!(
+                        self.peek_token(1),
+                        Some(Token::RightParen | Token::Comma | Token::NewLine)
+                    ) =>
+                {
+                    // the identifier which is not part of an expression
+                    let id = id_ref.to_string();
                     self.next_token(); // consume identifier
                     args.push(FunctionCallArg::Identifier(id));
                 }
                 _ => {
-                    return Err(Error::MessageWithLocation(
-                        "Unsupported argument value.".to_owned(),
-                        self.last_range,
-                    ));
+                    let expression = self.parse_expression()?;
+                    args.push(FunctionCallArg::Expression(Box::new(expression)));
                 }
             }
 
-            let found_sep = self.consume_new_line_or_comma_if_exist();
-            if !found_sep {
+            if !self.consume_new_line_or_comma_if_exist() {
                 break;
             }
+            separator = Some(self.last_range);
         }
 
-        self.expect_token(&Token::RightParen)?; // consume ')'
-
-        let function_call = FunctionCall {
-            name,
-            expression: Box::new(expression),
-            args,
-        };
-
-        Ok(Expression::FunctionCall(Box::new(function_call)))
+        Ok(args)
     }
 
     fn parse_literal(&mut self) -> Result<Literal, Error> {
@@ -645,6 +994,7 @@ impl<'a> Parser<'a> {
         //   - string
         //   - charset
         //   - preset_charset
+        //   - special char
         //   - status
 
         match self.peek_token(0) {
@@ -678,13 +1028,29 @@ impl<'a> Parser<'a> {
                         Literal::String(string)
                     }
                     Token::PresetCharSet(preset_charset_ref) => {
-                        let preset_charset = preset_charset_ref.to_owned();
+                        let preset_charset = preset_charset_ref.to_string();
                         self.next_token(); // consume preset charset
                         Literal::PresetCharSet(preset_charset)
                     }
+                    Token::Special(special_ref) => {
+                        let special = special_ref.to_string();
+                        self.next_token(); // consume special char
+                        Literal::Special(special)
+                    }
                     Token::Status(status_ref) => {
-                        let status = status_ref.to_owned();
+                        let status = status_ref.to_string();
                         self.next_token(); // consume status
+
+                        // `match_start_reset` can also be written in the
+                        // function form `match_start_reset()`.
+                        if status == "match_start_reset"
+                            && self.peek_token_and_equals(0, &Token::LeftParen)
+                        {
+                            self.next_token(); // consume '('
+                            self.consume_new_line_if_exist(); // consume trailing new-line
+                            self.expect_token(&Token::RightParen)?; // consume ')'
+                        }
+
                         Literal::Status(status)
                     }
                     _ => {
@@ -703,6 +1069,80 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_byte(&mut self) -> Result<Literal, Error> {
+        // "byte" "(" number ")" ?
+        // "byte_range" "(" number ".." number ")" ?
+        // ------------ ---                        -
+        // ^            ^__ validated              ^__ to here
+        // | current, validated
+
+        let name = self.expect_identifier()?;
+
+        self.next_token(); // consume '('
+        self.consume_new_line_if_exist(); // consume trailing new-line
+
+        let start = self.expect_byte()?;
+
+        let literal = if name == "byte" {
+            Literal::Byte(start)
+        } else {
+            self.consume_new_line_if_exist();
+            self.expect_token(&Token::Interval)?; // consume '..'
+            self.consume_new_line_if_exist();
+
+            let end_included = self.expect_byte()?;
+            if start > end_included {
+                return Err(Error::MessageWithLocation(
+                    "The start of byte range is greater than the end.".to_owned(),
+                    self.last_range,
+                ));
+            }
+
+            Literal::ByteRange(ByteRange {
+                start,
+                end_included,
+            })
+        };
+
+        self.consume_new_line_if_exist();
+        self.expect_token(&Token::RightParen)?; // consume ')'
+
+        Ok(literal)
+    }
+
+    fn parse_number_range(&mut self) -> Result<Literal, Error> {
+        // "number_range" "(" number "," number ")" ?
+        // -------------- ---                       -
+        // ^              ^__ validated             ^__ to here
+        // | current, validated
+
+        self.next_token(); // consume 'number_range'
+        self.next_token(); // consume '('
+        self.consume_new_line_if_exist(); // consume trailing new-line
+
+        let start = self.expect_number()?;
+
+        self.consume_new_line_if_exist();
+        self.expect_token(&Token::Comma)?; // consume ','
+        self.consume_new_line_if_exist();
+
+        let end_included = self.expect_number()?;
+        if start > end_included {
+            return Err(Error::MessageWithLocation(
+                "The start of number range is greater than the end.".to_owned(),
+                self.last_range,
+            ));
+        }
+
+        self.consume_new_line_if_exist();
+        self.expect_token(&Token::RightParen)?; // consume ')'
+
+        Ok(Literal::NumberRange(NumberRange {
+            start,
+            end_included,
+        }))
+    }
+
     fn parse_charset(&mut self) -> Result<Vec<CharSetElement>, Error> {
         // "[" {char | char_range | preset_charset | status} "]" ?
         // ---                                                   -
@@ -736,21 +1176,24 @@ impl<'a> Parser<'a> {
                 }
                 Token::PresetCharSet(preset_charset_ref) => {
                     // preset char set
-                    let preset_charset = preset_charset_ref.to_owned();
+                    let preset_charset = preset_charset_ref.to_string();
                     self.next_token(); // consume preset charset
                     elements.push(CharSetElement::PresetCharSet(preset_charset));
                 }
                 Token::Status(status_ref) => {
                     // status
                     // such as "first", "last", "bound"
-                    let status = status_ref.to_owned();
+                    let status = status_ref.to_string();
                     self.next_token(); // consume status
                     elements.push(CharSetElement::Status(status));
                 }
                 _ => {
                     return Err(Error::MessageWithLocation(
-                        "Unexpected char set element.".to_owned(),
-                        self.last_range,
+                        format!(
+                            "Unexpected {} in the charset, expect a char, a char range, a preset charset or an assertion.",
+                            token.get_description()
+                        ),
+                        *self.peek_range(0).unwrap(),
                     ));
                 }
             }
@@ -773,18 +1216,51 @@ impl<'a> Parser<'a> {
         // |    | vali..  | validated
         // | current, validated
 
+        let start_range = *self.peek_range(0).unwrap();
         let char_start = self.expect_char()?; // consume start char
         self.consume_new_line_if_exist();
 
         self.next_token(); // consume '..'
         self.consume_new_line_if_exist();
 
-        let char_end = self.expect_char()?; // consume end char
+        // the escaped chars are chars too, e.g. `'\t'..'\r'` and
+        // `'\u{0}'..'\u{10ffff}'`.
+        let char_end = match self.peek_token(0) {
+            Some(Token::Char(c)) => {
+                let c = *c;
+                self.next_token(); // consume end char
+                c
+            }
+            Some(token) => {
+                return Err(Error::MessageWithLocation(
+                    format!(
+                        "Expect a char as the end of the char range, found {}.",
+                        token.get_description()
+                    ),
+                    *self.peek_range(0).unwrap(),
+                ));
+            }
+            None => {
+                return Err(Error::UnexpectedEndOfDocument(
+                    "Expect a char as the end of the char range.".to_owned(),
+                ));
+            }
+        };
 
-        Ok(CharRange {
+        let char_range = CharRange {
             start: char_start,
             end_included: char_end,
-        })
+        };
+
+        // the error covers the whole range
+        if let Err(Error::Message(message)) = char_range.validate() {
+            return Err(Error::MessageWithLocation(
+                message,
+                Location::from_range_pair(&start_range, &self.last_range),
+            ));
+        }
+
+        Ok(char_range)
     }
 }
 
@@ -794,6 +1270,34 @@ enum NotationQuantifier {
     AtLeast(u32),
 }
 
+// the maximum number of the arguments of the function besides the
+// expression, `None` if it takes any number of arguments.
+fn max_function_args(name: &FunctionName) -> Option<usize> {
+    match name {
+        FunctionName::Optional
+        | FunctionName::OneOrMore
+        | FunctionName::ZeroOrMore
+        | FunctionName::OptionalLazy
+        | FunctionName::OneOrMoreLazy
+        | FunctionName::ZeroOrMoreLazy
+        | FunctionName::Capture
+        | FunctionName::Normalized
+        | FunctionName::IgnoreCase => Some(0),
+        FunctionName::Repeat
+        | FunctionName::RepeatLazy
+        | FunctionName::AtLeast
+        | FunctionName::AtLeastLazy
+        | FunctionName::IsBefore
+        | FunctionName::IsAfter
+        | FunctionName::IsNotBefore
+        | FunctionName::IsNotAfter
+        | FunctionName::Name
+        | FunctionName::Tag => Some(1),
+        FunctionName::RepeatRange | FunctionName::RepeatRangeLazy => Some(2),
+        FunctionName::Line => None,
+    }
+}
+
 fn function_name_from_str(name_str: &str, range: &Location) -> Result<FunctionName, Error> {
     let name = match name_str {
         // Greedy quantifier
@@ -822,6 +1326,14 @@ fn function_name_from_str(name_str: &str, range: &Location) -> Result<FunctionNa
         "name" => FunctionName::Name,
         "capture" => FunctionName::Capture,
 
+        // String matching
+        "normalized" => FunctionName::Normalized,
+        "ignore_case" => FunctionName::IgnoreCase,
+
+        // Line
+        "line" => FunctionName::Line,
+        "tag" => FunctionName::Tag,
+
         // Unexpect
         _ => {
             return Err(Error::MessageWithLocation(
@@ -862,25 +1374,171 @@ fn function_name_from_notation_token(
 }
 
 pub fn parse_from_str(s: &str) -> Result<Program, Error> {
+    parse_from_str_with_limits(s, &Limits::default())
+}
+
+pub fn parse_from_str_with_limits(s: &str, limits: &Limits) -> Result<Program, Error> {
+    parse_from_str_with_vars(s, &HashMap::new(), limits)
+}
+
+// parse the pattern that references the external variables by `var(name)`,
+// see `CompileOptions::vars`.
+pub fn parse_from_str_with_vars(
+    s: &str,
+    vars: &HashMap<String, String>,
+    limits: &Limits,
+) -> Result<Program, Error> {
+    let (program, _) = parse_from_str_with_vars_and_locations(s, vars, limits)?;
+    Ok(program)
+}
+
+// like `parse_from_str_with_vars`, and returns the locations of the
+// expressions, i.e. `SourceMap::expressions`, the expressions that come
+// from a `define` have the locations inside the definition.
+pub(crate) fn parse_from_str_with_vars_and_locations(
+    s: &str,
+    vars: &HashMap<String, String>,
+    limits: &Limits,
+) -> Result<(Program, Vec<NodeLocation>), Error> {
     let tokens = lex_from_str(s)?;
     let clean_tokens = clean(tokens);
     let normalized_tokens = normalize(clean_tokens);
-    let expanded_tokens = expand(normalized_tokens)?;
-    let expanded_and_normalized_tokens = normalize(expanded_tokens);
-    let mut token_iter = expanded_and_normalized_tokens.into_iter();
-    let mut peekable_token_iter = PeekableIter::new(&mut token_iter, 3);
-    let mut parser = Parser::new(&mut peekable_token_iter);
-    parser.parse_program()
+    let substituted_tokens = substitute_variables(normalized_tokens, vars)?;
+    let expanded_tokens = expand(substituted_tokens, limits)?;
+    parse_tokens(expanded_tokens, limits)
+}
+
+// the intermediate results of parsing a pattern, see `parse_stages_from_str`.
+#[derive(Debug, PartialEq)]
+pub struct ParseStages {
+    // the tokens from the lexer, including the comments
+    pub tokens: Vec<TokenWithRange>,
+
+    // the tokens without the comments
+    pub cleaned: Vec<TokenWithRange>,
+
+    // the tokens with the blanks and commas merged
+    pub normalized: Vec<TokenWithRange>,
+
+    // the tokens with the variables substituted and the macros
+    // (both `define` and the built-in ones) expanded
+    pub expanded: Vec<TokenWithRange>,
+
+    pub program: Program,
+}
+
+// parse the pattern and keep the result of each stage of the pipeline
+// (lex → clean → normalize → expand → parse), e.g. for previewing what
+// a `define` expands to, or for debugging the parser.
+//
+// the program is the same as the one returned by `parse_from_str_with_vars`.
+pub fn parse_stages_from_str(
+    s: &str,
+    vars: &HashMap<String, String>,
+    limits: &Limits,
+) -> Result<ParseStages, Error> {
+    let tokens = lex_from_str(s)?;
+    let cleaned = clean(tokens.clone());
+    let normalized = normalize(cleaned.clone());
+    let substituted_tokens = substitute_variables(normalized.clone(), vars)?;
+    let expanded = expand(substituted_tokens, limits)?;
+    let program = parse_from_tokens(expanded.clone(), limits)?;
+
+    Ok(ParseStages {
+        tokens,
+        cleaned,
+        normalized,
+        expanded,
+        program,
+    })
+}
+
+// parse the tokens of any stage after lexing, the comments must have
+// been removed and the macros must have been expanded.
+pub fn parse_from_tokens(tokens: Vec<TokenWithRange>, limits: &Limits) -> Result<Program, Error> {
+    let (program, _) = parse_tokens(tokens, limits)?;
+    Ok(program)
+}
+
+// parse the pattern and returns the locations of the expressions and
+// the definitions along with the program, e.g. for syntax highlighting
+// and go-to-definition in editors.
+//
+// the expressions that come from a `define` are located at the
+// identifiers that refer to the definition.
+pub fn parse_from_str_with_locations(s: &str) -> Result<(Program, SourceMap), Error> {
+    parse_from_str_with_options(s, &ParseOptions::default())
+}
+
+// like `parse_from_str_with_locations`, and the comments are attached
+// to the source map if `keep_comments` is set.
+pub fn parse_from_str_with_options(
+    s: &str,
+    options: &ParseOptions,
+) -> Result<(Program, SourceMap), Error> {
+    let tokens = lex_from_str(s)?;
+    let comment_tokens = if options.keep_comments {
+        tokens.clone()
+    } else {
+        vec![]
+    };
+
+    let clean_tokens = clean(tokens);
+    let normalized_tokens = normalize(clean_tokens);
+    let substituted_tokens = substitute_variables(normalized_tokens, &HashMap::new())?;
+    let definitions = collect_definition_locations(&substituted_tokens);
+    let expanded_tokens = expand_at_references(substituted_tokens, &options.limits)?;
+    let (program, expressions) = parse_tokens(expanded_tokens, &options.limits)?;
+
+    let mut source_map = SourceMap {
+        expressions,
+        definitions,
+        comments: vec![],
+    };
+
+    if options.keep_comments {
+        attach_comments(&comment_tokens, &mut source_map);
+    }
+
+    Ok((program, source_map))
+}
+
+fn parse_tokens(
+    tokens: Vec<TokenWithRange>,
+    limits: &Limits,
+) -> Result<(Program, Vec<NodeLocation>), Error> {
+    let normalized_tokens = normalize(tokens);
+    let mut token_iter = normalized_tokens.into_iter();
+    let mut peekable_token_iter = PeekableIter::new(&mut token_iter, 3);
+    let mut parser = Parser::new(&mut peekable_token_iter, limits);
+    let program = parser.parse_program()?;
+    Ok((program, parser.locations))
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::collections::HashMap;
+
     use pretty_assertions::assert_eq;
 
-    use crate::ast::{CharRange, CharSet, CharSetElement, Expression, Literal, Program};
+    use crate::{
+        ast::{
+            quote, ByteRange, CharRange, CharSet, CharSetElement, DefinitionLocation, Expression,
+            Literal, NodeLocation, NumberRange, PatternOptions, Program, SelfTest, SyntaxVersion,
+        },
+        error::Error,
+        location::Location,
+        options::{Limits, ParseOptions},
+        process::Process,
+        token::{Token, TokenWithRange, FUNCTION_NAMES},
+    };
 
-    use super::parse_from_str;
+    use super::{
+        function_name_from_str, parse_from_str, parse_from_str_with_limits,
+        parse_from_str_with_locations, parse_from_str_with_options, parse_from_tokens,
+        parse_stages_from_str,
+    };
 
     #[test]
     fn test_parse_literal_simple() {
@@ -900,11 +1558,42 @@ start, 'a', "foo", char_word
                     Expression::Literal(Literal::Char('a')),
                     Expression::Literal(Literal::String("foo".to_owned())),
                     Expression::Literal(Literal::PresetCharSet("char_word".to_owned())),
-                ]
+                ],
+                tests: vec![],
+                options: PatternOptions::default(),
+                syntax_version: None,
             }
         );
 
         assert_eq!(program.to_string(), r#"start, 'a', "foo", char_word"#);
+
+        // the function form of `match_start_reset`
+        assert_eq!(
+            parse_from_str(r#"'a', match_start_reset(), 'b'"#)
+                .unwrap()
+                .to_string(),
+            r#"'a', match_start_reset, 'b'"#
+        );
+    }
+
+    #[test]
+    fn test_parse_leftover_tokens() {
+        assert_eq!(
+            parse_from_str(r#"'a' 'b'"#),
+            Err(Error::MessageWithLocation(
+                "Expect a comma or a new line, found char \"b\".".to_owned(),
+                Location::new_range(0, 4, 0, 4, 3)
+            ))
+        );
+
+        for s in [
+            r#"'a' )"#,
+            r#"start(), 'a'"#,
+            r#"'a', char_any()"#,
+            r#"'a', match_start_reset(, 'b'"#,
+        ] {
+            assert!(parse_from_str(s).is_err(), "{}", s);
+        }
     }
 
     #[test]
@@ -931,7 +1620,10 @@ start, 'a', "foo", char_word
                         CharSetElement::PresetCharSet("char_word".to_owned()),
                         CharSetElement::Status("end".to_owned())
                     ]
-                })),]
+                })),],
+                tests: vec![],
+                options: PatternOptions::default(),
+                syntax_version: None,
             }
         );
 
@@ -984,6 +1676,150 @@ start, 'a', "foo", char_word
             .to_string(),
             r#"['a', '0'..'9', char_word, end]"#
         );
+
+        // the unexpected element
+        assert!(matches!(
+            parse_from_str(r#"['a', "bc"]"#),
+            Err(Error::MessageWithLocation(message, location))
+                if message == "Unexpected string in the charset, expect a char, a char range, a preset charset or an assertion."
+                    && location.index == 6
+        ));
+        assert!(matches!(
+            parse_from_str(r#"['a', ['b']]"#),
+            Err(Error::MessageWithLocation(message, _))
+                if message.starts_with("Unexpected left bracket \"[\" in the charset")
+        ));
+
+        // the escaped chars as the ends of ranges
+        assert_eq!(
+            parse_from_str(r#"['\t'..'\r', '\x00'..'\x7f', '\u{4e00}'..'\u{9fff}', 'a'..'a']"#)
+                .unwrap()
+                .to_string(),
+            r#"['\t'..'\r', '\0'..'\u{7f}', '一'..'鿿', 'a'..'a']"#
+        );
+
+        // err: the start is greater than the end, the error covers the range
+        assert_eq!(
+            parse_from_str("['0'..'9', 'z'\n..'a']"),
+            Err(Error::MessageWithLocation(
+                "The start of char range 'z'..'a' is greater than the end.".to_owned(),
+                Location::new_range(0, 11, 0, 11, 9)
+            ))
+        );
+        assert!(parse_from_str(r#"['\u{10ffff}'..'\0']"#).is_err());
+
+        // err: the end is not a char
+        assert_eq!(
+            parse_from_str(r#"['a'..char_word]"#),
+            Err(Error::MessageWithLocation(
+                "Expect a char as the end of the char range, found preset charset \"char_word\"."
+                    .to_owned(),
+                Location::new_range(0, 6, 0, 6, 9)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_literal_byte() {
+        let program = parse_from_str(
+            r#"
+byte(0x7f), byte_range(0x80..0xff), byte(10)
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            program,
+            Program {
+                expressions: vec![
+                    Expression::Literal(Literal::Byte(0x7f)),
+                    Expression::Literal(Literal::ByteRange(ByteRange {
+                        start: 0x80,
+                        end_included: 0xff
+                    })),
+                    Expression::Literal(Literal::Byte(10)),
+                ],
+                tests: vec![],
+                options: PatternOptions::default(),
+                syntax_version: None,
+            }
+        );
+
+        assert_eq!(
+            program.to_string(),
+            r#"byte(0x7f), byte_range(0x80..0xff), byte(0x0a)"#
+        );
+
+        // err: exceeds 0xff
+        assert!(parse_from_str(r#"byte(0x100)"#).is_err());
+
+        // err: reversed range
+        assert!(parse_from_str(r#"byte_range(0xff..0x80)"#).is_err());
+
+        // err: not a number
+        assert!(parse_from_str(r#"byte('a')"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_literal_number_range() {
+        let program = parse_from_str(
+            r#"
+number_range(0, 255), number_range(
+    1900,
+    2099
+)
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            program,
+            Program {
+                expressions: vec![
+                    Expression::Literal(Literal::NumberRange(NumberRange {
+                        start: 0,
+                        end_included: 255
+                    })),
+                    Expression::Literal(Literal::NumberRange(NumberRange {
+                        start: 1900,
+                        end_included: 2099
+                    })),
+                ],
+                tests: vec![],
+                options: PatternOptions::default(),
+                syntax_version: None,
+            }
+        );
+
+        assert_eq!(
+            program.to_string(),
+            r#"number_range(0, 255), number_range(1900, 2099)"#
+        );
+
+        // err: reversed range
+        assert!(parse_from_str(r#"number_range(255, 0)"#).is_err());
+
+        // err: missing the end
+        assert!(parse_from_str(r#"number_range(1)"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_quoted_literal() {
+        for text in [
+            "",
+            "abc",
+            r#"a"b'c\d"#,
+            "'a', char_word+ || (end)",
+            "tab\t, new line\n\r, nul\0, esc\u{1b}",
+            "文字",
+        ] {
+            assert_eq!(
+                parse_from_str(&quote(text)).unwrap(),
+                Program::literal(text)
+            );
+        }
+
+        assert_eq!(quote("a\"b\n"), r#""a\"b\n""#);
     }
 
     #[test]
@@ -1068,6 +1904,79 @@ at_least('c', 11)"#
         );
     }
 
+    #[test]
+    fn test_parse_expression_function_call_expression_args() {
+        assert_eq!(
+            parse_from_str(
+                r#"
+'a'.is_before('b' || "cd")
+is_not_after('x', capture(char_digit+))
+"#,
+            )
+            .unwrap()
+            .to_string(),
+            r#"is_before('a', 'b' || "cd")
+is_not_after('x', capture(one_or_more(char_digit)))"#
+        );
+
+        assert_eq!(
+            parse_from_str(r#"line_start, line(("ERROR", char_any+)), line_end"#)
+                .unwrap()
+                .to_string(),
+            "line_start\nline((\"ERROR\", one_or_more(char_any)))\nline_end"
+        );
+
+        assert_eq!(
+            parse_from_str(r#"'a'.tag("x") || tag(char_digit+, "y")"#)
+                .unwrap()
+                .to_string(),
+            r#"tag('a', "x") || tag(one_or_more(char_digit), "y")"#
+        );
+
+        assert_eq!(
+            parse_from_str(r#""select".ignore_case(), ignore_case('a'+)"#)
+                .unwrap()
+                .to_string(),
+            "ignore_case(\"select\")\nignore_case(one_or_more('a'))"
+        );
+
+        // the sequence argument is wrapped in parentheses
+        assert_eq!(
+            parse_from_str(r#"'a'.is_before(('b', 'c') || 'd')"#)
+                .unwrap()
+                .to_string(),
+            r#"is_before('a', ('b', 'c') || 'd')"#
+        );
+
+        // err: the extra argument, the error points at the comma before it
+        assert_eq!(
+            parse_from_str(r#"'a'.is_before('b', 'c')"#),
+            Err(Error::MessageWithLocation(
+                "Unexpected argument 2 of the function \"is_before\", it takes 1 argument \
+                 besides the expression. The comma separates the arguments, wrap the \
+                 expressions in parentheses to pass a sequence as one expression, \
+                 e.g. `('a', 'b')`."
+                    .to_owned(),
+                Location::new_range(0, 17, 0, 17, 1)
+            ))
+        );
+
+        assert!(matches!(
+            parse_from_str("one_or_more('a'\n'b')"),
+            Err(Error::MessageWithLocation(
+                message,
+                Location { line: 0, column: 15, .. }
+            )) if message.starts_with("Unexpected argument 1 of the function \"one_or_more\", \
+                it takes no argument")
+        ));
+        assert!(parse_from_str(r#"char_digit.repeat_range(1, 2, 3)"#).is_err());
+        assert!(parse_from_str(r#"name('a', x, y)"#).is_err());
+        assert!(parse_from_str(r#"tag('a', "x", "y")"#).is_err());
+
+        // the trailing comma
+        assert!(parse_from_str("'a'.is_before(\n'b',\n)").is_ok());
+    }
+
     #[test]
     fn test_parse_expression_notations() {
         assert_eq!(
@@ -1127,10 +2036,13 @@ at_least_lazy('z', 11)"#
                 program,
                 Program {
                     // definitions: vec![],
-                    expressions: vec![Expression::Or(
-                        Box::new(Expression::Literal(Literal::Char('a'))),
-                        Box::new(Expression::Literal(Literal::Char('b'))),
-                    )]
+                    expressions: vec![Expression::OrList(vec![
+                        Expression::Literal(Literal::Char('a')),
+                        Expression::Literal(Literal::Char('b')),
+                    ])],
+                    tests: vec![],
+                    options: PatternOptions::default(),
+                    syntax_version: None,
                 }
             );
 
@@ -1149,13 +2061,14 @@ at_least_lazy('z', 11)"#
                 program,
                 Program {
                     // definitions: vec![],
-                    expressions: vec![Expression::Or(
-                        Box::new(Expression::Literal(Literal::Char('a'))),
-                        Box::new(Expression::Or(
-                            Box::new(Expression::Literal(Literal::Char('b'))),
-                            Box::new(Expression::Literal(Literal::Char('c'))),
-                        )),
-                    )]
+                    expressions: vec![Expression::OrList(vec![
+                        Expression::Literal(Literal::Char('a')),
+                        Expression::Literal(Literal::Char('b')),
+                        Expression::Literal(Literal::Char('c')),
+                    ])],
+                    tests: vec![],
+                    options: PatternOptions::default(),
+                    syntax_version: None,
                 }
             );
 
@@ -1172,6 +2085,38 @@ char_digit.one_or_more() || [char_word, '-']+
             .to_string(),
             r#"one_or_more(char_digit) || one_or_more([char_word, '-'])"#
         );
+
+        // empty alternatives
+        assert_eq!(
+            parse_from_str(
+                r#"('a' || ), ( || 'b'), ('c' ||
+), 'd'"#
+            )
+            .unwrap()
+            .to_string(),
+            r#"('a' || ()), (() || 'b'), ('c' || ()), 'd'"#
+        );
+
+        // the parenthesized alternation is an alternative
+        assert_eq!(
+            parse_from_str(r#"('a' || 'b') || 'c' || () || 'd'"#)
+                .unwrap()
+                .to_string(),
+            r#"('a' || 'b') || 'c' || () || 'd'"#
+        );
+
+        assert_eq!(
+            parse_from_str(r#"'a' || "#).unwrap(),
+            Program {
+                expressions: vec![Expression::OrList(vec![
+                    Expression::Literal(Literal::Char('a')),
+                    Expression::Group(vec![])
+                ])],
+                tests: vec![],
+                options: PatternOptions::default(),
+                syntax_version: None,
+            }
+        );
     }
 
     #[test]
@@ -1372,4 +2317,404 @@ one_or_more_lazy(char_any)
 '<', '/', tag_name, '>'"
         );
     }
+
+    #[test]
+    fn test_parse_with_locations() {
+        let node = |index: usize, length: usize, children: Vec<NodeLocation>| {
+            NodeLocation::new(Location::new_range(0, index, 0, index, length), children)
+        };
+
+        // 0         1
+        // 0123456789012345678
+        // 'a', ("bc" || 'd')+
+        let (program, source_map) =
+            parse_from_str_with_locations(r#"'a', ("bc" || 'd')+"#).unwrap();
+        assert_eq!(program.to_string(), "'a'\none_or_more((\"bc\" || 'd'))");
+        assert_eq!(
+            source_map.expressions,
+            vec![
+                node(0, 3, vec![]),
+                node(
+                    5,
+                    14,
+                    vec![node(
+                        5,
+                        13,
+                        vec![node(6, 11, vec![node(6, 4, vec![]), node(14, 3, vec![])])]
+                    )]
+                )
+            ]
+        );
+        assert!(source_map.definitions.is_empty());
+
+        // the arguments of function call, and the empty alternative
+        // 0         1         2
+        // 012345678901234567890123
+        // 'a'.is_before('b' || )
+        let (_, source_map) = parse_from_str_with_locations(r#"'a'.is_before('b' || )"#).unwrap();
+        assert_eq!(
+            source_map.expressions,
+            vec![node(
+                0,
+                22,
+                vec![
+                    node(0, 3, vec![]),
+                    node(14, 6, vec![node(14, 3, vec![]), node(20, 0, vec![])])
+                ]
+            )]
+        );
+
+        // definitions
+        let (_, source_map) = parse_from_str_with_locations(
+            r#"define(d, char_digit)
+d+, d"#,
+        )
+        .unwrap();
+        assert_eq!(
+            source_map.definitions,
+            vec![DefinitionLocation {
+                name: "d".to_owned(),
+                name_location: Location::new_range(0, 7, 0, 7, 1),
+                location: Location::new_range(0, 0, 0, 0, 21),
+                references: vec![
+                    Location::new_range(0, 22, 1, 0, 1),
+                    Location::new_range(0, 26, 1, 4, 1),
+                ],
+                doc_comments: vec![],
+                comments: vec![]
+            }]
+        );
+
+        // the expressions of definition are located at the references
+        assert_eq!(
+            source_map.expressions,
+            vec![
+                NodeLocation::new(
+                    Location::new_range(0, 22, 1, 0, 2),
+                    vec![NodeLocation::new(
+                        Location::new_range(0, 22, 1, 0, 1),
+                        vec![]
+                    )]
+                ),
+                NodeLocation::new(Location::new_range(0, 26, 1, 4, 1), vec![])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        // the default limit of the nesting depth
+        let source = "(".repeat(200) + "'a'" + &")".repeat(200);
+        assert!(matches!(
+            parse_from_str(&source),
+            Err(Error::MessageWithLocation(..))
+        ));
+
+        let limits = Limits {
+            max_nesting_depth: 3,
+            max_nodes: 5,
+            ..Limits::default()
+        };
+
+        assert!(parse_from_str_with_limits(r#"('a', ('b'))"#, &limits).is_ok());
+        assert!(parse_from_str_with_limits(r#"('a', (('b')))"#, &limits).is_err());
+
+        // the chained function calls and notations are nested
+        assert!(parse_from_str_with_limits(r#"'a'.optional()+"#, &limits).is_ok());
+        assert!(parse_from_str_with_limits(r#"'a'.optional()+.optional()"#, &limits).is_err());
+        assert!(parse_from_str_with_limits(r#"'a'{2}.optional(), 'b'*"#, &limits).is_ok());
+
+        let source = "'a'".to_owned() + &".optional()".repeat(20_000);
+        assert!(matches!(
+            parse_from_str(&source),
+            Err(Error::MessageWithLocation(..))
+        ));
+
+        // the alternation counts as an expression
+        assert!(parse_from_str_with_limits(r#"'a' || 'b' || 'c' || 'd'"#, &limits).is_ok());
+        assert!(parse_from_str_with_limits(r#"'a' || 'b' || 'c' || 'd' || 'e'"#, &limits).is_err());
+
+        // the quantifiers count as expressions
+        assert!(parse_from_str_with_limits(r#"'a'+, 'b', 'c'"#, &limits).is_ok());
+        assert!(parse_from_str_with_limits(r#"'a'+, 'b'+, 'c'+"#, &limits).is_err());
+
+        let options = ParseOptions {
+            limits,
+            ..ParseOptions::default()
+        };
+        assert!(parse_from_str_with_options(r#"'a'+, 'b'+, 'c'+"#, &options).is_err());
+    }
+    #[test]
+    fn test_parse_self_tests() {
+        let program = parse_from_str(
+            r#"
+define(user, char_word+)
+user, '@', user
+expect_match("foo@example")
+expect_no_match(
+    "foo@"
+)"#,
+        )
+        .unwrap();
+
+        assert_eq!(program.expressions.len(), 3);
+        assert_eq!(
+            program.tests,
+            vec![
+                SelfTest {
+                    text: "foo@example".to_owned(),
+                    should_match: true
+                },
+                SelfTest {
+                    text: "foo@".to_owned(),
+                    should_match: false
+                },
+            ]
+        );
+
+        // the test cases are written after the expressions
+        assert_eq!(
+            program.to_string(),
+            r#"one_or_more(char_word)
+'@'
+one_or_more(char_word)
+expect_match("foo@example")
+expect_no_match("foo@")"#
+        );
+        assert_eq!(parse_from_str(&program.to_string()).unwrap(), program);
+
+        // err: the text is not a string
+        assert!(matches!(
+            parse_from_str(r#"'a', expect_match('a')"#),
+            Err(Error::MessageWithLocation(..))
+        ));
+
+        // err: the test case is not at the top level
+        assert!(parse_from_str(r#"('a', expect_match("a"))"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let program = parse_from_str(
+            r#"
+options(
+    multiline
+    ignore_case
+)
+'a', end"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.options,
+            PatternOptions {
+                ignore_case: true,
+                multiline: true,
+                ..PatternOptions::default()
+            }
+        );
+        assert_eq!(program.expressions.len(), 2);
+
+        assert_eq!(
+            program.to_string(),
+            r#"options(ignore_case, multiline)
+'a', end"#
+        );
+        assert_eq!(parse_from_str(&program.to_string()).unwrap(), program);
+
+        // the empty options
+        assert_eq!(
+            parse_from_str(r#"options(), 'a'"#).unwrap().options,
+            PatternOptions::default()
+        );
+
+        // err: unknown option
+        assert!(matches!(
+            parse_from_str(r#"options(ignore_case, foo)"#),
+            Err(Error::MessageWithLocation(
+                _,
+                Location {
+                    index: 21,
+                    length: 3,
+                    ..
+                }
+            ))
+        ));
+
+        // err: the options are not at the top
+        assert!(matches!(
+            parse_from_str(r#"'a', options(dot_all)"#),
+            Err(Error::MessageWithLocation(..))
+        ));
+
+        // err: not an identifier
+        assert!(parse_from_str(r#"options("dot_all")"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_syntax_version() {
+        let program = parse_from_str(
+            r#"syntax_version(1)
+options(dot_all)
+'a'"#,
+        )
+        .unwrap();
+        assert_eq!(program.syntax_version, Some(SyntaxVersion::V1));
+        assert!(program.options.dot_all);
+        assert_eq!(
+            program.to_string(),
+            "syntax_version(1)\noptions(dot_all)\n'a'"
+        );
+        assert_eq!(parse_from_str(&program.to_string()).unwrap(), program);
+
+        // not declared
+        assert_eq!(parse_from_str(r#"'a'"#).unwrap().syntax_version, None);
+
+        // err: unsupported version
+        assert!(matches!(
+            parse_from_str(r#"syntax_version(2), 'a'"#),
+            Err(Error::MessageWithLocation(message, location))
+                if message == "Unsupported syntax version 2, the latest version is 1."
+                    && location.index == 15
+        ));
+
+        // err: not at the beginning
+        assert!(parse_from_str(r#"options(dot_all), syntax_version(1)"#).is_err());
+        assert!(parse_from_str(r#"syntax_version(1), syntax_version(1)"#).is_err());
+        assert!(parse_from_str(r#"'a', syntax_version(1)"#).is_err());
+
+        // err: not a number
+        assert!(parse_from_str(r#"syntax_version(v1)"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_stages() {
+        let source = r#"define(pair, (char_word+, '=', char_word+)) // the pair
+
+pair, var(sep),
+pair"#;
+        let vars = HashMap::from([("sep".to_owned(), ";".to_owned())]);
+        let stages = parse_stages_from_str(source, &vars, &Limits::default()).unwrap();
+
+        let count = |tokens: &[TokenWithRange], token: Token| {
+            tokens.iter().filter(|t| t.token == token).count()
+        };
+
+        // the comment is removed by cleaning
+        assert!(stages
+            .tokens
+            .iter()
+            .any(|t| matches!(t.token, Token::Comment(_))));
+        assert_eq!(stages.cleaned.len(), stages.tokens.len() - 1);
+
+        // the blanks and the commas are merged by normalizing
+        assert_eq!(count(&stages.cleaned, Token::NewLine), 3);
+        assert_eq!(count(&stages.normalized, Token::NewLine), 1);
+
+        // the definition is expanded and the variable is substituted
+        assert_eq!(count(&stages.expanded, Token::new_identifier("pair")), 0);
+        assert_eq!(count(&stages.expanded, Token::Char(';')), 1);
+
+        assert_eq!(
+            stages.program.to_string(),
+            r#"(one_or_more(char_word), '=', one_or_more(char_word)), ';', (one_or_more(char_word), '=', one_or_more(char_word))"#
+        );
+        assert_eq!(
+            parse_from_tokens(stages.expanded, &Limits::default()).unwrap(),
+            stages.program
+        );
+    }
+
+    #[test]
+    fn test_parse_quoted_identifiers() {
+        let program = parse_from_str(
+            r#"name(char_word+, `tag name`), '=', `tag name`, name('x', `start`), `start`, start"#,
+        )
+        .unwrap();
+
+        // the names are quoted only when necessary
+        assert_eq!(
+            program.to_string(),
+            r#"name(one_or_more(char_word), `tag name`)
+'=', `tag name`
+name('x', `start`)
+`start`, start"#
+        );
+        assert_eq!(parse_from_str(&program.to_string()).unwrap(), program);
+
+        // the escaped chars
+        let program = parse_from_str(r#"name('a', `a\`b\\c\td`), `x`, `_1`, `1a`"#).unwrap();
+        assert_eq!(
+            program.to_string(),
+            r#"name('a', `a\`b\\c\td`)
+x, _1, `1a`"#
+        );
+        assert_eq!(parse_from_str(&program.to_string()).unwrap(), program);
+
+        // the quoted macro names
+        let program = parse_from_str(
+            r#"
+define(`my macro`, 'a')
+define(`start`, 'b')
+`my macro`, `start`, start"#,
+        )
+        .unwrap();
+        assert_eq!(program.to_string(), r#"'a', 'b', start"#);
+
+        // the back-references to the groups with quoted names
+        let process = Process::new(r#"name(char_digit, `the digit`), '-', `the digit`"#).unwrap();
+        assert_eq!(process.capture_group_index("the digit"), Some(1));
+        assert!(process.new_instance("1-1").is_match(0));
+        assert!(!process.new_instance("1-2").is_match(0));
+    }
+
+    #[test]
+    fn test_parse_reserved_capture_names() {
+        let error = |s: &str| match parse_from_str(s) {
+            Err(Error::MessageWithLocation(message, location)) => (message, location.index),
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert_eq!(
+            error(r#"name('a', char_word)"#),
+            (
+                "The capture group name \"char_word\" conflicts with the preset charset \"char_word\"."
+                    .to_owned(),
+                10
+            )
+        );
+        assert_eq!(
+            error(r#"'a'.name(start)"#),
+            (
+                "The capture group name \"start\" conflicts with the assertion \"start\"."
+                    .to_owned(),
+                9
+            )
+        );
+        assert_eq!(
+            error(r#"name('a', char_any)"#).0,
+            "The capture group name \"char_any\" conflicts with the special char \"char_any\"."
+        );
+        assert_eq!(
+            error(r#"name('a', optional)"#).0,
+            "The capture group name \"optional\" conflicts with the function \"optional\"."
+        );
+        assert_eq!(
+            error(r#"'a'.name(define)"#).0,
+            "The capture group name \"define\" conflicts with the keyword \"define\"."
+        );
+
+        // the names that contain the reserved words
+        assert!(parse_from_str(r#"name('a', start_2), name('b', my_optional)"#).is_ok());
+
+        // the quoted names
+        assert!(parse_from_str(r#"name('a', `start`), name('b', `optional`)"#).is_ok());
+
+        // all function names are known by the parser
+        for name in FUNCTION_NAMES {
+            if !["number_range", "byte", "byte_range"].contains(&name) {
+                assert!(function_name_from_str(name, &Location::new_position(0, 0, 0, 0)).is_ok());
+            }
+        }
+    }
 }
@@ -0,0 +1,245 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the information of a pattern that is known without matching, e.g.
+// for showing the capture groups of the pattern that the user is
+// typing, or for validating the patterns of a configuration file:
+//
+// ```
+// let info = pattern_info(r#"start, name(char_digit+, id), end"#)?;
+// assert_eq!(info.groups.len(), 2);
+// assert!(info.anchored_start && info.anchored_end);
+// ```
+//
+// the anchors are the `start` and `end` assertions without the option
+// `multiline`, a pattern is anchored only if all of its alternatives
+// are anchored, e.g. `(start, 'a') || 'b'` is not anchored.
+
+use crate::{
+    compiler::compile_from_str_with,
+    error::Error,
+    options::CompileOptions,
+    state::{CaptureGroup, StateSet},
+    transition::{Status, StatusTransition, StringTransition, Transition},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternInfo {
+    // the capture groups, the index 0 is the whole match
+    pub groups: Vec<CaptureGroup>,
+
+    // the minimum and maximum length of a match in chars, the maximum
+    // is `None` if it is unbounded.
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+
+    // whether every match starts at the start of text, or ends at the
+    // end of text.
+    pub anchored_start: bool,
+    pub anchored_end: bool,
+
+    pub uses_back_references: bool,
+}
+
+impl PatternInfo {
+    pub(crate) fn new(state_set: &StateSet) -> Self {
+        PatternInfo {
+            groups: state_set.capture_groups.clone(),
+            min_length: state_set.length_bounds.min,
+            max_length: state_set.length_bounds.max,
+            anchored_start: is_anchored(state_set, Status::Start),
+            anchored_end: is_anchored(state_set, Status::End),
+            uses_back_references: state_set.has_back_references,
+        }
+    }
+}
+
+pub fn pattern_info(pattern: &str) -> Result<PatternInfo, Error> {
+    pattern_info_with(pattern, &CompileOptions::default())
+}
+
+pub fn pattern_info_with(pattern: &str, options: &CompileOptions) -> Result<PatternInfo, Error> {
+    let state_set = compile_from_str_with(pattern, options)?;
+    Ok(PatternInfo::new(&state_set))
+}
+
+// whether all routes from the start state (for `Status::Start`), or all
+// routes to the end state (for `Status::End`) pass the anchor before
+// consuming any char.
+//
+// the routes are walked through the transitions that consume nothing,
+// the counters are ignored, so the routes that are never taken may make
+// the pattern not anchored, but not the other way around.
+fn is_anchored(state_set: &StateSet, anchor: Status) -> bool {
+    let backward = anchor == Status::End;
+    let state_count = state_set.state_count();
+
+    // the transitions by the target state index for walking backward
+    let mut incoming: Vec<Vec<(usize, &Transition)>> = vec![];
+    if backward {
+        incoming.resize(state_count, vec![]);
+        for state_index in 0..state_count {
+            let mut next_link_index = state_set.get_first_link_index(state_index);
+            while let Some(link_index) = next_link_index {
+                let (transition, target_state_index, next) = state_set.get_link(link_index);
+                incoming[target_state_index].push((state_index, transition));
+                next_link_index = next;
+            }
+        }
+    }
+
+    let (first_state_index, last_state_index) = if backward {
+        (state_set.end_node_index, state_set.start_node_index)
+    } else {
+        (state_set.start_node_index, state_set.end_node_index)
+    };
+
+    let mut visited = vec![false; state_count];
+    let mut pending = vec![first_state_index];
+
+    while let Some(state_index) = pending.pop() {
+        if state_index == last_state_index {
+            // the empty route without the anchor
+            return false;
+        }
+
+        if visited[state_index] {
+            continue;
+        }
+        visited[state_index] = true;
+
+        let mut links = vec![];
+        if backward {
+            links.extend(incoming[state_index].iter().copied());
+        } else {
+            let mut next_link_index = state_set.get_first_link_index(state_index);
+            while let Some(link_index) = next_link_index {
+                let (transition, target_state_index, next) = state_set.get_link(link_index);
+                links.push((target_state_index, transition));
+                next_link_index = next;
+            }
+        }
+
+        for (state_index, transition) in links {
+            match transition {
+                Transition::Status(StatusTransition {
+                    status,
+                    multiline: false,
+                    ..
+                }) if *status == anchor => {}
+                Transition::Jump(_)
+                | Transition::Status(_)
+                | Transition::CounterReset(_)
+                | Transition::CounterCheck(_)
+                | Transition::CounterInc(_)
+                | Transition::CounterExit(_)
+                | Transition::CaptureStart(_)
+                | Transition::CaptureEnd(_)
+                | Transition::LookAround(_)
+                | Transition::MatchStartReset(_)
+                | Transition::Tag(_) => pending.push(state_index),
+                Transition::String(StringTransition { chars, .. }) if chars.is_empty() => {
+                    pending.push(state_index)
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{options::CompileOptions, process::Process, state::CaptureGroup};
+
+    use super::{pattern_info, pattern_info_with, PatternInfo};
+
+    #[test]
+    fn test_pattern_info() {
+        assert_eq!(
+            pattern_info(r#"start, name(char_digit+, id), '-', id, end"#).unwrap(),
+            PatternInfo {
+                groups: vec![
+                    CaptureGroup {
+                        index: 0,
+                        name: None,
+                        in_repetition: false,
+                        in_look_around: false,
+                    },
+                    CaptureGroup {
+                        index: 1,
+                        name: Some("id".to_owned()),
+                        in_repetition: false,
+                        in_look_around: false,
+                    },
+                ],
+                min_length: 2, // the back-reference may be empty
+                max_length: None,
+                anchored_start: true,
+                anchored_end: true,
+                uses_back_references: true,
+            }
+        );
+
+        let info = pattern_info(r#"'a'{2,3}, capture('b'?)"#).unwrap();
+        assert_eq!(info.groups.len(), 2);
+        assert_eq!((info.min_length, info.max_length), (2, Some(4)));
+        assert!(!info.anchored_start && !info.anchored_end);
+        assert!(!info.uses_back_references);
+
+        // err: the invalid pattern
+        assert!(pattern_info(r#"'a', x"#).is_err());
+
+        // the same as the process
+        let process = Process::new(r#"name('a', x)+, "bc""#).unwrap();
+        assert_eq!(
+            process.info(),
+            pattern_info(r#"name('a', x)+, "bc""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pattern_info_anchors() {
+        let anchors = |s: &str| {
+            let info = pattern_info(s).unwrap();
+            (info.anchored_start, info.anchored_end)
+        };
+
+        assert_eq!(anchors(r#"start, 'a'"#), (true, false));
+        assert_eq!(anchors(r#"'a', end"#), (false, true));
+
+        // the zero-width expressions before the anchors
+        assert_eq!(
+            anchors(r#"capture((start, 'a'.is_before('b'))), char_word*, end"#),
+            (true, true)
+        );
+
+        // all alternatives must be anchored
+        assert_eq!(anchors(r#"start, ("a" || "b"), end"#), (true, true));
+        assert_eq!(anchors(r#"(start, 'a') || (start, 'b')"#), (true, false));
+        assert_eq!(anchors(r#"start, 'a' || 'b', end"#), (true, true));
+        assert_eq!(anchors(r#"(start, 'a') || 'b', end"#), (false, true));
+        assert_eq!(anchors(r#"start?, 'a'"#), (false, false));
+
+        // the chars before the anchors
+        assert_eq!(anchors(r#"'a'*, start"#), (false, false));
+
+        // the line boundaries are not anchors
+        assert_eq!(
+            anchors(r#"options(multiline), start, 'a', end"#),
+            (false, false)
+        );
+        let options = CompileOptions {
+            multiline: true,
+            ..CompileOptions::default()
+        };
+        let info = pattern_info_with(r#"start, 'a', end"#, &options).unwrap();
+        assert!(!info.anchored_start && !info.anchored_end);
+    }
+}
@@ -0,0 +1,3851 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::{
+    collections::BTreeMap,
+    ops::{ControlFlow, Range},
+};
+
+use crate::{
+    ast::Program,
+    batch::MatchAll,
+    captures::{borrow_matches, capture_spans, Captures, FromCaptures, Match},
+    compiler::{compile, compile_from_str_with},
+    context::Context,
+    debugjson::generate_debug_json,
+    equivalence::{is_equivalent, is_subset},
+    error::Error,
+    generator::Generator,
+    options::{CompileOptions, MatchLimits},
+    patterninfo::PatternInfo,
+    serialization::{deserialize, serialize},
+    state::{CaptureGroup, LookAroundRoute, StateSet},
+    transition::{
+        CaptureSlot, CharTransition, Counter, FrameState, LookAroundTransition, StringTransition,
+        Transition, TransitionTrait,
+    },
+    window::WindowMatches,
+};
+
+pub struct Process {
+    state_set: StateSet,
+}
+
+impl Process {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        Self::new_with_options(pattern, &CompileOptions::default())
+    }
+
+    pub fn new_with_options(pattern: &str, options: &CompileOptions) -> Result<Self, Error> {
+        let state_set = compile_from_str_with(pattern, options)?;
+        Ok(Process { state_set })
+    }
+
+    // create a process from a parsed program, e.g. a program that
+    // is combined by `Program::concat` and `Program::alternate`.
+    pub fn from_program(program: &Program, options: &CompileOptions) -> Result<Self, Error> {
+        let state_set = compile(program, options)?;
+        Ok(Process { state_set })
+    }
+
+    // load a process from the bytes serialized by `to_bytes` or
+    // generated by `generate_rust_source`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let state_set = deserialize(bytes)?;
+        Ok(Process { state_set })
+    }
+
+    // serialize the compiled states, so that the process can be
+    // loaded by `from_bytes` without compiling the pattern again.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize(&self.state_set)
+    }
+
+    // the capture groups ordered by their indices, the first one is
+    // the group 0, i.e. the whole match.
+    //
+    // the groups are numbered by the order of their names in the source,
+    // the inner group comes before the outer one, see
+    // `StateSet::capture_groups` for the details.
+    pub fn capture_groups(&self) -> &[CaptureGroup] {
+        &self.state_set.capture_groups
+    }
+
+    // the names of the capture groups ordered by their indices, the
+    // group 0 and the groups created by `capture()` have no name, e.g.
+    // `[None, Some("year"), None]` for the pattern
+    // `name(char_digit{4}, year), capture('-')`.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.state_set
+            .capture_groups
+            .iter()
+            .map(|group| group.name.as_deref())
+    }
+
+    // the index of the capture group with the name.
+    pub fn capture_group_index(&self, name: &str) -> Option<usize> {
+        find_capture_group_index(&self.state_set, name)
+    }
+
+    // the number of capture groups, not including the group 0.
+    pub fn capture_group_count(&self) -> usize {
+        self.state_set.capture_groups.len() - 1
+    }
+
+    // the minimum number of chars of a match, e.g. it is 3 for
+    // the pattern `"ab" || "cde"`.
+    //
+    // in bytes mode, a match takes at least the same number of bytes.
+    pub fn min_match_length(&self) -> usize {
+        self.state_set.length_bounds.min
+    }
+
+    // the maximum number of chars of a match, it is `None` if the pattern
+    // contains an unbounded repetition, e.g. `char_digit+`.
+    //
+    // in bytes mode, a char of the pattern may match up to 4 bytes.
+    pub fn max_match_length(&self) -> Option<usize> {
+        self.state_set.length_bounds.max
+    }
+
+    // the maximum number of bytes of a match in bytes mode, it is
+    // `None` if the pattern contains an unbounded repetition.
+    pub fn max_match_bytes(&self) -> Option<usize> {
+        self.state_set.byte_length_bounds.max
+    }
+
+    // the number of the compiled states, together with the methods
+    // below, it is useful for comparing the compilations of patterns.
+    pub fn state_count(&self) -> usize {
+        self.state_set.state_count()
+    }
+
+    pub fn transition_count(&self) -> usize {
+        self.state_set.transition_count()
+    }
+
+    // the number of transitions of each kind, e.g. "char", "jump"
+    // and "counter_check".
+    pub fn transition_kind_counts(&self) -> BTreeMap<&'static str, usize> {
+        self.state_set.transition_kind_counts()
+    }
+
+    // the capture groups, the match lengths and the anchors of the
+    // pattern, see `pattern_info`.
+    pub fn info(&self) -> PatternInfo {
+        PatternInfo::new(&self.state_set)
+    }
+
+    // the compiled states, transitions, capture groups and look-around
+    // routes in JSON, for the visualizers and the tests that inspect the
+    // compilation, see `debugjson` for the format.
+    pub fn to_debug_json(&self) -> String {
+        generate_debug_json(&self.state_set)
+    }
+
+    // the routes of the look-around assertions and how they are nested.
+    pub fn look_around_routes(&self) -> Vec<LookAroundRoute> {
+        self.state_set.look_around_routes()
+    }
+
+    // find the first match in the text and convert the capture
+    // groups into the specified type.
+    pub fn capture_into<T: FromCaptures>(&self, text: &str) -> Result<T, Error> {
+        let mut instance = self.new_instance(text);
+        match instance.captures(0) {
+            Some(captures) => T::from_captures(&captures),
+            None => Err(Error::Message(
+                "The text does not match the pattern.".to_owned(),
+            )),
+        }
+    }
+
+    // find the first match in the text, the match borrows the text
+    // instead of copying the matched text, see `Match`.
+    pub fn find<'t>(&'t self, text: &'t str) -> Option<Match<'t>> {
+        let match_range = self.new_instance(text).exec(0)?;
+        borrow_matches(text, &[Some(match_range)], &self.state_set.capture_groups)
+            .pop()
+            .flatten()
+    }
+
+    // like `find`, but also returns the capture groups, the index 0 is
+    // the whole match, and the groups that do not participate in the
+    // match are `None`.
+    pub fn find_groups<'t>(&'t self, text: &'t str) -> Option<Vec<Option<Match<'t>>>> {
+        let match_ranges = self.new_instance(text).exec_with_captures(0)?;
+        Some(borrow_matches(
+            text,
+            &match_ranges,
+            &self.state_set.capture_groups,
+        ))
+    }
+
+    // find the first match and returns the spans of its capture groups
+    // by the byte offsets of the text, for highlighting the nested groups,
+    // see `capture_spans`.
+    pub fn find_capture_spans(&self, text: &str) -> Option<Vec<(MatchRange, usize)>> {
+        let match_ranges = self.new_instance(text).exec_with_captures(0)?;
+        let spans = capture_spans(&match_ranges);
+
+        let max_end = spans.last().map_or(0, |(range, _)| range.end);
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(text.len()))
+            .take(max_end + 1)
+            .collect();
+
+        Some(
+            spans
+                .into_iter()
+                .map(|(range, index)| {
+                    (
+                        MatchRange::new(offsets[range.start], offsets[range.end]),
+                        index,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    // whether every text that is matched entirely by this pattern is
+    // also matched entirely by the other one.
+    //
+    // the captures are ignored, an error is returned if either pattern
+    // contains assertions, look-arounds or byte transitions.
+    pub fn is_subset_of(&self, other: &Process) -> Result<bool, Error> {
+        is_subset(&self.state_set, &other.state_set)
+    }
+
+    // whether the two patterns match entirely the same texts,
+    // see `is_subset_of` for the limitations.
+    pub fn is_equivalent_to(&self, other: &Process) -> Result<bool, Error> {
+        is_equivalent(&self.state_set, &other.state_set)
+    }
+
+    // create a generator of the sample texts that are matched
+    // entirely by this pattern, the same seed generates the same texts.
+    pub fn new_generator(&self, seed: u64) -> Generator<'_> {
+        Generator::new(&self.state_set, seed)
+    }
+
+    // find the matches in a large buffer (e.g. a memory-mapped file) by
+    // loading one window of `window_size` bytes at a time, the positions
+    // of the matches are absolute in the buffer, see the module `window`
+    // for the details.
+    //
+    // the adjacent windows overlap by `max_match_bytes`, so the pattern
+    // must not contain unbounded repetitions, and the window size must
+    // be greater than the overlap.
+    pub fn scan_windows<'b>(
+        &self,
+        buffer: &'b [u8],
+        window_size: usize,
+    ) -> Result<WindowMatches<'_, 'b>, Error> {
+        let Some(overlap) = self.max_match_bytes() else {
+            return Err(Error::Message(
+                "The maximum match length of the pattern is unbounded.".to_owned(),
+            ));
+        };
+
+        if window_size <= overlap {
+            return Err(Error::Message(format!(
+                "The window size {} must be greater than the maximum match length {}.",
+                window_size, overlap
+            )));
+        }
+
+        let instance = self.new_instance_from_bytes(&[]);
+        Ok(WindowMatches::new(instance, buffer, window_size, overlap))
+    }
+
+    // find the first match of each text, the texts share one instance,
+    // so it is faster than creating an instance for each text when
+    // matching many small texts, e.g.
+    //
+    // ```
+    // let invalid = process.match_all(column.lines()).filter(Option::is_none).count();
+    // ```
+    pub fn match_all<'a, 't, I>(&'a self, texts: I) -> MatchAll<'a, I::IntoIter>
+    where
+        I: IntoIterator<Item = &'t str>,
+    {
+        MatchAll::new(self.new_instance(""), texts.into_iter())
+    }
+
+    // the names of the tags, indexed by the tag index,
+    // see `Instance::exec_with_tag`.
+    pub fn tags(&self) -> &[String] {
+        &self.state_set.tags
+    }
+
+    // the indices of the tags of all matches in the text, sorted and
+    // without duplicates, e.g. for routing one text to all rules that
+    // match it, with the rules combined by `Program::alternate` and
+    // tagged by `Program::tag`.
+    //
+    // the matches are found like `Instance::scan`, so only the first
+    // alternative that matches at a position is reported, the matches
+    // without tags are skipped.
+    pub fn matching_tags(&self, text: &str) -> Vec<usize> {
+        let mut instance = self.new_instance(text);
+        let mut tags = vec![];
+        let mut position = 0;
+
+        while position <= instance.context.length {
+            let Some((match_range, tag)) = instance.exec_with_tag(position) else {
+                break;
+            };
+
+            if let Some(tag) = tag {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+
+            position = if match_range.start == match_range.end {
+                match_range.end + 1
+            } else {
+                match_range.end
+            };
+        }
+
+        tags.sort_unstable();
+        tags
+    }
+
+    pub fn new_instance(&self, text: &str) -> Instance<'_> {
+        Instance::new(&self.state_set, Context::new(text))
+    }
+
+    // create an instance for matching raw bytes, the chars are read
+    // as UTF-8, and the positions of `MatchRange` are counted by bytes.
+    pub fn new_instance_from_bytes(&self, bytes: &[u8]) -> Instance<'_> {
+        Instance::new(&self.state_set, Context::from_bytes(bytes))
+    }
+}
+
+// the chars of the pattern if it matches a plain string case-sensitively,
+// e.g. `"abc"`, `'a'` and `("ab", 'c')`.
+fn get_literal(state_set: &StateSet) -> Option<(Vec<char>, Vec<u8>)> {
+    let link_index = state_set.get_first_link_index(state_set.start_node_index)?;
+    let (transition, target_state_index, next_link_index) = state_set.get_link(link_index);
+    if next_link_index.is_some()
+        || target_state_index != state_set.end_node_index
+        || state_set
+            .get_first_link_index(state_set.end_node_index)
+            .is_some()
+    {
+        return None;
+    }
+
+    let chars = match transition {
+        Transition::String(StringTransition {
+            chars,
+            ignore_case: false,
+        }) if !chars.is_empty() => chars.clone(),
+        Transition::Char(CharTransition {
+            character,
+            ignore_case: false,
+        }) => vec![*character],
+        _ => return None,
+    };
+
+    let bytes = chars.iter().collect::<String>().into_bytes();
+    Some((chars, bytes))
+}
+
+// find the first needle in the haystack that starts between `start`
+// and `last_position` (inclusive), the positions of the first item of
+// the needle are scanned and then the rest items are compared.
+//
+// returns the position of the needle, and the last position that has
+// the first item but not the whole needle before it.
+fn search_slice<T: PartialEq>(
+    haystack: &[T],
+    needle: &[T],
+    start: usize,
+    last_position: usize,
+) -> (Option<usize>, Option<usize>) {
+    let mut failed = None;
+    let mut position = start;
+    while position <= last_position {
+        let Some(offset) = haystack[position..=last_position]
+            .iter()
+            .position(|item| *item == needle[0])
+        else {
+            break;
+        };
+
+        position += offset;
+        if haystack[position..].starts_with(needle) {
+            return (Some(position), failed);
+        }
+
+        failed = Some(position);
+        position += 1;
+    }
+
+    (None, failed)
+}
+
+fn find_capture_group_index(state_set: &StateSet, name: &str) -> Option<usize> {
+    state_set
+        .capture_groups
+        .iter()
+        .find(|group| group.name.as_deref() == Some(name))
+        .map(|group| group.index)
+}
+
+pub struct Instance<'a> {
+    state_set: &'a StateSet,
+    context: Context,
+    last_match_empty: bool, // whether the previous match is empty
+    stats: Option<MatchStats>,
+
+    // the buffers that are reused across the threads and the calls of
+    // `exec`, so that matching does not allocate for each attempt.
+    frame_buffers: Vec<Vec<Frame>>,
+    spare_states: Vec<FrameState>,
+
+    // whether the capture transitions record the positions, they are
+    // treated as jumps when the caller asks for the whole match or a
+    // boolean only, so the capture slots are not allocated or copied.
+    // the captures are always recorded if there are back-references,
+    // see `records_captures`.
+    record_captures: bool,
+
+    // the capture groups that are recorded, indexed by the capture
+    // index, the capture transitions of the other groups are treated
+    // as jumps, it is empty if all groups are recorded, see
+    // `exec_select_indices`.
+    selected_captures: Vec<bool>,
+
+    // whether the numbers of times that the groups are matched are
+    // kept, and the numbers of the last match, indexed by the capture
+    // index, see `exec_with_capture_counts`.
+    record_counts: bool,
+    capture_counts: Vec<u32>,
+
+    // the tag of the last match, see `exec_with_tag`.
+    last_tag: Option<usize>,
+
+    // the furthest position where a transition failed since the last
+    // call of `exec`, and the failed transitions there, i.e. the pairs
+    // of (state index, link index), see `last_failure_info`.
+    failure_position: Option<usize>,
+    failed_links: Vec<(usize, usize)>,
+    look_around_depth: usize,
+
+    // the limits of frames and threads, the numbers of the frames and
+    // threads that exist currently, and the error of the limit that
+    // stops the match attempt, see `MatchLimits`.
+    limits: MatchLimits,
+    frame_count: usize,
+    thread_count: usize,
+    limit_error: Option<String>,
+
+    // the chars (and their UTF-8 bytes) of the pattern that is a plain
+    // string, i.e. a single string or char transition from the start
+    // state to the end state, it is searched directly instead of
+    // running the threads, see `find_literal`.
+    literal: Option<(Vec<char>, Vec<u8>)>,
+}
+
+// the statistics of matching, they are accumulated across the calls of
+// `exec` until they are reset, it is useful for comparing the costs of
+// the patterns.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MatchStats {
+    pub steps: usize,      // the number of transitions checked
+    pub backtracks: usize, // the number of frames popped because all transitions failed
+    pub threads: usize,    // the number of threads, including the look-around ones
+    pub peak_depth: usize, // the maximum number of frames of a thread
+}
+
+// the furthest position that the last match attempt reached, see
+// `Instance::last_failure_info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureInfo {
+    pub position: usize,
+
+    // the transitions that failed at the position, in the order
+    // they are tried.
+    pub transitions: Vec<FailedTransition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedTransition {
+    pub state_index: usize,
+    pub description: String, // e.g. `Char 'a'`, `Charset [0..9]`
+}
+
+// the result of `Instance::exec_partial`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartialMatch {
+    // the text contains a match
+    Complete(MatchRange),
+
+    // there is no match, but the text (from a position) may become
+    // a match with more chars appended
+    Partial,
+
+    // there is no match even with more chars appended
+    NoMatch,
+}
+
+// the result of `Instance::exec_steps` and `Instance::resume`.
+pub enum ExecProgress {
+    // the attempts are finished, with the match if there is one
+    Done(Option<MatchRange>),
+
+    // the number of steps is used up, the matching can be continued
+    // from the checkpoint
+    Pending(ExecCheckpoint),
+}
+
+// the suspended matching, i.e. the position of the current attempt
+// and its backtracking frames, see `Instance::resume`.
+pub struct ExecCheckpoint {
+    position: usize,
+    frames: Vec<Frame>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchRange {
+    pub start: usize, // the position of the first matched character
+    pub end: usize,   // the position after the last matched character
+}
+
+impl MatchRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        MatchRange { start, end }
+    }
+}
+
+// the backtracking frame
+struct Frame {
+    state_index: usize,
+    position: usize,
+    next_link_index: Option<usize>, // the next transition to try
+    state: FrameState,              // the counters and captures of this frame
+}
+
+impl<'a> Instance<'a> {
+    pub(crate) fn new(state_set: &'a StateSet, context: Context) -> Self {
+        Instance {
+            state_set,
+            context,
+            last_match_empty: false,
+            stats: None,
+            frame_buffers: vec![],
+            spare_states: vec![],
+            record_captures: true,
+            selected_captures: vec![],
+            record_counts: false,
+            capture_counts: vec![],
+            last_tag: None,
+            failure_position: None,
+            failed_links: vec![],
+            look_around_depth: 0,
+            limits: MatchLimits::default(),
+            frame_count: 0,
+            thread_count: 0,
+            limit_error: None,
+            literal: get_literal(state_set),
+        }
+    }
+
+    // replace the text to match, the instance is reset as if it is
+    // newly created, but the allocated buffers are kept, e.g.
+    //
+    // ```
+    // let mut instance = process.new_instance("");
+    // for line in lines {
+    //     instance.reset(line);
+    //     if let Some(m) = instance.exec(0) { ... }
+    // }
+    // ```
+    //
+    // the statistics are not cleared, see `enable_stats`.
+    pub fn reset(&mut self, text: &str) {
+        self.context.reset_text(text);
+        self.last_match_empty = false;
+    }
+
+    // like `reset`, but replace with the bytes, and the instance
+    // switches to bytes mode.
+    pub fn reset_bytes(&mut self, bytes: &[u8]) {
+        self.context.reset_bytes(bytes);
+        self.last_match_empty = false;
+    }
+
+    // find the first match by trying each position from `start`
+    // to the end of text.
+    pub fn exec(&mut self, start: usize) -> Option<MatchRange> {
+        let mut match_ranges = [None];
+        if self.exec_into(start, &mut match_ranges) {
+            match_ranges[0]
+        } else {
+            None
+        }
+    }
+
+    // find the first match and returns the ranges of all capture groups,
+    // the index 0 is the whole match, and the groups that do not
+    // participate in the match are `None`.
+    pub fn exec_with_captures(&mut self, start: usize) -> Option<Vec<Option<MatchRange>>> {
+        let mut match_ranges = vec![None; self.state_set.capture_groups.len()];
+        if self.exec_into(start, &mut match_ranges) {
+            Some(match_ranges)
+        } else {
+            None
+        }
+    }
+
+    // like `exec_with_captures`, but also returns the number of times
+    // that each group is matched, e.g. the pattern
+    // `(name(char_digit, d), ',')+` matches "1,2,3," with the counts
+    // `[1, 3]`, and the range of the group "d" is the last digit "3".
+    //
+    // the index 0 (the whole match) is always 1, and the groups that do
+    // not participate in the match are 0. the iterations that are
+    // backtracked are not counted.
+    pub fn exec_with_capture_counts(
+        &mut self,
+        start: usize,
+    ) -> Option<(Vec<Option<MatchRange>>, Vec<u32>)> {
+        self.record_counts = true;
+        self.capture_counts.clear();
+        let result = self.exec_with_captures(start);
+        self.record_counts = false;
+        let match_ranges = result?;
+
+        let mut counts = vec![0; match_ranges.len()];
+        for (count, captured) in counts.iter_mut().zip(&self.capture_counts) {
+            *count = *captured;
+        }
+        counts[0] = 1;
+        Some((match_ranges, counts))
+    }
+
+    // like `exec`, but also returns the index of the tag recorded by
+    // the match, i.e. the last `tag(...)` that the match passes through,
+    // or `None` if it passes through no tag, e.g. the pattern
+    // `tag("GET", "read") || tag("POST", "write")` matches "POST" with
+    // the tag 1, see `Process::tags` for the names.
+    pub fn exec_with_tag(&mut self, start: usize) -> Option<(MatchRange, Option<usize>)> {
+        let match_range = self.exec(start)?;
+        Some((match_range, self.last_tag))
+    }
+
+    // like `exec_with_captures`, but writes the ranges of capture groups
+    // into the caller-provided slice instead of allocating a new vector,
+    // returns whether a match is found, e.g.
+    //
+    // ```
+    // let mut match_ranges = [None; 3];
+    // while instance.exec_into(start, &mut match_ranges) { ... }
+    // ```
+    //
+    // the group `i` is written to `match_ranges[i]`, the groups beyond
+    // the slice are skipped, and the extra slots are set to `None`,
+    // the size for all groups is `Process::capture_groups().len()`.
+    //
+    // the slice is not changed if there is no match.
+    pub fn exec_into(&mut self, start: usize, match_ranges: &mut [Option<MatchRange>]) -> bool {
+        // the group 0 comes from the start and the end of the thread
+        self.record_captures = match_ranges.len() > 1;
+        self.clear_failure();
+        self.last_tag = None;
+
+        // the positions that are too near to the end of text to
+        // fit the shortest match are skipped.
+        let Some(last_position) = self
+            .context
+            .length
+            .checked_sub(self.state_set.length_bounds.min)
+        else {
+            return false;
+        };
+
+        if self.literal.is_some() && self.stats.is_none() && self.limits.max_frames >= 2 {
+            return self.find_literal(start, last_position, match_ranges);
+        }
+
+        for position in start..=last_position {
+            if !self.may_start_at(position) {
+                continue;
+            }
+
+            if self.start_thread(position, match_ranges) {
+                return true;
+            }
+
+            if self.limit_error.is_some() {
+                break;
+            }
+        }
+
+        false
+    }
+
+    // find the first match and returns the ranges of the capture groups
+    // with the names only, in the order of the names, the unknown names
+    // and the groups that do not participate in the match are `None`, e.g.
+    //
+    // ```
+    // let ranges = instance.exec_select(0, &["year", "month"])?;
+    // ```
+    //
+    // the other groups are not recorded while matching, so it is faster
+    // than `exec_with_captures` for the patterns with many groups when
+    // only a few of them are needed.
+    pub fn exec_select(&mut self, start: usize, names: &[&str]) -> Option<Vec<Option<MatchRange>>> {
+        let indices: Vec<usize> = names
+            .iter()
+            .map(|name| find_capture_group_index(self.state_set, name).unwrap_or(usize::MAX))
+            .collect();
+        self.exec_select_indices(start, &indices)
+    }
+
+    // like `exec_select`, but the groups are specified by the indices,
+    // the index 0 is the whole match.
+    //
+    // all groups are still recorded if the pattern contains
+    // back-references, since they read the captured texts.
+    pub fn exec_select_indices(
+        &mut self,
+        start: usize,
+        indices: &[usize],
+    ) -> Option<Vec<Option<MatchRange>>> {
+        let count = self.state_set.capture_groups.len();
+        self.selected_captures.clear();
+        self.selected_captures.resize(count, false);
+        for index in indices {
+            if let Some(selected) = self.selected_captures.get_mut(*index) {
+                *selected = true;
+            }
+        }
+
+        let mut match_ranges = vec![None; count];
+        let found = self.exec_into(start, &mut match_ranges);
+        self.selected_captures.clear();
+
+        found.then(|| {
+            indices
+                .iter()
+                .map(|index| match_ranges.get(*index).copied().flatten())
+                .collect()
+        })
+    }
+
+    // whether the pattern matches the text from `start`, it is faster
+    // than `exec` for the patterns with capture groups, since the
+    // captures are not recorded.
+    pub fn is_match(&mut self, start: usize) -> bool {
+        self.exec_into(start, &mut [])
+    }
+
+    // like `exec`, but checks at most `max_steps` transitions and returns
+    // `ExecProgress::Pending` if the matching is not finished, so that
+    // matching a large text does not block the thread for too long, e.g.
+    //
+    // ```
+    // let mut progress = instance.exec_steps(0, 10_000);
+    // while let ExecProgress::Pending(checkpoint) = progress {
+    //     yield_now().await;
+    //     progress = instance.resume(checkpoint, 10_000);
+    // }
+    // ```
+    //
+    // the steps are counted like `MatchStats::steps`, except that the
+    // transitions inside the look-arounds are not counted, i.e. a
+    // look-around is one step, since their threads are not suspended.
+    pub fn exec_steps(&mut self, start: usize, max_steps: usize) -> ExecProgress {
+        self.clear_failure();
+        self.continue_exec(
+            ExecCheckpoint {
+                position: start,
+                frames: vec![],
+            },
+            max_steps,
+        )
+    }
+
+    // continue the matching from the checkpoint of `exec_steps` (or of
+    // the previous `resume`), checks at most `max_steps` transitions.
+    //
+    // the checkpoint must be resumed by the same instance, and the text
+    // must not be changed in between.
+    pub fn resume(&mut self, checkpoint: ExecCheckpoint, max_steps: usize) -> ExecProgress {
+        self.continue_exec(checkpoint, max_steps)
+    }
+
+    fn continue_exec(&mut self, checkpoint: ExecCheckpoint, max_steps: usize) -> ExecProgress {
+        self.record_captures = false;
+
+        let Some(last_position) = self
+            .context
+            .length
+            .checked_sub(self.state_set.length_bounds.min)
+        else {
+            return ExecProgress::Done(None);
+        };
+
+        let ExecCheckpoint {
+            mut position,
+            mut frames,
+        } = checkpoint;
+
+        // the suspended thread counts again
+        if !frames.is_empty() {
+            self.thread_count += 1;
+            self.frame_count += frames.len();
+        }
+
+        let mut steps = max_steps;
+        loop {
+            if frames.is_empty() {
+                while position <= last_position && !self.may_start_at(position) {
+                    position += 1;
+                }
+
+                if position > last_position {
+                    return ExecProgress::Done(None);
+                }
+
+                let state = self.new_state();
+                match self.begin_thread(self.state_set.start_node_index, position, state) {
+                    Some(thread_frames) => frames = thread_frames,
+                    None => return ExecProgress::Done(None),
+                }
+            }
+
+            let result = self.run_frames(
+                &mut frames,
+                self.state_set.end_node_index,
+                None,
+                Some(&mut steps),
+            );
+
+            if let Some((end, state)) = result {
+                self.end_thread(frames);
+                let mut match_ranges = [None];
+                self.finish_match(position, end, state, &mut match_ranges);
+                return ExecProgress::Done(match_ranges[0]);
+            }
+
+            if !frames.is_empty() && self.limit_error.is_none() {
+                // suspended, the frames are kept by the checkpoint
+                self.thread_count -= 1;
+                self.frame_count -= frames.len();
+                return ExecProgress::Pending(ExecCheckpoint { position, frames });
+            }
+
+            self.end_thread(std::mem::take(&mut frames));
+            if self.limit_error.is_some() {
+                return ExecProgress::Done(None);
+            }
+            position += 1;
+        }
+    }
+
+    // like `exec`, but also reports whether the text is the beginning
+    // of a possible match when there is no match, i.e. some attempts
+    // fail only because they reach the end of text, e.g. validating
+    // a date field as the user types with the pattern
+    // `start, char_digit{4}, '-', char_digit{2}, end`:
+    //
+    // - "2024-0": `PartialMatch::Partial`
+    // - "2024-01": `PartialMatch::Complete(..)`
+    // - "2024-x": `PartialMatch::NoMatch`
+    //
+    // note that the end of text is always the beginning of a possible
+    // match if the pattern is not anchored by `start`, and it is
+    // conservative with the look-arounds, i.e. a read beyond the end
+    // inside them also counts.
+    pub fn exec_partial(&mut self, start: usize) -> PartialMatch {
+        self.record_captures = false;
+        self.clear_failure();
+        self.context.reached_end.set(false);
+
+        // unlike `exec`, the positions near to the end of text are
+        // not skipped, since they may begin a partial match.
+        let mut match_ranges = [None];
+        for position in start..=self.context.length {
+            if self.start_thread(position, &mut match_ranges) {
+                if let Some(match_range) = match_ranges[0] {
+                    return PartialMatch::Complete(match_range);
+                }
+            }
+        }
+
+        if self.context.reached_end.get() {
+            PartialMatch::Partial
+        } else {
+            PartialMatch::NoMatch
+        }
+    }
+
+    // find the first match in the window `offset..offset + length` of the
+    // buffer, the positions of the match are absolute in the buffer.
+    //
+    // the instance is reset to the bytes of the window, i.e. only the
+    // window is copied, see `Process::scan_windows` for scanning
+    // the whole buffer.
+    pub fn exec_window(
+        &mut self,
+        buffer: &[u8],
+        offset: usize,
+        length: usize,
+    ) -> Option<MatchRange> {
+        self.reset_bytes(&buffer[offset..offset + length]);
+        self.exec(0)
+            .map(|m| MatchRange::new(m.start + offset, m.end + offset))
+    }
+
+    // find the first match and returns the texts of all capture groups,
+    // the bytes are converted to text lossily in bytes mode.
+    pub fn captures(&mut self, start: usize) -> Option<Captures> {
+        let match_ranges = self.exec_with_captures(start)?;
+
+        let names = self
+            .state_set
+            .capture_groups
+            .iter()
+            .map(|group| group.name.clone())
+            .collect();
+
+        let values = match_ranges
+            .iter()
+            .map(|match_range| {
+                match_range.as_ref().map(|MatchRange { start, end }| {
+                    if self.context.bytes_mode {
+                        String::from_utf8_lossy(&self.context.bytes[*start..*end]).into_owned()
+                    } else {
+                        self.context.text[*start..*end].iter().collect()
+                    }
+                })
+            })
+            .collect();
+
+        Some(Captures::new(names, match_ranges, values))
+    }
+
+    // find the first match, and returns it along with the range of
+    // positions that have been read while searching it, including
+    // the failed attempts and the look-arounds.
+    pub(crate) fn exec_with_read_range(
+        &mut self,
+        start: usize,
+    ) -> Option<(MatchRange, usize, usize)> {
+        self.context.reset_read_range();
+        let match_range = self.exec(start)?;
+        let read_start = self.context.read_start.get().min(start);
+        let read_end = self.context.read_end.get().max(match_range.end);
+        Some((match_range, read_start, read_end))
+    }
+
+    // the end position of the previous match, it is 0 if there is
+    // no match yet.
+    pub fn last_match_end(&self) -> usize {
+        self.context.last_match_end
+    }
+
+    // find the next match from where the previous match ended, e.g.
+    // scanning tokens without gaps with the pattern
+    // `continue_from_last, (char_word+ || char_space+)`.
+    //
+    // the search starts one position further after an empty match,
+    // so the repeated calls always terminate.
+    pub fn exec_next(&mut self) -> Option<MatchRange> {
+        let start = self.context.last_match_end;
+        let start = if self.last_match_empty {
+            start + 1
+        } else {
+            start
+        };
+        self.exec(start)
+    }
+
+    // call the function for each match from `start` in document order,
+    // with the ranges of all capture groups (the index 0 is the whole
+    // match, see `exec_with_captures`), returns the number of matches, e.g.
+    //
+    // ```
+    // let index = process.capture_group_index("user").unwrap();
+    // instance.scan(0, |match_ranges| {
+    //     if let Some(range) = match_ranges[index] { ... }
+    //     ControlFlow::Continue(())
+    // });
+    // ```
+    //
+    // the ranges are written into one buffer that is reused for all
+    // matches, so a huge text can be scanned without collecting the
+    // results, and the scan stops early when the function returns
+    // `ControlFlow::Break`.
+    pub fn scan<F>(&mut self, start: usize, mut f: F) -> usize
+    where
+        F: FnMut(&[Option<MatchRange>]) -> ControlFlow<()>,
+    {
+        let mut match_ranges = vec![None; self.state_set.capture_groups.len()];
+        let mut position = start;
+        let mut count = 0;
+
+        while position <= self.context.length && self.exec_into(position, &mut match_ranges) {
+            let Some(match_range) = match_ranges[0] else {
+                break;
+            };
+            count += 1;
+
+            if f(&match_ranges).is_break() {
+                break;
+            }
+
+            // the search starts one position further after an
+            // empty match, like `exec_next`.
+            position = if match_range.start == match_range.end {
+                match_range.end + 1
+            } else {
+                match_range.end
+            };
+        }
+
+        count
+    }
+
+    // start collecting the statistics of matching, the collected
+    // statistics are cleared.
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(MatchStats::default());
+    }
+
+    // the statistics collected since `enable_stats` was called,
+    // returns `None` if the statistics are not enabled.
+    pub fn stats(&self) -> Option<&MatchStats> {
+        self.stats.as_ref()
+    }
+
+    // set the limits of frames and threads for the following calls,
+    // see `MatchLimits`.
+    pub fn set_limits(&mut self, limits: MatchLimits) {
+        self.limits = limits;
+    }
+
+    // like `exec`, but returns `Error::LimitExceeded` if a limit is
+    // exceeded before a match is found, instead of `None`.
+    pub fn try_exec(&mut self, start: usize) -> Result<Option<MatchRange>, Error> {
+        let result = self.exec(start);
+        self.take_limit_error().map(|_| result)
+    }
+
+    // like `exec_with_captures`, but returns `Error::LimitExceeded` if
+    // a limit is exceeded before a match is found.
+    pub fn try_exec_with_captures(
+        &mut self,
+        start: usize,
+    ) -> Result<Option<Vec<Option<MatchRange>>>, Error> {
+        let result = self.exec_with_captures(start);
+        self.take_limit_error().map(|_| result)
+    }
+
+    fn take_limit_error(&mut self) -> Result<(), Error> {
+        match self.limit_error.take() {
+            Some(message) => Err(Error::LimitExceeded(message)),
+            None => Ok(()),
+        }
+    }
+
+    // the approximate number of bytes allocated by the instance, i.e. the
+    // copy of the text and the buffers of frames and states that are kept
+    // for reusing, it grows with the deepest backtracking so far, and it
+    // can be bounded by `set_limits`.
+    pub fn allocated_bytes(&self) -> usize {
+        let text_bytes =
+            self.context.text.capacity() * size_of::<char>() + self.context.bytes.capacity();
+
+        let frame_bytes: usize = self
+            .frame_buffers
+            .iter()
+            .map(|frames| frames.capacity() * size_of::<Frame>())
+            .sum::<usize>()
+            + self.frame_buffers.capacity() * size_of::<Vec<Frame>>();
+
+        let state_bytes: usize = self
+            .spare_states
+            .iter()
+            .map(|state| {
+                state.counters.heap_capacity() * size_of::<Counter>()
+                    + state.captures.heap_capacity() * size_of::<CaptureSlot>()
+            })
+            .sum::<usize>()
+            + self.spare_states.capacity() * size_of::<FrameState>();
+
+        let other_bytes = self.failed_links.capacity() * size_of::<(usize, usize)>()
+            + self.selected_captures.capacity();
+
+        text_bytes + frame_bytes + state_bytes + other_bytes
+    }
+
+    pub fn length(&self) -> usize {
+        self.context.length
+    }
+
+    // replace the chars from `start` to `end` (exclusive) with the text,
+    // the instance must be created from text.
+    pub fn replace_text(&mut self, start: usize, end: usize, text: &str) {
+        assert!(!self.context.bytes_mode);
+        self.context.replace_text(start, end, text);
+    }
+
+    // replace the bytes from `start` to `end` (exclusive), the instance
+    // must be created from bytes.
+    pub fn replace_bytes(&mut self, start: usize, end: usize, bytes: &[u8]) {
+        assert!(self.context.bytes_mode);
+        self.context.replace_bytes(start, end, bytes);
+    }
+
+    // match only at the specified position, unlike `exec`, it does not
+    // scan forward when the match fails.
+    //
+    // it is useful for incremental lexing, e.g. matching tokens one by one.
+    pub fn exec_anchored(&mut self, start: usize) -> Option<MatchRange> {
+        self.record_captures = false;
+        self.clear_failure();
+
+        if start.saturating_add(self.state_set.length_bounds.min) > self.context.length {
+            return None;
+        }
+
+        let mut match_ranges = [None];
+        if self.start_thread(start, &mut match_ranges) {
+            match_ranges[0]
+        } else {
+            None
+        }
+    }
+
+    // whether the whole text is matched by the pattern.
+    pub(crate) fn exec_exact(&mut self) -> bool {
+        self.record_captures = false;
+        self.clear_failure();
+        let state = self.new_state();
+
+        match self.run_thread(
+            self.state_set.start_node_index,
+            self.state_set.end_node_index,
+            0,
+            Some(self.context.length),
+            state,
+        ) {
+            Some((_, state)) => {
+                self.recycle_state(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // the furthest position that the last call of `exec` (and the other
+    // `exec_*` methods) reached before the transitions failed, it helps
+    // to find out why a near-matching text is rejected, e.g. the pattern
+    // `"foo", char_digit+` fails at position 3 of the text "foo-1" with
+    // the expected transition `Charset [char_digit]`.
+    //
+    // the failures inside the look-arounds are not recorded, and it is
+    // `None` if no transition has been tried, e.g. the text is shorter
+    // than the shortest match.
+    pub fn last_failure_info(&self) -> Option<FailureInfo> {
+        let position = self.failure_position?;
+
+        let transitions = self
+            .failed_links
+            .iter()
+            .map(|(state_index, link_index)| {
+                let (transition, _, _) = self.state_set.get_link(*link_index);
+                FailedTransition {
+                    state_index: *state_index,
+                    description: transition.to_string(),
+                }
+            })
+            .collect();
+
+        Some(FailureInfo {
+            position,
+            transitions,
+        })
+    }
+
+    fn clear_failure(&mut self) {
+        self.failure_position = None;
+        self.failed_links.clear();
+        self.limit_error = None;
+    }
+
+    fn record_failure(&mut self, position: usize, state_index: usize, link_index: usize) {
+        // e.g. a negative look-ahead succeeds when its sub-line fails
+        if self.look_around_depth > 0 {
+            return;
+        }
+
+        match self.failure_position {
+            Some(furthest) if furthest > position => {}
+            Some(furthest) if furthest == position => {
+                if !self.failed_links.contains(&(state_index, link_index)) {
+                    self.failed_links.push((state_index, link_index));
+                }
+            }
+            _ => {
+                self.failure_position = Some(position);
+                self.failed_links.clear();
+                self.failed_links.push((state_index, link_index));
+            }
+        }
+    }
+
+    // search the literal of the pattern from `start`, it has the same
+    // results as running the threads at each position, including the
+    // read range and the failure info.
+    fn find_literal(
+        &mut self,
+        start: usize,
+        last_position: usize,
+        match_ranges: &mut [Option<MatchRange>],
+    ) -> bool {
+        let (chars, bytes) = self.literal.as_ref().unwrap();
+        let (found, failed_position, length) = if self.context.bytes_mode {
+            let (found, failed) = search_slice(&self.context.bytes, bytes, start, last_position);
+            (found, failed, bytes.len())
+        } else {
+            let (found, failed) = search_slice(&self.context.text, chars, start, last_position);
+            (found, failed, chars.len())
+        };
+
+        // the attempts before the match (or all attempts if there is no
+        // match) fail at the positions with the first char of the literal.
+        if let Some(position) = failed_position {
+            let state_index = self.state_set.start_node_index;
+            let link_index = self.state_set.get_first_link_index(state_index).unwrap();
+            self.record_failure(position, state_index, link_index);
+        }
+
+        let Some(position) = found else {
+            self.context.record_read(start, self.context.length + 1);
+            return false;
+        };
+
+        let end = position + length;
+        self.context.record_read(start, end);
+
+        for (idx, match_range) in match_ranges.iter_mut().enumerate() {
+            *match_range = (idx == 0).then_some(MatchRange::new(position, end));
+        }
+        self.context.last_match_end = end;
+        self.last_match_empty = false;
+        true
+    }
+
+    // whether a match can start at the position, according to the
+    // chars that can begin a match.
+    fn may_start_at(&self, position: usize) -> bool {
+        match &self.state_set.first_chars {
+            Some(ranges) => match self.context.read_char(position) {
+                Some((c, _)) => ranges
+                    .iter()
+                    .any(|(start, end_included)| *start <= c && c <= *end_included),
+                None => false,
+            },
+            None => true,
+        }
+    }
+
+    // try to match at the specified position, the ranges of capture
+    // groups are written to the slice if it succeeds.
+    fn start_thread(&mut self, position: usize, match_ranges: &mut [Option<MatchRange>]) -> bool {
+        let state = self.new_state();
+
+        let Some((end, state)) = self.run_thread(
+            self.state_set.start_node_index,
+            self.state_set.end_node_index,
+            position,
+            None,
+            state,
+        ) else {
+            return false;
+        };
+
+        self.finish_match(position, end, state, match_ranges);
+        true
+    }
+
+    // write the ranges of the succeeded thread that starts at the position.
+    fn finish_match(
+        &mut self,
+        position: usize,
+        end: usize,
+        state: FrameState,
+        match_ranges: &mut [Option<MatchRange>],
+    ) {
+        // the match may start later than the thread, see `match_start_reset`
+        let match_start = state.match_start.unwrap_or(position);
+
+        for (idx, match_range) in match_ranges.iter_mut().enumerate() {
+            *match_range = match state.captures.get(idx) {
+                _ if idx == 0 => Some(MatchRange::new(match_start, end)),
+                Some(CaptureSlot {
+                    start: Some(start),
+                    end: Some(end),
+                    ..
+                }) => Some(MatchRange::new(*start, *end)),
+                _ => None,
+            };
+        }
+
+        self.last_tag = state.tag;
+
+        if self.record_counts {
+            self.capture_counts.clear();
+            self.capture_counts
+                .extend(state.captures.iter().map(|slot| slot.count));
+        }
+
+        self.recycle_state(state);
+        self.context.last_match_end = end;
+        self.last_match_empty = match_start == end;
+    }
+
+    // the state for starting a thread, i.e. no counters and
+    // no captures.
+    fn new_state(&mut self) -> FrameState {
+        let mut state = self.spare_states.pop().unwrap_or_default();
+        state.counters.clear();
+        state.captures.clear();
+        state.match_start = None;
+        state.tag = None;
+        if self.records_captures() {
+            state
+                .captures
+                .resize(self.state_set.capture_groups.len(), CaptureSlot::default());
+        }
+        state
+    }
+
+    fn records_captures(&self) -> bool {
+        self.record_captures || self.state_set.has_back_references
+    }
+
+    // whether the transition is a capture transition that does not
+    // need to update the frame state.
+    fn is_skipped_capture(&self, transition: &Transition) -> bool {
+        let capture_index = match transition {
+            Transition::CaptureStart(t) => t.capture_index,
+            Transition::CaptureEnd(t) => t.capture_index,
+            _ => return false,
+        };
+
+        !self.records_captures()
+            || (!self.state_set.has_back_references
+                && self
+                    .selected_captures
+                    .get(capture_index)
+                    .is_some_and(|selected| !selected))
+    }
+
+    fn clone_state(&mut self, source: &FrameState) -> FrameState {
+        let mut state = self.spare_states.pop().unwrap_or_default();
+        state.clone_from(source);
+        state
+    }
+
+    fn recycle_state(&mut self, state: FrameState) {
+        self.spare_states.push(state);
+    }
+
+    // run the states from `start_state_index` to `end_state_index`,
+    // returns the end position and the frame state if it succeeds.
+    //
+    // transitions are tried in the order of the link list of a state,
+    // and the thread goes back to the previous frame and tries the
+    // next transition when all transitions of a state fail.
+    //
+    // if `expected_end` is specified, the thread only succeeds when it
+    // ends at the position, it is used by look-behind.
+    fn run_thread(
+        &mut self,
+        start_state_index: usize,
+        end_state_index: usize,
+        position: usize,
+        expected_end: Option<usize>,
+        state: FrameState,
+    ) -> Option<(usize, FrameState)> {
+        let mut frames = self.begin_thread(start_state_index, position, state)?;
+        let result = self.run_frames(&mut frames, end_state_index, expected_end, None);
+        self.end_thread(frames);
+        result
+    }
+
+    // create the frames of a thread with the first frame, returns `None`
+    // if the limit of threads is exceeded.
+    fn begin_thread(
+        &mut self,
+        start_state_index: usize,
+        position: usize,
+        state: FrameState,
+    ) -> Option<Vec<Frame>> {
+        if self.limit_error.is_some() || !self.check_limits(1) {
+            self.recycle_state(state);
+            return None;
+        }
+        self.thread_count += 1;
+
+        // the look-arounds run nested threads, so each thread takes
+        // its own buffer.
+        let mut frames = self.frame_buffers.pop().unwrap_or_default();
+        frames.push(Frame {
+            state_index: start_state_index,
+            position,
+            next_link_index: self.state_set.get_first_link_index(start_state_index),
+            state,
+        });
+        self.frame_count += 1;
+
+        if let Some(stats) = &mut self.stats {
+            stats.threads += 1;
+            stats.peak_depth = stats.peak_depth.max(1);
+        }
+
+        Some(frames)
+    }
+
+    fn end_thread(&mut self, frames: Vec<Frame>) {
+        self.recycle_frames(frames);
+        self.thread_count -= 1;
+    }
+
+    // run the frames of a thread until it reaches the end state or all
+    // frames fail, returns the end position and the frame state of the
+    // succeeded frame, which is removed from the frames.
+    //
+    // if `steps` is specified, the thread is suspended when the number of
+    // the transitions to check is used up, i.e. it returns `None` but
+    // the frames are kept, while the frames of a failed thread are
+    // empty unless a limit is exceeded.
+    fn run_frames(
+        &mut self,
+        frames: &mut Vec<Frame>,
+        end_state_index: usize,
+        expected_end: Option<usize>,
+        mut steps: Option<&mut usize>,
+    ) -> Option<(usize, FrameState)> {
+        let state_set = self.state_set;
+
+        while let Some(frame) = frames.last_mut() {
+            // a limit is exceeded by this thread or a nested one
+            if self.limit_error.is_some() {
+                break;
+            }
+
+            if frame.state_index == end_state_index
+                && expected_end.is_none_or(|e| e == frame.position)
+            {
+                let frame = frames.pop().unwrap();
+                self.frame_count -= 1;
+                return Some((frame.position, frame.state));
+            }
+
+            let link_index = match frame.next_link_index {
+                Some(idx) => idx,
+                None => {
+                    // all transitions of the current state failed
+                    let frame = frames.pop().unwrap();
+                    self.frame_count -= 1;
+                    self.recycle_state(frame.state);
+                    if let Some(stats) = &mut self.stats {
+                        stats.backtracks += 1;
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(steps) = steps.as_deref_mut() {
+                if *steps == 0 {
+                    return None;
+                }
+                *steps -= 1;
+            }
+
+            let (transition, target_state_index, next_link_index) = state_set.get_link(link_index);
+            frame.next_link_index = next_link_index;
+
+            if let Some(stats) = &mut self.stats {
+                stats.steps += 1;
+            }
+
+            let position = frame.position;
+            let state_index = frame.state_index;
+            let (forward, state) = match transition {
+                Transition::LookAround(look_around) => {
+                    let frame_state = self.clone_state(&frame.state);
+                    match self.check_look_around(look_around, position, frame_state) {
+                        Some(state) => (0, state),
+                        None => {
+                            self.record_failure(position, state_index, link_index);
+                            continue;
+                        }
+                    }
+                }
+                Transition::BackReference(back_reference) => {
+                    self.context.position = position;
+                    match back_reference.check_captures(&self.context, &frame.state.captures) {
+                        Some(forward) => (forward, self.clone_state(&frame.state)),
+                        None => {
+                            self.record_failure(position, state_index, link_index);
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    self.context.position = position;
+                    match transition.check(&self.context) {
+                        Some(forward) => {
+                            let mut state = self.clone_state(&frame.state);
+                            if !self.is_skipped_capture(transition)
+                                && !transition.update_frame_state(&mut state, position)
+                            {
+                                self.recycle_state(state);
+                                continue;
+                            }
+                            (forward, state)
+                        }
+                        None => {
+                            self.record_failure(position, state_index, link_index);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            if !self.check_limits(0) {
+                self.recycle_state(state);
+                break;
+            }
+
+            frames.push(Frame {
+                state_index: target_state_index,
+                position: position + forward,
+                next_link_index: state_set.get_first_link_index(target_state_index),
+                state,
+            });
+            self.frame_count += 1;
+
+            if let Some(stats) = &mut self.stats {
+                stats.peak_depth = stats.peak_depth.max(frames.len());
+            }
+        }
+
+        None
+    }
+
+    // whether one more frame (and the number of more threads) can be
+    // created within the limits, otherwise the limit error is set.
+    fn check_limits(&mut self, more_threads: usize) -> bool {
+        let limits = &self.limits;
+        let message = if self.thread_count + more_threads > limits.max_threads {
+            format!(
+                "The number of threads exceeds the limit {}.",
+                limits.max_threads
+            )
+        } else if self.frame_count >= limits.max_frames {
+            format!(
+                "The number of backtracking frames exceeds the limit {}.",
+                limits.max_frames
+            )
+        } else {
+            return true;
+        };
+
+        self.limit_error = Some(message);
+        false
+    }
+
+    fn recycle_frames(&mut self, mut frames: Vec<Frame>) {
+        self.frame_count -= frames.len();
+        for frame in frames.drain(..) {
+            self.recycle_state(frame.state);
+        }
+        self.frame_buffers.push(frames);
+    }
+
+    // the start positions of the look-behind route that ends at
+    // the position, it is empty if the route is longer than the text
+    // before the position.
+    fn look_behind_starts(
+        &self,
+        look_around: &LookAroundTransition,
+        position: usize,
+    ) -> Range<usize> {
+        let Some((bounds, byte_bounds)) = self
+            .state_set
+            .look_behind_bounds
+            .get(&look_around.start_state_index)
+        else {
+            return 0..position + 1;
+        };
+
+        let bounds = if self.context.bytes_mode {
+            byte_bounds
+        } else {
+            bounds
+        };
+
+        match position.checked_sub(bounds.min) {
+            Some(last) => bounds.max.map_or(0, |max| position.saturating_sub(max))..last + 1,
+            None => 0..0,
+        }
+    }
+
+    // check the look-around assertion at the position, returns the new
+    // frame state if it succeeds.
+    //
+    // the sub-line is run by a nested thread, i.e. the recursion depth
+    // grows with the nesting depth of the look-arounds, which is
+    // limited by `Limits::max_look_around_depth` when compiling.
+    //
+    // the captures made inside a positive look-around are kept, and the
+    // captures of a negative look-around are always discarded.
+    fn check_look_around(
+        &mut self,
+        look_around: &LookAroundTransition,
+        position: usize,
+        mut state: FrameState,
+    ) -> Option<FrameState> {
+        self.look_around_depth += 1;
+        let result = if look_around.is_behind {
+            // try the farthest start position first, so the greedy
+            // repetitions take as many chars as possible, e.g.
+            // `'c'.is_after(capture('b'+))` captures "bb" in "abbc".
+            //
+            // only the start positions that fit the length of the
+            // route are tried, e.g. `'c'.is_after("ab")` runs the
+            // route at `position - 2` only.
+            self.look_behind_starts(look_around, position)
+                .find_map(|start| {
+                    let thread_state = self.clone_state(&state);
+                    self.run_thread(
+                        look_around.start_state_index,
+                        look_around.end_state_index,
+                        start,
+                        Some(position),
+                        thread_state,
+                    )
+                })
+        } else {
+            let thread_state = self.clone_state(&state);
+            self.run_thread(
+                look_around.start_state_index,
+                look_around.end_state_index,
+                position,
+                None,
+                thread_state,
+            )
+        };
+        self.look_around_depth -= 1;
+
+        match (result, look_around.negative) {
+            (Some((_, mut line_state)), false) => {
+                // keep the counters, the match start and the tag of
+                // the outer thread
+                std::mem::swap(&mut line_state.counters, &mut state.counters);
+                line_state.match_start = state.match_start;
+                line_state.tag = state.tag;
+                self.recycle_state(state);
+                Some(line_state)
+            }
+            (None, true) => Some(state),
+            (Some((_, line_state)), true) => {
+                self.recycle_state(line_state);
+                self.recycle_state(state);
+                None
+            }
+            (None, false) => {
+                self.recycle_state(state);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        ast::Program,
+        error::Error,
+        options::{CompileOptions, MatchLimits},
+        parser::parse_from_str,
+        state::{CaptureGroup, LookAroundRoute},
+    };
+
+    use super::{ExecProgress, Instance, MatchRange, MatchStats, PartialMatch, Process};
+
+    #[test]
+    fn test_process_char() {
+        let process = Process::new(r#"'a', 'b'"#).unwrap();
+
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+
+        let mut instance = process.new_instance("xxabxab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 4)));
+        assert_eq!(instance.exec(4), Some(MatchRange::new(5, 7)));
+        assert_eq!(instance.exec(6), None);
+
+        let mut instance = process.new_instance("a");
+        assert_eq!(instance.exec(0), None);
+    }
+
+    #[test]
+    fn test_process_logic_or() {
+        let process = Process::new(r#"'a', ('b' || 'c'), 'd'"#).unwrap();
+
+        let mut instance = process.new_instance("abd acd aed");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+        assert_eq!(instance.exec(3), Some(MatchRange::new(4, 7)));
+        assert_eq!(instance.exec(7), None);
+
+        // the alternation of literals keeps the order of the branches
+        let process = Process::new(r#"capture("ab" || 'a' || "abc"), char_any"#).unwrap();
+        let mut instance = process.new_instance("abc");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 3)),
+                Some(MatchRange::new(0, 2))
+            ])
+        );
+
+        let mut instance = process.new_instance("ab");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 2)),
+                Some(MatchRange::new(0, 1))
+            ])
+        );
+
+        let process = Process::new(r#""GET" || "PUT" || "POST" || "PATCH" || "DELETE""#).unwrap();
+        let mut instance = process.new_instance("PATCH /a, DELETE /b, PUSH /c, POST /d");
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(0, 5)));
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(10, 16)));
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(30, 34)));
+        assert_eq!(instance.exec_next(), None);
+
+        // the long alternations, e.g. the generated patterns, are not
+        // nested, so they are not limited by the nesting depth.
+        let words: Vec<String> = (0..5000).map(|i| format!("\"k{}\"", i)).collect();
+        let process = Process::new(&(words.join(" || ") + ", end")).unwrap();
+        let mut instance = process.new_instance("k4999");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+
+        let words: Vec<String> = (0..5000)
+            .map(|i| format!("(\"k{}\", '-', char_digit)", i))
+            .collect();
+        let process = Process::new(&words.join(" || ")).unwrap();
+        let mut instance = process.new_instance("k5000-1 k4999-7");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(8, 15)));
+    }
+
+    #[test]
+    fn test_process_char_any() {
+        // `char_any` does not match the new line char
+        let process = Process::new(r#"'a', char_any, 'b'"#).unwrap();
+
+        let mut instance = process.new_instance("axb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        let mut instance = process.new_instance("a\nb");
+        assert_eq!(instance.exec(0), None);
+
+        let mut instance = process.new_instance("a\nb a\tb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 7)));
+
+        // `char_any_including_newline` matches any char
+        let process = Process::new(r#"'a', char_any_including_newline, 'b'"#).unwrap();
+
+        let mut instance = process.new_instance("axb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        let mut instance = process.new_instance("a\nb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        // it does not match the end of text
+        let mut instance = process.new_instance("a");
+        assert_eq!(instance.exec(0), None);
+    }
+
+    #[test]
+    fn test_process_grapheme() {
+        // the emoji with a skin tone modifier, the family joined by ZWJ,
+        // the combining sequence, the flag and the new line "\r\n".
+        let text = "\u{1f44d}\u{1f3fd}\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}e\u{301}\u{1f1e8}\u{1f1f3}\r\n";
+
+        let process = Process::new(r#"grapheme_any"#).unwrap();
+        let mut instance = process.new_instance(text);
+        let mut ranges = vec![];
+        let mut start = 0;
+        while let Some(range) = instance.exec(start) {
+            ranges.push(range);
+            start = range.end;
+        }
+        assert_eq!(
+            ranges,
+            vec![
+                MatchRange::new(0, 2),
+                MatchRange::new(2, 7),
+                MatchRange::new(7, 9),
+                MatchRange::new(9, 11),
+                MatchRange::new(11, 12), // the '\r' without the new line
+            ]
+        );
+
+        // `char_any` counts the code points unless the option is set
+        let process = Process::new(r#"start, char_any{2}, end"#).unwrap();
+        assert_eq!(
+            process.new_instance("\u{1f44d}\u{1f3fd}").exec(0),
+            Some(MatchRange::new(0, 2))
+        );
+        assert_eq!(process.new_instance("e\u{301}x").exec(0), None);
+
+        let process = Process::new(r#"options(grapheme), start, char_any{2}, end"#).unwrap();
+        assert_eq!(process.new_instance("\u{1f44d}\u{1f3fd}").exec(0), None);
+        assert_eq!(
+            process.new_instance("e\u{301}x").exec(0),
+            Some(MatchRange::new(0, 3))
+        );
+        assert_eq!(
+            process
+                .new_instance("\u{1f1e8}\u{1f1f3}\u{1f1ef}\u{1f1f5}")
+                .exec(0),
+            Some(MatchRange::new(0, 4))
+        );
+
+        // the negative charset consumes the whole cluster
+        let process = Process::new(r#"options(grapheme), start, !['a'], 'b'"#).unwrap();
+        assert_eq!(
+            process.new_instance("e\u{301}b").exec(0),
+            Some(MatchRange::new(0, 3))
+        );
+        assert_eq!(process.new_instance("a\u{301}b").exec(0), None);
+
+        let process = Process::new(r#"start, !['a'], 'b'"#).unwrap();
+        assert_eq!(process.new_instance("e\u{301}b").exec(0), None);
+
+        // bytes mode
+        let process = Process::new(r#"grapheme_any, 'x'"#).unwrap();
+        assert_eq!(
+            process
+                .new_instance_from_bytes("e\u{301}x".as_bytes())
+                .exec(0),
+            Some(MatchRange::new(0, 4))
+        );
+    }
+
+    #[test]
+    fn test_process_string() {
+        let process = Process::new(r#""abc""#).unwrap();
+
+        let mut instance = process.new_instance("ab abc abcabc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 6)));
+        assert_eq!(instance.exec(6), Some(MatchRange::new(7, 10)));
+        assert_eq!(instance.exec(10), Some(MatchRange::new(10, 13)));
+        assert_eq!(instance.exec(13), None);
+    }
+
+    #[test]
+    fn test_process_literal() {
+        // the literal patterns are searched directly, and the statistics
+        // disable the direct search, so the results of the threads are
+        // compared.
+        for pattern in [r#""abc""#, r#"'文'"#, r#"("ab", 'c')"#, r#""字a""#] {
+            let process = Process::new(pattern).unwrap();
+            assert!(process.new_instance("").literal.is_some());
+
+            for text in ["", "ab abc", "aab", "文字abc", "字文字a", "abcabc", "ab"] {
+                let mut fast = process.new_instance(text);
+                let mut slow = process.new_instance(text);
+                slow.enable_stats();
+
+                for start in 0..=text.chars().count() {
+                    assert_eq!(
+                        fast.exec_with_read_range(start),
+                        slow.exec_with_read_range(start)
+                    );
+                    assert_eq!(fast.last_failure_info(), slow.last_failure_info());
+                    assert_eq!(
+                        fast.exec_with_captures(start),
+                        slow.exec_with_captures(start)
+                    );
+                    assert_eq!(fast.exec_next(), slow.exec_next());
+                }
+
+                let mut fast = process.new_instance_from_bytes(text.as_bytes());
+                let mut slow = process.new_instance_from_bytes(text.as_bytes());
+                slow.enable_stats();
+                for start in 0..=text.len() {
+                    assert_eq!(fast.exec(start), slow.exec(start));
+                    assert_eq!(fast.last_failure_info(), slow.last_failure_info());
+                }
+            }
+        }
+
+        // not literals
+        for pattern in [r#""abc"?"#, r#"name("abc", x)"#, r#""abc", end"#] {
+            let process = Process::new(pattern).unwrap();
+            assert!(process.new_instance("").literal.is_none());
+        }
+        let process = Process::new_with_options(
+            r#""abc""#,
+            &CompileOptions {
+                ignore_case: true,
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(process.new_instance("").literal.is_none());
+    }
+
+    #[test]
+    fn test_process_function_normalized() {
+        let process = Process::new(r#"normalized("caf\u{e9}"), '!'"#).unwrap();
+
+        // composed
+        let mut instance = process.new_instance("caf\u{e9}!");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+
+        // decomposed
+        let mut instance = process.new_instance("cafe\u{301}!");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 6)));
+
+        // case-sensitive by default
+        let mut instance = process.new_instance("CAF\u{c9}!");
+        assert_eq!(instance.exec(0), None);
+
+        // the option `ignore_case`
+        let process = Process::new_with_options(
+            r#"normalized("caf\u{e9}"), '!'"#,
+            &CompileOptions {
+                ignore_case: true,
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap();
+        let mut instance = process.new_instance("CAF\u{c9}!");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+        let mut instance = process.new_instance("CAFE\u{301}!");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 6)));
+
+        // the plain string matches the composed form only
+        let process = Process::new(r#""caf\u{e9}", '!'"#).unwrap();
+
+        let mut instance = process.new_instance("cafe\u{301}!");
+        assert_eq!(instance.exec(0), None);
+    }
+
+    #[test]
+    fn test_process_bytes() {
+        let process = Process::new(r#"byte(0x00), byte_range(0x80..0xff), 'a'"#).unwrap();
+
+        let mut instance =
+            process.new_instance_from_bytes(&[0x61, 0x00, 0x7f, 0x61, 0x00, 0xfe, 0x61]);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 7)));
+
+        // bytes do not match in text mode
+        let mut instance = process.new_instance("\u{0}\u{fe}a");
+        assert_eq!(instance.exec(0), None);
+
+        // chars and strings are read as UTF-8 in bytes mode
+        let process = Process::new(r#""文字", char_any, '!'"#).unwrap();
+
+        let mut instance = process.new_instance_from_bytes("abc文字🍞!".as_bytes());
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 14)));
+
+        // malformed UTF-8 bytes
+        let mut instance = process
+            .new_instance_from_bytes(&[0xff, 0xe6, 0x96, 0x87, 0xe5, 0xad, 0x97, 0xff, b'!']);
+        assert_eq!(instance.exec(0), None);
+    }
+
+    #[test]
+    fn test_process_exec_anchored() {
+        let process = Process::new(r#""ab" || 'c'"#).unwrap();
+
+        let mut instance = process.new_instance("xabcab");
+        assert_eq!(instance.exec_anchored(0), None);
+        assert_eq!(instance.exec_anchored(1), Some(MatchRange::new(1, 3)));
+        assert_eq!(instance.exec_anchored(2), None);
+        assert_eq!(instance.exec_anchored(3), Some(MatchRange::new(3, 4)));
+        assert_eq!(instance.exec_anchored(4), Some(MatchRange::new(4, 6)));
+        assert_eq!(instance.exec_anchored(6), None);
+        assert_eq!(instance.exec_anchored(7), None);
+
+        // `exec` scans forward
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 3)));
+    }
+
+    #[test]
+    fn test_process_optional() {
+        let process = Process::new(r#"'a', 'b'?, 'c'"#).unwrap();
+
+        let mut instance = process.new_instance("ac abc abbc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+        assert_eq!(instance.exec(2), Some(MatchRange::new(3, 6)));
+        assert_eq!(instance.exec(6), None);
+
+        // greedy and lazy
+        let process = Process::new(r#"'a', 'b'?"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+
+        let process = Process::new(r#"'a', 'b'??"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 1)));
+    }
+
+    #[test]
+    fn test_process_empty_alternative() {
+        // `('b' || )` is equivalent to `'b'?` and `( || 'b')` to `'b'??`
+        for (pattern, equivalent) in [
+            (r#"'a', ('b' || ), 'c'"#, r#"'a', 'b'?, 'c'"#),
+            (r#"'a', ( || 'b')"#, r#"'a', 'b'??"#),
+            (r#"'a', ('b' || || 'c')"#, r#"'a', ('b' || 'c'??)"#),
+        ] {
+            let process = Process::new(pattern).unwrap();
+            let equivalent_process = Process::new(equivalent).unwrap();
+            for text in ["ac", "abc", "abbc", "ab", "a"] {
+                let mut instance = process.new_instance(text);
+                let mut equivalent_instance = equivalent_process.new_instance(text);
+                assert_eq!(
+                    instance.exec(0),
+                    equivalent_instance.exec(0),
+                    "{} with {}",
+                    pattern,
+                    text
+                );
+            }
+        }
+
+        // the empty group matches the empty string
+        let process = Process::new(r#"'a', (), 'b'"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+
+        let process = Process::new(r#"()"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(1), Some(MatchRange::new(1, 1)));
+    }
+
+    #[test]
+    fn test_process_repetition() {
+        let process = Process::new(r#"'a'+"#).unwrap();
+        let mut instance = process.new_instance("xaaay");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 4)));
+
+        let process = Process::new(r#"'a'+?"#).unwrap();
+        let mut instance = process.new_instance("xaaay");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        let process = Process::new(r#"'x', 'a'*, 'y'"#).unwrap();
+        let mut instance = process.new_instance("xy xaay");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+        assert_eq!(instance.exec(2), Some(MatchRange::new(3, 7)));
+
+        let process = Process::new(r#"'a'{2,3}"#).unwrap();
+        let mut instance = process.new_instance("a aaaa");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 5)));
+        assert_eq!(instance.exec(5), None);
+
+        let process = Process::new(r#"'a'{2,}?, 'b'"#).unwrap();
+        let mut instance = process.new_instance("ab aaaab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 8)));
+
+        // backtracking into the repetition
+        let process = Process::new(r#"'a'*, "ab""#).unwrap();
+        let mut instance = process.new_instance("aaab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 4)));
+
+        // a fixed repetition of literals
+        let process = Process::new(r#"('a', "bc"){2}, 'd'"#).unwrap();
+        let mut instance = process.new_instance("abcd abcabcd");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(5, 12)));
+    }
+
+    #[test]
+    fn test_process_number_range() {
+        for (start, end_included) in [(0, 255), (7, 1234), (100, 199), (99, 100)] {
+            let process = Process::new(&format!(
+                "start, number_range({}, {}), end",
+                start, end_included
+            ))
+            .unwrap();
+
+            for n in 0..1300 {
+                let text = n.to_string();
+                let mut instance = process.new_instance(&text);
+                assert_eq!(
+                    instance.exec(0).is_some(),
+                    (start..=end_included).contains(&n),
+                    "number_range({}, {}) with {}",
+                    start,
+                    end_included,
+                    n
+                );
+            }
+        }
+
+        // the longest number is preferred
+        let process = Process::new(r#"number_range(0, 255)"#).unwrap();
+        let mut instance = process.new_instance("x250y");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 4)));
+
+        // the widest range
+        let process = Process::new(r#"start, number_range(0, 4294967295), end"#).unwrap();
+        let mut instance = process.new_instance("4294967295");
+        assert!(instance.exec(0).is_some());
+        let mut instance = process.new_instance("4294967296");
+        assert!(instance.exec(0).is_none());
+    }
+
+    #[test]
+    fn test_process_repetition_counters() {
+        // nested repetitions, each has its own counter
+        let process = Process::new(r#"('a', ['b']{2}){2}"#).unwrap();
+        let mut instance = process.new_instance("abbab abbabb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(6, 12)));
+
+        // the same repetition in alternating branches
+        let process = Process::new(r#"((['x']{2}, 'a') || (['x']{3}, 'b')){2}"#).unwrap();
+        let mut instance = process.new_instance("xxxbxxa");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 7)));
+
+        // the counter is restored when backtracking out of an iteration
+        let process = Process::new(r#"("ab" || 'a'){3}, 'c'"#).unwrap();
+        let mut instance = process.new_instance("abaabc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 6)));
+
+        // the repeated expression matches empty
+        let process = Process::new(r#"('a'?)*, 'b'"#).unwrap();
+        let mut instance = process.new_instance("aab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        let process = Process::new(r#"('a'?){3}, 'b'"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+    }
+
+    #[test]
+    fn test_process_captures() {
+        let process = Process::new(r#"name(char_any+?, user), '@', capture("a" || "b")"#).unwrap();
+
+        let mut instance = process.new_instance("xx@a");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 4)),
+                Some(MatchRange::new(0, 2)),
+                Some(MatchRange::new(3, 4))
+            ])
+        );
+
+        // the group in a repetition keeps the last iteration
+        let process = Process::new(r#"capture('a' || 'b')+"#).unwrap();
+        let mut instance = process.new_instance("abba");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 4)),
+                Some(MatchRange::new(3, 4))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_process_exec_into() {
+        let process =
+            Process::new(r#"name(char_digit+, major), ('.', name(char_digit+, minor))?"#).unwrap();
+        assert_eq!(process.capture_groups().len(), 3);
+
+        let mut instance = process.new_instance("v1.2 v3");
+        let mut match_ranges = [None; 3];
+
+        assert!(instance.exec_into(0, &mut match_ranges));
+        assert_eq!(
+            match_ranges,
+            [
+                Some(MatchRange::new(1, 4)),
+                Some(MatchRange::new(1, 2)),
+                Some(MatchRange::new(3, 4))
+            ]
+        );
+
+        // the group that does not participate is cleared
+        assert!(instance.exec_into(4, &mut match_ranges));
+        assert_eq!(
+            match_ranges,
+            [
+                Some(MatchRange::new(6, 7)),
+                Some(MatchRange::new(6, 7)),
+                None
+            ]
+        );
+
+        // the slice is unchanged if there is no match
+        assert!(!instance.exec_into(7, &mut match_ranges));
+        assert_eq!(match_ranges[0], Some(MatchRange::new(6, 7)));
+
+        // the shorter and the longer slices
+        let mut match_ranges = [None; 1];
+        assert!(instance.exec_into(0, &mut match_ranges));
+        assert_eq!(match_ranges, [Some(MatchRange::new(1, 4))]);
+
+        let mut match_ranges = [Some(MatchRange::new(0, 0)); 4];
+        assert!(instance.exec_into(0, &mut match_ranges));
+        assert_eq!(match_ranges[2], Some(MatchRange::new(3, 4)));
+        assert_eq!(match_ranges[3], None);
+
+        // the empty slice only checks whether it matches
+        assert!(instance.exec_into(0, &mut []));
+    }
+
+    #[test]
+    fn test_process_negative_charset_bytes() {
+        // the negative charset never splits a UTF-8 sequence
+        let process = Process::new(r#"!['a'..'c']+"#).unwrap();
+        let mut bytes = "x文🍞b".as_bytes().to_vec();
+        bytes.extend_from_slice(&[0xe6, 0x96, b'y']);
+
+        let mut instance = process.new_instance_from_bytes(&bytes);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 8)));
+        assert_eq!(instance.exec(8), Some(MatchRange::new(11, 12)));
+
+        // the bytes of the malformed char can be matched by `byte_range`
+        let process = Process::new(r#"!['a'..'c'], byte_range(0x80..0xff)+"#).unwrap();
+        let mut instance = process.new_instance_from_bytes(&[0xff, b'x', 0x96, 0x96]);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 4)));
+    }
+
+    #[test]
+    fn test_process_scan() {
+        let process =
+            Process::new(r#"name(char_word+, key), '=', name(char_digit*, value)"#).unwrap();
+        let key = process.capture_group_index("key").unwrap();
+        let value = process.capture_group_index("value").unwrap();
+
+        let text = "a=1, b=, c=23";
+        let mut instance = process.new_instance(text);
+        let mut pairs = vec![];
+        let count = instance.scan(0, |match_ranges| {
+            let key = match_ranges[key].unwrap();
+            let value = match_ranges[value].unwrap();
+            pairs.push((&text[key.start..key.end], &text[value.start..value.end]));
+            ControlFlow::Continue(())
+        });
+        assert_eq!(count, 3);
+        assert_eq!(pairs, vec![("a", "1"), ("b", ""), ("c", "23")]);
+
+        // stop early
+        let count = instance.scan(2, |match_ranges| {
+            assert_eq!(match_ranges[0], Some(MatchRange::new(5, 7)));
+            ControlFlow::Break(())
+        });
+        assert_eq!(count, 1);
+
+        // the empty matches
+        let process = Process::new(r#"'a'?"#).unwrap();
+        let mut instance = process.new_instance("xa");
+        let mut ranges = vec![];
+        instance.scan(0, |match_ranges| {
+            ranges.push(match_ranges[0].unwrap());
+            ControlFlow::Continue(())
+        });
+        assert_eq!(
+            ranges,
+            vec![
+                MatchRange::new(0, 0),
+                MatchRange::new(1, 2),
+                MatchRange::new(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_exec_partial() {
+        let process = Process::new(r#"start, char_digit{4}, '-', char_digit{2}, end"#).unwrap();
+
+        let partial = ["", "2", "2024", "2024-", "2024-0"];
+        for text in partial {
+            let mut instance = process.new_instance(text);
+            assert_eq!(instance.exec_partial(0), PartialMatch::Partial, "{}", text);
+        }
+
+        let mut instance = process.new_instance("2024-01");
+        assert_eq!(
+            instance.exec_partial(0),
+            PartialMatch::Complete(MatchRange::new(0, 7))
+        );
+
+        let no_match = ["x", "2024-x", "2024-012", "20240"];
+        for text in no_match {
+            let mut instance = process.new_instance(text);
+            assert_eq!(instance.exec_partial(0), PartialMatch::NoMatch, "{}", text);
+        }
+
+        // the strings and the alternations
+        let process = Process::new(r#"start, ("GET" || "POST"), ' ', char_not_space+"#).unwrap();
+        let mut instance = process.new_instance("PO");
+        assert_eq!(instance.exec_partial(0), PartialMatch::Partial);
+        let mut instance = process.new_instance("PUT");
+        assert_eq!(instance.exec_partial(0), PartialMatch::NoMatch);
+
+        let mut instance = process.new_instance("POST /");
+        assert_eq!(
+            instance.exec_partial(0),
+            PartialMatch::Complete(MatchRange::new(0, 6))
+        );
+
+        // the end of text begins a match if the pattern is not anchored
+        let process = Process::new(r#""GET""#).unwrap();
+        let mut instance = process.new_instance("PUT");
+        assert_eq!(instance.exec_partial(0), PartialMatch::Partial);
+
+        // bytes mode, an incomplete UTF-8 char at the end
+        let process = Process::new(r#"start, 'a', '文'"#).unwrap();
+        let mut instance = process.new_instance_from_bytes(&"a文".as_bytes()[..2]);
+        assert_eq!(instance.exec_partial(0), PartialMatch::Partial);
+        let mut instance = process.new_instance_from_bytes(b"ab");
+        assert_eq!(instance.exec_partial(0), PartialMatch::NoMatch);
+    }
+
+    #[test]
+    fn test_process_is_match() {
+        let process = Process::new(
+            r#"name(char_word+, user), '@', name(char_word+, domain).is_before(capture('.'))"#,
+        )
+        .unwrap();
+
+        let mut instance = process.new_instance("mail: foo@bar.com");
+        assert!(instance.is_match(0));
+        assert!(instance.is_match(6));
+        assert!(!instance.is_match(11));
+
+        // the captures are recorded by the later calls
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(6, 13)),
+                Some(MatchRange::new(6, 9)),
+                Some(MatchRange::new(10, 13)),
+                Some(MatchRange::new(13, 14)),
+            ])
+        );
+
+        let mut match_ranges = [None; 2];
+        assert!(instance.exec_into(0, &mut match_ranges));
+        assert_eq!(
+            match_ranges,
+            [Some(MatchRange::new(6, 13)), Some(MatchRange::new(6, 9))]
+        );
+
+        // the captures inside repetitions and negative look-arounds
+        let process = Process::new(r#"(capture('a'), 'b'.is_not_after(capture('c')))+"#).unwrap();
+        let mut instance = process.new_instance("xabab");
+        assert!(instance.is_match(0));
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 5)));
+        assert_eq!(
+            instance.exec_with_captures(0).unwrap()[1],
+            Some(MatchRange::new(3, 4))
+        );
+
+        instance.reset("xcb");
+        assert!(!instance.is_match(0));
+    }
+
+    #[test]
+    fn test_process_capture_counts() {
+        let process = Process::new("(name(char_digit, d), ',')+, name('x', x)?").unwrap();
+        let mut instance = process.new_instance("a1,2,3,b");
+        assert_eq!(
+            instance.exec_with_capture_counts(0),
+            Some((
+                vec![
+                    Some(MatchRange::new(1, 7)),
+                    Some(MatchRange::new(5, 6)),
+                    None
+                ],
+                vec![1, 3, 0]
+            ))
+        );
+
+        // the backtracked iterations are not counted
+        let process = Process::new("(name(char_word, w))+, 'c'").unwrap();
+        let mut instance = process.new_instance("abcd");
+        assert_eq!(
+            instance.exec_with_capture_counts(0),
+            Some((
+                vec![Some(MatchRange::new(0, 3)), Some(MatchRange::new(1, 2))],
+                vec![1, 2]
+            ))
+        );
+
+        // no match, and the plain string
+        instance.reset("ab");
+        assert_eq!(instance.exec_with_capture_counts(0), None);
+
+        let process = Process::new("\"ab\"").unwrap();
+        let mut instance = process.new_instance("xab");
+        assert_eq!(
+            instance.exec_with_capture_counts(0),
+            Some((vec![Some(MatchRange::new(1, 3))], vec![1]))
+        );
+    }
+
+    #[test]
+    fn test_process_tags() {
+        let process = Process::new(
+            r#"start, (tag("GET", "read") || tag("HEAD", "read") || tag("POST", "write")), ' '"#,
+        )
+        .unwrap();
+        assert_eq!(process.tags(), ["read".to_owned(), "write".to_owned()]);
+
+        let mut instance = process.new_instance("POST /");
+        assert_eq!(
+            instance.exec_with_tag(0),
+            Some((MatchRange::new(0, 5), Some(1)))
+        );
+
+        let mut instance = process.new_instance("HEAD /");
+        assert_eq!(
+            instance.exec_with_tag(0),
+            Some((MatchRange::new(0, 5), Some(0)))
+        );
+
+        // the tag of the branch that fails later is discarded
+        let process = Process::new(r#"(tag('a', "x"), 'b') || (tag('a', "y"), 'c')"#).unwrap();
+        let mut instance = process.new_instance("ac");
+        assert_eq!(
+            instance.exec_with_tag(0),
+            Some((MatchRange::new(0, 2), Some(1)))
+        );
+
+        // the last tag wins, and the tag in the look-around has no effect
+        let process =
+            Process::new(r#"tag(tag('a', "x"), "y"), 'b'.is_before(tag('c', "z"))"#).unwrap();
+        assert_eq!(process.tags(), ["y", "x", "z"]);
+        let mut instance = process.new_instance("abc");
+        assert_eq!(
+            instance.exec_with_tag(0),
+            Some((MatchRange::new(0, 2), Some(0)))
+        );
+
+        // no tag
+        let process = Process::new(r#"tag('a', "x") || 'b'"#).unwrap();
+        let mut instance = process.new_instance("b");
+        assert_eq!(
+            instance.exec_with_tag(0),
+            Some((MatchRange::new(0, 1), None))
+        );
+        assert_eq!(instance.exec_with_tag(1), None);
+    }
+
+    #[test]
+    fn test_process_matching_tags() {
+        let program = Program::alternate(vec![
+            Program::tag(Program::literal("/users"), "users"),
+            Program::tag(Program::literal("/posts"), "posts"),
+            Program::tag(parse_from_str(r#"'/', char_digit+"#).unwrap(), "id"),
+        ]);
+        let process = Process::from_program(&program, &CompileOptions::default()).unwrap();
+        assert_eq!(process.tags(), ["users", "posts", "id"]);
+
+        assert_eq!(process.matching_tags("/posts/12/users/3"), vec![0, 1, 2]);
+        assert_eq!(process.matching_tags("/posts/posts"), vec![1]);
+        assert!(process.matching_tags("/about").is_empty());
+    }
+
+    #[test]
+    fn test_process_exec_steps() {
+        // run to the end with the number of steps, returns the result
+        // and the number of calls.
+        fn exec_steps(
+            instance: &mut Instance,
+            start: usize,
+            max_steps: usize,
+        ) -> (Option<MatchRange>, usize) {
+            let mut calls = 1;
+            let mut progress = instance.exec_steps(start, max_steps);
+            loop {
+                match progress {
+                    ExecProgress::Done(result) => return (result, calls),
+                    ExecProgress::Pending(checkpoint) => {
+                        calls += 1;
+                        progress = instance.resume(checkpoint, max_steps);
+                    }
+                }
+            }
+        }
+
+        let process = Process::new(r#"'a'+, 'b', 'c'"#).unwrap();
+        let mut instance = process.new_instance("aaaaaaab aaaaaaabc");
+        let expected = instance.exec(0);
+        assert_eq!(expected, Some(MatchRange::new(9, 18)));
+
+        instance.enable_stats();
+        instance.exec(0);
+        let total_steps = instance.stats().unwrap().steps;
+
+        assert_eq!(exec_steps(&mut instance, 0, usize::MAX), (expected, 1));
+        let (result, calls) = exec_steps(&mut instance, 0, 3);
+        assert_eq!(result, expected);
+        assert_eq!(calls, total_steps.div_ceil(3));
+
+        // the steps are counted like the statistics
+        assert_eq!(instance.stats().unwrap().steps, total_steps * 3);
+
+        // no match
+        assert_eq!(exec_steps(&mut instance, 17, 3).0, None);
+
+        // the look-arounds
+        let process = Process::new(r#"'a'+, 'b'.is_before('c')"#).unwrap();
+        let mut instance = process.new_instance("aaab aaabc");
+        assert_eq!(
+            exec_steps(&mut instance, 0, 2).0,
+            Some(MatchRange::new(5, 9))
+        );
+
+        // the next match after a suspended one
+        let process = Process::new(r#"char_digit+"#).unwrap();
+        let mut instance = process.new_instance("12 345");
+        assert_eq!(
+            exec_steps(&mut instance, 0, 1).0,
+            Some(MatchRange::new(0, 2))
+        );
+        assert_eq!(instance.last_match_end(), 2);
+        assert_eq!(
+            exec_steps(&mut instance, 2, 1).0,
+            Some(MatchRange::new(3, 6))
+        );
+
+        // the limits
+        let process = Process::new(r#"'a'+, 'b'"#).unwrap();
+        let mut instance = process.new_instance("aaaaaaaa");
+        instance.set_limits(MatchLimits {
+            max_frames: 4,
+            ..MatchLimits::default()
+        });
+        assert_eq!(exec_steps(&mut instance, 0, 2).0, None);
+        assert!(instance.try_exec(0).is_err());
+    }
+
+    #[test]
+    fn test_process_last_failure_info() {
+        let process = Process::new(r#""foo", char_digit+, ('.' || ';')"#).unwrap();
+        let mut instance = process.new_instance("a foo-1 foo12,");
+        assert_eq!(instance.exec(0), None);
+
+        let info = instance.last_failure_info().unwrap();
+        assert_eq!(info.position, 13);
+        assert_eq!(
+            info.transitions
+                .iter()
+                .map(|t| t.description.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Charset [char_digit]", "Char '.'", "Char ';'"]
+        );
+
+        // the info is cleared by the next call
+        instance.reset("foo-1");
+        assert_eq!(instance.exec_anchored(0), None);
+        assert_eq!(instance.last_failure_info().unwrap().position, 3);
+
+        instance.reset("foo1.");
+        assert!(instance.exec(0).is_some());
+        assert_eq!(instance.last_failure_info().unwrap().position, 4);
+
+        // the failures inside look-arounds are not recorded
+        let process = Process::new(r#"'a'.is_not_before("bc"), 'b'"#).unwrap();
+        let mut instance = process.new_instance("abc");
+        assert_eq!(instance.exec(0), None);
+        let info = instance.last_failure_info().unwrap();
+        assert_eq!(info.position, 1);
+        assert_eq!(info.transitions.len(), 1);
+        assert!(info.transitions[0]
+            .description
+            .starts_with("Negative look ahead"));
+
+        // no transition has been tried
+        instance.reset("");
+        assert_eq!(instance.exec(0), None);
+        assert_eq!(instance.last_failure_info(), None);
+    }
+
+    #[test]
+    fn test_process_program_composition() {
+        let user = parse_from_str(r#"name(char_word+, user)"#).unwrap();
+        let domain = parse_from_str(r#"'@', name(char_word+, domain)"#).unwrap();
+        let number = parse_from_str(r#"char_digit+"#).unwrap();
+
+        let program = Program::alternate(vec![
+            Program::concat(user, domain),
+            Program::capture(number, "number"),
+        ]);
+        assert_eq!(
+            program.to_string(),
+            r#"(name(one_or_more(char_word), user), '@', name(one_or_more(char_word), domain)) || name(one_or_more(char_digit), number)"#
+        );
+
+        let process = Process::from_program(&program, &CompileOptions::default()).unwrap();
+        assert_eq!(process.capture_group_count(), 3);
+
+        let mut instance = process.new_instance("foo@bar");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 7)),
+                Some(MatchRange::new(0, 3)),
+                Some(MatchRange::new(4, 7)),
+                None,
+            ])
+        );
+
+        let mut instance = process.new_instance("@ 123");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(2, 5)),
+                None,
+                None,
+                Some(MatchRange::new(2, 5)),
+            ])
+        );
+
+        // err: duplicated capture name
+        let a = parse_from_str(r#"name('a', x)"#).unwrap();
+        let b = parse_from_str(r#"name('b', x)"#).unwrap();
+        assert!(Process::from_program(&Program::concat(a, b), &CompileOptions::default()).is_err());
+
+        // rename the groups to avoid the clash
+        let a = parse_from_str(r#"name('a', x)"#).unwrap();
+        let b = parse_from_str(r#"name('b', x), capture(char_digit)"#).unwrap();
+        let program = Program::concat(a, b.prefix_group_names("inner_"));
+        assert_eq!(
+            program.to_string(),
+            "name('a', x)\nname('b', inner_x)\ncapture(char_digit)"
+        );
+
+        let process = Process::from_program(&program, &CompileOptions::default()).unwrap();
+        let mut instance = process.new_instance("ab1");
+        let captures = instance.captures(0).unwrap();
+        assert_eq!(captures.name("x"), Some("a"));
+        assert_eq!(captures.name("inner_x"), Some("b"));
+
+        // the references are renamed as well
+        let program = parse_from_str(r#"name(char_word+, key), '/', key"#)
+            .unwrap()
+            .rename_groups(|name| name.to_uppercase());
+        assert_eq!(
+            program.to_string(),
+            "name(one_or_more(char_word), KEY)\n'/', KEY"
+        );
+    }
+
+    #[test]
+    fn test_process_self_tests() {
+        let program = parse_from_str(
+            r#"
+start, char_word+, '@', char_word+, ('.', char_word+)*, end
+expect_match("user@example.com")
+expect_match("user@")
+expect_no_match("foo@")
+expect_no_match("foo@bar")"#,
+        )
+        .unwrap();
+
+        let failed_tests = program.run_self_tests(&CompileOptions::default()).unwrap();
+        assert_eq!(
+            failed_tests
+                .iter()
+                .map(|test| test.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                r#"expect_match("user@")"#.to_owned(),
+                r#"expect_no_match("foo@bar")"#.to_owned()
+            ]
+        );
+
+        // err: the pattern can not be compiled
+        let program = parse_from_str(r#"name('a', x), name('b', x), expect_match("ab")"#).unwrap();
+        assert!(program.run_self_tests(&CompileOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_process_continue_from_last() {
+        // scan tokens without gaps
+        let process =
+            Process::new(r#"continue_from_last, (char_word+ || char_space+ || ['+', '='])"#)
+                .unwrap();
+        let mut instance = process.new_instance("a = b+1 ? c");
+        let mut tokens = vec![];
+        while let Some(m) = instance.exec_next() {
+            tokens.push((m.start, m.end));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                (0, 1),
+                (1, 2),
+                (2, 3),
+                (3, 4),
+                (4, 5),
+                (5, 6),
+                (6, 7),
+                (7, 8)
+            ]
+        );
+        assert_eq!(instance.last_match_end(), 8);
+
+        // the first match must start at 0
+        let mut instance = process.new_instance("?a");
+        assert_eq!(instance.exec(0), None);
+
+        // `exec` continues from the previous match too
+        let process = Process::new(r#"continue_from_last, 'a'"#).unwrap();
+        let mut instance = process.new_instance("aaba");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 1)));
+        assert_eq!(instance.exec(1), Some(MatchRange::new(1, 2)));
+        assert_eq!(instance.exec(2), None);
+
+        // the empty matches
+        let process = Process::new(r#"continue_from_last, 'a'*"#).unwrap();
+        let mut instance = process.new_instance("aab");
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(0, 2)));
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(2, 2)));
+        assert_eq!(instance.exec_next(), None);
+    }
+
+    #[test]
+    fn test_process_match_start_reset() {
+        let process = Process::new(r#""price: ", match_start_reset, char_digit+"#).unwrap();
+        let mut instance = process.new_instance("total price: 42 USD");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(13, 15)));
+
+        // the function form
+        let process = Process::new(r#"'a', match_start_reset(), 'b'"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        // the captures are not affected
+        let process = Process::new(
+            r#"name(char_word+, key), '=', match_start_reset, name(char_word+, value)"#,
+        )
+        .unwrap();
+        let mut instance = process.new_instance("a=1, bc=23");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(2, 3)),
+                Some(MatchRange::new(0, 1)),
+                Some(MatchRange::new(2, 3)),
+            ])
+        );
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(8, 10)));
+        assert_eq!(instance.exec_next(), None);
+
+        // the reset of a failed branch is discarded
+        let process = Process::new(r#"('a', match_start_reset, 'x') || ('a', 'b')"#).unwrap();
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+
+        // the last reset wins
+        let process = Process::new(r#"('a', match_start_reset)+, 'b'"#).unwrap();
+        let mut instance = process.new_instance("xaaab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 5)));
+
+        // the reset inside a look-around has no effect
+        let process =
+            Process::new(r#"'a', 'b'.is_before((match_start_reset, 'c')), char_any"#).unwrap();
+        let mut instance = process.new_instance("abc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        // the empty matches
+        let process = Process::new(r#"'a', match_start_reset"#).unwrap();
+        let mut instance = process.new_instance("aba");
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(1, 1)));
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(3, 3)));
+        assert_eq!(instance.exec_next(), None);
+    }
+
+    #[test]
+    fn test_process_reset() {
+        let process = Process::new(
+            r#"name(char_digit+, number).is_before(['a'..'z']), name(['a'..'z'], unit)"#,
+        )
+        .unwrap();
+
+        let mut instance = process.new_instance("");
+        assert_eq!(instance.exec(0), None);
+
+        let texts = ["10s", "x 3m", "5 h", "42kg"];
+        let results: Vec<_> = texts
+            .iter()
+            .map(|text| {
+                instance.reset(text);
+                instance.exec_with_captures(0)
+            })
+            .collect();
+
+        // the same as the new instances
+        let expected: Vec<_> = texts
+            .iter()
+            .map(|text| process.new_instance(text).exec_with_captures(0))
+            .collect();
+        assert_eq!(results, expected);
+        assert_eq!(
+            results[1],
+            Some(vec![
+                Some(MatchRange::new(2, 4)),
+                Some(MatchRange::new(2, 3)),
+                Some(MatchRange::new(3, 4)),
+            ])
+        );
+
+        // the state of the previous text is cleared
+        let process = Process::new(r#"continue_from_last, 'a'*"#).unwrap();
+        let mut instance = process.new_instance("aab");
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(0, 2)));
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(2, 2)));
+
+        instance.reset("ab");
+        assert_eq!(instance.last_match_end(), 0);
+        assert_eq!(instance.exec_next(), Some(MatchRange::new(0, 1)));
+        assert_eq!(instance.length(), 2);
+
+        // switch to bytes mode and back
+        let process = Process::new(r#"'文'"#).unwrap();
+        let mut instance = process.new_instance("a文");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        instance.reset_bytes("a文".as_bytes());
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 4)));
+
+        instance.reset("文");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 1)));
+    }
+
+    #[test]
+    fn test_process_introspection() {
+        let process = Process::new(r#"'a', 'b'+"#).unwrap();
+        assert_eq!(process.state_count(), 7);
+        assert_eq!(process.transition_count(), 7);
+        assert_eq!(
+            process
+                .transition_kind_counts()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![
+                ("char", 2),
+                ("counter_check", 1),
+                ("counter_exit", 1),
+                ("counter_inc", 1),
+                ("counter_reset", 1),
+                ("jump", 1)
+            ]
+        );
+        assert_eq!(process.look_around_routes(), vec![]);
+
+        let process =
+            Process::new(r#"'a'.is_after('x'), 'b'.is_before('c'.is_not_after('d'))"#).unwrap();
+        assert_eq!(
+            process.look_around_routes(),
+            vec![
+                LookAroundRoute {
+                    state_index: 4,
+                    start_state_index: 2,
+                    end_state_index: 3,
+                    is_behind: true,
+                    negative: false,
+                    parent: None
+                },
+                LookAroundRoute {
+                    state_index: 6,
+                    start_state_index: 11,
+                    end_state_index: 8,
+                    is_behind: false,
+                    negative: false,
+                    parent: None
+                },
+                LookAroundRoute {
+                    state_index: 11,
+                    start_state_index: 9,
+                    end_state_index: 10,
+                    is_behind: true,
+                    negative: true,
+                    parent: Some(1)
+                }
+            ]
+        );
+        assert_eq!(process.transition_kind_counts()["look_around"], 3);
+    }
+
+    #[test]
+    fn test_process_from_bytes() {
+        let process =
+            Process::new(r#"name(char_word+, user), '@', name(char_word+, domain)"#).unwrap();
+        let loaded = Process::from_bytes(&process.to_bytes()).unwrap();
+        assert_eq!(loaded.capture_group_index("domain"), Some(2));
+
+        let mut instance = loaded.new_instance("hi foo@bar");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(3, 10)),
+                Some(MatchRange::new(3, 6)),
+                Some(MatchRange::new(7, 10)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_process_stats() {
+        let process = Process::new(r#"'a', char_digit"#).unwrap();
+        let mut instance = process.new_instance("xa1");
+        assert_eq!(instance.stats(), None);
+
+        instance.enable_stats();
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 3)));
+        assert_eq!(
+            instance.stats(),
+            Some(&MatchStats {
+                // the position 0 is skipped since 'x' can not begin
+                // a match, then 'a', jump and the digit at 1
+                steps: 3,
+                backtracks: 0,
+                threads: 1,
+                peak_depth: 4,
+            })
+        );
+
+        // the nested repetitions take more steps than the equivalent one
+        let text = "aaaaaaaaaaaaaaaab";
+        let count_steps = |pattern: &str| {
+            let process = Process::new(pattern).unwrap();
+            let mut instance = process.new_instance(text);
+            instance.enable_stats();
+            assert_eq!(instance.exec(0), None);
+            instance.stats().unwrap().steps
+        };
+        assert!(count_steps(r#"start, ('a'+)+, end"#) > count_steps(r#"start, 'a'+, end"#) * 100);
+    }
+
+    #[test]
+    fn test_process_first_chars() {
+        // only the positions of 'a' and 'b' start threads
+        let process = Process::new(r#"(capture('a') || "bc"), char_digit"#).unwrap();
+        let mut instance = process.new_instance("xxaxbc1xx");
+        instance.enable_stats();
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 7)));
+        assert_eq!(instance.stats().unwrap().threads, 2);
+
+        // the empty match is possible, all positions are tried
+        let process = Process::new(r#"'a'?"#).unwrap();
+        let mut instance = process.new_instance("xxa");
+        instance.enable_stats();
+        assert_eq!(instance.exec(1), Some(MatchRange::new(1, 1)));
+        assert_eq!(instance.stats().unwrap().threads, 1);
+
+        // the char that ignores case is not filtered
+        let options = CompileOptions {
+            ignore_case: true,
+            ..CompileOptions::default()
+        };
+        let process = Process::new_with_options(r#"'k'"#, &options).unwrap();
+        let mut instance = process.new_instance("xK");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        // ignore case for a part of the pattern
+        let process = Process::new(r#"ignore_case("select"), ' ', "Id""#).unwrap();
+        let mut instance = process.new_instance("x SeLeCt Id");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 11)));
+        let mut instance = process.new_instance("SELECT ID");
+        assert_eq!(instance.exec(0), None);
+
+        // the normalized strings
+        for pattern in [
+            r#"ignore_case(normalized("caf\u{e9}"))"#,
+            r#"normalized("caf\u{e9}").ignore_case()"#,
+        ] {
+            let process = Process::new(pattern).unwrap();
+            let mut instance = process.new_instance("CAF\u{c9}");
+            assert_eq!(instance.exec(0), Some(MatchRange::new(0, 4)));
+            let mut instance = process.new_instance("CAFE\u{301}");
+            assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+        }
+    }
+
+    #[test]
+    fn test_process_min_length() {
+        let process = Process::new(r#"char_digit{3}"#).unwrap();
+
+        // the positions 3 and 4 are skipped
+        let mut instance = process.new_instance("12 34");
+        instance.enable_stats();
+        assert_eq!(instance.exec(0), None);
+        assert_eq!(instance.stats().unwrap().threads, 2);
+
+        let mut instance = process.new_instance("12 345");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 6)));
+        assert_eq!(instance.exec_anchored(3), Some(MatchRange::new(3, 6)));
+        assert_eq!(instance.exec_anchored(4), None);
+
+        // the text is shorter than the shortest match
+        let mut instance = process.new_instance("12");
+        assert_eq!(instance.exec(0), None);
+        assert_eq!(instance.exec_anchored(0), None);
+
+        // a char takes many positions in bytes mode
+        let process = Process::new(r#"'文', char_any"#).unwrap();
+        let mut instance = process.new_instance_from_bytes("文字".as_bytes());
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 6)));
+    }
+
+    #[test]
+    fn test_process_exec_select() {
+        let process = Process::new(
+            r#"name(char_digit{4}, year), '-', name(char_digit{2}, month), '-', name(char_digit{2}, day)"#,
+        )
+        .unwrap();
+
+        let mut instance = process.new_instance("on 2024-03-15");
+        assert_eq!(
+            instance.exec_select(0, &["month", "year", "foo"]),
+            Some(vec![
+                Some(MatchRange::new(8, 10)),
+                Some(MatchRange::new(3, 7)),
+                None
+            ])
+        );
+        assert_eq!(
+            instance.exec_select_indices(0, &[0, 3, 9]),
+            Some(vec![
+                Some(MatchRange::new(3, 13)),
+                Some(MatchRange::new(11, 13)),
+                None
+            ])
+        );
+        assert_eq!(instance.exec_select(4, &["year"]), None);
+
+        // the selection does not affect the following calls
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(3, 13)),
+                Some(MatchRange::new(3, 7)),
+                Some(MatchRange::new(8, 10)),
+                Some(MatchRange::new(11, 13)),
+            ])
+        );
+
+        // the unselected groups referenced by back-references
+        let process = Process::new(r#"name(char_word, x), '=', x, name('!'?, y)"#).unwrap();
+        let mut instance = process.new_instance("a=b c=c!");
+        assert_eq!(
+            instance.exec_select(0, &["y"]),
+            Some(vec![Some(MatchRange::new(7, 8))])
+        );
+    }
+
+    #[test]
+    fn test_process_match_limits() {
+        // the greedy repetition keeps one frame for each char
+        let process = Process::new(r#"char_any*, 'x'"#).unwrap();
+        let text = "a".repeat(100);
+        let mut instance = process.new_instance(&text);
+        let initial_bytes = instance.allocated_bytes();
+
+        instance.set_limits(MatchLimits {
+            max_frames: 50,
+            ..MatchLimits::default()
+        });
+        assert!(matches!(
+            instance.try_exec(0),
+            Err(Error::LimitExceeded(m)) if m == "The number of backtracking frames exceeds the limit 50."
+        ));
+        assert_eq!(instance.exec(0), None);
+        assert_eq!((instance.frame_count, instance.thread_count), (0, 0));
+
+        instance.set_limits(MatchLimits {
+            max_frames: 1000,
+            ..MatchLimits::default()
+        });
+        assert_eq!(instance.try_exec(0), Ok(None));
+        assert!(instance.allocated_bytes() > initial_bytes);
+
+        instance.reset("aax");
+        assert_eq!(
+            instance.try_exec_with_captures(0),
+            Ok(Some(vec![Some(MatchRange::new(0, 3))]))
+        );
+
+        // the nested look-arounds run the nested threads
+        let process = Process::new(r#"'a'.is_before('b'.is_before('c'))"#).unwrap();
+        let mut instance = process.new_instance("abc");
+        instance.set_limits(MatchLimits {
+            max_threads: 2,
+            ..MatchLimits::default()
+        });
+        assert!(matches!(
+            instance.try_exec(0),
+            Err(Error::LimitExceeded(m)) if m == "The number of threads exceeds the limit 2."
+        ));
+        assert_eq!((instance.frame_count, instance.thread_count), (0, 0));
+
+        instance.set_limits(MatchLimits {
+            max_threads: 3,
+            ..MatchLimits::default()
+        });
+        assert_eq!(instance.try_exec(0), Ok(Some(MatchRange::new(0, 1))));
+    }
+
+    #[test]
+    fn test_process_match_length() {
+        // the fixed-width field
+        let process = Process::new(r#"char_digit{4}, '-', char_digit{2}"#).unwrap();
+        assert_eq!(process.min_match_length(), 7);
+        assert_eq!(process.max_match_length(), Some(7));
+
+        let process = Process::new(r#"'v', char_digit{1,3}, ('.', char_digit+)?"#).unwrap();
+        assert_eq!(process.min_match_length(), 2);
+        assert_eq!(process.max_match_length(), None);
+
+        let process = Process::new(r#"start, "yes" || "no", end"#).unwrap();
+        assert_eq!(process.min_match_length(), 2);
+        assert_eq!(process.max_match_length(), Some(3));
+
+        // the bounds are kept by the serialization
+        let process = Process::new(r#"'a', 'b'{2,4}?"#).unwrap();
+        let process = Process::from_bytes(&process.to_bytes()).unwrap();
+        assert_eq!(process.min_match_length(), 3);
+        assert_eq!(process.max_match_length(), Some(5));
+    }
+
+    #[test]
+    fn test_process_capture_group_index() {
+        let process = Process::new(r#"'a'.name(inner).name(outer), name('b', last)"#).unwrap();
+        assert_eq!(process.capture_group_index("inner"), Some(1));
+        assert_eq!(process.capture_group_index("outer"), Some(2));
+        assert_eq!(process.capture_group_index("last"), Some(3));
+        assert_eq!(process.capture_group_index("none"), None);
+
+        // the back-reference refers to the renumbered group
+        let process = Process::new(r#"('a'.name(x), 'b').name(y), x"#).unwrap();
+        let mut instance = process.new_instance("aba");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 3)),
+                Some(MatchRange::new(0, 1)),
+                Some(MatchRange::new(0, 2))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_process_capture_names() {
+        let process =
+            Process::new(r#"name(char_digit{4}, year), capture('-'), 'a'.name(inner).name(outer)"#)
+                .unwrap();
+        assert_eq!(
+            process.capture_names().collect::<Vec<_>>(),
+            vec![None, Some("year"), None, Some("inner"), Some("outer")]
+        );
+
+        let process = Process::new(r#"'a'"#).unwrap();
+        assert_eq!(process.capture_names().collect::<Vec<_>>(), vec![None]);
+    }
+
+    #[test]
+    fn test_process_builtin_macros() {
+        let process =
+            Process::new(r#"date_iso8601(), 'T', time_hh_mm_ss(), timezone_offset()"#).unwrap();
+
+        let names: Vec<Option<String>> = process
+            .capture_groups()
+            .iter()
+            .map(|group| group.name.clone())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                None,
+                Some("year".to_owned()),
+                Some("month".to_owned()),
+                Some("day".to_owned()),
+                Some("hour".to_owned()),
+                Some("minute".to_owned()),
+                Some("second".to_owned()),
+                Some("timezone".to_owned()),
+            ]
+        );
+
+        let mut instance = process.new_instance("at 2024-02-29T23:59:01+08:00");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(3, 28)),
+                Some(MatchRange::new(3, 7)),
+                Some(MatchRange::new(8, 10)),
+                Some(MatchRange::new(11, 13)),
+                Some(MatchRange::new(14, 16)),
+                Some(MatchRange::new(17, 19)),
+                Some(MatchRange::new(20, 22)),
+                Some(MatchRange::new(22, 28)),
+            ])
+        );
+
+        let mut instance = process.new_instance("2024-01-01T00:00:00Z");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 20)));
+
+        // invalid month, hour and offset
+        let mut instance = process.new_instance("2024-13-01T00:00:00Z");
+        assert_eq!(instance.exec(0), None);
+        let mut instance = process.new_instance("2024-12-01T24:00:00Z");
+        assert_eq!(instance.exec(0), None);
+        let mut instance = process.new_instance("2024-12-01T00:00:00+0860");
+        assert_eq!(instance.exec(0), None);
+    }
+
+    #[test]
+    fn test_process_captures_backtracking() {
+        // the captures of the failed branch are discarded
+        let process =
+            Process::new(r#"(name('a', first), 'x') || (name('a', second), 'y')"#).unwrap();
+        let mut instance = process.new_instance("ay");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 2)),
+                None,
+                Some(MatchRange::new(0, 1))
+            ])
+        );
+
+        // backtracking into a repetition
+        let process = Process::new(r#"capture('a'*), capture('a'), 'b'"#).unwrap();
+        let mut instance = process.new_instance("aaab");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 4)),
+                Some(MatchRange::new(0, 2)),
+                Some(MatchRange::new(2, 3))
+            ])
+        );
+
+        // the optional group does not participate
+        let process = Process::new(r#"'a', capture('b')?, 'c'"#).unwrap();
+        let mut instance = process.new_instance("abx ac");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![Some(MatchRange::new(4, 6)), None])
+        );
+    }
+
+    #[test]
+    fn test_process_repeated_captures() {
+        let text = "ab1ab2ab3";
+
+        // the last iteration is kept by default
+        let process = Process::new(r#"(('a', 'b').name(x), char_digit).repeat(3)"#).unwrap();
+        let mut instance = process.new_instance(text);
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 9)),
+                Some(MatchRange::new(6, 8))
+            ])
+        );
+
+        // keep the first iteration
+        let options = CompileOptions {
+            keep_first_capture: true,
+            ..CompileOptions::default()
+        };
+        let process =
+            Process::new_with_options(r#"(('a', 'b').name(x), char_digit).repeat(3)"#, &options)
+                .unwrap();
+        let mut instance = process.new_instance(text);
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 9)),
+                Some(MatchRange::new(0, 2))
+            ])
+        );
+
+        // the iteration that is backtracked does not count
+        let process =
+            Process::new_with_options(r#"name(char_word, x)+, char_word"#, &options).unwrap();
+        let mut instance = process.new_instance("abc");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 3)),
+                Some(MatchRange::new(0, 1))
+            ])
+        );
+
+        let process = Process::new(r#"name(char_word, x)+, char_word"#).unwrap();
+        let mut instance = process.new_instance("abc");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 3)),
+                Some(MatchRange::new(1, 2))
+            ])
+        );
+
+        // a nested repetition, the first iteration of the outer
+        // repetition keeps its own first inner iteration
+        let process =
+            Process::new_with_options(r#"(name(char_digit, x)+, ',')+"#, &options).unwrap();
+        let mut instance = process.new_instance("12,34,");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 6)),
+                Some(MatchRange::new(0, 1))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_process_look_around() {
+        // look ahead
+        let process = Process::new(r#"'a'.is_before('b')"#).unwrap();
+        let mut instance = process.new_instance("ac ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 4)));
+
+        let process = Process::new(r#"'a'.is_not_before('b')"#).unwrap();
+        let mut instance = process.new_instance("ab ac");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 4)));
+
+        // look behind
+        let process = Process::new(r#"'a'.is_after("xy" || 'z')"#).unwrap();
+        let mut instance = process.new_instance("ya xya za");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(5, 6)));
+        assert_eq!(instance.exec(6), Some(MatchRange::new(8, 9)));
+
+        let process = Process::new(r#"'a'.is_not_after('x')"#).unwrap();
+        let mut instance = process.new_instance("xa ya");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 5)));
+
+        // look around inside repetition
+        let process = Process::new(r#"('a'.is_before('b'), char_any)+"#).unwrap();
+        let mut instance = process.new_instance("abababac");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 6)));
+
+        // the deepest nesting allowed by default
+        let pattern = (0..32).fold("'x'".to_owned(), |inner, _| {
+            format!("'a'.is_before({})", inner)
+        });
+        let process = Process::new(&pattern).unwrap();
+        let text = "a".repeat(32) + "x";
+        let mut instance = process.new_instance(&text);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 1)));
+        assert_eq!(instance.exec(1), None);
+    }
+
+    #[test]
+    fn test_process_look_around_captures() {
+        // the captures inside a positive look-around are visible
+        let process = Process::new(r#"'a'.is_before(capture('b'+))"#).unwrap();
+        let mut instance = process.new_instance("abbc");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 1)),
+                Some(MatchRange::new(1, 3))
+            ])
+        );
+
+        let process = Process::new(r#"'c'.is_after(capture('b'+))"#).unwrap();
+        let mut instance = process.new_instance("abbc");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(3, 4)),
+                Some(MatchRange::new(1, 3))
+            ])
+        );
+
+        // but the captures inside a negative look-around are not
+        let process = Process::new(r#"'a'.is_not_before(capture('b')), char_any"#).unwrap();
+        let mut instance = process.new_instance("abac");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![Some(MatchRange::new(2, 4)), None])
+        );
+    }
+
+    #[test]
+    fn test_process_look_behind_bounds() {
+        // only the start positions that fit the length of the look-behind
+        // route are tried, i.e. it does not scan the text before
+        let text = "x".repeat(100_000) + "yza";
+        let process = Process::new(r#"'a'.is_after("yz" || 'q')"#).unwrap();
+        let mut instance = process.new_instance(&text);
+        instance.enable_stats();
+        assert_eq!(instance.exec(0), Some(MatchRange::new(100_002, 100_003)));
+        assert_eq!(instance.stats().unwrap().threads, 2);
+
+        let process = Process::new(r#"'a'.is_not_after(('x', char_digit{1,3}))"#).unwrap();
+        let mut instance = process.new_instance(&text);
+        instance.enable_stats();
+        assert_eq!(instance.exec(0), Some(MatchRange::new(100_002, 100_003)));
+        assert_eq!(instance.stats().unwrap().threads, 1 + 3);
+
+        // the route is longer than the text before
+        let process = Process::new(r#"'a'.is_after("xyz")"#).unwrap();
+        let mut instance = process.new_instance("za");
+        assert_eq!(instance.exec(0), None);
+
+        // the bounds of bytes in bytes mode
+        let process = Process::new(r#"'a'.is_after("文" || 'b')"#).unwrap();
+        let bytes = ["x".repeat(1000), "文a".to_owned()].concat();
+        let mut instance = process.new_instance_from_bytes(bytes.as_bytes());
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1003, 1004)));
+
+        // the unbounded route
+        let process = Process::new(r#"'a'.is_after((start, 'x'+))"#).unwrap();
+        let mut instance = process.new_instance(&text);
+        assert_eq!(instance.exec(0), None);
+        instance.reset("xxa");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 3)));
+    }
+
+    #[test]
+    fn test_process_back_reference() {
+        let process = Process::new(r#"name(char_word+, x), '=', x"#).unwrap();
+        let mut instance = process.new_instance("ab=ac ab=ab");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(6, 11)),
+                Some(MatchRange::new(6, 8))
+            ])
+        );
+
+        // the captures are recorded for the back-references even if
+        // they are not requested
+        assert_eq!(instance.exec(0), Some(MatchRange::new(6, 11)));
+        assert!(process.new_instance("ab=ab").is_match(0));
+        assert!(!process.new_instance("ab=ac").is_match(0));
+
+        // the back-reference to an empty capture
+        let process = Process::new(r#"name('a'?, x), '-', x"#).unwrap();
+        let mut instance = process.new_instance("-");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 1)));
+
+        // the back-reference to a group that does not participate fails
+        let process = Process::new(r#"(name('a', x) || 'b'), x"#).unwrap();
+        assert_eq!(process.new_instance("b").exec(0), None);
+        assert_eq!(
+            process.new_instance("baa").exec(0),
+            Some(MatchRange::new(1, 3))
+        );
+
+        // the back-reference inside a repetition refers to the last iteration
+        let process = Process::new(r#"(name(char_digit, x), x)+"#).unwrap();
+        let mut instance = process.new_instance("1122312");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 4)));
+
+        // ignore case
+        let process = Process::new_with_options(
+            r#"name(char_word+, x), '=', x"#,
+            &CompileOptions {
+                ignore_case: true,
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap();
+        let mut instance = process.new_instance("Ab=aB");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+
+        // bytes mode, including the invalid UTF-8 bytes
+        let process = Process::new(r#"name(byte_range(0x80..0xff)+, x), '=', x"#).unwrap();
+        let mut instance = process.new_instance_from_bytes(b"\xff\xfe=\xff\xfe");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+        let mut instance = process.new_instance_from_bytes(b"\xff=\xfe");
+        assert_eq!(instance.exec(0), None);
+
+        // err: the group is not defined before the back-reference
+        assert!(Process::new(r#"x, name('a', x)"#).is_err());
+    }
+
+    #[test]
+    fn test_process_back_reference_look_around() {
+        // the look-behind refers to a capture of the main line, the
+        // captured text ends at the current position.
+        let process =
+            Process::new(r#"name(char_digit+, x), '-', char_digit+, '.'.is_after(x)"#).unwrap();
+        assert_eq!(process.new_instance("12-113.").exec(0), None);
+        let mut instance = process.new_instance("12-113. 12-112.");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(8, 15)),
+                Some(MatchRange::new(8, 10))
+            ])
+        );
+
+        let process = Process::new(r#"name(char_word, x), '-'.is_not_after(x), char_any"#).unwrap();
+        let mut instance = process.new_instance("a-b-");
+        assert_eq!(instance.exec(0), None);
+
+        // the look-ahead refers to a capture of the main line
+        let process = Process::new(r#"name(char_word, x).is_before((char_word, x))"#).unwrap();
+        let mut instance = process.new_instance("abcb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        let process = Process::new(r#"name(char_word, x).is_not_before(x)"#).unwrap();
+        let mut instance = process.new_instance("aab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        // the main line refers to a capture of a positive look-ahead
+        let process = Process::new(r#"'a'.is_before(name(char_digit+, x)), x, '!'"#).unwrap();
+        let mut instance = process.new_instance("a1a12!");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(2, 6)),
+                Some(MatchRange::new(3, 5))
+            ])
+        );
+
+        // the main line refers to a capture of a positive look-behind
+        let process = Process::new(r#"'='.is_after(name(char_word+, x)), x"#).unwrap();
+        let mut instance = process.new_instance("ab=ab");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(2, 5)),
+                Some(MatchRange::new(0, 2))
+            ])
+        );
+
+        // the look-behind refers to a capture of an outer look-behind
+        let process = Process::new(r#"'c'.is_after((name('a', x), 'b'.is_after(x)))"#).unwrap();
+        let mut instance = process.new_instance("bc abc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(5, 6)));
+    }
+
+    #[test]
+    fn test_process_charset() {
+        let process = Process::new(r#"['a'..'c', '_', char_digit]+"#).unwrap();
+        let mut instance = process.new_instance("xyz a_1cd");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 8)));
+
+        let process = Process::new(r#"!['a'..'z', char_space]+"#).unwrap();
+        let mut instance = process.new_instance("abc D1_ e");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 7)));
+
+        let process = Process::new(r#"char_not_word, char_word"#).unwrap();
+        let mut instance = process.new_instance("ab-cd");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 4)));
+    }
+
+    #[test]
+    fn test_process_status() {
+        let process = Process::new(r#"start, 'a'"#).unwrap();
+        let mut instance = process.new_instance("aa");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 1)));
+        assert_eq!(instance.exec(1), None);
+
+        let process = Process::new(r#"'a', end"#).unwrap();
+        let mut instance = process.new_instance("aa");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 2)));
+
+        let process = Process::new(r#"bound, "ab", bound"#).unwrap();
+        let mut instance = process.new_instance("abc ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 6)));
+
+        let process = Process::new(r#"not_bound, 'b'"#).unwrap();
+        let mut instance = process.new_instance("b ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 4)));
+
+        // the start and end of words
+        let process = Process::new(r#"word_start, char_word+"#).unwrap();
+        let mut instance = process.new_instance("  foo.bar");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 5)));
+        assert_eq!(instance.exec(3), Some(MatchRange::new(6, 9)));
+
+        let process = Process::new(r#"char_word+?, word_end"#).unwrap();
+        let mut instance = process.new_instance("foo.bar");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+        assert_eq!(instance.exec(1), Some(MatchRange::new(1, 3)));
+
+        let process = Process::new(r#"word_end"#).unwrap();
+        let mut instance = process.new_instance(" ab ");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 3)));
+        let mut instance = process.new_instance("ab");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 2)));
+
+        let process = Process::new(r#"word_start"#).unwrap();
+        let mut instance = process.new_instance(" . ");
+        assert_eq!(instance.exec(0), None);
+    }
+
+    #[test]
+    fn test_process_compile_options() {
+        // ignore case
+        let options = CompileOptions {
+            ignore_case: true,
+            ..CompileOptions::default()
+        };
+
+        let process = Process::new_with_options(r#""abc", 'd', ['x'..'z']"#, &options).unwrap();
+        let mut instance = process.new_instance("ABcDy");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 5)));
+
+        let process = Process::new_with_options(r#"!['a'..'z']"#, &options).unwrap();
+        let mut instance = process.new_instance("aB1");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 3)));
+
+        // the case mapping beyond ASCII
+        let process = Process::new_with_options(r#"['à'..'ÿ']+, ['k']"#, &options).unwrap();
+        let mut instance = process.new_instance("xÄÉöK");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 5)));
+        let mut instance = process.new_instance("Äk\u{212a}");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+
+        // the charsets match the same chars as the chars, the multi-char
+        // case mappings are not used, e.g. the uppercase of 'ß' is "SS".
+        for (pattern, text) in [
+            ("'S'", "ß"),
+            ("['S']", "ß"),
+            ("['A'..'Z']", "ß"),
+            ("[char_word]", "ß"),
+            ("'i'", "İ"),
+            ("['i']", "İ"),
+            ("['\u{307}']", "İ"),
+            ("'I'", "ı"),
+            ("['I']", "ı"),
+        ] {
+            let process = Process::new_with_options(pattern, &options).unwrap();
+            let mut instance = process.new_instance(text);
+            assert_eq!(instance.exec(0), None, "{} matches {}", pattern, text);
+        }
+
+        // multiline
+        let options = CompileOptions {
+            multiline: true,
+            ..CompileOptions::default()
+        };
+
+        let process = Process::new_with_options(r#"start, 'b', end"#, &options).unwrap();
+        let mut instance = process.new_instance("ab\nb\nbc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 4)));
+
+        let process = Process::new(r#"start, 'b', end"#).unwrap();
+        let mut instance = process.new_instance("ab\nb\nbc");
+        assert_eq!(instance.exec(0), None);
+
+        // the line boundaries regardless of the option
+        let process = Process::new(r#"line_start, 'b', line_end"#).unwrap();
+        let mut instance = process.new_instance("ab\nb\nbc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(3, 4)));
+
+        let process = Process::new(r#"line("ERROR", char_any*)"#).unwrap();
+        let mut instance = process.new_instance("INFO ok\nERROR disk full\nERROR");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(8, 23)));
+        assert_eq!(instance.exec(23), Some(MatchRange::new(24, 29)));
+
+        let mut instance = process.new_instance("INFO ERROR\r\nWARN");
+        assert_eq!(instance.exec(0), None);
+
+        assert!(Process::new(r#"line('a', 2)"#).is_err());
+
+        // dot all
+        let options = CompileOptions {
+            dot_all: true,
+            ..CompileOptions::default()
+        };
+
+        let process = Process::new_with_options(r#"'a', char_any, 'b'"#, &options).unwrap();
+        let mut instance = process.new_instance("a\nb");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        // crlf
+        let options = CompileOptions {
+            multiline: true,
+            crlf: true,
+            ..CompileOptions::default()
+        };
+
+        let process = Process::new_with_options(r#"start, char_any+, end"#, &options).unwrap();
+        let mut instance = process.new_instance("ab\r\ncd\ref\ngh");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 2)));
+        assert_eq!(instance.exec(2), Some(MatchRange::new(4, 6)));
+        assert_eq!(instance.exec(6), Some(MatchRange::new(7, 9)));
+        assert_eq!(instance.exec(9), Some(MatchRange::new(10, 12)));
+
+        // no line boundaries between '\r' and '\n'
+        let process = Process::new_with_options(r#"end, '\n'"#, &options).unwrap();
+        let mut instance = process.new_instance("a\r\nb\nc");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 5)));
+
+        let process = Process::new_with_options(r#"start"#, &options).unwrap();
+        let mut instance = process.new_instance("a\r\nb");
+        assert_eq!(instance.exec(1), Some(MatchRange::new(3, 3)));
+
+        // without the option, '\r' is an ordinary char
+        let process = Process::new_with_options(
+            r#"start, char_any+, end"#,
+            &CompileOptions {
+                multiline: true,
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap();
+        let mut instance = process.new_instance("ab\r\ncd");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(0, 3)));
+
+        // unicode
+        let options = CompileOptions {
+            unicode: true,
+            ..CompileOptions::default()
+        };
+
+        let process = Process::new_with_options(r#"char_word+"#, &options).unwrap();
+        let mut instance = process.new_instance("- caf\u{e9}文字 -");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 8)));
+
+        let process = Process::new(r#"char_word+"#).unwrap();
+        let mut instance = process.new_instance("- caf\u{e9}文字 -");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(2, 5)));
+
+        // the Unicode white spaces, e.g. NBSP and the ideographic space
+        let text = "a\u{a0}\u{3000}\u{2028} \tb";
+        let process = Process::new_with_options(r#"char_space+"#, &options).unwrap();
+        let mut instance = process.new_instance(text);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(1, 6)));
+
+        let process = Process::new(r#"char_space+"#).unwrap();
+        let mut instance = process.new_instance(text);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 6)));
+
+        // the Unicode words with the ASCII white spaces only
+        let process = Process::new_with_options(
+            r#"char_word+, char_space_ascii+, char_word+, char_not_space_ascii"#,
+            &options,
+        )
+        .unwrap();
+        let mut instance = process.new_instance("caf\u{e9}\u{a0}文 字\u{3000}");
+        assert_eq!(instance.exec(0), Some(MatchRange::new(5, 9)));
+
+        let process = Process::new_with_options(r#"[char_space_ascii, '-']+"#, &options).unwrap();
+        let mut instance = process.new_instance(text);
+        assert_eq!(instance.exec(0), Some(MatchRange::new(4, 6)));
+
+        // max capture groups
+        let options = CompileOptions {
+            max_capture_groups: Some(1),
+            ..CompileOptions::default()
+        };
+
+        assert!(Process::new_with_options(r#"capture('a')"#, &options).is_ok());
+        assert!(Process::new_with_options(r#"capture('a'), capture('b')"#, &options).is_err());
+    }
+
+    #[test]
+    fn test_process_capture_groups() {
+        let process =
+            Process::new(r#"name('a', first), capture('b')+, 'c'.is_before(name('d', second))"#)
+                .unwrap();
+
+        assert_eq!(process.capture_group_count(), 3);
+        assert_eq!(
+            process.capture_groups(),
+            &[
+                CaptureGroup {
+                    index: 0,
+                    name: None,
+                    in_repetition: false,
+                    in_look_around: false
+                },
+                CaptureGroup {
+                    index: 1,
+                    name: Some("first".to_owned()),
+                    in_repetition: false,
+                    in_look_around: false
+                },
+                CaptureGroup {
+                    index: 2,
+                    name: None,
+                    in_repetition: true,
+                    in_look_around: false
+                },
+                CaptureGroup {
+                    index: 3,
+                    name: Some("second".to_owned()),
+                    in_repetition: false,
+                    in_look_around: true
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_shared_capture_name() {
+        let process = Process::new(r#"(name("ab", x), '!') || (name('c', x), '?')"#).unwrap();
+        assert_eq!(process.capture_group_count(), 1);
+
+        let mut instance = process.new_instance("c? ab!");
+        assert_eq!(
+            instance.exec_with_captures(0),
+            Some(vec![
+                Some(MatchRange::new(0, 2)),
+                Some(MatchRange::new(0, 1))
+            ])
+        );
+        assert_eq!(
+            instance.exec_with_captures(2),
+            Some(vec![
+                Some(MatchRange::new(3, 6)),
+                Some(MatchRange::new(3, 5))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_process_is_subset_and_equivalent() {
+        let a = Process::new(r#"name(char_digit{4}, year), '-', char_digit{2}"#).unwrap();
+        let b = Process::new(r#"char_digit+, '-', char_digit+"#).unwrap();
+        assert!(a.is_subset_of(&b).unwrap());
+        assert!(!b.is_subset_of(&a).unwrap());
+        assert!(!a.is_equivalent_to(&b).unwrap());
+
+        let c = Process::new(r#"['0'..'9']{4}, "-", ['0'..'9']{2}"#).unwrap();
+        assert!(a.is_equivalent_to(&c).unwrap());
+
+        // err: the assertions are not supported
+        let d = Process::new(r#"start, 'a'"#).unwrap();
+        assert!(d.is_subset_of(&a).is_err());
+    }
+}
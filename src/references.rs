@@ -0,0 +1,324 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// lists the back-references of a program and checks whether they refer
+// to the capture groups defined before them, e.g. in the pattern
+//
+// ```anreg
+// name(char_word+, key), '=', key, value
+// ```
+//
+// `key` refers to the group "key", and `value` refers to no group.
+//
+// the groups are defined in the order of the function form of the
+// pattern, which is the order the compiler assigns the capture indices,
+// e.g. `name('a', x).is_after(x)` is valid, and `x, name('a', x)` is a
+// forward reference.
+//
+// the position of a back-reference is the indices of the expressions
+// from the top level, the same as the position of `Difference`, see the
+// module `diff`.
+
+use std::fmt::Display;
+
+use crate::{
+    ast::{Expression, FunctionCallArg, FunctionName, NodeLocation, Program},
+    error::Error,
+    location::Location,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackReference {
+    pub name: String,
+    pub position: Vec<usize>,
+    pub status: BackReferenceStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackReferenceStatus {
+    Resolved,
+    Forward, // the group is defined after the back-reference
+    Unknown, // there is no group with the name
+}
+
+impl Display for BackReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indices: Vec<String> = self.position.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}: {}", indices.join("."), self.name)?;
+        match self.status {
+            BackReferenceStatus::Resolved => Ok(()),
+            BackReferenceStatus::Forward => f.write_str(" (forward)"),
+            BackReferenceStatus::Unknown => f.write_str(" (unknown)"),
+        }
+    }
+}
+
+// the back-references in the order of the function form of the pattern,
+// along with their locations if the locations of the expressions are
+// given, i.e. `SourceMap::expressions`.
+pub fn find_back_references(
+    program: &Program,
+    locations: Option<&[NodeLocation]>,
+) -> Vec<(BackReference, Option<Location>)> {
+    let mut defined_names: Vec<&str> = vec![];
+
+    // the back-references and whether their groups are defined before
+    let mut references: Vec<(BackReference, Option<Location>, bool)> = vec![];
+
+    // walk without recursion, so the program that is constructed
+    // directly (and not limited by the parser) can be very deep.
+    let mut pending: Vec<Pending> = program
+        .expressions
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(idx, expression)| {
+            let node = locations.and_then(|nodes| nodes.get(idx));
+            Pending::Expression(expression, node, vec![idx])
+        })
+        .collect();
+
+    while let Some(item) = pending.pop() {
+        let (expression, node, position) = match item {
+            Pending::Expression(expression, node, position) => (expression, node, position),
+            Pending::Reference(name, position, location) => {
+                let defined = defined_names.contains(&name);
+                let reference = BackReference {
+                    name: name.to_owned(),
+                    position,
+                    status: BackReferenceStatus::Resolved,
+                };
+                references.push((reference, location, defined));
+                continue;
+            }
+        };
+
+        let mut children = vec![];
+        match expression {
+            Expression::Identifier(name) => {
+                children.push(Pending::Reference(name, position, node.map(|n| n.location)));
+            }
+            Expression::Group(expressions) => {
+                for (idx, e) in expressions.iter().enumerate() {
+                    let child_node = node.and_then(|n| n.children.get(idx));
+                    children.push(Pending::Expression(
+                        e,
+                        child_node,
+                        child_position(&position, idx),
+                    ));
+                }
+            }
+            Expression::OrList(alternatives) => {
+                // the alternatives are indexed as a list, see `Difference`
+                for (idx, e) in alternatives.iter().enumerate() {
+                    let child_node = node.and_then(|n| n.children.get(idx));
+                    children.push(Pending::Expression(
+                        e,
+                        child_node,
+                        child_position(&position, idx),
+                    ));
+                }
+            }
+            Expression::FunctionCall(function_call) => {
+                // the name is defined before the expression is compiled,
+                // e.g. `name('a', x).is_after(x)`.
+                if function_call.name == FunctionName::Name {
+                    if let Some(FunctionCallArg::Identifier(name)) = function_call.args.first() {
+                        defined_names.push(name);
+                    }
+                }
+
+                // the children of the node are the expression and the
+                // arguments that are expressions, the identifier arguments
+                // take the location of the function call.
+                let mut child_nodes = node.map(|n| n.children.iter());
+                let mut next_child_node = || child_nodes.as_mut().and_then(|c| c.next());
+
+                children.push(Pending::Expression(
+                    &function_call.expression,
+                    next_child_node(),
+                    child_position(&position, 0),
+                ));
+
+                for (idx, arg) in function_call.args.iter().enumerate() {
+                    let arg_position = child_position(&position, idx + 1);
+                    match arg {
+                        FunctionCallArg::Expression(e) => {
+                            children.push(Pending::Expression(e, next_child_node(), arg_position))
+                        }
+                        FunctionCallArg::Identifier(name)
+                            if function_call.name != FunctionName::Name =>
+                        {
+                            children.push(Pending::Reference(
+                                name,
+                                arg_position,
+                                node.map(|n| n.location),
+                            ))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Expression::Literal(_) => {}
+        }
+
+        pending.extend(children.into_iter().rev());
+    }
+
+    references
+        .into_iter()
+        .map(|(mut reference, location, defined)| {
+            reference.status = if defined {
+                BackReferenceStatus::Resolved
+            } else if defined_names.contains(&reference.name.as_str()) {
+                BackReferenceStatus::Forward
+            } else {
+                BackReferenceStatus::Unknown
+            };
+            (reference, location)
+        })
+        .collect()
+}
+
+// check that all back-references refer to the groups defined before
+// them, the error lists all invalid ones, and it takes the location of
+// the first one if the locations are given.
+pub fn check_back_references(
+    program: &Program,
+    locations: Option<&[NodeLocation]>,
+) -> Result<(), Error> {
+    let mut lines = vec![];
+    let mut first_location = None;
+
+    for (reference, location) in find_back_references(program, locations) {
+        let problem = match reference.status {
+            BackReferenceStatus::Resolved => continue,
+            BackReferenceStatus::Forward => "refers to the capture group defined after it",
+            BackReferenceStatus::Unknown => "does not refer to any capture group",
+        };
+
+        let at = match location {
+            Some(location) => {
+                first_location.get_or_insert(location);
+                format!(
+                    " at line {}, column {}",
+                    location.line + 1,
+                    location.column + 1
+                )
+            }
+            None => String::new(),
+        };
+
+        lines.push(format!(
+            "The back-reference \"{}\"{} {}.",
+            reference.name, at, problem
+        ));
+    }
+
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let message = lines.join("\n");
+    match first_location {
+        Some(location) => Err(Error::MessageWithLocation(message, location)),
+        None => Err(Error::Message(message)),
+    }
+}
+
+enum Pending<'a> {
+    Expression(&'a Expression, Option<&'a NodeLocation>, Vec<usize>),
+    Reference(&'a str, Vec<usize>, Option<Location>),
+}
+
+fn child_position(position: &[usize], idx: usize) -> Vec<usize> {
+    let mut child = position.to_vec();
+    child.push(idx);
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        ast::Expression, compiler::compile_from_str, error::Error, location::Location,
+        parser::parse_from_str,
+    };
+
+    use super::BackReferenceStatus;
+
+    fn references(s: &str) -> Vec<String> {
+        parse_from_str(s)
+            .unwrap()
+            .back_references()
+            .iter()
+            .map(|reference| reference.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_back_references() {
+        assert_eq!(
+            references(r#"name(char_word+, key), '=', key, value"#),
+            vec!["2: key", "3: value (unknown)"]
+        );
+
+        // the alternatives and the arguments of the function calls
+        assert_eq!(
+            references(r#"x, ('a' || name('b', x) || x), 'c'.is_after(x)"#),
+            vec!["0: x (forward)", "1.0.2: x", "2.1: x"]
+        );
+
+        // the expression comes before the look-around
+        assert_eq!(
+            references(r#"name('a', x).is_after(x), line(y, name('b', y))"#),
+            vec!["0.1: x", "1.0: y (forward)"]
+        );
+
+        let program = parse_from_str(r#"name('a', x), x"#).unwrap();
+        assert_eq!(
+            program.back_references()[0].status,
+            BackReferenceStatus::Resolved
+        );
+        assert!(program.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_back_references() {
+        let program = parse_from_str(r#"y, name('a', x), (x || z)"#).unwrap();
+        assert_eq!(
+            program.validate(),
+            Err(Error::Message(
+                "The back-reference \"y\" does not refer to any capture group.\n\
+                 The back-reference \"z\" does not refer to any capture group."
+                    .to_owned()
+            ))
+        );
+
+        // the compiler reports the locations
+        assert_eq!(
+            compile_from_str("'a', x,\nname('b', x), y").err(),
+            Some(Error::MessageWithLocation(
+                "The back-reference \"x\" at line 1, column 6 refers to the capture group \
+                 defined after it.\n\
+                 The back-reference \"y\" at line 2, column 15 does not refer to any \
+                 capture group."
+                    .to_owned(),
+                Location::new_range(0, 5, 0, 5, 1)
+            ))
+        );
+
+        // the deep program that is constructed directly
+        let mut program = parse_from_str("x").unwrap();
+        let mut expression = program.expressions.pop().unwrap();
+        for _ in 0..10_000 {
+            expression = Expression::Group(vec![expression]);
+        }
+        program.expressions.push(expression);
+        assert!(program.validate().is_err());
+    }
+}
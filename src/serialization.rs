@@ -0,0 +1,722 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the compiled states can be serialized into bytes and loaded back,
+// so the patterns can be compiled at build time (e.g. in `build.rs`)
+// and the startup does not pay the cost of compilation.
+//
+// the format:
+//
+// - magic "ANRG" and the format version (1 byte)
+// - the start and end state indices
+// - the capture groups: count, then (name?, in_repetition, in_look_around)
+// - the tags: count, then names
+// - the states: count, then for each state the transitions in
+//   the order of trying: count, then (target state index, transition)
+//
+// the integers (including chars) are encoded as unsigned LEB128,
+// and the strings are encoded as length and UTF-8 bytes.
+
+use crate::{
+    compiler::compile,
+    error::Error,
+    options::CompileOptions,
+    parser::parse_from_str,
+    state::{CaptureGroup, StateSet},
+    transition::{
+        BackReferenceTransition, ByteRangeTransition, ByteTransition, CaptureEndTransition,
+        CaptureStartTransition, CharSetItem, CharSetTransition, CharTransition,
+        CounterCheckTransition, CounterExitTransition, CounterIncTransition,
+        CounterResetTransition, JumpTransition, LookAroundTransition, MatchStartResetTransition,
+        NormalizedStringTransition, PresetCharSet, SpecialCharTransition, Status, StatusTransition,
+        StringTransition, TagTransition, Transition,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"ANRG";
+const FORMAT_VERSION: u8 = 6;
+
+pub fn serialize(state_set: &StateSet) -> Vec<u8> {
+    let mut writer = Writer { bytes: vec![] };
+    writer.bytes.extend_from_slice(MAGIC);
+    writer.bytes.push(FORMAT_VERSION);
+
+    writer.write_usize(state_set.start_node_index);
+    writer.write_usize(state_set.end_node_index);
+
+    writer.write_usize(state_set.capture_groups.len());
+    for group in &state_set.capture_groups {
+        match &group.name {
+            Some(name) => {
+                writer.write_bool(true);
+                writer.write_str(name);
+            }
+            None => writer.write_bool(false),
+        }
+        writer.write_bool(group.in_repetition);
+        writer.write_bool(group.in_look_around);
+    }
+
+    writer.write_usize(state_set.tags.len());
+    for tag in &state_set.tags {
+        writer.write_str(tag);
+    }
+
+    writer.write_usize(state_set.state_count());
+    for state_index in 0..state_set.state_count() {
+        let mut links = vec![];
+        let mut next_link_index = state_set.get_first_link_index(state_index);
+        while let Some(link_index) = next_link_index {
+            let (transition, target_state_index, next) = state_set.get_link(link_index);
+            links.push((transition, target_state_index));
+            next_link_index = next;
+        }
+
+        writer.write_usize(links.len());
+        for (transition, target_state_index) in links {
+            writer.write_usize(target_state_index);
+            writer.write_transition(transition);
+        }
+    }
+
+    writer.bytes
+}
+
+pub fn deserialize(bytes: &[u8]) -> Result<StateSet, Error> {
+    let mut reader = Reader { bytes, position: 0 };
+
+    if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(Error::Message(
+            "The data is not a serialized pattern.".to_owned(),
+        ));
+    }
+
+    let version = reader.read_u8()?;
+    if version != FORMAT_VERSION {
+        return Err(Error::Message(format!(
+            "Unsupported serialized pattern version: {}.",
+            version
+        )));
+    }
+
+    let mut state_set = StateSet::new();
+    state_set.start_node_index = reader.read_usize()?;
+    state_set.end_node_index = reader.read_usize()?;
+
+    // each group takes at least three bytes
+    let group_count = reader.read_usize()?;
+    if group_count > bytes.len() {
+        return Err(reader.invalid());
+    }
+
+    state_set.capture_groups.clear();
+    for index in 0..group_count {
+        let name = if reader.read_bool()? {
+            Some(reader.read_string()?)
+        } else {
+            None
+        };
+        state_set.capture_groups.push(CaptureGroup {
+            index,
+            name,
+            in_repetition: reader.read_bool()?,
+            in_look_around: reader.read_bool()?,
+        });
+    }
+
+    if state_set.capture_groups.is_empty() {
+        return Err(reader.invalid());
+    }
+
+    // each tag takes at least one byte
+    let tag_count = reader.read_usize()?;
+    if tag_count > bytes.len() {
+        return Err(reader.invalid());
+    }
+
+    for _ in 0..tag_count {
+        state_set.tags.push(reader.read_string()?);
+    }
+
+    // each state takes at least one byte
+    let state_count = reader.read_usize()?;
+    if state_count > bytes.len() {
+        return Err(reader.invalid());
+    }
+
+    for _ in 0..state_count {
+        state_set.new_state();
+    }
+
+    let check_state_index = |idx: usize, reader: &Reader| {
+        if idx < state_count {
+            Ok(idx)
+        } else {
+            Err(reader.invalid())
+        }
+    };
+
+    check_state_index(state_set.start_node_index, &reader)?;
+    check_state_index(state_set.end_node_index, &reader)?;
+
+    for state_index in 0..state_count {
+        let link_count = reader.read_usize()?;
+        for _ in 0..link_count {
+            let target_state_index = check_state_index(reader.read_usize()?, &reader)?;
+            let transition = reader.read_transition()?;
+
+            // the indices in the transitions must be valid, otherwise
+            // the process panics when matching.
+            let valid = match &transition {
+                Transition::CaptureStart(CaptureStartTransition { capture_index, .. })
+                | Transition::CaptureEnd(CaptureEndTransition { capture_index, .. })
+                | Transition::BackReference(BackReferenceTransition { capture_index, .. }) => {
+                    *capture_index < group_count
+                }
+                Transition::LookAround(LookAroundTransition {
+                    start_state_index,
+                    end_state_index,
+                    ..
+                }) => *start_state_index < state_count && *end_state_index < state_count,
+                Transition::Tag(TagTransition { tag_index }) => *tag_index < tag_count,
+                _ => true,
+            };
+            if !valid {
+                return Err(reader.invalid());
+            }
+
+            state_set.append_transition(state_index, target_state_index, transition);
+        }
+    }
+
+    if reader.position != bytes.len() {
+        return Err(reader.invalid());
+    }
+
+    state_set.update_first_chars();
+    state_set.update_length_bounds();
+    Ok(state_set)
+}
+
+// compile the pattern and generate the Rust source of a static
+// byte array, it is intended to be used in `build.rs`, e.g.
+//
+// ```rust
+// // build.rs
+// let source = anreg::generate_rust_source("EMAIL", pattern, &CompileOptions::default())?;
+// std::fs::write(out_dir.join("email.rs"), source)?;
+//
+// // main.rs
+// include!(concat!(env!("OUT_DIR"), "/email.rs"));
+// let process = Process::from_bytes(EMAIL)?;
+// ```
+pub fn generate_rust_source(
+    name: &str,
+    pattern: &str,
+    options: &CompileOptions,
+) -> Result<String, Error> {
+    let program = parse_from_str(pattern)?;
+    let state_set = compile(&program, options)?;
+    let bytes = serialize(&state_set);
+
+    let mut lines = vec![format!("pub static {}: &[u8] = &[", name)];
+    for chunk in bytes.chunks(16) {
+        let items: Vec<String> = chunk.iter().map(|b| format!("0x{:02x},", b)).collect();
+        lines.push(format!("    {}", items.join(" ")));
+    }
+    lines.push("];".to_owned());
+
+    Ok(lines.join("\n") + "\n")
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn write_usize(&mut self, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                break;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    fn write_char(&mut self, c: char) {
+        self.write_usize(c as usize);
+    }
+
+    fn write_chars(&mut self, chars: &[char]) {
+        self.write_usize(chars.len());
+        for c in chars {
+            self.write_char(*c);
+        }
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_usize(s.len());
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn write_transition(&mut self, transition: &Transition) {
+        match transition {
+            Transition::Jump(_) => self.bytes.push(0),
+            Transition::Char(CharTransition {
+                character,
+                ignore_case,
+            }) => {
+                self.bytes.push(1);
+                self.write_char(*character);
+                self.write_bool(*ignore_case);
+            }
+            Transition::SpecialChar(SpecialCharTransition {
+                include_new_line,
+                crlf,
+                grapheme,
+            }) => {
+                self.bytes.push(2);
+                self.write_bool(*include_new_line);
+                self.write_bool(*crlf);
+                self.write_bool(*grapheme);
+            }
+            Transition::String(StringTransition { chars, ignore_case }) => {
+                self.bytes.push(3);
+                self.write_chars(chars);
+                self.write_bool(*ignore_case);
+            }
+            Transition::CharSet(CharSetTransition {
+                items,
+                negative,
+                ignore_case,
+                unicode,
+                grapheme,
+                ..
+            }) => {
+                self.bytes.push(4);
+                self.write_usize(items.len());
+                for item in items {
+                    match item {
+                        CharSetItem::Char(c) => {
+                            self.bytes.push(0);
+                            self.write_char(*c);
+                        }
+                        CharSetItem::Range(start, end_included) => {
+                            self.bytes.push(1);
+                            self.write_char(*start);
+                            self.write_char(*end_included);
+                        }
+                        CharSetItem::Preset(preset, negative) => {
+                            self.bytes.push(2);
+                            self.bytes.push(match preset {
+                                PresetCharSet::Space => 0,
+                                PresetCharSet::Word => 1,
+                                PresetCharSet::Digit => 2,
+                                PresetCharSet::AsciiSpace => 3,
+                            });
+                            self.write_bool(*negative);
+                        }
+                    }
+                }
+                self.write_bool(*negative);
+                self.write_bool(*ignore_case);
+                self.write_bool(*unicode);
+                self.write_bool(*grapheme);
+            }
+            Transition::Status(StatusTransition {
+                status,
+                multiline,
+                crlf,
+                unicode,
+            }) => {
+                self.bytes.push(5);
+                self.bytes.push(match status {
+                    Status::Start => 0,
+                    Status::End => 1,
+                    Status::Bound => 2,
+                    Status::NotBound => 3,
+                    Status::ContinueFromLast => 4,
+                    Status::WordStart => 5,
+                    Status::WordEnd => 6,
+                });
+                self.write_bool(*multiline);
+                self.write_bool(*crlf);
+                self.write_bool(*unicode);
+            }
+            Transition::NormalizedString(NormalizedStringTransition { chars, ignore_case }) => {
+                self.bytes.push(6);
+                self.write_chars(chars);
+                self.write_bool(*ignore_case);
+            }
+            Transition::Byte(ByteTransition { byte }) => {
+                self.bytes.push(7);
+                self.bytes.push(*byte);
+            }
+            Transition::ByteRange(ByteRangeTransition {
+                start,
+                end_included,
+            }) => {
+                self.bytes.push(8);
+                self.bytes.push(*start);
+                self.bytes.push(*end_included);
+            }
+            Transition::CounterReset(_) => self.bytes.push(9),
+            Transition::CounterCheck(CounterCheckTransition { max }) => {
+                self.bytes.push(10);
+                match max {
+                    Some(max) => {
+                        self.write_bool(true);
+                        self.write_usize(*max);
+                    }
+                    None => self.write_bool(false),
+                }
+            }
+            Transition::CounterInc(CounterIncTransition { min }) => {
+                self.bytes.push(11);
+                self.write_usize(*min);
+            }
+            Transition::CounterExit(CounterExitTransition { min }) => {
+                self.bytes.push(12);
+                self.write_usize(*min);
+            }
+            Transition::CaptureStart(CaptureStartTransition {
+                capture_index,
+                keep_first,
+            }) => {
+                self.bytes.push(13);
+                self.write_usize(*capture_index);
+                self.write_bool(*keep_first);
+            }
+            Transition::CaptureEnd(CaptureEndTransition {
+                capture_index,
+                keep_first,
+            }) => {
+                self.bytes.push(14);
+                self.write_usize(*capture_index);
+                self.write_bool(*keep_first);
+            }
+            Transition::LookAround(LookAroundTransition {
+                start_state_index,
+                end_state_index,
+                is_behind,
+                negative,
+            }) => {
+                self.bytes.push(15);
+                self.write_usize(*start_state_index);
+                self.write_usize(*end_state_index);
+                self.write_bool(*is_behind);
+                self.write_bool(*negative);
+            }
+            Transition::BackReference(BackReferenceTransition {
+                capture_index,
+                ignore_case,
+            }) => {
+                self.bytes.push(16);
+                self.write_usize(*capture_index);
+                self.write_bool(*ignore_case);
+            }
+            Transition::MatchStartReset(_) => self.bytes.push(17),
+            Transition::Tag(TagTransition { tag_index }) => {
+                self.bytes.push(18);
+                self.write_usize(*tag_index);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn invalid(&self) -> Error {
+        Error::Message(format!(
+            "Invalid serialized pattern at byte {}.",
+            self.position
+        ))
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        match self.bytes.get(self.position) {
+            Some(byte) => {
+                self.position += 1;
+                Ok(*byte)
+            }
+            None => Err(self.invalid()),
+        }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&[u8], Error> {
+        let end = self
+            .position
+            .checked_add(length)
+            .ok_or_else(|| self.invalid())?;
+        match self.bytes.get(self.position..end) {
+            Some(bytes) => {
+                self.position = end;
+                Ok(bytes)
+            }
+            None => Err(self.invalid()),
+        }
+    }
+
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        let mut value: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            if shift >= usize::BITS {
+                return Err(self.invalid());
+            }
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_bool(&mut self) -> Result<bool, Error> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(self.invalid()),
+        }
+    }
+
+    fn read_char(&mut self) -> Result<char, Error> {
+        let value = self.read_usize()?;
+        u32::try_from(value)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.invalid())
+    }
+
+    fn read_chars(&mut self) -> Result<Vec<char>, Error> {
+        let length = self.read_usize()?;
+        let mut chars = vec![];
+        for _ in 0..length {
+            chars.push(self.read_char()?);
+        }
+        Ok(chars)
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let length = self.read_usize()?;
+        let bytes = self.read_bytes(length)?.to_vec();
+        String::from_utf8(bytes).map_err(|_| self.invalid())
+    }
+
+    fn read_transition(&mut self) -> Result<Transition, Error> {
+        let transition = match self.read_u8()? {
+            0 => Transition::Jump(JumpTransition),
+            1 => Transition::Char(CharTransition {
+                character: self.read_char()?,
+                ignore_case: self.read_bool()?,
+            }),
+            2 => Transition::SpecialChar(SpecialCharTransition::new(
+                self.read_bool()?,
+                self.read_bool()?,
+                self.read_bool()?,
+            )),
+            3 => Transition::String(StringTransition {
+                chars: self.read_chars()?,
+                ignore_case: self.read_bool()?,
+            }),
+            4 => {
+                let count = self.read_usize()?;
+                let mut items = vec![];
+                for _ in 0..count {
+                    let item = match self.read_u8()? {
+                        0 => CharSetItem::Char(self.read_char()?),
+                        1 => CharSetItem::Range(self.read_char()?, self.read_char()?),
+                        2 => {
+                            let preset = match self.read_u8()? {
+                                0 => PresetCharSet::Space,
+                                1 => PresetCharSet::Word,
+                                2 => PresetCharSet::Digit,
+                                3 => PresetCharSet::AsciiSpace,
+                                _ => return Err(self.invalid()),
+                            };
+                            CharSetItem::Preset(preset, self.read_bool()?)
+                        }
+                        _ => return Err(self.invalid()),
+                    };
+                    items.push(item);
+                }
+                Transition::CharSet(CharSetTransition::new(
+                    items,
+                    self.read_bool()?,
+                    self.read_bool()?,
+                    self.read_bool()?,
+                    self.read_bool()?,
+                ))
+            }
+            5 => {
+                let status = match self.read_u8()? {
+                    0 => Status::Start,
+                    1 => Status::End,
+                    2 => Status::Bound,
+                    3 => Status::NotBound,
+                    4 => Status::ContinueFromLast,
+                    5 => Status::WordStart,
+                    6 => Status::WordEnd,
+                    _ => return Err(self.invalid()),
+                };
+                Transition::Status(StatusTransition::new(
+                    status,
+                    self.read_bool()?,
+                    self.read_bool()?,
+                    self.read_bool()?,
+                ))
+            }
+            6 => Transition::NormalizedString(NormalizedStringTransition {
+                chars: self.read_chars()?,
+                ignore_case: self.read_bool()?,
+            }),
+            7 => Transition::Byte(ByteTransition::new(self.read_u8()?)),
+            8 => Transition::ByteRange(ByteRangeTransition::new(self.read_u8()?, self.read_u8()?)),
+            9 => Transition::CounterReset(CounterResetTransition),
+            10 => {
+                let max = if self.read_bool()? {
+                    Some(self.read_usize()?)
+                } else {
+                    None
+                };
+                Transition::CounterCheck(CounterCheckTransition::new(max))
+            }
+            11 => Transition::CounterInc(CounterIncTransition::new(self.read_usize()?)),
+            12 => Transition::CounterExit(CounterExitTransition::new(self.read_usize()?)),
+            13 => Transition::CaptureStart(CaptureStartTransition::new(
+                self.read_usize()?,
+                self.read_bool()?,
+            )),
+            14 => Transition::CaptureEnd(CaptureEndTransition::new(
+                self.read_usize()?,
+                self.read_bool()?,
+            )),
+            15 => Transition::LookAround(LookAroundTransition::new(
+                self.read_usize()?,
+                self.read_usize()?,
+                self.read_bool()?,
+                self.read_bool()?,
+            )),
+            16 => Transition::BackReference(BackReferenceTransition::new(
+                self.read_usize()?,
+                self.read_bool()?,
+            )),
+            17 => Transition::MatchStartReset(MatchStartResetTransition),
+            18 => Transition::Tag(TagTransition {
+                tag_index: self.read_usize()?,
+            }),
+            _ => return Err(self.invalid()),
+        };
+
+        Ok(transition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::{assert_eq, assert_str_eq};
+
+    use crate::{compiler::compile_from_str, options::CompileOptions};
+
+    use super::{deserialize, generate_rust_source, serialize};
+
+    #[test]
+    fn test_serialize_and_deserialize() {
+        for pattern in [
+            r#"'a', "bc", char_any, char_any_including_newline"#,
+            r#"['a'..'f', '_', char_not_space], !['x'], start, end, bound, not_bound, word_start, word_end"#,
+            r#"normalized("caf\u{e9}"), byte(0x7f), byte_range(0x80..0xff)"#,
+            r#"name(char_word+?, x), capture('a'{2,3}), 'b'*, 'c'.is_after('d'+)"#,
+            r#"('a' || 'b')?, 'x'.is_not_before('y'), continue_from_last"#,
+            r#"name(char_word+, x), '=', x, 'y'.is_after(x)"#,
+            r#"options(grapheme), grapheme_any, char_any, !['a']"#,
+            r#"'a', match_start_reset, 'b'"#,
+            r#"options(unicode), char_space_ascii, [char_not_space_ascii, char_word]"#,
+            r#"tag("GET", "read") || tag("HEAD", "read") || tag("POST", "write")"#,
+        ] {
+            let state_set = compile_from_str(pattern).unwrap();
+            let bytes = serialize(&state_set);
+            let loaded = deserialize(&bytes).unwrap();
+
+            assert_str_eq!(
+                loaded.generate_states_and_transitions_text(),
+                state_set.generate_states_and_transitions_text()
+            );
+            assert_eq!(loaded.capture_groups, state_set.capture_groups);
+            assert_eq!(loaded.tags, state_set.tags);
+            assert_eq!(serialize(&loaded), bytes);
+        }
+
+        let bytes = serialize(&compile_from_str(r#"name('a', x)"#).unwrap());
+
+        // err: not serialized data
+        assert!(deserialize(b"abcdefg").is_err());
+
+        // err: truncated
+        assert!(deserialize(&bytes[..bytes.len() - 1]).is_err());
+
+        // err: trailing bytes
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(deserialize(&longer).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_cyclic_look_around() {
+        let state_set = compile_from_str(r#"'a'.is_before('b')"#).unwrap();
+        let route = state_set.look_around_routes()[0].clone();
+        let mut bytes = serialize(&state_set);
+
+        // the route of the look-around starts from the state that
+        // holds the look-around, i.e. the route contains itself.
+        let transition = [
+            15,
+            route.start_state_index as u8,
+            route.end_state_index as u8,
+        ];
+        let offset = bytes
+            .windows(transition.len())
+            .position(|w| w == transition)
+            .unwrap();
+        bytes[offset + 1] = route.state_index as u8;
+
+        // the look-around is found on the main route and on its own
+        // route, and the route is walked once.
+        let loaded = deserialize(&bytes).unwrap();
+        let routes = loaded.look_around_routes();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].start_state_index, route.state_index);
+        assert_eq!(routes[1].parent, Some(0));
+    }
+
+    #[test]
+    fn test_generate_rust_source() {
+        let source = generate_rust_source("DIGIT", "'1'", &CompileOptions::default()).unwrap();
+        assert_str_eq!(
+            source,
+            "\
+pub static DIGIT: &[u8] = &[
+    0x41, 0x4e, 0x52, 0x47, 0x06, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01, 0x01, 0x01,
+    0x31, 0x00, 0x00,
+];
+"
+        );
+
+        assert!(generate_rust_source("X", "'1", &CompileOptions::default()).is_err());
+    }
+}
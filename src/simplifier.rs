@@ -0,0 +1,322 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the simplification of the AST, see `Program::simplify`.
+//
+// the rewritings keep the texts matched by the program, the order of
+// the alternatives and the indices of the capture groups:
+//
+// - the nested groups are flattened, e.g. `('a', ('b', 'c'))` becomes
+//   `('a', 'b', 'c')`, and a group with one expression is unwrapped.
+// - the adjacent chars and strings are merged, e.g. `'a', "bc"` becomes
+//   `"abc"`.
+// - the repetitions are canonicalized, e.g. `repeat_range(x, 1, 1)`
+//   becomes `x`, `repeat_range(x, 2, 2)` becomes `repeat(x, 2)` and
+//   `at_least(x, 1)` becomes `one_or_more(x)`.
+// - the nested optionals are folded, e.g. `optional(optional(x))`
+//   becomes `optional(x)`.
+
+use crate::ast::{Expression, FunctionCall, FunctionCallArg, FunctionName, Literal};
+
+// simplify a series of expressions, e.g. the expressions of
+// a program or a group.
+pub fn simplify_expressions(expressions: Vec<Expression>) -> Vec<Expression> {
+    let mut simplified: Vec<Expression> = vec![];
+
+    for expression in expressions {
+        match simplify_expression(expression) {
+            // the expressions of the nested group are spliced into
+            // the series, an empty group is dropped.
+            Expression::Group(children) => {
+                for child in children {
+                    push_expression(&mut simplified, child);
+                }
+            }
+            expression => push_expression(&mut simplified, expression),
+        }
+    }
+
+    simplified
+}
+
+// append the expression to the series, the adjacent chars and strings
+// are merged into one string.
+fn push_expression(expressions: &mut Vec<Expression>, expression: Expression) {
+    let merged = match (expressions.last(), &expression) {
+        (Some(Expression::Literal(last)), Expression::Literal(current)) => {
+            match (get_literal_string(last), get_literal_string(current)) {
+                (Some(a), Some(b)) => Some(a + &b),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    match merged {
+        Some(s) => {
+            *expressions.last_mut().unwrap() = Expression::Literal(Literal::String(s));
+        }
+        None => expressions.push(expression),
+    }
+}
+
+fn get_literal_string(literal: &Literal) -> Option<String> {
+    match literal {
+        Literal::Char(c) => Some(c.to_string()),
+        Literal::String(s) => Some(s.to_owned()),
+        _ => None,
+    }
+}
+
+fn simplify_expression(expression: Expression) -> Expression {
+    match expression {
+        Expression::Group(expressions) => {
+            let mut expressions = simplify_expressions(expressions);
+            if expressions.len() == 1 {
+                expressions.pop().unwrap()
+            } else {
+                Expression::Group(expressions)
+            }
+        }
+        Expression::OrList(alternatives) => {
+            // the nested alternations are merged, e.g.
+            // `('a' || 'b') || 'c'` becomes `'a' || 'b' || 'c'`.
+            let mut simplified = vec![];
+            for e in alternatives {
+                match simplify_expression(e) {
+                    Expression::OrList(items) => simplified.extend(items),
+                    e => simplified.push(e),
+                }
+            }
+            Expression::OrList(simplified)
+        }
+        Expression::FunctionCall(function_call) => simplify_function_call(*function_call),
+        Expression::Literal(_) | Expression::Identifier(_) => expression,
+    }
+}
+
+fn simplify_function_call(function_call: FunctionCall) -> Expression {
+    let FunctionCall {
+        name,
+        expression,
+        args,
+    } = function_call;
+
+    let expression = simplify_expression(*expression);
+    let args: Vec<FunctionCallArg> = args
+        .into_iter()
+        .map(|arg| match arg {
+            FunctionCallArg::Expression(e) => {
+                FunctionCallArg::Expression(Box::new(simplify_expression(*e)))
+            }
+            _ => arg,
+        })
+        .collect();
+
+    let numbers: Vec<u32> = args
+        .iter()
+        .filter_map(|arg| match arg {
+            FunctionCallArg::Number(n) => Some(*n),
+            _ => None,
+        })
+        .collect();
+
+    // the canonical name of the repetition, `None` if the expression
+    // is repeated exactly once.
+    let canonical = match (name, numbers.as_slice()) {
+        (FunctionName::Repeat | FunctionName::RepeatLazy, [1])
+        | (FunctionName::RepeatRange | FunctionName::RepeatRangeLazy, [1, 1]) => None,
+        (FunctionName::RepeatRange | FunctionName::RepeatRangeLazy, [m, n]) if m == n => {
+            Some((FunctionName::Repeat, vec![FunctionCallArg::Number(*m)]))
+        }
+        (FunctionName::RepeatLazy, _) => Some((FunctionName::Repeat, args)),
+        (FunctionName::RepeatRange, [0, 1]) => Some((FunctionName::Optional, vec![])),
+        (FunctionName::RepeatRangeLazy, [0, 1]) => Some((FunctionName::OptionalLazy, vec![])),
+        (FunctionName::AtLeast, [0]) => Some((FunctionName::ZeroOrMore, vec![])),
+        (FunctionName::AtLeast, [1]) => Some((FunctionName::OneOrMore, vec![])),
+        (FunctionName::AtLeastLazy, [0]) => Some((FunctionName::ZeroOrMoreLazy, vec![])),
+        (FunctionName::AtLeastLazy, [1]) => Some((FunctionName::OneOrMoreLazy, vec![])),
+        _ => Some((name, args)),
+    };
+
+    let Some((name, args)) = canonical else {
+        return expression;
+    };
+
+    // `optional(optional(x))` is `optional(x)`, and so is the lazy one.
+    match (name, expression) {
+        (FunctionName::Optional | FunctionName::OptionalLazy, Expression::FunctionCall(inner))
+            if inner.name == name =>
+        {
+            Expression::FunctionCall(inner)
+        }
+        (name, expression) => Expression::FunctionCall(Box::new(FunctionCall {
+            name,
+            expression: Box::new(expression),
+            args,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        options::CompileOptions,
+        parser::parse_from_str,
+        process::{MatchRange, Process},
+        testing::{check_round_trip, pseudo_random_programs},
+    };
+
+    fn simplify(s: &str) -> String {
+        parse_from_str(s).unwrap().simplify().to_string()
+    }
+
+    #[test]
+    fn test_simplify_groups() {
+        assert_eq!(
+            simplify(r#"('a', ('b', (char_digit, end)))"#),
+            r#""ab", char_digit, end"#
+        );
+        assert_eq!(simplify(r#"(('a' || 'b')), ()"#), r#"'a' || 'b'"#);
+        assert_eq!(simplify(r#"(char_digit)+"#), r#"one_or_more(char_digit)"#);
+        assert_eq!(
+            simplify(r#"(('x', 'y') || 'b') || (('c'))"#),
+            r#""xy" || 'b' || 'c'"#
+        );
+
+        // the nested alternations are merged
+        assert_eq!(
+            simplify(r#"(('a' || 'b')) || 'c' || ('d' || ('e' || 'f'))"#),
+            r#"'a' || 'b' || 'c' || 'd' || 'e' || 'f'"#
+        );
+
+        // the empty alternative
+        assert_eq!(simplify(r#"'a' || ()"#), r#"'a' || ()"#);
+    }
+
+    #[test]
+    fn test_simplify_literals() {
+        assert_eq!(simplify(r#"'a', 'b', "cd", 'e'"#), r#""abcde""#);
+        assert_eq!(
+            simplify(r#"'a', char_digit, "b", ('c', 'd')"#),
+            r#"'a', char_digit, "bcd""#
+        );
+        assert_eq!(
+            simplify(r#"name(('a', "bc"), x), 'd'+"#),
+            r#"name("abc", x)
+one_or_more('d')"#
+        );
+    }
+
+    #[test]
+    fn test_simplify_repetitions() {
+        assert_eq!(
+            simplify(
+                r#"
+'a'{1}
+'b'{1,1}
+'c'{2,2}
+'d'{2,2}?
+'e'{3}?
+'f'{0,1}
+'g'{0,1}?
+'h'{0,}
+'i'{1,}?
+'j'{2,5}
+'k'{0}
+"#
+            ),
+            r#""ab"
+repeat('c', 2)
+repeat('d', 2)
+repeat('e', 3)
+optional('f')
+optional_lazy('g')
+zero_or_more('h')
+one_or_more_lazy('i')
+repeat_range('j', 2, 5)
+repeat('k', 0)"#
+        );
+
+        // the nested optionals
+        assert_eq!(simplify(r#"('a'?)?"#), r#"optional('a')"#);
+        assert_eq!(simplify(r#"(('a'{0,1}))?"#), r#"optional('a')"#);
+        assert_eq!(simplify(r#"(('a'??))??"#), r#"optional_lazy('a')"#);
+        assert_eq!(simplify(r#"('a'??)?"#), r#"optional(optional_lazy('a'))"#);
+
+        // the sub-expressions of the look-arounds
+        assert_eq!(
+            simplify(r#"'a'.is_before(('b', 'c'){1})"#),
+            r#"is_before('a', "bc")"#
+        );
+    }
+
+    #[test]
+    fn test_simplify_captures() {
+        let source = r#"(('a', name(char_digit{1,1}, x)), ('b'?)?){1}, capture('c'{1,})"#;
+        let program = parse_from_str(source).unwrap().simplify();
+        assert_eq!(
+            program.to_string(),
+            r#"'a'
+name(char_digit, x)
+optional('b')
+capture(one_or_more('c'))"#
+        );
+
+        let process = Process::new(source).unwrap();
+        let simplified = Process::from_program(&program, &CompileOptions::default()).unwrap();
+
+        let expected = Some(vec![
+            Some(MatchRange::new(1, 5)),
+            Some(MatchRange::new(2, 3)),
+            Some(MatchRange::new(4, 5)),
+        ]);
+        assert_eq!(
+            process.new_instance("-a1bc").exec_with_captures(0),
+            expected
+        );
+        assert_eq!(
+            simplified.new_instance("-a1bc").exec_with_captures(0),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_simplify_arbitrary_programs() {
+        for program in pseudo_random_programs(7, 300) {
+            let text = program.to_string();
+            let simplified = program.simplify();
+
+            // the simplified program can be printed and parsed again,
+            // and it can not be simplified further.
+            check_round_trip(&simplified).unwrap();
+            let again = parse_from_str(&simplified.to_string()).unwrap().simplify();
+            assert_eq!(again, simplified, "{}", text);
+
+            // the simplified program finds the same matches with the same
+            // captures in the sample texts.
+            let options = CompileOptions::default();
+            let original = Process::new(&text).unwrap();
+            let simplified = Process::from_program(&simplified, &options).unwrap();
+
+            for sample in ["", "a1 b2\n_", "\"ab'\0"] {
+                let collect = |process: &Process| {
+                    let mut matches = vec![];
+                    process.new_instance(sample).scan(0, |match_ranges| {
+                        matches.push(match_ranges.to_vec());
+                        ControlFlow::Continue(())
+                    });
+                    matches
+                };
+                assert_eq!(collect(&simplified), collect(&original), "{}", text);
+            }
+        }
+    }
+}
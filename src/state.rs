@@ -4,7 +4,18 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
-use crate::transition::Transition;
+use std::collections::BTreeMap;
+
+use crate::{
+    matchlength::{
+        match_byte_length_bounds, match_length_bounds, route_length_bounds, LengthBounds,
+    },
+    transition::{
+        BackReferenceTransition, CaptureEndTransition, CaptureStartTransition, CharSetItem,
+        CharSetTransition, CharTransition, CounterExitTransition, LookAroundTransition,
+        PresetCharSet, StringTransition, Transition,
+    },
+};
 
 // state set --\
 //             |-- state node --\
@@ -19,11 +30,84 @@ use crate::transition::Transition;
 pub struct StateSet {
     pub start_node_index: usize,
     pub end_node_index: usize,
+
+    // the capture groups ordered by their indices, the group 0 is
+    // the whole match.
+    //
+    // the groups are numbered by the order of their appearance in the
+    // source, i.e. the order of their names, which follow the captured
+    // expressions in both the function form and the method form
+    // (which is stable across releases):
+    // - the groups on the left come first, e.g. in
+    //   `name('a', x), name('b', y)`, x is 1 and y is 2.
+    // - the inner group comes before the outer one, e.g. in
+    //   `'a'.name(x).name(y)`, i.e. `name(name('a', x), y)`,
+    //   x is 1 and y is 2.
+    // - the groups of the subject come before the groups of the
+    //   look-around assertion, e.g. in `capture('a').is_after(capture('b'))`,
+    //   'a' is 1 and 'b' is 2.
+    // - the groups with the same name in different branches of an
+    //   alternation share the index of the first one.
+    pub capture_groups: Vec<CaptureGroup>,
+
+    // the ranges of chars that can begin a match, the start positions
+    // with other chars are skipped by the process.
+    // it is `None` if any char (or nothing) can begin a match.
+    pub first_chars: Option<Vec<(char, char)>>,
+
+    // the minimum and maximum length of a match, the start positions
+    // that are too near to the end of text for the minimum length are
+    // skipped by the process.
+    pub length_bounds: LengthBounds,
+
+    // the bounds of the number of bytes of a match in bytes mode.
+    pub byte_length_bounds: LengthBounds,
+
+    // the length bounds of the look-behind routes, indexed by the start
+    // state of the route, i.e. (the bounds in text mode, the bounds of
+    // bytes in bytes mode), the process only tries the start positions
+    // within the bounds before the current position.
+    pub look_behind_bounds: BTreeMap<usize, (LengthBounds, LengthBounds)>,
+
+    // whether there are back-references, the process records the
+    // captures for them even if the caller does not ask for the
+    // captures.
+    pub has_back_references: bool,
+
+    // the names of the tags, indexed by the tag index, e.g. the pattern
+    // `tag("GET", "read") || tag("POST", "write")` has the tags "read" and
+    // "write", the tags with the same name share one index.
+    pub tags: Vec<String>,
+
     states: Vec<StateNode>,
     links: Vec<LinkNode>,
     transitions: Vec<TransitionNode>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureGroup {
+    pub index: usize,
+    pub name: Option<String>, // the groups created by `capture()` have no name
+    pub in_repetition: bool,  // the group may be captured many times, the last one is kept
+    pub in_look_around: bool, // the group is inside a look-around assertion
+}
+
+// a look-around assertion of the compiled states, the states of the
+// assertion form a separate route from `start_state_index` to
+// `end_state_index`, which is run by a nested thread.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookAroundRoute {
+    pub state_index: usize, // the state that the look-around transition starts from
+    pub start_state_index: usize,
+    pub end_state_index: usize,
+    pub is_behind: bool,
+    pub negative: bool,
+
+    // the index of the look-around whose route contains this one,
+    // it is `None` if this one is on the main route.
+    pub parent: Option<usize>,
+}
+
 // Every state node has one or more transitions.
 struct StateNode {
     link_head_index: Option<usize>,
@@ -46,6 +130,18 @@ impl StateSet {
         StateSet {
             start_node_index: 0,
             end_node_index: 0,
+            capture_groups: vec![CaptureGroup {
+                index: 0,
+                name: None,
+                in_repetition: false,
+                in_look_around: false,
+            }],
+            first_chars: None,
+            length_bounds: LengthBounds { min: 0, max: None },
+            byte_length_bounds: LengthBounds { min: 0, max: None },
+            look_behind_bounds: BTreeMap::new(),
+            has_back_references: false,
+            tags: vec![],
             states: vec![],
             links: vec![],
             transitions: vec![],
@@ -56,6 +152,247 @@ impl StateSet {
         self.states.is_empty()
     }
 
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn transition_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    // the transitions of the state, along with the indices
+    // of their target states.
+    fn state_transitions(&self, state_index: usize) -> Vec<(&Transition, usize)> {
+        let mut transitions = vec![];
+        let mut next_link_index = self.get_first_link_index(state_index);
+        while let Some(link_index) = next_link_index {
+            let (transition, target_state_index, next_index) = self.get_link(link_index);
+            transitions.push((transition, target_state_index));
+            next_link_index = next_index;
+        }
+        transitions
+    }
+
+    // the number of transitions of each kind, see `Transition::kind`
+    // for the names of the kinds.
+    pub fn transition_kind_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for state_index in 0..self.states.len() {
+            for (transition, _) in self.state_transitions(state_index) {
+                *counts.entry(transition.kind()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    // the look-around routes, the ones on the main route come first,
+    // and a route always comes after the one that contains it.
+    //
+    // each route is walked once, i.e. the look-arounds inside a route
+    // that is shared by many look-arounds (the identical sub-expressions
+    // share one route) take the first one as the parent, and a route that
+    // contains itself (which is only possible in the corrupted data, see
+    // `deserialize`) does not make the walk endless.
+    pub fn look_around_routes(&self) -> Vec<LookAroundRoute> {
+        let mut routes: Vec<LookAroundRoute> = vec![];
+
+        // the start states of the walked routes
+        let mut walked = vec![false; self.states.len()];
+        walked[self.start_node_index] = true;
+
+        // walk the main route, then the routes of the found look-arounds
+        let mut pending = vec![(self.start_node_index, None)];
+        let mut idx = 0;
+        while idx < pending.len() {
+            let (start_state_index, parent) = pending[idx];
+            idx += 1;
+
+            let mut visited = vec![false; self.states.len()];
+            let mut stack = vec![start_state_index];
+            visited[start_state_index] = true;
+
+            while let Some(state_index) = stack.pop() {
+                for (transition, target_state_index) in self.state_transitions(state_index) {
+                    if let Transition::LookAround(LookAroundTransition {
+                        start_state_index,
+                        end_state_index,
+                        is_behind,
+                        negative,
+                    }) = transition
+                    {
+                        if !walked[*start_state_index] {
+                            walked[*start_state_index] = true;
+                            pending.push((*start_state_index, Some(routes.len())));
+                        }
+                        routes.push(LookAroundRoute {
+                            state_index,
+                            start_state_index: *start_state_index,
+                            end_state_index: *end_state_index,
+                            is_behind: *is_behind,
+                            negative: *negative,
+                            parent,
+                        });
+                    }
+
+                    if !visited[target_state_index] {
+                        visited[target_state_index] = true;
+                        stack.push(target_state_index);
+                    }
+                }
+            }
+        }
+
+        routes
+    }
+
+    // compute the chars that can begin a match by walking the
+    // transitions from the start state, the transitions that do not
+    // consume chars (jumps, counters, captures and assertions) are
+    // passed through.
+    //
+    // the result is a superset of the possible first chars, e.g. the
+    // assertions are assumed to succeed.
+    //
+    // the walk tracks whether the current counter is just reset, so
+    // the repetition with `min > 0` can not be skipped, e.g. `'a'+`.
+    pub fn update_first_chars(&mut self) {
+        self.first_chars = self.compute_first_chars();
+    }
+
+    // change the index of each capture group to `new_indices[index]`,
+    // including the indices in the transitions.
+    pub fn renumber_capture_groups(&mut self, new_indices: &[usize]) {
+        for group in &mut self.capture_groups {
+            group.index = new_indices[group.index];
+        }
+        self.capture_groups.sort_by_key(|group| group.index);
+
+        for node in &mut self.transitions {
+            match &mut node.transition {
+                Transition::CaptureStart(CaptureStartTransition { capture_index, .. })
+                | Transition::CaptureEnd(CaptureEndTransition { capture_index, .. })
+                | Transition::BackReference(BackReferenceTransition { capture_index, .. }) => {
+                    *capture_index = new_indices[*capture_index];
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn update_length_bounds(&mut self) {
+        self.length_bounds = match_length_bounds(self);
+        self.byte_length_bounds = match_byte_length_bounds(self);
+
+        self.look_behind_bounds = self
+            .look_around_routes()
+            .iter()
+            .filter(|route| route.is_behind)
+            .map(|route| {
+                let bounds = |bytes_mode| {
+                    route_length_bounds(
+                        self,
+                        route.start_state_index,
+                        route.end_state_index,
+                        bytes_mode,
+                    )
+                };
+                (route.start_state_index, (bounds(false), bounds(true)))
+            })
+            .collect();
+    }
+
+    fn compute_first_chars(&self) -> Option<Vec<(char, char)>> {
+        let mut ranges = vec![];
+        let mut visited = vec![[false; 2]; self.states.len()];
+        let mut pending = vec![(self.start_node_index, false)];
+
+        while let Some((state_index, counter_reset)) = pending.pop() {
+            if state_index == self.end_node_index {
+                // the empty match is possible
+                return None;
+            }
+
+            if visited[state_index][counter_reset as usize] {
+                continue;
+            }
+            visited[state_index][counter_reset as usize] = true;
+
+            let mut next_link_index = self.get_first_link_index(state_index);
+            while let Some(link_index) = next_link_index {
+                let (transition, target_state_index, next) = self.get_link(link_index);
+                next_link_index = next;
+
+                match transition {
+                    Transition::Jump(_)
+                    | Transition::Status(_)
+                    | Transition::CaptureStart(_)
+                    | Transition::CaptureEnd(_)
+                    | Transition::LookAround(_)
+                    | Transition::MatchStartReset(_)
+                    | Transition::Tag(_) => pending.push((target_state_index, counter_reset)),
+                    Transition::CounterReset(_) => pending.push((target_state_index, true)),
+                    Transition::CounterExit(CounterExitTransition { min })
+                        if counter_reset && *min > 0 => {}
+                    Transition::CounterCheck(_)
+                    | Transition::CounterInc(_)
+                    | Transition::CounterExit(_) => pending.push((target_state_index, false)),
+                    Transition::Char(CharTransition {
+                        character,
+                        ignore_case: false,
+                    }) => ranges.push((*character, *character)),
+                    Transition::String(StringTransition {
+                        chars,
+                        ignore_case: false,
+                    }) => match chars.first() {
+                        Some(c) => ranges.push((*c, *c)),
+                        None => pending.push((target_state_index, counter_reset)),
+                    },
+                    Transition::CharSet(CharSetTransition {
+                        items,
+                        negative: false,
+                        unicode,
+                        folded,
+                        ..
+                    }) => {
+                        ranges.extend(folded);
+                        for item in items {
+                            match item {
+                                CharSetItem::Char(c) => ranges.push((*c, *c)),
+                                CharSetItem::Range(start, end_included) => {
+                                    ranges.push((*start, *end_included))
+                                }
+                                CharSetItem::Preset(PresetCharSet::AsciiSpace, false) => {
+                                    ranges.extend([('\t', '\r'), (' ', ' ')])
+                                }
+                                CharSetItem::Preset(preset, false) if !unicode => match preset {
+                                    PresetCharSet::Space | PresetCharSet::AsciiSpace => {
+                                        ranges.extend([('\t', '\r'), (' ', ' ')])
+                                    }
+                                    PresetCharSet::Word => ranges.extend([
+                                        ('0', '9'),
+                                        ('A', 'Z'),
+                                        ('_', '_'),
+                                        ('a', 'z'),
+                                    ]),
+                                    PresetCharSet::Digit => ranges.push(('0', '9')),
+                                },
+                                _ => return None,
+                            }
+                        }
+                    }
+                    _ => {
+                        // the special chars, the chars that ignore case
+                        // (except the charsets), the normalized strings
+                        // and the bytes
+                        return None;
+                    }
+                }
+            }
+        }
+
+        Some(ranges)
+    }
+
     // return the index of the new state node
     pub fn new_state(&mut self) -> usize {
         let state = StateNode {
@@ -87,6 +424,8 @@ impl StateSet {
         target_state_index: usize,
         transition: Transition,
     ) {
+        self.has_back_references |= matches!(transition, Transition::BackReference(_));
+
         let transition_node = TransitionNode {
             transition,
             target_state_index,
@@ -171,6 +510,24 @@ impl StateSet {
         }
     }
 
+    // return the index of the first link node of the specified state,
+    // or None if the state has no transition.
+    pub fn get_first_link_index(&self, state_index: usize) -> Option<usize> {
+        self.states[state_index].get_first_transition_index()
+    }
+
+    // return the transition, the index of the target state and
+    // the index of the next link node.
+    pub fn get_link(&self, link_index: usize) -> (&Transition, usize, Option<usize>) {
+        let link_node = &self.links[link_index];
+        let transition_node = &self.transitions[link_node.transition_index];
+        (
+            &transition_node.transition,
+            transition_node.target_state_index,
+            link_node.next_index,
+        )
+    }
+
     // for debug
     //     pub fn get_transition_index_list(&self, source_state_index: usize) -> Vec<usize> {
     //         let mut indices = vec![];
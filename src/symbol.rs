@@ -0,0 +1,135 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use std::{borrow::Borrow, collections::HashSet, fmt::Display, hash::Hash, ops::Deref, rc::Rc};
+
+// the interned name of an identifier, a status, a preset charset or
+// a special char.
+//
+// the symbols with the same name share one string in the table of the
+// interner, so the tokens are cloned (e.g. when expanding the macros)
+// without allocating, and the symbols from the same interner are
+// compared by the pointers.
+//
+// the symbols from different interners (e.g. the built-in macros) are
+// still equal if their names are the same.
+#[derive(Clone, Eq)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    // a symbol that does not belong to any interner.
+    pub fn new(name: &str) -> Self {
+        Symbol(Rc::from(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", &*self.0)
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// the table of the symbols, one interner is used by the lexer for
+// a whole source.
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashSet<Symbol>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns the symbol of the name, the name is allocated only
+    // the first time it is interned.
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        match self.symbols.get(name) {
+            Some(symbol) => symbol.clone(),
+            None => {
+                let symbol = Symbol::new(name);
+                self.symbols.insert(symbol.clone());
+                symbol
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use std::rc::Rc;
+
+    use super::{Interner, Symbol};
+
+    #[test]
+    fn test_interner() {
+        let mut interner = Interner::new();
+        let a1 = interner.intern("abc");
+        let b = interner.intern("xyz");
+        let a2 = interner.intern("abc");
+
+        assert_eq!(interner.symbols.len(), 2);
+        assert!(Rc::ptr_eq(&a1.0, &a2.0));
+        assert_eq!(a1, a2);
+        assert_ne!(a1, b);
+
+        // the symbols from different interners
+        assert_eq!(a1, Symbol::new("abc"));
+        assert!(a1 == "abc");
+        assert_eq!(a1.as_str(), "abc");
+        assert_eq!(format!("{:?}", a1), "\"abc\"");
+    }
+}
@@ -0,0 +1,355 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the helpers for fuzzing the whole pipeline, they are available
+// with the feature `testing`, e.g. a fuzz target:
+//
+// ```rust
+// fuzz_target!(|data: &[u8]| {
+//     let mut u = Unstructured::new(data);
+//     let program = arbitrary_program(&mut u);
+//     assert_round_trip(&program);
+// });
+// ```
+//
+// the generated programs are canonical, i.e. a program is equal to
+// the one that is parsed from its text, e.g. an alternative is never
+// an alternation without the parentheses, since `a || b || c` is parsed
+// as one alternation with three alternatives.
+
+use crate::{
+    ast::{
+        ByteRange, CharRange, CharSet, CharSetElement, Expression, FunctionCall, FunctionCallArg,
+        FunctionName, Literal, NumberRange, PatternOptions, Program,
+    },
+    compiler::compile,
+    error::Error,
+    options::CompileOptions,
+    parser::parse_from_str,
+};
+
+const MAX_DEPTH: usize = 4;
+const MAX_ELEMENTS: usize = 4;
+
+// the chars for the literals, including the ones that need escaping
+const SAMPLE_CHARS: [char; 16] = [
+    'a', 'b', 'z', 'A', '0', '9', '_', ' ', '\'', '"', '\\', '\n', '\t', '\0', '\u{1}', '文',
+];
+
+const PRESET_CHARSETS: [&str; 8] = [
+    "char_word",
+    "char_not_word",
+    "char_space",
+    "char_not_space",
+    "char_space_ascii",
+    "char_not_space_ascii",
+    "char_digit",
+    "char_not_digit",
+];
+
+const STATUSES: [&str; 9] = [
+    "start",
+    "end",
+    "bound",
+    "not_bound",
+    "word_start",
+    "word_end",
+    "continue_from_last",
+    "line_start",
+    "line_end",
+];
+
+const SPECIALS: [&str; 2] = ["char_any", "char_any_including_newline"];
+
+// the source of arbitrary values, the values are taken from the data
+// one by one, and zeros are taken when the data is exhausted.
+pub struct Unstructured<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Unstructured<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Unstructured { data, position: 0 }
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        let b = self.data.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        b
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u8() & 1 == 1
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        u32::from_le_bytes([
+            self.next_u8(),
+            self.next_u8(),
+            self.next_u8(),
+            self.next_u8(),
+        ])
+    }
+
+    // a number in `0..length`, the length must not be zero.
+    pub fn choose_index(&mut self, length: usize) -> usize {
+        self.next_u8() as usize % length
+    }
+
+    pub fn choose<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.choose_index(items.len())]
+    }
+}
+
+// generate a program that can be compiled, the capture names are
+// `n0`, `n1`, ... so they never conflict.
+pub fn arbitrary_program(u: &mut Unstructured) -> Program {
+    let mut generator = ProgramGenerator { name_count: 0 };
+    let count = u.choose_index(MAX_ELEMENTS + 1);
+    let expressions = (0..count)
+        .map(|_| generator.expression(u, MAX_DEPTH))
+        .collect();
+    Program {
+        expressions,
+        tests: vec![],
+        options: PatternOptions::default(),
+        syntax_version: None,
+    }
+}
+
+// the programs generated from the data of a simple linear congruential
+// generator, for the tests that need many arbitrary programs.
+#[cfg(test)]
+pub fn pseudo_random_programs(seed: u32, count: usize) -> impl Iterator<Item = Program> {
+    let mut seed = seed;
+    (0..count).map(move |_| {
+        let data: Vec<u8> = (0..256)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        arbitrary_program(&mut Unstructured::new(&data))
+    })
+}
+
+// check that the program is unchanged after converting it to text and
+// parsing it back, and that the parsed program can be compiled.
+pub fn check_round_trip(program: &Program) -> Result<(), Error> {
+    let text = program.to_string();
+    let parsed = parse_from_str(&text)?;
+
+    if &parsed != program {
+        return Err(Error::Message(format!(
+            "The program is changed after round trip, text: {}",
+            text
+        )));
+    }
+
+    compile(&parsed, &CompileOptions::default())?;
+    Ok(())
+}
+
+// the panicking version of `check_round_trip` for fuzz targets.
+pub fn assert_round_trip(program: &Program) {
+    if let Err(e) = check_round_trip(program) {
+        panic!("Round trip failed: {:?}\nprogram: {:?}", e, program);
+    }
+}
+
+struct ProgramGenerator {
+    name_count: usize,
+}
+
+impl ProgramGenerator {
+    fn expression(&mut self, u: &mut Unstructured, depth: usize) -> Expression {
+        if depth == 0 {
+            return Expression::Literal(self.literal(u));
+        }
+
+        match u.choose_index(6) {
+            0 | 1 => Expression::Literal(self.literal(u)),
+            2 => {
+                let count = u.choose_index(MAX_ELEMENTS);
+                Expression::Group((0..count).map(|_| self.expression(u, depth - 1)).collect())
+            }
+            3 | 4 => Expression::FunctionCall(Box::new(self.function_call(u, depth))),
+            _ => {
+                // an alternative can not be an alternation, see the
+                // comments at the top of this file.
+                let count = 2 + u.choose_index(2);
+                let alternatives = (0..count)
+                    .map(|_| match self.expression(u, depth - 1) {
+                        Expression::OrList(..) => Expression::Group(vec![]),
+                        e => e,
+                    })
+                    .collect();
+                Expression::OrList(alternatives)
+            }
+        }
+    }
+
+    fn function_call(&mut self, u: &mut Unstructured, depth: usize) -> FunctionCall {
+        let name = u.choose(&[
+            FunctionName::Optional,
+            FunctionName::OneOrMore,
+            FunctionName::ZeroOrMore,
+            FunctionName::Repeat,
+            FunctionName::RepeatRange,
+            FunctionName::AtLeast,
+            FunctionName::OptionalLazy,
+            FunctionName::OneOrMoreLazy,
+            FunctionName::ZeroOrMoreLazy,
+            FunctionName::RepeatLazy,
+            FunctionName::RepeatRangeLazy,
+            FunctionName::AtLeastLazy,
+            FunctionName::IsBefore,
+            FunctionName::IsAfter,
+            FunctionName::IsNotBefore,
+            FunctionName::IsNotAfter,
+            FunctionName::Name,
+            FunctionName::Capture,
+            FunctionName::Normalized,
+            FunctionName::IgnoreCase,
+            FunctionName::Line,
+            FunctionName::Tag,
+        ]);
+
+        if name == FunctionName::Normalized {
+            let s = self.string(u);
+            return FunctionCall {
+                name,
+                expression: Box::new(Expression::Literal(Literal::String(s))),
+                args: vec![],
+            };
+        }
+
+        let expression = Box::new(self.expression(u, depth - 1));
+        let small_number = |u: &mut Unstructured| u.choose_index(4) as u32;
+
+        let args = match name {
+            FunctionName::Repeat
+            | FunctionName::RepeatLazy
+            | FunctionName::AtLeast
+            | FunctionName::AtLeastLazy => vec![FunctionCallArg::Number(small_number(u))],
+            FunctionName::RepeatRange | FunctionName::RepeatRangeLazy => {
+                let min = small_number(u);
+                let max = min + small_number(u);
+                vec![FunctionCallArg::Number(min), FunctionCallArg::Number(max)]
+            }
+            FunctionName::IsBefore
+            | FunctionName::IsAfter
+            | FunctionName::IsNotBefore
+            | FunctionName::IsNotAfter => {
+                let e = self.expression(u, depth - 1);
+                vec![FunctionCallArg::Expression(Box::new(e))]
+            }
+            FunctionName::Name => {
+                let id = format!("n{}", self.name_count);
+                self.name_count += 1;
+                vec![FunctionCallArg::Identifier(id)]
+            }
+            FunctionName::Tag => {
+                let s = self.string(u);
+                vec![FunctionCallArg::Expression(Box::new(Expression::Literal(
+                    Literal::String(s),
+                )))]
+            }
+            _ => vec![],
+        };
+
+        FunctionCall {
+            name,
+            expression,
+            args,
+        }
+    }
+
+    fn literal(&mut self, u: &mut Unstructured) -> Literal {
+        match u.choose_index(9) {
+            0 | 1 => Literal::Char(u.choose(&SAMPLE_CHARS)),
+            2 => Literal::String(self.string(u)),
+            3 => {
+                let count = u.choose_index(MAX_ELEMENTS) + 1;
+                let elements = (0..count)
+                    .map(|_| match u.choose_index(3) {
+                        0 => CharSetElement::Char(u.choose(&SAMPLE_CHARS)),
+                        1 => {
+                            let (start, end_included) =
+                                ordered(u.choose(&SAMPLE_CHARS), u.choose(&SAMPLE_CHARS));
+                            CharSetElement::CharRange(CharRange {
+                                start,
+                                end_included,
+                            })
+                        }
+                        _ => CharSetElement::PresetCharSet(u.choose(&PRESET_CHARSETS).to_owned()),
+                    })
+                    .collect();
+                Literal::CharSet(CharSet {
+                    negative: u.next_bool(),
+                    elements,
+                })
+            }
+            4 => Literal::PresetCharSet(u.choose(&PRESET_CHARSETS).to_owned()),
+            5 => Literal::Special(u.choose(&SPECIALS).to_owned()),
+            6 => Literal::Status(u.choose(&STATUSES).to_owned()),
+            7 => {
+                if u.next_bool() {
+                    Literal::Byte(u.next_u8())
+                } else {
+                    let (start, end_included) = ordered(u.next_u8(), u.next_u8());
+                    Literal::ByteRange(ByteRange {
+                        start,
+                        end_included,
+                    })
+                }
+            }
+            _ => {
+                let (start, end_included) = ordered(u.next_u32() % 1000, u.next_u32() % 1000);
+                Literal::NumberRange(NumberRange {
+                    start,
+                    end_included,
+                })
+            }
+        }
+    }
+
+    fn string(&mut self, u: &mut Unstructured) -> String {
+        let count = u.choose_index(MAX_ELEMENTS) + 1;
+        (0..count).map(|_| u.choose(&SAMPLE_CHARS)).collect()
+    }
+}
+
+fn ordered<T: Ord>(a: T, b: T) -> (T, T) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        arbitrary_program, assert_round_trip, check_round_trip, pseudo_random_programs,
+        Unstructured,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        for program in pseudo_random_programs(1, 500) {
+            if let Err(e) = check_round_trip(&program) {
+                panic!("{:?}\n{}\n{:?}", e, program, program);
+            }
+        }
+
+        // the exhausted data generates an empty program
+        let program = arbitrary_program(&mut Unstructured::new(&[]));
+        assert!(program.expressions.is_empty());
+        assert_round_trip(&program);
+    }
+}
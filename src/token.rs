@@ -4,7 +4,7 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
-use crate::location::Location;
+use crate::{location::Location, symbol::Symbol};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
@@ -37,9 +37,16 @@ pub enum Token {
     RightParen,
 
     // [a-zA-Z0-9_] and '\u{a0}' - '\u{d7ff}' and '\u{e000}' - '\u{10ffff}'
-    Identifier(String),
-    Status(String),
-    PresetCharSet(String),
+    Identifier(Symbol),
+
+    // the identifier enclosed by backticks, e.g. `tag name`, it can
+    // contain any chars and never means a keyword, a function or
+    // an assertion, e.g. `start` is the back-reference to the capture
+    // group "start" instead of the assertion.
+    QuotedIdentifier(Symbol),
+    Status(Symbol),
+    PresetCharSet(Symbol),
+    Special(Symbol),
     Number(u32),
     Char(char),
     String(String),
@@ -48,7 +55,6 @@ pub enum Token {
     /*
      * Notations/Symbols
      */
-
     // ?
     Question,
 
@@ -99,8 +105,10 @@ impl Token {
             Token::Dot => "dot \".\"".to_owned(),
             Token::LogicOr => "logic or \"||\"".to_owned(),
             Token::Identifier(id) => format!("identifier \"{}\"", id),
+            Token::QuotedIdentifier(id) => format!("quoted identifier \"{}\"", id),
             Token::Status(k) => format!("status \"{}\"", k),
             Token::PresetCharSet(s) => format!("preset charset \"{}\"", s),
+            Token::Special(s) => format!("special char \"{}\"", s),
             Token::Number(n) => format!("number \"{}\"", n),
             Token::Char(c) => format!("char \"{}\"", c),
             Token::String(_) => "string".to_owned(),
@@ -115,6 +123,135 @@ impl Token {
             Token::RightBrace => "right brace \"}\"".to_owned(),
         }
     }
+
+    // the name of the identifier, either quoted or not.
+    pub fn get_identifier(&self) -> Option<&Symbol> {
+        match self {
+            Token::Identifier(id) | Token::QuotedIdentifier(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+// the names that are lexed as the status tokens, the preset charset
+// tokens and the special char tokens instead of the identifiers.
+pub const STATUS_NAMES: [&str; 10] = [
+    "start",
+    "end",
+    "bound",
+    "not_bound",
+    "word_start",
+    "word_end",
+    "continue_from_last",
+    "match_start_reset",
+    "line_start",
+    "line_end",
+];
+
+pub const PRESET_CHARSET_NAMES: [&str; 8] = [
+    "char_space",
+    "char_not_space",
+    "char_space_ascii",
+    "char_not_space_ascii",
+    "char_word",
+    "char_not_word",
+    "char_digit",
+    "char_not_digit",
+];
+
+pub const SPECIAL_CHAR_NAMES: [&str; 3] =
+    ["char_any", "char_any_including_newline", "grapheme_any"];
+
+// the names of the functions, including the ones that are parsed as
+// literals, e.g. `byte(0x41)`.
+pub const FUNCTION_NAMES: [&str; 25] = [
+    "optional",
+    "one_or_more",
+    "zero_or_more",
+    "repeat",
+    "repeat_range",
+    "at_least",
+    "optional_lazy",
+    "one_or_more_lazy",
+    "zero_or_more_lazy",
+    "repeat_lazy",
+    "repeat_range_lazy",
+    "at_least_lazy",
+    "is_before",
+    "is_after",
+    "is_not_before",
+    "is_not_after",
+    "name",
+    "capture",
+    "normalized",
+    "ignore_case",
+    "line",
+    "tag",
+    "number_range",
+    "byte",
+    "byte_range",
+];
+
+// the statements, they are not functions although they look like ones.
+pub const KEYWORDS: [&str; 7] = [
+    "define",
+    "define_charset",
+    "var",
+    "options",
+    "syntax_version",
+    "expect_match",
+    "expect_no_match",
+];
+
+// the kind of the reserved word (for the error messages), e.g.
+// "assertion" for `start`, or None if the name can be used as the
+// name of a capture group or a macro.
+pub fn get_reserved_word_kind(name: &str) -> Option<&'static str> {
+    if STATUS_NAMES.contains(&name) {
+        Some("assertion")
+    } else if PRESET_CHARSET_NAMES.contains(&name) {
+        Some("preset charset")
+    } else if SPECIAL_CHAR_NAMES.contains(&name) {
+        Some("special char")
+    } else if FUNCTION_NAMES.contains(&name) {
+        Some("function")
+    } else if KEYWORDS.contains(&name) {
+        Some("keyword")
+    } else {
+        None
+    }
+}
+
+// the source text of the identifier, it is quoted by backticks if the
+// name is a reserved word or contains the chars that are not allowed in
+// identifiers, e.g. "tag name" becomes `tag name`.
+pub fn format_identifier(name: &str) -> String {
+    let is_identifier_char = |c: char| matches!(c, '0'..='9' | 'a'..='z' | 'A'..='Z' | '_' | '\u{a0}'..='\u{d7ff}' | '\u{e000}'..='\u{10ffff}');
+
+    let is_plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| !c.is_ascii_digit() && is_identifier_char(c))
+        && name.chars().all(is_identifier_char)
+        && get_reserved_word_kind(name).is_none();
+
+    if is_plain {
+        name.to_owned()
+    } else {
+        let mut s = String::from("`");
+        for c in name.chars() {
+            match c {
+                '\\' => s.push_str("\\\\"),
+                '`' => s.push_str("\\`"),
+                '\n' => s.push_str("\\n"),
+                '\r' => s.push_str("\\r"),
+                '\t' => s.push_str("\\t"),
+                _ => s.push(c),
+            }
+        }
+        s.push('`');
+        s
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -0,0 +1,201 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+use crate::{
+    error::Error,
+    process::{Instance, MatchRange, Process},
+};
+
+// A tokenizer built from an ordered list of named patterns.
+//
+// At each position, all patterns are tried and the longest match wins,
+// if two or more patterns produce the match with the same length,
+// the first declared one wins.
+//
+// Note that the match of each pattern is the one found first by the
+// pattern's own priority (i.e. the alternatives are tried in order and
+// the repetitions are greedy unless they are lazy), like `exec`, and it
+// is not necessarily the longest one of the pattern, e.g. the pattern
+// `"a" || "ab"` produces the token "a" from the text "ab", so the
+// longer alternatives should be written first, i.e. `"ab" || "a"`.
+pub struct Tokenizer {
+    rules: Vec<(String, Process)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TokenMatch<'a> {
+    pub name: &'a str,
+    pub range: MatchRange,
+}
+
+impl Tokenizer {
+    pub fn new(patterns: &[(&str, &str)]) -> Result<Self, Error> {
+        let mut rules = vec![];
+        for (name, pattern) in patterns {
+            let process = Process::new(pattern)?;
+            rules.push((name.to_string(), process));
+        }
+
+        Ok(Tokenizer { rules })
+    }
+
+    pub fn tokenize(&self, text: &str) -> Tokens<'_> {
+        let instances = self
+            .rules
+            .iter()
+            .map(|(name, process)| (name.as_str(), process.new_instance(text)))
+            .collect();
+
+        Tokens {
+            instances,
+            position: 0,
+        }
+    }
+}
+
+// The iterator of tokens, it stops when reaching the end of text,
+// or none of the patterns matches at the current position,
+// check `position()` to tell the difference.
+pub struct Tokens<'a> {
+    instances: Vec<(&'a str, Instance<'a>)>,
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    // the position where the next token starts.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = TokenMatch<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut longest: Option<TokenMatch<'a>> = None;
+
+        for (name, instance) in self.instances.iter_mut() {
+            if let Some(range) = instance.exec_anchored(self.position) {
+                let length = range.end - range.start;
+
+                // the empty match is ignored, otherwise the tokenizer
+                // would be stuck at the current position.
+                if length == 0 {
+                    continue;
+                }
+
+                let is_longer = match &longest {
+                    Some(token) => length > token.range.end - token.range.start,
+                    None => true,
+                };
+
+                if is_longer {
+                    longest = Some(TokenMatch { name, range });
+                }
+            }
+        }
+
+        if let Some(token) = &longest {
+            self.position = token.range.end;
+        }
+
+        longest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::process::MatchRange;
+
+    use super::{TokenMatch, Tokenizer};
+
+    #[test]
+    fn test_tokenize() {
+        let tokenizer = Tokenizer::new(&[
+            ("keyword_if", r#""if""#),
+            ("keyword_in", r#""in""#),
+            ("identifier", r#""ifx" || "if" || "i" || "x""#),
+            ("space", r#"' '"#),
+        ])
+        .unwrap();
+
+        let mut tokens = tokenizer.tokenize("if ifx in i");
+
+        let expected = vec![
+            ("keyword_if", 0, 2), // tie, the first declared wins
+            ("space", 2, 3),
+            ("identifier", 3, 6), // the longest wins
+            ("space", 6, 7),
+            ("keyword_in", 7, 9),
+            ("space", 9, 10),
+            ("identifier", 10, 11),
+        ];
+
+        for (name, start, end) in expected {
+            assert_eq!(
+                tokens.next(),
+                Some(TokenMatch {
+                    name,
+                    range: MatchRange::new(start, end)
+                })
+            );
+        }
+
+        assert_eq!(tokens.next(), None);
+        assert_eq!(tokens.position(), 11);
+    }
+
+    #[test]
+    fn test_tokenize_priority_within_pattern() {
+        let get_tokens = |patterns: &[(&str, &str)], text: &str| -> Vec<(String, usize, usize)> {
+            Tokenizer::new(patterns)
+                .unwrap()
+                .tokenize(text)
+                .map(|t| (t.name.to_owned(), t.range.start, t.range.end))
+                .collect()
+        };
+
+        // the first alternative wins though the second one is longer
+        assert_eq!(
+            get_tokens(&[("x", r#""a" || "ab""#), ("b", r#"'b'"#)], "ab"),
+            vec![("x".to_owned(), 0, 1), ("b".to_owned(), 1, 2)]
+        );
+        assert_eq!(
+            get_tokens(&[("x", r#""ab" || "a""#), ("b", r#"'b'"#)], "ab"),
+            vec![("x".to_owned(), 0, 2)]
+        );
+
+        // the lazy repetition
+        assert_eq!(
+            get_tokens(&[("x", r#"'a'+?"#)], "aa"),
+            vec![("x".to_owned(), 0, 1), ("x".to_owned(), 1, 2)]
+        );
+
+        // the longest one across the patterns still wins
+        assert_eq!(
+            get_tokens(&[("x", r#""a" || "ab""#), ("y", r#""ab""#)], "ab"),
+            vec![("y".to_owned(), 0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unmatched() {
+        let tokenizer = Tokenizer::new(&[("a", r#"'a'"#), ("b", r#"'b'"#)]).unwrap();
+
+        let mut tokens = tokenizer.tokenize("abxa");
+        assert_eq!(tokens.next().unwrap().name, "a");
+        assert_eq!(tokens.next().unwrap().name, "b");
+        assert_eq!(tokens.next(), None);
+
+        // stopped at the unmatched char 'x'
+        assert_eq!(tokens.position(), 2);
+
+        // err: invalid pattern
+        assert!(Tokenizer::new(&[("a", r#"'a"#)]).is_err());
+    }
+}
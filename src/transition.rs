@@ -4,20 +4,42 @@
 // the Mozilla Public License version 2.0 and additional exceptions,
 // more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
 
-use std::fmt::Display;
+use std::{cmp::Ordering, fmt::Display};
 
-use crate::context::Context;
+use crate::{
+    casefolding::fold_closure,
+    context::{is_word_char, Context},
+    grapheme::grapheme_length,
+    inlinevec::InlineVec,
+    normalization::{decompose_char, decompose_str, is_combining_mark},
+};
 
-trait TransitionTrait {
-    fn validated(&self, context: &Context) -> bool;
-
-    // Move position
-    fn forward(&self) -> usize;
+pub trait TransitionTrait {
+    // returns the number of chars to move forward if the
+    // transition is validated, otherwise returns None.
+    fn check(&self, context: &Context) -> Option<usize>;
 }
 
 pub enum Transition {
     Jump(JumpTransition),
     Char(CharTransition),
+    SpecialChar(SpecialCharTransition),
+    String(StringTransition),
+    CharSet(CharSetTransition),
+    Status(StatusTransition),
+    NormalizedString(NormalizedStringTransition),
+    Byte(ByteTransition),
+    ByteRange(ByteRangeTransition),
+    CounterReset(CounterResetTransition),
+    CounterCheck(CounterCheckTransition),
+    CounterInc(CounterIncTransition),
+    CounterExit(CounterExitTransition),
+    CaptureStart(CaptureStartTransition),
+    CaptureEnd(CaptureEndTransition),
+    LookAround(LookAroundTransition),
+    BackReference(BackReferenceTransition),
+    MatchStartReset(MatchStartResetTransition),
+    Tag(TagTransition),
 }
 
 impl Display for Transition {
@@ -25,54 +47,1189 @@ impl Display for Transition {
         match self {
             Transition::Jump(_) => f.write_str("Jump"),
             Transition::Char(CharTransition {
-                character, /*, inverse */
+                character,
+                ignore_case, /*, inverse */
+            }) => {
+                // if *inverse {
+                //     format!("^{}", character)
+                // } else {
+                //     character.to_string()
+                // }
+                write!(
+                    f,
+                    "Char '{}'{}",
+                    character,
+                    ignore_case_suffix(*ignore_case)
+                )
+            }
+            Transition::SpecialChar(SpecialCharTransition {
+                include_new_line,
+                crlf,
+                grapheme,
+            }) => {
+                let grapheme_suffix = if *grapheme { ", grapheme" } else { "" };
+                if *include_new_line {
+                    write!(f, "Any char including new line{}", grapheme_suffix)
+                } else {
+                    write!(
+                        f,
+                        "Any char{}{}",
+                        if *crlf { ", crlf" } else { "" },
+                        grapheme_suffix
+                    )
+                }
+            }
+            Transition::String(StringTransition { chars, ignore_case }) => {
+                let s: String = chars.iter().collect();
+                write!(f, "String \"{}\"{}", s, ignore_case_suffix(*ignore_case))
+            }
+            Transition::CharSet(CharSetTransition {
+                items,
+                negative,
+                ignore_case,
+                grapheme,
+                ..
+            }) => {
+                let s: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(
+                    f,
+                    "Charset {}[{}]{}{}",
+                    if *negative { "!" } else { "" },
+                    s.join(", "),
+                    ignore_case_suffix(*ignore_case),
+                    if *negative && *grapheme {
+                        ", grapheme"
+                    } else {
+                        ""
+                    }
+                )
+            }
+            Transition::Status(StatusTransition {
+                status,
+                multiline,
+                crlf,
+                unicode: _,
+            }) => {
+                write!(
+                    f,
+                    "Status {}{}{}",
+                    status,
+                    if *multiline { ", multiline" } else { "" },
+                    if *crlf { ", crlf" } else { "" }
+                )
+            }
+            Transition::NormalizedString(NormalizedStringTransition { chars, ignore_case }) => {
+                let s: String = chars.iter().collect();
+                write!(
+                    f,
+                    "Normalized string \"{}\"{}",
+                    s.escape_default(),
+                    ignore_case_suffix(*ignore_case)
+                )
+            }
+            Transition::Byte(ByteTransition { byte }) => write!(f, "Byte 0x{:02x}", byte),
+            Transition::ByteRange(ByteRangeTransition {
+                start,
+                end_included,
+            }) => write!(f, "Byte range 0x{:02x}..0x{:02x}", start, end_included),
+            Transition::CounterReset(_) => f.write_str("Counter reset"),
+            Transition::CounterCheck(CounterCheckTransition { max }) => match max {
+                Some(m) => write!(f, "Counter check, max {}", m),
+                None => f.write_str("Counter check, unbounded"),
+            },
+            Transition::CounterInc(CounterIncTransition { min }) => {
+                write!(f, "Counter inc, min {}", min)
+            }
+            Transition::CounterExit(CounterExitTransition { min }) => {
+                write!(f, "Counter exit, min {}", min)
+            }
+            Transition::CaptureStart(CaptureStartTransition {
+                capture_index,
+                keep_first,
+            }) => {
+                write!(f, "Capture start {{{}}}", capture_index)?;
+                if *keep_first {
+                    write!(f, ", keep first")?;
+                }
+                Ok(())
+            }
+            Transition::CaptureEnd(CaptureEndTransition {
+                capture_index,
+                keep_first,
+            }) => {
+                write!(f, "Capture end {{{}}}", capture_index)?;
+                if *keep_first {
+                    write!(f, ", keep first")?;
+                }
+                Ok(())
+            }
+            Transition::LookAround(LookAroundTransition {
+                start_state_index,
+                end_state_index,
+                is_behind,
+                negative,
+            }) => {
+                write!(
+                    f,
+                    "{} {} ({}, {})",
+                    if *negative { "Negative look" } else { "Look" },
+                    if *is_behind { "behind" } else { "ahead" },
+                    start_state_index,
+                    end_state_index
+                )
+            }
+            Transition::BackReference(BackReferenceTransition {
+                capture_index,
+                ignore_case,
             }) => {
                 write!(
                     f,
-                    "Char '{}'",
-                    character // if *inverse {
-                              //     format!("^{}", character)
-                              // } else {
-                              //     character.to_string()
-                              // }
+                    "Back reference {{{}}}{}",
+                    capture_index,
+                    ignore_case_suffix(*ignore_case)
                 )
             }
+            Transition::MatchStartReset(_) => f.write_str("Match start reset"),
+            Transition::Tag(TagTransition { tag_index }) => write!(f, "Tag {{{}}}", tag_index),
         }
     }
 }
 
+fn ignore_case_suffix(ignore_case: bool) -> &'static str {
+    if ignore_case {
+        ", ignore case"
+    } else {
+        ""
+    }
+}
+
 // Jump/Epsilon
 pub struct JumpTransition;
 
 pub struct CharTransition {
     pub character: char,
+    pub ignore_case: bool,
     // pub inverse: bool,
 }
 
+// `char_any`, `char_any_including_newline` and `grapheme_any`
+pub struct SpecialCharTransition {
+    pub include_new_line: bool,
+    pub crlf: bool,     // the char '\r' is also excluded
+    pub grapheme: bool, // consumes a whole extended grapheme cluster
+}
+
+pub struct StringTransition {
+    pub chars: Vec<char>,
+    pub ignore_case: bool,
+}
+
+pub struct CharSetTransition {
+    pub items: Vec<CharSetItem>,
+    pub negative: bool,
+    pub ignore_case: bool,
+    pub unicode: bool, // the preset charsets match Unicode chars
+
+    // the negative charset consumes the whole extended grapheme cluster
+    // that begins with the accepted char.
+    pub grapheme: bool,
+
+    // the chars that match by case mapping when ignoring case, they are
+    // computed when the transition is created, see `fold_closure`.
+    pub folded: Vec<(char, char)>,
+}
+
+pub enum CharSetItem {
+    Char(char),
+    Range(char, char),
+    Preset(PresetCharSet, bool), // the preset charset and whether it is negative
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresetCharSet {
+    Space,
+    Word,
+    Digit,
+    AsciiSpace, // the ASCII white spaces regardless of the option `unicode`
+}
+
+impl PresetCharSet {
+    // the name without the prefix `char_`, e.g. "word"
+    pub fn name(&self) -> &'static str {
+        match self {
+            PresetCharSet::Space => "space",
+            PresetCharSet::Word => "word",
+            PresetCharSet::Digit => "digit",
+            PresetCharSet::AsciiSpace => "space_ascii",
+        }
+    }
+}
+
+// `start`, `end`, `bound`, `not_bound`, `word_start`, `word_end`
+// and `continue_from_last`
+pub struct StatusTransition {
+    pub status: Status,
+    pub multiline: bool, // `start` and `end` also match the line boundaries
+    pub crlf: bool,      // the line boundaries include "\r\n" and '\r'
+    pub unicode: bool,   // the word assertions check Unicode word chars
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Start,
+    End,
+    Bound,
+    NotBound,
+    WordStart, // like `\<` of GNU regular expressions
+    WordEnd,   // like `\>` of GNU regular expressions
+
+    // the position where the previous match of the instance ended,
+    // like `\G` of the ordinary regular expressions.
+    ContinueFromLast,
+}
+
+impl Display for CharSetItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CharSetItem::Char(c) => write!(f, "'{}'", c),
+            CharSetItem::Range(start, end_included) => {
+                write!(f, "'{}'..'{}'", start, end_included)
+            }
+            CharSetItem::Preset(preset, negative) => {
+                if *negative {
+                    write!(f, "char_not_{}", preset.name())
+                } else {
+                    write!(f, "char_{}", preset.name())
+                }
+            }
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Start => f.write_str("start"),
+            Status::End => f.write_str("end"),
+            Status::Bound => f.write_str("bound"),
+            Status::NotBound => f.write_str("not_bound"),
+            Status::WordStart => f.write_str("word_start"),
+            Status::WordEnd => f.write_str("word_end"),
+            Status::ContinueFromLast => f.write_str("continue_from_last"),
+        }
+    }
+}
+
+// matches the string by canonical equivalence, i.e. the precomposed
+// chars (NFC) and the decomposed chars (NFD) are treated as the same,
+// e.g. "caf\u{e9}" and "cafe\u{301}".
+pub struct NormalizedStringTransition {
+    pub chars: Vec<char>, // the decomposed chars
+    pub ignore_case: bool,
+}
+
+// `byte(0x..)`, only matches in bytes mode
+pub struct ByteTransition {
+    pub byte: u8,
+}
+
+// `byte_range(0x..0x..)`, only matches in bytes mode
+pub struct ByteRangeTransition {
+    pub start: u8,
+    pub end_included: u8,
+}
+
+// the states carried by each backtracking frame.
+//
+// they are stored in the frame rather than the instance, so the same
+// repetition can be active in several frames (e.g. nested or alternating)
+// without interfering, and the counters and captures made by a failed
+// branch are discarded automatically when the thread backtracks.
+//
+// most patterns have only a few nested repetitions and capture groups,
+// so the items are stored inline to avoid allocations when the frame
+// states are created and cloned.
+#[derive(Debug, Clone, Default)]
+pub struct FrameState {
+    pub counters: InlineVec<Counter, 4>,
+    pub captures: InlineVec<CaptureSlot, 4>,
+
+    // the position set by `match_start_reset`, the match starts at
+    // the start position of the thread if it is `None`.
+    pub match_start: Option<usize>,
+
+    // the last tag recorded by `tag(...)`, see `TagTransition`.
+    pub tag: Option<usize>,
+}
+
+// the repetition counter.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Counter {
+    pub value: usize,    // the number of completed iterations
+    pub position: usize, // the position where the current iteration starts
+}
+
+// the positions of a capture group, the group is captured only if
+// both positions are recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CaptureSlot {
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+
+    // the number of times the group is completed, e.g. the group
+    // inside a repetition is counted once for each iteration.
+    pub count: u32,
+}
+
+// pushes a new counter when entering a repetition
+pub struct CounterResetTransition;
+
+// enters the next iteration if the counter is less than `max`
+pub struct CounterCheckTransition {
+    pub max: Option<usize>,
+}
+
+// increases the counter when an iteration completes,
+// an iteration that matches nothing is rejected once `min` is reached,
+// otherwise the repetition would loop forever.
+pub struct CounterIncTransition {
+    pub min: usize,
+}
+
+// pops the counter when leaving a repetition,
+// it is rejected if the counter is less than `min`.
+pub struct CounterExitTransition {
+    pub min: usize,
+}
+
+// records the start position of a capture group
+pub struct CaptureStartTransition {
+    pub capture_index: usize,
+    pub keep_first: bool,
+}
+
+// records the end position of a capture group
+pub struct CaptureEndTransition {
+    pub capture_index: usize,
+    pub keep_first: bool,
+}
+
+// the look-around assertion.
+//
+// the sub-expression is compiled into a separate line of states which
+// is not connected to the main line, the process runs a sub-thread on
+// the line to check the assertion.
+pub struct LookAroundTransition {
+    pub start_state_index: usize,
+    pub end_state_index: usize,
+    pub is_behind: bool,
+    pub negative: bool,
+}
+
+// matches the text captured by a group, e.g. the identifier `x` in
+// `name(char_word+, x), '=', x`.
+//
+// the captured positions are read from the frame state, so it is
+// checked by the process rather than `TransitionTrait::check`.
+pub struct BackReferenceTransition {
+    pub capture_index: usize,
+    pub ignore_case: bool,
+}
+
+// `match_start_reset`, the reported match starts at the current position,
+// like `\K` of the ordinary regular expressions, e.g. the pattern
+// `"price: ", match_start_reset, char_digit+` matches "42" in "price: 42".
+//
+// the position is recorded in the frame state, so the reset made by a
+// branch that fails later is discarded, and the reset inside a
+// look-around assertion has no effect.
+pub struct MatchStartResetTransition;
+
+// `tag(expression, "name")`, the tag is recorded in the frame state when
+// the expression is matched, so the caller can tell which alternative
+// produced the match, e.g. `tag("GET", "read") || tag("POST", "write")`.
+//
+// the last recorded tag wins, and like `match_start_reset`, the tag
+// recorded by a branch that fails later is discarded, and the tag inside
+// a look-around assertion has no effect.
+pub struct TagTransition {
+    pub tag_index: usize, // the index of `StateSet::tags`
+}
+
 impl CharTransition {
     pub fn new(character: char /*, inverse: bool */) -> Self {
         CharTransition {
-            character, /*, inverse */
+            character,
+            ignore_case: false, /*, inverse */
+        }
+    }
+
+    pub fn new_ignore_case(character: char) -> Self {
+        CharTransition {
+            character,
+            ignore_case: true,
         }
     }
 }
 
-impl TransitionTrait for JumpTransition {
-    fn validated(&self, _context: &Context) -> bool {
-        true
+impl SpecialCharTransition {
+    pub fn new(include_new_line: bool, crlf: bool, grapheme: bool) -> Self {
+        SpecialCharTransition {
+            include_new_line,
+            crlf,
+            grapheme,
+        }
     }
+}
 
-    fn forward(&self) -> usize {
-        0
+impl StringTransition {
+    pub fn new(s: &str) -> Self {
+        StringTransition {
+            chars: s.chars().collect(),
+            ignore_case: false,
+        }
+    }
+
+    pub fn new_ignore_case(s: &str) -> Self {
+        StringTransition {
+            chars: s.chars().collect(),
+            ignore_case: true,
+        }
+    }
+}
+
+impl CharSetTransition {
+    pub fn new(
+        items: Vec<CharSetItem>,
+        negative: bool,
+        ignore_case: bool,
+        unicode: bool,
+        grapheme: bool,
+    ) -> Self {
+        let mut transition = CharSetTransition {
+            items,
+            negative,
+            ignore_case,
+            unicode,
+            grapheme,
+            folded: vec![],
+        };
+
+        if ignore_case {
+            transition.folded = fold_closure(|c| transition.contains(c));
+        }
+
+        transition
+    }
+
+    // whether the char is accepted by this charset.
+    pub fn matches_char(&self, c: char) -> bool {
+        let found = self.contains(c)
+            || (self.ignore_case
+                && self
+                    .folded
+                    .binary_search_by(|(start, end_included)| {
+                        if *end_included < c {
+                            Ordering::Less
+                        } else if *start > c {
+                            Ordering::Greater
+                        } else {
+                            Ordering::Equal
+                        }
+                    })
+                    .is_ok());
+
+        found != self.negative
+    }
+
+    fn contains(&self, c: char) -> bool {
+        self.items.iter().any(|item| match item {
+            CharSetItem::Char(e) => *e == c,
+            CharSetItem::Range(start, end_included) => *start <= c && c <= *end_included,
+            CharSetItem::Preset(preset, negative) => {
+                is_preset_char(*preset, c, self.unicode) != *negative
+            }
+        })
+    }
+}
+
+impl StatusTransition {
+    pub fn new(status: Status, multiline: bool, crlf: bool, unicode: bool) -> Self {
+        StatusTransition {
+            status,
+            multiline,
+            crlf,
+            unicode,
+        }
+    }
+
+    // the position after a new line, in crlf mode the '\r' is also
+    // a new line except the one followed by '\n', i.e. there is no line
+    // boundary inside "\r\n".
+    fn is_line_start(&self, context: &Context) -> bool {
+        let position = context.position;
+        match context.read_previous_char(position) {
+            Some('\n') => true,
+            Some('\r') if self.crlf => !matches!(context.read_char(position), Some(('\n', _))),
+            _ => false,
+        }
+    }
+
+    // the position before a new line, see `is_line_start`.
+    fn is_line_end(&self, context: &Context) -> bool {
+        let position = context.position;
+        match context.read_char(position) {
+            Some(('\n', _)) => !(self.crlf && context.read_previous_char(position) == Some('\r')),
+            Some(('\r', _)) => self.crlf,
+            _ => false,
+        }
+    }
+}
+
+fn is_preset_char(preset: PresetCharSet, c: char, unicode: bool) -> bool {
+    match preset {
+        PresetCharSet::Space => {
+            if unicode {
+                c.is_whitespace()
+            } else {
+                c.is_ascii_whitespace()
+            }
+        }
+        PresetCharSet::Word => is_word_char(c, unicode),
+        PresetCharSet::AsciiSpace => c.is_ascii_whitespace(),
+        PresetCharSet::Digit => {
+            if unicode {
+                c.is_numeric()
+            } else {
+                c.is_ascii_digit()
+            }
+        }
+    }
+}
+
+pub fn equals_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+impl NormalizedStringTransition {
+    pub fn new(s: &str) -> Self {
+        NormalizedStringTransition {
+            chars: decompose_str(s),
+            ignore_case: false,
+        }
+    }
+
+    pub fn new_ignore_case(s: &str) -> Self {
+        NormalizedStringTransition {
+            chars: decompose_str(s),
+            ignore_case: true,
+        }
+    }
+}
+
+impl ByteTransition {
+    pub fn new(byte: u8) -> Self {
+        ByteTransition { byte }
+    }
+}
+
+impl ByteRangeTransition {
+    pub fn new(start: u8, end_included: u8) -> Self {
+        ByteRangeTransition {
+            start,
+            end_included,
+        }
+    }
+}
+
+impl CounterCheckTransition {
+    pub fn new(max: Option<usize>) -> Self {
+        CounterCheckTransition { max }
+    }
+}
+
+impl CounterIncTransition {
+    pub fn new(min: usize) -> Self {
+        CounterIncTransition { min }
+    }
+}
+
+impl CounterExitTransition {
+    pub fn new(min: usize) -> Self {
+        CounterExitTransition { min }
+    }
+}
+
+impl CaptureStartTransition {
+    pub fn new(capture_index: usize, keep_first: bool) -> Self {
+        CaptureStartTransition {
+            capture_index,
+            keep_first,
+        }
+    }
+}
+
+impl CaptureEndTransition {
+    pub fn new(capture_index: usize, keep_first: bool) -> Self {
+        CaptureEndTransition {
+            capture_index,
+            keep_first,
+        }
+    }
+}
+
+impl LookAroundTransition {
+    pub fn new(
+        start_state_index: usize,
+        end_state_index: usize,
+        is_behind: bool,
+        negative: bool,
+    ) -> Self {
+        LookAroundTransition {
+            start_state_index,
+            end_state_index,
+            is_behind,
+            negative,
+        }
+    }
+}
+
+impl BackReferenceTransition {
+    pub fn new(capture_index: usize, ignore_case: bool) -> Self {
+        BackReferenceTransition {
+            capture_index,
+            ignore_case,
+        }
+    }
+
+    // returns the number of chars (or bytes) of the captured text if it
+    // is found at the current position, or None if the group has not
+    // been captured.
+    //
+    // the positions of the captures are absolute, so the captures made
+    // by the main line are also valid inside the look-around lines, and
+    // vice versa.
+    pub fn check_captures(&self, context: &Context, captures: &[CaptureSlot]) -> Option<usize> {
+        let CaptureSlot {
+            start: Some(start),
+            end: Some(end),
+            ..
+        } = captures.get(self.capture_index)?
+        else {
+            return None;
+        };
+
+        let mut captured_position = *start;
+        let mut position = context.position;
+
+        // the bytes are compared directly, so the invalid UTF-8 bytes
+        // can also be back-referenced.
+        if context.bytes_mode && !self.ignore_case {
+            while captured_position < *end {
+                match context.read_byte(position) {
+                    Some(b) if Some(b) == context.read_byte(captured_position) => {
+                        captured_position += 1;
+                        position += 1;
+                    }
+                    _ => return None,
+                }
+            }
+            return Some(position - context.position);
+        }
+
+        while captured_position < *end {
+            let (expected_char, captured_length) = context.read_char(captured_position)?;
+            match context.read_char(position) {
+                Some((c, length))
+                    if c == expected_char
+                        || (self.ignore_case && equals_ignore_case(c, expected_char)) =>
+                {
+                    captured_position += captured_length;
+                    position += length;
+                }
+                _ => return None,
+            }
+        }
+
+        Some(position - context.position)
+    }
+}
+
+impl Transition {
+    // the name of the kind of the transition, e.g. "char" and
+    // "counter_reset", it is used for the statistics of the states.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Transition::Jump(_) => "jump",
+            Transition::Char(_) => "char",
+            Transition::SpecialChar(_) => "special_char",
+            Transition::String(_) => "string",
+            Transition::CharSet(_) => "charset",
+            Transition::Status(_) => "status",
+            Transition::NormalizedString(_) => "normalized_string",
+            Transition::Byte(_) => "byte",
+            Transition::ByteRange(_) => "byte_range",
+            Transition::CounterReset(_) => "counter_reset",
+            Transition::CounterCheck(_) => "counter_check",
+            Transition::CounterInc(_) => "counter_inc",
+            Transition::CounterExit(_) => "counter_exit",
+            Transition::CaptureStart(_) => "capture_start",
+            Transition::CaptureEnd(_) => "capture_end",
+            Transition::LookAround(_) => "look_around",
+            Transition::BackReference(_) => "back_reference",
+            Transition::MatchStartReset(_) => "match_start_reset",
+            Transition::Tag(_) => "tag",
+        }
+    }
+
+    // updates the counters and captures of the current frame, returns false
+    // if the transition is rejected by the counters.
+    //
+    // the transitions other than the counter and capture transitions
+    // are always accepted.
+    pub fn update_frame_state(&self, state: &mut FrameState, position: usize) -> bool {
+        let counters = &mut state.counters;
+        match self {
+            Transition::CounterReset(_) => {
+                counters.push(Counter { value: 0, position });
+                true
+            }
+            Transition::CounterCheck(CounterCheckTransition { max }) => {
+                let counter = counters.last_mut().unwrap();
+                if matches!(max, Some(m) if counter.value >= *m) {
+                    false
+                } else {
+                    counter.position = position;
+                    true
+                }
+            }
+            Transition::CounterInc(CounterIncTransition { min }) => {
+                let counter = counters.last_mut().unwrap();
+                if counter.position == position && counter.value >= *min {
+                    false
+                } else {
+                    counter.value += 1;
+                    true
+                }
+            }
+            Transition::CounterExit(CounterExitTransition { min }) => {
+                if counters.last().unwrap().value < *min {
+                    false
+                } else {
+                    counters.pop();
+                    true
+                }
+            }
+            Transition::CaptureStart(CaptureStartTransition {
+                capture_index,
+                keep_first,
+            }) => {
+                // a capture inside a repetition keeps the last iteration by
+                // default, or the first (completed) iteration when `keep_first`
+                // is set, i.e. a completed slot is left untouched.
+                let slot = &mut state.captures[*capture_index];
+                if !(*keep_first && slot.end.is_some()) {
+                    *slot = CaptureSlot {
+                        start: Some(position),
+                        end: None,
+                        count: slot.count,
+                    };
+                }
+                true
+            }
+            Transition::CaptureEnd(CaptureEndTransition {
+                capture_index,
+                keep_first,
+            }) => {
+                let slot = &mut state.captures[*capture_index];
+                if !(*keep_first && slot.end.is_some()) {
+                    slot.end = Some(position);
+                }
+                slot.count += 1;
+                true
+            }
+            Transition::MatchStartReset(_) => {
+                state.match_start = Some(position);
+                true
+            }
+            Transition::Tag(TagTransition { tag_index }) => {
+                state.tag = Some(*tag_index);
+                true
+            }
+            _ => true,
+        }
+    }
+}
+
+impl TransitionTrait for Transition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        match self {
+            Transition::Jump(t) => t.check(context),
+            Transition::Char(t) => t.check(context),
+            Transition::SpecialChar(t) => t.check(context),
+            Transition::String(t) => t.check(context),
+            Transition::CharSet(t) => t.check(context),
+            Transition::Status(t) => t.check(context),
+            Transition::NormalizedString(t) => t.check(context),
+            Transition::Byte(t) => t.check(context),
+            Transition::ByteRange(t) => t.check(context),
+            Transition::CounterReset(_)
+            | Transition::CounterCheck(_)
+            | Transition::CounterInc(_)
+            | Transition::CounterExit(_)
+            | Transition::CaptureStart(_)
+            | Transition::CaptureEnd(_)
+            | Transition::MatchStartReset(_)
+            | Transition::Tag(_) => Some(0),
+            Transition::LookAround(_) | Transition::BackReference(_) => {
+                // it is checked by the process
+                unreachable!()
+            }
+        }
+    }
+}
+
+impl TransitionTrait for JumpTransition {
+    fn check(&self, _context: &Context) -> Option<usize> {
+        Some(0)
     }
 }
 
 impl TransitionTrait for CharTransition {
-    fn validated(&self, context: &Context) -> bool {
-        self.character == context.get_current_char() /* ^ self.inverse */
+    fn check(&self, context: &Context) -> Option<usize> {
+        match context.read_char(context.position) {
+            Some((c, length))
+                if c == self.character
+                    || (self.ignore_case && equals_ignore_case(c, self.character))
+                /* ^ self.inverse */ =>
+            {
+                Some(length)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TransitionTrait for SpecialCharTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        match context.read_char(context.position) {
+            Some((c, length))
+                if self.include_new_line || (c != '\n' && !(self.crlf && c == '\r')) =>
+            {
+                // the cluster "\r\n" is not consumed unless the new line
+                // is included, so `char_any` never crosses a line.
+                if self.grapheme && (self.include_new_line || c != '\r') {
+                    Some(grapheme_length(context, context.position, c, length))
+                } else {
+                    Some(length)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TransitionTrait for StringTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        let mut position = context.position;
+
+        for expected_char in &self.chars {
+            match context.read_char(position) {
+                Some((c, length))
+                    if c == *expected_char
+                        || (self.ignore_case && equals_ignore_case(c, *expected_char)) =>
+                {
+                    position += length;
+                }
+                _ => {
+                    return None;
+                }
+            }
+        }
+
+        Some(position - context.position)
+    }
+}
+
+impl TransitionTrait for CharSetTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        // a charset (including the negative one) consumes exactly one
+        // whole char, the malformed bytes are never matched, e.g. the
+        // charset `!['a']` does not match the continuation byte of
+        // a multi-byte char, nor the byte 0xff.
+        let (c, length) = context.read_char(context.position)?;
+
+        // the length must be the one of the char, otherwise the next
+        // transition would start in the middle of a UTF-8 sequence.
+        if context.bytes_mode && length != c.len_utf8() {
+            return None;
+        }
+
+        if !self.matches_char(c) {
+            None
+        } else if self.negative && self.grapheme {
+            Some(grapheme_length(context, context.position, c, length))
+        } else {
+            Some(length)
+        }
+    }
+}
+
+impl TransitionTrait for StatusTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        let position = context.position;
+
+        let matched = match self.status {
+            // the boundaries of text are recorded as read, like reading
+            // the char at the position, so the match that depends on them
+            // is affected by inserting text there, see `IncrementalMatcher`.
+            Status::Start if position == 0 => {
+                context.record_read(position, position + 1);
+                true
+            }
+            Status::Start => self.multiline && self.is_line_start(context),
+            Status::End if position == context.length => {
+                context.record_read(position, position + 1);
+                true
+            }
+            Status::End => self.multiline && self.is_line_end(context),
+            Status::Bound => context.is_word_bound(position, self.unicode),
+            Status::NotBound => !context.is_word_bound(position, self.unicode),
+            Status::WordStart => context.is_word_start(position, self.unicode),
+            Status::WordEnd => context.is_word_end(position, self.unicode),
+            Status::ContinueFromLast => position == context.last_match_end,
+        };
+
+        if matched {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+impl TransitionTrait for NormalizedStringTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        // decompose the chars of text one by one and compare them
+        // with the decomposed chars of the transition.
+        let mut position = context.position;
+        let mut matched: usize = 0;
+
+        while matched < self.chars.len() {
+            let (current_char, length) = context.read_char(position)?;
+            let decomposed_chars: Vec<char> = match decompose_char(current_char) {
+                Some(s) => s.chars().collect(),
+                None => vec![current_char],
+            };
+
+            for c in decomposed_chars {
+                // the decomposed chars are compared one by one, so the
+                // base char of "\u{c9}" matches the one of "\u{e9}".
+                if matched >= self.chars.len()
+                    || !(self.chars[matched] == c
+                        || (self.ignore_case && equals_ignore_case(self.chars[matched], c)))
+                {
+                    return None;
+                }
+                matched += 1;
+            }
+
+            position += length;
+        }
+
+        // the following combining mark belongs to the last matched char,
+        // e.g. "cafe" should not match "cafe\u{301}".
+        if let Some((c, _)) = context.read_char(position) {
+            if is_combining_mark(c) {
+                return None;
+            }
+        }
+
+        Some(position - context.position)
     }
+}
+
+impl TransitionTrait for ByteTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        match context.read_byte(context.position) {
+            Some(b) if b == self.byte => Some(1),
+            _ => None,
+        }
+    }
+}
 
-    fn forward(&self) -> usize {
-        1
+impl TransitionTrait for ByteRangeTransition {
+    fn check(&self, context: &Context) -> Option<usize> {
+        match context.read_byte(context.position) {
+            Some(b) if b >= self.start && b <= self.end_included => Some(1),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+
+    use super::{
+        ByteRangeTransition, ByteTransition, CharSetItem, CharSetTransition, CharTransition,
+        NormalizedStringTransition, SpecialCharTransition, StringTransition, TransitionTrait,
+    };
+
+    #[test]
+    fn test_special_char_transition() {
+        let mut context = Context::new("a\n");
+
+        let char_any = SpecialCharTransition::new(false, false, false);
+        let char_any_including_newline = SpecialCharTransition::new(true, false, false);
+
+        // 'a'
+        assert_eq!(char_any.check(&context), Some(1));
+        assert_eq!(char_any_including_newline.check(&context), Some(1));
+
+        // '\n'
+        context.position = 1;
+        assert_eq!(char_any.check(&context), None);
+        assert_eq!(char_any_including_newline.check(&context), Some(1));
+
+        // end of text
+        context.position = 2;
+        assert_eq!(char_any.check(&context), None);
+        assert_eq!(char_any_including_newline.check(&context), None);
+    }
+
+    #[test]
+    fn test_string_transition() {
+        let mut context = Context::new("abcab");
+        let transition = StringTransition::new("ab");
+
+        assert_eq!(transition.check(&context), Some(2));
+
+        context.position = 1;
+        assert_eq!(transition.check(&context), None);
+
+        context.position = 3;
+        assert_eq!(transition.check(&context), Some(2));
+
+        context.position = 4;
+        assert_eq!(transition.check(&context), None);
+    }
+
+    #[test]
+    fn test_normalized_string_transition() {
+        let composed = NormalizedStringTransition::new("caf\u{e9}");
+        let decomposed = NormalizedStringTransition::new("cafe\u{301}");
+
+        // composed text
+        let context = Context::new("caf\u{e9}");
+        assert_eq!(composed.check(&context), Some(4));
+        assert_eq!(decomposed.check(&context), Some(4));
+
+        // decomposed text
+        let context = Context::new("cafe\u{301}");
+        assert_eq!(composed.check(&context), Some(5));
+        assert_eq!(decomposed.check(&context), Some(5));
+
+        // the combining mark belongs to the last char
+        let transition = NormalizedStringTransition::new("cafe");
+        assert_eq!(transition.check(&Context::new("cafe\u{301}")), None);
+        assert_eq!(transition.check(&Context::new("caf\u{e9}")), None);
+        assert_eq!(transition.check(&Context::new("cafes")), Some(4));
+
+        // the decomposed chars of text should not be split
+        let transition = NormalizedStringTransition::new("a");
+        assert_eq!(transition.check(&Context::new("\u{e1}")), None);
+
+        // ignore case
+        assert_eq!(composed.check(&Context::new("CAF\u{c9}")), None);
+        let transition = NormalizedStringTransition::new_ignore_case("caf\u{e9}");
+        assert_eq!(transition.check(&Context::new("CAF\u{c9}")), Some(4));
+        assert_eq!(transition.check(&Context::new("CAFE\u{301}")), Some(5));
+        assert_eq!(transition.check(&Context::new("CAFE")), None);
+    }
+
+    #[test]
+    fn test_bytes_mode() {
+        let mut context = Context::from_bytes(&[b'a', 0xe6, 0x96, 0x87, 0xff, b'\n']);
+
+        // chars are read as UTF-8
+        assert_eq!(CharTransition::new('a').check(&context), Some(1));
+        assert_eq!(StringTransition::new("a文").check(&context), Some(4));
+
+        context.position = 1;
+        assert_eq!(CharTransition::new('文').check(&context), Some(3));
+        assert_eq!(
+            SpecialCharTransition::new(false, false, false).check(&context),
+            Some(3)
+        );
+        assert_eq!(ByteTransition::new(0xe6).check(&context), Some(1));
+
+        // continuation byte
+        context.position = 2;
+        assert_eq!(
+            SpecialCharTransition::new(true, false, false).check(&context),
+            None
+        );
+        assert_eq!(
+            ByteRangeTransition::new(0x80, 0xbf).check(&context),
+            Some(1)
+        );
+
+        // malformed byte
+        context.position = 4;
+        assert_eq!(
+            SpecialCharTransition::new(true, false, false).check(&context),
+            None
+        );
+        assert_eq!(ByteTransition::new(0xff).check(&context), Some(1));
+        assert_eq!(ByteRangeTransition::new(0x80, 0xfe).check(&context), None);
+
+        // bytes do not match in text mode
+        let context = Context::new("a");
+        assert_eq!(ByteTransition::new(b'a').check(&context), None);
+    }
+
+    #[test]
+    fn test_negative_charset_bytes_mode() {
+        let charset = CharSetTransition::new(
+            vec![CharSetItem::Range('a', 'c')],
+            true,
+            false,
+            false,
+            false,
+        );
+
+        // one whole char is consumed
+        let bytes = "x文🍞".as_bytes();
+        let mut context = Context::from_bytes(bytes);
+        assert_eq!(charset.check(&context), Some(1));
+        context.position = 1;
+        assert_eq!(charset.check(&context), Some(3));
+        context.position = 4;
+        assert_eq!(charset.check(&context), Some(4));
+
+        // the continuation bytes
+        for position in [2, 3, 5, 6, 7] {
+            context.position = position;
+            assert_eq!(charset.check(&context), None);
+        }
+
+        // the end
+        context.position = 8;
+        assert_eq!(charset.check(&context), None);
+
+        // the malformed bytes
+        let malformed: [&[u8]; 5] = [
+            &[0xff],             // invalid byte
+            &[0xe6, 0x96],       // incomplete
+            &[0xe6, b'a', b'a'], // interrupted
+            &[0xc0, 0x80],       // overlong encoding
+            &[0xed, 0xa0, 0x80], // surrogate
+        ];
+
+        for bytes in malformed {
+            let context = Context::from_bytes(bytes);
+            assert_eq!(charset.check(&context), None, "{:x?}", bytes);
+        }
     }
 }
@@ -0,0 +1,65 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// read a UTF-8 char from the specified position of bytes,
+// returns the char and the number of bytes it takes,
+// or None if the bytes are incomplete or malformed.
+//
+// the length of the UTF-8 char is determined by the first byte:
+//
+// 0xxxxxxx                            -> 1 byte
+// 110xxxxx 10xxxxxx                   -> 2 bytes
+// 1110xxxx 10xxxxxx 10xxxxxx          -> 3 bytes
+// 11110xxx 10xxxxxx 10xxxxxx 10xxxxxx -> 4 bytes
+pub fn read_char(bytes: &[u8], position: usize) -> Option<(char, usize)> {
+    let first = *bytes.get(position)?;
+
+    let length = if first & 0b1000_0000 == 0 {
+        1
+    } else if first & 0b1110_0000 == 0b1100_0000 {
+        2
+    } else if first & 0b1111_0000 == 0b1110_0000 {
+        3
+    } else if first & 0b1111_1000 == 0b1111_0000 {
+        4
+    } else {
+        return None;
+    };
+
+    let end = position + length;
+    if end > bytes.len() {
+        return None;
+    }
+
+    // `from_utf8` rejects the overlong encodings and the surrogates.
+    let s = std::str::from_utf8(&bytes[position..end]).ok()?;
+    s.chars().next().map(|c| (c, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_char;
+
+    #[test]
+    fn test_read_char() {
+        let bytes = "a文🍞".as_bytes();
+        assert_eq!(read_char(bytes, 0), Some(('a', 1)));
+        assert_eq!(read_char(bytes, 1), Some(('文', 3)));
+        assert_eq!(read_char(bytes, 4), Some(('🍞', 4)));
+        assert_eq!(read_char(bytes, 8), None);
+
+        // continuation byte
+        assert_eq!(read_char(bytes, 2), None);
+
+        // incomplete
+        assert_eq!(read_char(&bytes[..3], 1), None);
+
+        // malformed
+        assert_eq!(read_char(&[0xff], 0), None);
+        assert_eq!(read_char(&[0xc0, 0x80], 0), None); // overlong
+        assert_eq!(read_char(&[0xed, 0xa0, 0x80], 0), None); // surrogate
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// the external variables, i.e. the values passed in by `CompileOptions::vars`,
+// e.g. the pattern `char_word+, var(sep), char_word+` with the variable
+// `sep = ","` is compiled as `char_word+, ',', char_word+`.
+//
+// the reference `var(name)` is replaced with a char or string token, so the
+// value is always a literal, the metacharacters of ANREG in the value have
+// no special meaning.
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Error,
+    location::Location,
+    token::{Token, TokenWithRange},
+};
+
+pub fn substitute_variables(
+    tokens: Vec<TokenWithRange>,
+    vars: &HashMap<String, String>,
+) -> Result<Vec<TokenWithRange>, Error> {
+    let mut substituted_tokens = Vec::with_capacity(tokens.len());
+    let mut idx = 0;
+
+    while idx < tokens.len() {
+        let is_reference = matches!(&tokens[idx].token, Token::Identifier(id) if id == "var")
+            && matches!(
+                tokens.get(idx + 1),
+                Some(TokenWithRange {
+                    token: Token::LeftParen,
+                    ..
+                })
+            );
+
+        if !is_reference {
+            substituted_tokens.push(tokens[idx].clone());
+            idx += 1;
+            continue;
+        }
+
+        // "var" "(" identifier ")"
+        // the new lines inside the parentheses are allowed.
+        let start_range = tokens[idx].range;
+        idx += 2;
+
+        let mut parts = tokens[idx..]
+            .iter()
+            .filter(|t| t.token != Token::NewLine)
+            .take(2);
+
+        let (name, name_range) = match parts.next() {
+            Some(TokenWithRange {
+                token: Token::Identifier(name) | Token::QuotedIdentifier(name),
+                range,
+            }) => (name.to_owned(), *range),
+            Some(t) => {
+                return Err(Error::MessageWithLocation(
+                    "Expect a variable name.".to_owned(),
+                    t.range,
+                ));
+            }
+            None => {
+                return Err(Error::UnexpectedEndOfDocument(
+                    "Expect a variable name.".to_owned(),
+                ));
+            }
+        };
+
+        let end_range = match parts.next() {
+            Some(TokenWithRange {
+                token: Token::RightParen,
+                range,
+            }) => *range,
+            Some(t) => {
+                return Err(Error::MessageWithLocation(
+                    "Expect token: \")\".".to_owned(),
+                    t.range.get_position_by_range_start(),
+                ));
+            }
+            None => {
+                return Err(Error::UnexpectedEndOfDocument(
+                    "Expect token: \")\".".to_owned(),
+                ));
+            }
+        };
+
+        let Some(value) = vars.get(name.as_str()) else {
+            return Err(Error::MessageWithLocation(
+                format!("The variable \"{}\" is not defined.", name),
+                name_range,
+            ));
+        };
+
+        let mut chars = value.chars();
+        let token = match (chars.next(), chars.next()) {
+            (Some(c), None) => Token::Char(c),
+            _ => Token::String(value.to_owned()),
+        };
+
+        substituted_tokens.push(TokenWithRange {
+            token,
+            range: Location::from_range_pair(&start_range, &end_range),
+        });
+
+        // skip to the token after ")"
+        while tokens[idx].range != end_range {
+            idx += 1;
+        }
+        idx += 1;
+    }
+
+    Ok(substituted_tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        ast::{Expression, Literal},
+        commentcleaner::clean,
+        error::Error,
+        lexer::lex_from_str,
+        location::Location,
+        normalizer::normalize,
+        options::CompileOptions,
+        parser::parse_from_str_with_vars,
+        process::Process,
+        token::Token,
+    };
+
+    use super::substitute_variables;
+
+    fn substitute(s: &str, vars: &[(&str, &str)]) -> Result<Vec<Token>, Error> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let tokens = normalize(clean(lex_from_str(s).unwrap()));
+        let tokens = substitute_variables(tokens, &vars)?;
+        Ok(tokens.into_iter().map(|t| t.token).collect())
+    }
+
+    #[test]
+    fn test_substitute_variables() {
+        let vars = [("sep", ","), ("prefix", "id:"), ("empty", "")];
+
+        assert_eq!(
+            substitute("'a', var(sep), var(\nprefix\n), var(empty)", &vars).unwrap(),
+            vec![
+                Token::Char('a'),
+                Token::Comma,
+                Token::Char(','),
+                Token::Comma,
+                Token::String("id:".to_owned()),
+                Token::Comma,
+                Token::String("".to_owned()),
+            ]
+        );
+
+        // the identifier `var` that is not a reference
+        assert_eq!(
+            substitute("var", &vars).unwrap(),
+            vec![Token::new_identifier("var")]
+        );
+
+        // the location of the substituted token is the reference
+        let tokens = normalize(clean(lex_from_str("'a', var(sep)").unwrap()));
+        let vars_map = HashMap::from([("sep".to_owned(), ",".to_owned())]);
+        let tokens = substitute_variables(tokens, &vars_map).unwrap();
+        assert_eq!(tokens[2].range, Location::new_range(0, 5, 0, 5, 8));
+
+        // err: undefined variable
+        assert!(matches!(
+            substitute("'a', var(foo)", &vars),
+            Err(Error::MessageWithLocation(_, Location { index: 9, .. }))
+        ));
+
+        // err: not a name
+        assert!(matches!(
+            substitute("var('a')", &vars),
+            Err(Error::MessageWithLocation(..))
+        ));
+
+        // err: incomplete
+        assert!(matches!(
+            substitute("var(sep", &vars),
+            Err(Error::UnexpectedEndOfDocument(..))
+        ));
+    }
+
+    #[test]
+    fn test_compile_with_variables() {
+        let options = CompileOptions {
+            vars: HashMap::from([
+                ("sep".to_owned(), ",".to_owned()),
+                ("quote".to_owned(), "'\"".to_owned()),
+            ]),
+            ..CompileOptions::default()
+        };
+
+        let process = Process::new_with_options(
+            r#"define(field, char_word+)
+field, (var(sep), field)*"#,
+            &options,
+        )
+        .unwrap();
+        let mut instance = process.new_instance("a,b,c;d");
+        assert_eq!(instance.exec(0).map(|m| m.end), Some(5));
+
+        // the value is matched literally
+        let process = Process::new_with_options(r#"var(quote)+"#, &options).unwrap();
+        let mut instance = process.new_instance("x'\"'\"");
+        assert_eq!(instance.exec(0).map(|m| (m.start, m.end)), Some((1, 5)));
+
+        let program =
+            parse_from_str_with_vars(r#"var(sep)"#, &options.vars, &options.limits).unwrap();
+        assert_eq!(
+            program.expressions,
+            vec![Expression::Literal(Literal::Char(','))]
+        );
+
+        // err: the variables are not passed
+        assert!(Process::new(r#"'a', var(sep)"#).is_err());
+    }
+}
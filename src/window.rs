@@ -0,0 +1,241 @@
+// Copyright (c) 2024 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of
+// the Mozilla Public License version 2.0 and additional exceptions,
+// more details in file LICENSE, LICENSE.additional and CONTRIBUTING.
+
+// scanning a large buffer (e.g. a memory-mapped file) window by window,
+// so that only one window is loaded into the instance at a time, e.g.
+//
+// ```
+// let mut matches = process.scan_windows(&mmap, 1 << 20)?;
+// for m in matches { ... } // the positions are absolute in the buffer
+// ```
+//
+// the adjacent windows overlap by the maximum length of a match, and
+// only the matches that start before the overlap are taken from a window,
+// so that each match is found in one window exactly:
+//
+// ```text
+// |----- window 0 -----|
+//             |----- window 1 -----|
+//                         |----- window 2 --|
+// |-- step --|overlap |
+// ```
+//
+// note that the statuses (e.g. `start`, `end` and `bound`) and the
+// look-arounds can only see the chars in the window.
+
+use crate::process::{Instance, MatchRange};
+
+// the iterator of windows `(offset, length)` over a buffer, the
+// windows overlap by `overlap` bytes, the last window ends at the
+// end of the buffer.
+pub struct Windows {
+    buffer_length: usize,
+    window_size: usize,
+    step: usize,
+    offset: Option<usize>, // the offset of the next window
+}
+
+// the windows of size `window_size` over the buffer, `overlap` must
+// be less than `window_size`.
+//
+// an empty buffer has one empty window.
+pub fn windows(buffer_length: usize, window_size: usize, overlap: usize) -> Windows {
+    assert!(overlap < window_size);
+
+    Windows {
+        buffer_length,
+        window_size,
+        step: window_size - overlap,
+        offset: Some(0),
+    }
+}
+
+impl Iterator for Windows {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset?;
+        let length = self.window_size.min(self.buffer_length - offset);
+
+        self.offset = if offset + length < self.buffer_length {
+            Some(offset + self.step)
+        } else {
+            None
+        };
+
+        Some((offset, length))
+    }
+}
+
+// the iterator of the matches in a buffer, see `Process::scan_windows`.
+pub struct WindowMatches<'a, 'b> {
+    instance: Instance<'a>,
+    buffer: &'b [u8],
+    windows: Windows,
+    window: Option<(usize, usize)>, // the window loaded into the instance
+    next_start: usize,              // the absolute position to search from
+}
+
+impl<'a, 'b> WindowMatches<'a, 'b> {
+    pub(crate) fn new(
+        instance: Instance<'a>,
+        buffer: &'b [u8],
+        window_size: usize,
+        overlap: usize,
+    ) -> Self {
+        WindowMatches {
+            instance,
+            buffer,
+            windows: windows(buffer.len(), window_size, overlap),
+            window: None,
+            next_start: 0,
+        }
+    }
+}
+
+impl Iterator for WindowMatches<'_, '_> {
+    type Item = MatchRange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (offset, length) = match self.window {
+                Some(window) => window,
+                None => {
+                    let (offset, length) = self.windows.next()?;
+                    self.instance
+                        .reset_bytes(&self.buffer[offset..offset + length]);
+                    self.window = Some((offset, length));
+                    (offset, length)
+                }
+            };
+
+            // the matches that start in the overlap are taken from the
+            // next window, except the last window.
+            let is_last_window = offset + length == self.buffer.len();
+            let step = self.windows.step;
+
+            let local_start = self.next_start.saturating_sub(offset);
+            let found = if local_start <= length {
+                self.instance.exec(local_start)
+            } else {
+                None
+            };
+
+            match found {
+                Some(m) if is_last_window || m.start < step => {
+                    let match_range = MatchRange::new(m.start + offset, m.end + offset);
+
+                    // the search starts one position further after an
+                    // empty match, like `Instance::exec_next`.
+                    self.next_start = if match_range.start == match_range.end {
+                        match_range.end + 1
+                    } else {
+                        match_range.end
+                    };
+
+                    return Some(match_range);
+                }
+                _ => {
+                    // no more matches in this window
+                    self.window = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::process::{MatchRange, Process};
+
+    use super::windows;
+
+    #[test]
+    fn test_windows() {
+        assert_eq!(
+            windows(10, 4, 1).collect::<Vec<_>>(),
+            vec![(0, 4), (3, 4), (6, 4)]
+        );
+        assert_eq!(
+            windows(11, 4, 1).collect::<Vec<_>>(),
+            vec![(0, 4), (3, 4), (6, 4), (9, 2)]
+        );
+        assert_eq!(windows(3, 4, 2).collect::<Vec<_>>(), vec![(0, 3)]);
+        assert_eq!(windows(0, 4, 2).collect::<Vec<_>>(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_scan_windows() {
+        let process = Process::new(r#"byte_range(0x30..0x39){2,3}"#).unwrap();
+        let buffer = b"a12b345c6789d0";
+
+        // all window sizes find the same matches as scanning the
+        // whole buffer.
+        let expected = vec![
+            MatchRange::new(1, 3),
+            MatchRange::new(4, 7),
+            MatchRange::new(8, 11),
+        ];
+
+        for window_size in 4..=buffer.len() + 1 {
+            assert_eq!(
+                process
+                    .scan_windows(buffer, window_size)
+                    .unwrap()
+                    .collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        // the multi-byte chars
+        let process = Process::new(r#"'文', char_any"#).unwrap();
+        assert_eq!(process.max_match_bytes(), Some(7));
+        let buffer = "ab文字cd文x文".as_bytes();
+        assert_eq!(
+            process.scan_windows(buffer, 8).unwrap().collect::<Vec<_>>(),
+            vec![MatchRange::new(2, 8), MatchRange::new(10, 14)]
+        );
+
+        // the empty matches
+        let process = Process::new(r#"'a'?"#).unwrap();
+        assert_eq!(
+            process.scan_windows(b"xax", 2).unwrap().collect::<Vec<_>>(),
+            vec![
+                MatchRange::new(0, 0),
+                MatchRange::new(1, 2),
+                MatchRange::new(2, 2),
+                MatchRange::new(3, 3),
+            ]
+        );
+
+        // err: unbounded
+        let process = Process::new(r#"char_digit+"#).unwrap();
+        assert!(process.scan_windows(buffer, 1024).is_err());
+
+        // err: the window is too small
+        let process = Process::new(r#""abcd""#).unwrap();
+        assert!(process.scan_windows(buffer, 4).is_err());
+    }
+
+    #[test]
+    fn test_exec_window() {
+        let process = Process::new(r#"char_digit+"#).unwrap();
+        let mut instance = process.new_instance_from_bytes(b"");
+
+        let buffer = b"12 345 6789";
+        assert_eq!(
+            instance.exec_window(buffer, 2, 6),
+            Some(MatchRange::new(3, 6))
+        );
+        assert_eq!(
+            instance.exec_window(buffer, 4, 5),
+            Some(MatchRange::new(4, 6))
+        );
+        assert_eq!(instance.exec_window(buffer, 6, 1), None);
+    }
+}